@@ -0,0 +1,363 @@
+//! Rules imported from reverse proxy ACL files.
+//!
+//! Teams moving SSRF controls from edge configuration into the application
+//! can load their existing lists into a [`PolicyConfig`], review the result,
+//! and build it:
+//!
+//! - [`PolicyConfig::import_nginx`] reads `allow` and `deny` directives, as
+//!   written for nginx's access module.
+//! - [`PolicyConfig::import_haproxy`] reads an HAProxy pattern file, as
+//!   loaded with `acl ... -f`, one address, CIDR or hostname per line.
+//!
+//! ```rust
+//! use url_jail::{AclAction, PolicyConfig};
+//!
+//! let mut config = PolicyConfig::default();
+//! config.import_nginx("allow 10.20.0.0/16;\ndeny 10.0.0.0/8;\n").unwrap();
+//! config
+//!     .import_haproxy("# partner APIs\napi.partner.example\n", AclAction::Allow)
+//!     .unwrap();
+//! let policy = config.build().unwrap();
+//! assert!(policy.is_ip_allowed("10.20.1.1".parse().unwrap()).is_ok());
+//! assert!(policy.is_hostname_allowed("eu.api.partner.example").is_ok());
+//! ```
+//!
+//! Allow rules in a [`CustomPolicy`](crate::CustomPolicy) take precedence
+//! over block rules, whereas nginx applies the first matching directive. A
+//! file whose meaning would change is rejected rather than converted.
+
+use std::net::IpAddr;
+
+use ipnet::IpNet;
+
+use crate::config::{PolicyConfig, RuleConfig};
+use crate::policy_builder::{check_host_pattern, parse_cidr};
+
+/// What the rules of an imported HAProxy pattern file do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AclAction {
+    /// Add the patterns as `allow_cidrs` and `allow_hosts`.
+    Allow,
+
+    /// Add the patterns as `block_cidrs` and `block_hosts`.
+    Block,
+}
+
+impl PolicyConfig {
+    /// Add the `allow` and `deny` directives of an nginx configuration
+    /// fragment, such as an included denylist file.
+    ///
+    /// Each directive takes an address, a CIDR, or `all`. `deny all` makes
+    /// the base allowlist-only (see [`Policy::BlockAll`](crate::Policy::BlockAll)),
+    /// so only the allowed addresses and any `allow_hosts` stay reachable;
+    /// `allow all` adds nothing. Comments start with `#`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first problem, prefixed with its line
+    /// (`line 3: ...`), and leaves the config unchanged. Refused are
+    /// directives other than `allow` and `deny`, `unix:` addresses,
+    /// directives after an `all` rule, which nginx never reaches, and an
+    /// `allow` overlapping an earlier `deny`, which nginx would apply first.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::PolicyConfig;
+    ///
+    /// let mut config = PolicyConfig::default();
+    /// config
+    ///     .import_nginx("allow 93.184.216.0/24;  # example.com\ndeny all;")
+    ///     .unwrap();
+    /// let policy = config.build().unwrap();
+    /// assert!(policy.is_host_allowed("93.184.216.34", 443).is_ok());
+    /// assert!(policy.is_host_allowed("1.1.1.1", 443).is_err());
+    ///
+    /// let shadowed = "deny 10.1.0.0/16;\nallow 10.0.0.0/8;";
+    /// let err = PolicyConfig::default().import_nginx(shadowed).unwrap_err();
+    /// assert!(err.starts_with("line 2: allow 10.0.0.0/8 overlaps"), "{}", err);
+    /// ```
+    pub fn import_nginx(&mut self, text: &str) -> Result<(), String> {
+        let mut imported = self.clone();
+        let mut denied: Vec<IpNet> = Vec::new();
+        let mut closed_by: Option<&str> = None;
+
+        for (line, words) in nginx_statements(text)? {
+            let at = |e: String| format!("line {}: {}", line, e);
+            let (action, value) = match words.as_slice() {
+                [action @ ("allow" | "deny"), value] => (*action, *value),
+                [action @ ("allow" | "deny"), ..] => {
+                    return Err(at(format!("{} takes one address", action)))
+                }
+                [directive, ..] => {
+                    return Err(at(format!(
+                        "unsupported directive {:?} (only allow and deny)",
+                        directive
+                    )))
+                }
+                [] => continue,
+            };
+            if let Some(rule) = closed_by {
+                return Err(at(format!("unreachable after '{}'", rule)));
+            }
+            if value == "all" {
+                if action == "deny" {
+                    imported.base = imported.base.with_allowlist_only();
+                    closed_by = Some("deny all");
+                } else {
+                    closed_by = Some("allow all");
+                }
+                continue;
+            }
+            if value.starts_with("unix:") {
+                return Err(at("unix: addresses are not supported".to_string()));
+            }
+            let net = parse_address(value).map_err(at)?;
+            if action == "deny" {
+                denied.push(net);
+                imported.block_cidrs.push(RuleConfig::from(net.to_string()));
+                continue;
+            }
+            if let Some(deny) = denied
+                .iter()
+                .find(|deny| deny.contains(&net) || net.contains(*deny))
+            {
+                return Err(at(format!(
+                    "allow {} overlaps the earlier deny {}, which nginx applies first \
+                     but a custom policy would not",
+                    net, deny
+                )));
+            }
+            imported.allow_cidrs.push(RuleConfig::from(net.to_string()));
+        }
+
+        *self = imported;
+        Ok(())
+    }
+
+    /// Add the patterns of an HAProxy pattern file as allow or block rules.
+    ///
+    /// Each line holds an address, a CIDR (`10.0.0.0/8` or
+    /// `10.0.0.0/255.0.0.0`), or a hostname. Hostnames match as with
+    /// `-m dom`: `example.com` covers itself and its subdomains, and is
+    /// added as `*.example.com`. A leading dot, as written for `-m end`, is
+    /// dropped, so `.example.com` covers `example.com` too. Lines starting
+    /// with `#` are comments.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first invalid line, prefixed with its
+    /// number (`line 3: ...`), and leaves the config unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{AclAction, PolicyConfig};
+    ///
+    /// let denylist = "# internal\n10.0.0.0/255.0.0.0\n.corp.example\n";
+    /// let mut config = PolicyConfig::default();
+    /// config.import_haproxy(denylist, AclAction::Block).unwrap();
+    /// assert_eq!(config.block_cidrs, ["10.0.0.0/8".into()]);
+    /// assert_eq!(config.block_hosts, ["*.corp.example".into()]);
+    /// ```
+    pub fn import_haproxy(&mut self, text: &str, action: AclAction) -> Result<(), String> {
+        let mut cidrs = Vec::new();
+        let mut hosts = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let at = |e: String| format!("line {}: {}", i + 1, e);
+            let pattern = line.trim();
+            if pattern.is_empty() || pattern.starts_with('#') {
+                continue;
+            }
+            if pattern.contains('/') || pattern.parse::<IpAddr>().is_ok() {
+                cidrs.push(RuleConfig::from(
+                    parse_address(pattern).map_err(at)?.to_string(),
+                ));
+                continue;
+            }
+            let host = pattern.to_lowercase();
+            let host = host.strip_suffix('.').unwrap_or(&host);
+            let host = host.strip_prefix('.').unwrap_or(host);
+            let rule = if host.starts_with("*.") {
+                host.to_string()
+            } else {
+                format!("*.{}", host)
+            };
+            check_host_pattern(&rule).map_err(|e| at(e.to_string()))?;
+            hosts.push(RuleConfig::from(rule));
+        }
+
+        let (cidr_rules, host_rules) = match action {
+            AclAction::Allow => (&mut self.allow_cidrs, &mut self.allow_hosts),
+            AclAction::Block => (&mut self.block_cidrs, &mut self.block_hosts),
+        };
+        cidr_rules.extend(cidrs);
+        host_rules.extend(hosts);
+        Ok(())
+    }
+}
+
+/// The statements of an nginx fragment, as `(line, words)`, without
+/// comments.
+fn nginx_statements(text: &str) -> Result<Vec<(usize, Vec<&str>)>, String> {
+    let mut statements = Vec::new();
+    let mut pending: Option<(usize, Vec<&str>)> = None;
+    for (i, line) in text.lines().enumerate() {
+        let code = line.split('#').next().unwrap_or_default();
+        let mut parts = code.split(';').peekable();
+        while let Some(part) = parts.next() {
+            let words = part.split_whitespace();
+            match &mut pending {
+                Some((_, pending_words)) => pending_words.extend(words),
+                None => {
+                    let words: Vec<&str> = words.collect();
+                    if !words.is_empty() {
+                        pending = Some((i + 1, words));
+                    }
+                }
+            }
+            // Every part but the last ends at a ';'
+            if parts.peek().is_some() {
+                statements.extend(pending.take());
+            }
+        }
+    }
+    match pending {
+        Some((line, _)) => Err(format!("line {}: missing ';'", line)),
+        None => Ok(statements),
+    }
+}
+
+/// Parse an address or CIDR, with a prefix length or an IPv4 netmask.
+fn parse_address(value: &str) -> Result<IpNet, String> {
+    if let Ok(ip) = value.parse::<IpAddr>() {
+        return Ok(IpNet::from(ip));
+    }
+    if let Some((ip, mask)) = value.split_once('/') {
+        if let (Ok(ip), Ok(mask)) = (ip.parse::<IpAddr>(), mask.parse::<IpAddr>()) {
+            return IpNet::with_netmask(ip, mask)
+                .map(|net| net.trunc())
+                .map_err(|_| format!("invalid netmask in {:?}", value));
+        }
+    }
+    parse_cidr(value)
+        .map(|net| net.trunc())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Policy;
+
+    #[test]
+    fn test_import_nginx() {
+        let text = "\
+# Egress denylist
+deny 169.254.0.0/16;
+deny 10.1.2.3;   deny 2001:db8::/32;
+allow
+    192.168.1.0/24;
+deny 172.16.5.9/12;
+";
+        let mut config = PolicyConfig::default();
+        config.import_nginx(text).unwrap();
+        assert_eq!(
+            config.block_cidrs,
+            [
+                "169.254.0.0/16".into(),
+                "10.1.2.3/32".into(),
+                "2001:db8::/32".into(),
+                "172.16.0.0/12".into(),
+            ]
+        );
+        assert_eq!(config.allow_cidrs, ["192.168.1.0/24".into()]);
+        assert!(!config.base.is_allowlist_only());
+
+        let mut config = PolicyConfig {
+            allow_hosts: vec!["api.example.com".into()],
+            ..Default::default()
+        };
+        config
+            .import_nginx("allow 93.184.216.0/24;\ndeny all;")
+            .unwrap();
+        assert_eq!(config.base, Policy::BlockAll.into());
+        let policy = config.build().unwrap();
+        assert!(policy.is_hostname_allowed("api.example.com").is_ok());
+        assert!(policy.is_hostname_allowed("example.org").is_err());
+        assert!(policy.is_host_allowed("93.184.216.34", 80).is_ok());
+    }
+
+    #[test]
+    fn test_import_nginx_rejects_changed_meaning() {
+        for (text, error) in [
+            ("deny 10.0.0.0/8", "line 1: missing ';'"),
+            (
+                "\nlimit_rate 10k;",
+                "line 2: unsupported directive \"limit_rate\" (only allow and deny)",
+            ),
+            ("deny 10.0.0.1 10.0.0.2;", "line 1: deny takes one address"),
+            ("allow unix:;", "line 1: unix: addresses are not supported"),
+            (
+                "deny all;\nallow 10.0.0.1;",
+                "line 2: unreachable after 'deny all'",
+            ),
+            (
+                "deny 10.0.0.0/8;\nallow 10.1.0.0/16;",
+                "line 2: allow 10.1.0.0/16 overlaps the earlier deny 10.0.0.0/8, \
+                 which nginx applies first but a custom policy would not",
+            ),
+        ] {
+            let mut config = PolicyConfig::default();
+            assert_eq!(config.import_nginx(text).unwrap_err(), error, "{}", text);
+            assert_eq!(config, PolicyConfig::default());
+        }
+        assert!(PolicyConfig::default()
+            .import_nginx("deny 10.0.0.0/33;")
+            .unwrap_err()
+            .starts_with("line 1: invalid CIDR"));
+        // An allow before an overlapping deny means the same in both
+        assert!(PolicyConfig::default()
+            .import_nginx("allow 10.1.0.0/16;\ndeny 10.0.0.0/8;")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_import_haproxy() {
+        let text = "\
+# partner APIs
+api.partner.example
+.cdn.example.net
+*.Assets.Example.
+93.184.216.34
+2606:4700::/32
+";
+        let mut config = PolicyConfig::default();
+        config.import_haproxy(text, AclAction::Allow).unwrap();
+        assert_eq!(
+            config.allow_hosts,
+            [
+                "*.api.partner.example".into(),
+                "*.cdn.example.net".into(),
+                "*.assets.example".into(),
+            ]
+        );
+        assert_eq!(
+            config.allow_cidrs,
+            ["93.184.216.34/32".into(), "2606:4700::/32".into()]
+        );
+        assert!(config.block_hosts.is_empty());
+        assert!(config.build().is_ok());
+
+        let mut config = PolicyConfig::default();
+        let err = config
+            .import_haproxy("10.0.0.0/8\n10.0.0.0/255.0.255.0\n", AclAction::Block)
+            .unwrap_err();
+        assert_eq!(err, "line 2: invalid netmask in \"10.0.0.0/255.0.255.0\"");
+        assert!(config
+            .import_haproxy("ok.example\nbad host\n", AclAction::Block)
+            .unwrap_err()
+            .starts_with("line 2: "));
+        assert_eq!(config, PolicyConfig::default());
+    }
+}
@@ -0,0 +1,538 @@
+//! Startup verification of allowlisted hostnames.
+//!
+//! An `allow_host` rule trusts a name, but names change hands: a partner's
+//! domain expires and is re-registered, or a record is repointed at an
+//! internal address. Validation still checks every resolved address, so
+//! such a host fails at request time; [`CustomPolicy::verify_allowlist`]
+//! finds it at startup instead, before the first user does.
+//! [`AllowlistMonitor`] keeps watching afterwards, reporting each host whose
+//! addresses change class, say from public to private, or stop resolving.
+//!
+//! ```rust,no_run
+//! use url_jail::{Policy, PolicyBuilder};
+//!
+//! # async fn startup() {
+//! let policy = PolicyBuilder::new(Policy::PublicOnly)
+//!     .allow_host("hooks.partner.example")
+//!     .build();
+//! for issue in policy.verify_allowlist().await {
+//!     eprintln!("allowlist: {}", issue);
+//! }
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::batch::join_all;
+use crate::classify::{DefaultClassifier, IpClass, IpClassifier};
+use crate::error::Error;
+use crate::policy_builder::{BlockReason, CustomPolicy};
+use crate::validate::{lookup, ValidateOptions};
+
+/// A problem with an allowlisted hostname, from
+/// [`CustomPolicy::verify_allowlist`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AllowlistIssue {
+    /// The host resolves to an address the policy blocks.
+    Blocked {
+        /// The `allow_host` rule, as written (`api.partner.example:8443`).
+        rule: String,
+        /// The blocked address.
+        ip: IpAddr,
+        /// Why the address is blocked.
+        reason: BlockReason,
+    },
+
+    /// The host did not resolve, as with an expired domain.
+    Unresolved {
+        /// The `allow_host` rule, as written.
+        rule: String,
+        /// The resolver error.
+        error: String,
+    },
+}
+
+impl AllowlistIssue {
+    /// The `allow_host` rule with the problem.
+    pub fn rule(&self) -> &str {
+        match self {
+            AllowlistIssue::Blocked { rule, .. } | AllowlistIssue::Unresolved { rule, .. } => rule,
+        }
+    }
+}
+
+impl fmt::Display for AllowlistIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AllowlistIssue::Blocked { rule, ip, reason } => {
+                write!(f, "allowed host {} resolves to {}: {}", rule, ip, reason)
+            }
+            AllowlistIssue::Unresolved { rule, error } => {
+                write!(f, "allowed host {} did not resolve: {}", rule, error)
+            }
+        }
+    }
+}
+
+impl CustomPolicy {
+    /// Resolve every allowlisted hostname and report those that resolve
+    /// into blocked ranges or not at all.
+    ///
+    /// Only rules naming a single host are checked: wildcards and globs
+    /// have no one name to resolve, and expired rules no longer apply.
+    /// Every address of each answer is checked, as validation does, but
+    /// checks are not counted in [`rule_stats`](Self::rule_stats). Hosts are
+    /// resolved concurrently with the system resolver; use
+    /// [`verify_allowlist_with_options`](Self::verify_allowlist_with_options)
+    /// to resolve as validation is configured to.
+    ///
+    /// An empty result means every checked host resolved to allowed
+    /// addresses. Issues are listed in rule order.
+    pub async fn verify_allowlist(&self) -> Vec<AllowlistIssue> {
+        self.verify_allowlist_with_options(ValidateOptions::default())
+            .await
+    }
+
+    /// Like [`verify_allowlist`](Self::verify_allowlist), resolving with the
+    /// [`resolver`](ValidateOptions::resolver) and
+    /// [`dns_timeout`](ValidateOptions::dns_timeout) of `options`.
+    ///
+    /// The cache and [`dns_fallback`](ValidateOptions::dns_fallback) are not
+    /// used, so every answer is fresh.
+    pub async fn verify_allowlist_with_options(
+        &self,
+        options: ValidateOptions,
+    ) -> Vec<AllowlistIssue> {
+        let mut issues = Vec::new();
+        for (rule, answer) in self.resolve_allowlist(&options).await {
+            match answer {
+                Ok(ips) => issues.extend(ips.into_iter().filter_map(|ip| {
+                    let reason = self.is_ip_allowed_uncounted(ip).err()?;
+                    Some(AllowlistIssue::Blocked {
+                        rule: rule.clone(),
+                        ip,
+                        reason,
+                    })
+                })),
+                Err(e) => issues.push(AllowlistIssue::Unresolved {
+                    rule,
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        for issue in &issues {
+            tracing::warn!(rule = issue.rule(), %issue, "allowlisted host failed verification");
+        }
+
+        issues
+    }
+
+    /// Resolve each exact, active allow-host rule, as `(rule, answer)`.
+    async fn resolve_allowlist(
+        &self,
+        options: &ValidateOptions,
+    ) -> Vec<(String, Result<Vec<IpAddr>, Error>)> {
+        let hosts = self.exact_allowed_hosts();
+        let lookups = hosts
+            .iter()
+            .map(|(_, host)| lookup(host.trim_start_matches('[').trim_end_matches(']'), options))
+            .collect();
+        let answers = join_all(lookups).await;
+        hosts
+            .into_iter()
+            .map(|(rule, _)| rule)
+            .zip(answers)
+            .collect()
+    }
+}
+
+/// What an allowlisted hostname resolved to, as seen by an
+/// [`AllowlistMonitor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AllowlistStatus {
+    /// The distinct [`IpClass`]es of its addresses, sorted by name.
+    Resolved(Vec<IpClass>),
+
+    /// The lookup failed, with the resolver error.
+    Unresolved(String),
+}
+
+impl AllowlistStatus {
+    fn of(answer: Result<Vec<IpAddr>, Error>) -> Self {
+        match answer {
+            Ok(ips) => {
+                let mut classes: Vec<IpClass> = ips
+                    .into_iter()
+                    .map(|ip| DefaultClassifier.classify(ip))
+                    .collect();
+                classes.sort_by_key(IpClass::as_str);
+                classes.dedup();
+                AllowlistStatus::Resolved(classes)
+            }
+            Err(e) => AllowlistStatus::Unresolved(e.to_string()),
+        }
+    }
+
+    /// Whether `other` differs in classification, ignoring error text.
+    fn differs(&self, other: &AllowlistStatus) -> bool {
+        match (self, other) {
+            (AllowlistStatus::Resolved(a), AllowlistStatus::Resolved(b)) => a != b,
+            (AllowlistStatus::Unresolved(_), AllowlistStatus::Unresolved(_)) => false,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for AllowlistStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AllowlistStatus::Resolved(classes) => {
+                let names: Vec<&str> = classes.iter().map(IpClass::as_str).collect();
+                f.write_str(&names.join("+"))
+            }
+            AllowlistStatus::Unresolved(error) => write!(f, "unresolved ({})", error),
+        }
+    }
+}
+
+/// An allowlisted hostname whose classification changed, reported by an
+/// [`AllowlistMonitor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowlistChange {
+    /// The `allow_host` rule, as written.
+    pub rule: String,
+
+    /// The status at the previous check.
+    pub before: AllowlistStatus,
+
+    /// The status now.
+    pub after: AllowlistStatus,
+}
+
+impl fmt::Display for AllowlistChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "allowed host {} changed from {} to {}",
+            self.rule, self.before, self.after
+        )
+    }
+}
+
+type ChangeFn = dyn Fn(&AllowlistChange) + Send + Sync;
+
+/// Periodically re-resolves a policy's allowlisted hostnames and reports
+/// changes in what they resolve to.
+///
+/// Each [`check`](Self::check) resolves every exact, active `allow_host`
+/// name and classifies its addresses with the [`DefaultClassifier`]. A host
+/// whose classes differ from the previous check, or that stops or starts
+/// resolving, yields an [`AllowlistChange`]: it is passed to the
+/// [`on_change`](Self::on_change) callback and, with the `tracing` feature,
+/// logged as a warning. The first check only records a baseline; run
+/// [`CustomPolicy::verify_allowlist`] at startup to judge it.
+///
+/// Clones share the recorded statuses.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use url_jail::{AllowlistMonitor, Policy, PolicyBuilder};
+///
+/// # async fn example() {
+/// let policy = PolicyBuilder::new(Policy::PublicOnly)
+///     .allow_host("hooks.partner.example")
+///     .build();
+/// let monitor = AllowlistMonitor::new(policy)
+///     .on_change(|change| eprintln!("SECURITY: {}", change))
+///     .spawn(Duration::from_secs(300));
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AllowlistMonitor {
+    policy: Arc<CustomPolicy>,
+    options: ValidateOptions,
+    on_change: Option<Arc<ChangeFn>>,
+    statuses: Arc<Mutex<BTreeMap<String, AllowlistStatus>>>,
+}
+
+impl AllowlistMonitor {
+    /// Monitor the allowlist of `policy`, resolving with the system
+    /// resolver.
+    pub fn new(policy: CustomPolicy) -> Self {
+        Self {
+            policy: Arc::new(policy),
+            options: ValidateOptions::default(),
+            on_change: None,
+            statuses: Arc::default(),
+        }
+    }
+
+    /// Resolve with the [`resolver`](ValidateOptions::resolver) and
+    /// [`dns_timeout`](ValidateOptions::dns_timeout) of `options`, as
+    /// [`CustomPolicy::verify_allowlist_with_options`] does.
+    pub fn options(mut self, options: ValidateOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Call `callback` for every change.
+    ///
+    /// The callback runs on the checking task, so it should be quick.
+    pub fn on_change<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&AllowlistChange) + Send + Sync + 'static,
+    {
+        self.on_change = Some(Arc::new(callback));
+        self
+    }
+
+    /// Check now and every `interval` after, on a background Tokio task.
+    ///
+    /// The task stops once every clone of the monitor is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a Tokio runtime.
+    pub fn spawn(self, interval: Duration) -> Self {
+        let statuses = Arc::downgrade(&self.statuses);
+        let (policy, options, on_change) = (
+            self.policy.clone(),
+            self.options.clone(),
+            self.on_change.clone(),
+        );
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                let Some(statuses) = statuses.upgrade() else {
+                    return;
+                };
+                let monitor = AllowlistMonitor {
+                    policy: policy.clone(),
+                    options: options.clone(),
+                    on_change: on_change.clone(),
+                    statuses,
+                };
+                monitor.check().await;
+            }
+        });
+        self
+    }
+
+    /// Resolve every monitored host now and return what changed since the
+    /// previous check.
+    ///
+    /// Rules that expired since the previous check are forgotten.
+    pub async fn check(&self) -> Vec<AllowlistChange> {
+        let answers = self.policy.resolve_allowlist(&self.options).await;
+
+        let mut changes = Vec::new();
+        {
+            let mut statuses = self.statuses.lock().unwrap_or_else(|e| e.into_inner());
+            let previous = std::mem::take(&mut *statuses);
+            for (rule, answer) in answers {
+                let after = AllowlistStatus::of(answer);
+                match previous.get(&rule) {
+                    Some(before) if before.differs(&after) => changes.push(AllowlistChange {
+                        rule: rule.clone(),
+                        before: before.clone(),
+                        after: after.clone(),
+                    }),
+                    _ => {}
+                }
+                statuses.insert(rule, after);
+            }
+        }
+
+        for change in &changes {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(rule = %change.rule, %change, "allowlisted host changed classification");
+
+            if let Some(callback) = &self.on_change {
+                callback(change);
+            }
+        }
+        changes
+    }
+
+    /// The status of each monitored host at the last check, by rule.
+    pub fn statuses(&self) -> Vec<(String, AllowlistStatus)> {
+        let statuses = self.statuses.lock().unwrap_or_else(|e| e.into_inner());
+        statuses
+            .iter()
+            .map(|(rule, status)| (rule.clone(), status.clone()))
+            .collect()
+    }
+}
+
+impl fmt::Debug for AllowlistMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AllowlistMonitor")
+            .field("policy", &self.policy)
+            .field("statuses", &self.statuses())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::error::Error;
+    use crate::resolver::{Resolve, ResolveFuture};
+    use crate::{Policy, PolicyBuilder};
+
+    struct TableResolver(Mutex<HashMap<&'static str, Vec<IpAddr>>>);
+
+    impl TableResolver {
+        fn new<const N: usize>(entries: [(&'static str, &[&str]); N]) -> Self {
+            Self(Mutex::new(HashMap::new())).with(entries)
+        }
+
+        fn with<const N: usize>(self, entries: [(&'static str, &[&str]); N]) -> Self {
+            self.set(entries);
+            self
+        }
+
+        fn set<const N: usize>(&self, entries: [(&'static str, &[&str]); N]) {
+            let mut table = self.0.lock().unwrap();
+            for (host, ips) in entries {
+                match ips {
+                    [] => table.remove(host),
+                    ips => table.insert(host, ips.iter().map(|ip| ip.parse().unwrap()).collect()),
+                };
+            }
+        }
+    }
+
+    impl Resolve for TableResolver {
+        fn lookup<'a>(&'a self, host: &'a str) -> ResolveFuture<'a> {
+            let answer = self.0.lock().unwrap().get(host).cloned();
+            Box::pin(async move { answer.ok_or_else(|| Error::dns_error(host, "NXDOMAIN")) })
+        }
+    }
+
+    fn options(resolver: &Arc<TableResolver>) -> ValidateOptions {
+        ValidateOptions {
+            resolver: Some(resolver.clone()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reports_blocked_and_unresolved_hosts() {
+        let resolver = Arc::new(TableResolver::new([
+            ("ok.partner.example", &["93.184.216.34"]),
+            ("moved.partner.example", &["93.184.216.34", "10.0.0.5"]),
+        ]));
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_host("ok.partner.example")
+            .allow_host("moved.partner.example:8443")
+            .allow_host("gone.partner.example")
+            .allow_host("*.cdn.example")
+            .allow_host_until("old.partner.example", std::time::SystemTime::UNIX_EPOCH)
+            .build();
+
+        let issues = policy
+            .verify_allowlist_with_options(options(&resolver))
+            .await;
+        assert_eq!(issues.len(), 2, "{:?}", issues);
+        assert_eq!(issues[0].rule(), "moved.partner.example:8443");
+        assert!(
+            matches!(issues[0], AllowlistIssue::Blocked { ip, .. } if ip.to_string() == "10.0.0.5")
+        );
+        assert!(issues[0]
+            .to_string()
+            .starts_with("allowed host moved.partner.example:8443 resolves to 10.0.0.5: "));
+        assert!(matches!(issues[1], AllowlistIssue::Unresolved { .. }));
+        assert_eq!(issues[1].rule(), "gone.partner.example");
+        assert!(policy.rule_stats().iter().all(|stat| stat.hits == 0));
+    }
+
+    #[tokio::test]
+    async fn test_monitor_reports_classification_changes() {
+        let resolver = Arc::new(TableResolver::new([
+            ("a.partner.example", &["93.184.216.34"]),
+            ("b.partner.example", &["93.184.216.35"]),
+        ]));
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_host("a.partner.example")
+            .allow_host("b.partner.example")
+            .allow_host("c.partner.example")
+            .build();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let monitor = AllowlistMonitor::new(policy)
+            .options(options(&resolver))
+            .on_change({
+                let seen = seen.clone();
+                move |change| seen.lock().unwrap().push(change.rule.clone())
+            });
+
+        // The first check is the baseline
+        assert_eq!(monitor.check().await, Vec::new());
+        assert_eq!(
+            monitor.statuses()[0],
+            (
+                "a.partner.example".to_string(),
+                AllowlistStatus::Resolved(vec![IpClass::Public])
+            )
+        );
+        assert!(matches!(
+            monitor.statuses()[2].1,
+            AllowlistStatus::Unresolved(_)
+        ));
+
+        resolver.set([
+            ("a.partner.example", &["10.0.0.5"]),
+            ("b.partner.example", &[]),
+        ]);
+        let changes = monitor.check().await;
+        assert_eq!(changes.len(), 2, "{:?}", changes);
+        assert_eq!(
+            changes[0].to_string(),
+            "allowed host a.partner.example changed from public to private"
+        );
+        assert!(changes[1]
+            .to_string()
+            .starts_with("allowed host b.partner.example changed from public to unresolved ("));
+        assert_eq!(
+            *seen.lock().unwrap(),
+            ["a.partner.example", "b.partner.example"]
+        );
+
+        // Unchanged classes report nothing, whatever the addresses
+        resolver.set([("a.partner.example", &["10.9.9.9"])]);
+        assert_eq!(monitor.check().await, Vec::new());
+    }
+
+    #[tokio::test]
+    async fn test_monitor_task_stops_with_monitor() {
+        let resolver = Arc::new(TableResolver::new([(
+            "a.partner.example",
+            &["93.184.216.34"],
+        )]));
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_host("a.partner.example")
+            .build();
+        let monitor = AllowlistMonitor::new(policy)
+            .options(options(&resolver))
+            .spawn(Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(monitor.statuses().len(), 1);
+
+        let statuses = Arc::downgrade(&monitor.statuses);
+        drop(monitor);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(statuses.upgrade().is_none());
+    }
+}
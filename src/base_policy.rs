@@ -0,0 +1,352 @@
+//! Fine-grained base policy flags.
+//!
+//! [`Policy`] covers the common cases. A [`BasePolicy`] picks which
+//! [`IpClass`]es are blocked one by one, for combinations such as "allow
+//! loopback in development but still block metadata". Use it as the base of
+//! a [`CustomPolicy`](crate::CustomPolicy):
+//!
+//! ```rust
+//! use url_jail::{BasePolicy, PolicyBuilder};
+//!
+//! let dev = BasePolicy::builder().block_loopback(false).build();
+//! let policy = PolicyBuilder::new(dev).build();
+//!
+//! assert!(policy.is_ip_allowed("127.0.0.1".parse().unwrap()).is_ok());
+//! assert!(policy.is_ip_allowed("169.254.169.254".parse().unwrap()).is_err());
+//! assert!(policy.is_ip_allowed("10.0.0.1".parse().unwrap()).is_err());
+//! ```
+//!
+//! The flags govern addresses only. Cloud metadata hostnames such as
+//! `metadata.google.internal` are blocked regardless.
+
+use crate::classify::IpClass;
+use crate::policy::Policy;
+
+/// Which address classes a custom policy blocks before its own rules.
+///
+/// Built with [`BasePolicy::builder`], or converted from a [`Policy`].
+/// [`IpClass::Public`] is never blocked by the base policy; use
+/// [`PolicyBuilder::block_cidr`](crate::PolicyBuilder::block_cidr) for that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BasePolicy {
+    unspecified: bool,
+    loopback: bool,
+    link_local: bool,
+    metadata: bool,
+    private: bool,
+    shared: bool,
+    allowlist_only: bool,
+}
+
+impl BasePolicy {
+    /// Start from [`Policy::PublicOnly`]: every non-public class blocked.
+    pub fn builder() -> BasePolicyBuilder {
+        BasePolicyBuilder {
+            flags: Policy::PublicOnly.into(),
+        }
+    }
+
+    /// Returns `true` if addresses of `class` are blocked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{BasePolicy, IpClass, Policy};
+    ///
+    /// let base = BasePolicy::from(Policy::AllowPrivate);
+    /// assert!(!base.blocks(IpClass::Private));
+    /// assert!(base.blocks(IpClass::Loopback));
+    /// ```
+    pub fn blocks(&self, class: IpClass) -> bool {
+        match class {
+            IpClass::Unspecified => self.unspecified,
+            IpClass::Loopback => self.loopback,
+            IpClass::LinkLocal => self.link_local,
+            IpClass::Metadata => self.metadata,
+            IpClass::Private => self.private,
+            IpClass::Shared => self.shared,
+            IpClass::Public => false,
+            // Classes added later are blocked until a flag covers them
+            _ => true,
+        }
+    }
+
+    /// Returns `true` if only hosts and addresses named by the custom
+    /// policy's allow rules are reachable.
+    ///
+    /// Set by [`Policy::BlockAll`] and
+    /// [`BasePolicyBuilder::allowlist_only`].
+    pub fn is_allowlist_only(&self) -> bool {
+        self.allowlist_only
+    }
+
+    /// These flags in allowlist-only mode.
+    pub(crate) fn with_allowlist_only(self) -> Self {
+        Self {
+            allowlist_only: true,
+            ..self
+        }
+    }
+
+    /// Flags that block every class either `self` or `other` blocks.
+    pub(crate) fn strictest(self, other: BasePolicy) -> Self {
+        Self {
+            unspecified: self.unspecified || other.unspecified,
+            loopback: self.loopback || other.loopback,
+            link_local: self.link_local || other.link_local,
+            metadata: self.metadata || other.metadata,
+            private: self.private || other.private,
+            shared: self.shared || other.shared,
+            allowlist_only: self.allowlist_only || other.allowlist_only,
+        }
+    }
+
+    /// The built-in [`Policy`] with exactly these flags, if any.
+    pub fn as_policy(&self) -> Option<Policy> {
+        [Policy::PublicOnly, Policy::AllowPrivate, Policy::BlockAll]
+            .into_iter()
+            .find(|policy| BasePolicy::from(*policy) == *self)
+    }
+
+    /// Stable name for fingerprints: the built-in policy name, or the
+    /// allowed classes.
+    pub(crate) fn name(&self) -> String {
+        // Both built-in policies allow shared addresses, so only blocking
+        // them is named and existing fingerprints stay the same
+        if self.shared {
+            let unshared = Self {
+                shared: false,
+                ..*self
+            };
+            return format!("{}+block=shared", unshared.name());
+        }
+        if self.allowlist_only && self.as_policy().is_none() {
+            let open = Self {
+                allowlist_only: false,
+                ..*self
+            };
+            return format!("{}+allowlist-only", open.name());
+        }
+        if let Some(policy) = self.as_policy() {
+            return format!("{:?}", policy);
+        }
+        let allowed: Vec<&str> = [
+            IpClass::Unspecified,
+            IpClass::Loopback,
+            IpClass::LinkLocal,
+            IpClass::Metadata,
+            IpClass::Private,
+        ]
+        .into_iter()
+        .filter(|class| !self.blocks(*class))
+        .map(|class| class.as_str())
+        .collect();
+        format!("Flags(allow={})", allowed.join("+"))
+    }
+}
+
+impl Default for BasePolicy {
+    fn default() -> Self {
+        Policy::default().into()
+    }
+}
+
+impl From<Policy> for BasePolicy {
+    fn from(policy: Policy) -> Self {
+        Self {
+            unspecified: true,
+            loopback: true,
+            link_local: true,
+            metadata: true,
+            private: policy != Policy::AllowPrivate,
+            shared: false,
+            allowlist_only: policy == Policy::BlockAll,
+        }
+    }
+}
+
+/// Builder for [`BasePolicy`], created by [`BasePolicy::builder`].
+#[derive(Debug, Clone)]
+pub struct BasePolicyBuilder {
+    flags: BasePolicy,
+}
+
+impl BasePolicyBuilder {
+    /// Block `0.0.0.0` and `::`.
+    pub fn block_unspecified(mut self, block: bool) -> Self {
+        self.flags.unspecified = block;
+        self
+    }
+
+    /// Block loopback addresses (`127.0.0.0/8`, `::1`).
+    pub fn block_loopback(mut self, block: bool) -> Self {
+        self.flags.loopback = block;
+        self
+    }
+
+    /// Block link-local addresses (`169.254.0.0/16`, `fe80::/10`).
+    pub fn block_link_local(mut self, block: bool) -> Self {
+        self.flags.link_local = block;
+        self
+    }
+
+    /// Block cloud metadata endpoints (`169.254.169.254`, `fd00:ec2::254`, ...).
+    pub fn block_metadata(mut self, block: bool) -> Self {
+        self.flags.metadata = block;
+        self
+    }
+
+    /// Block private networks (RFC 1918, `fc00::/7`).
+    pub fn block_private(mut self, block: bool) -> Self {
+        self.flags.private = block;
+        self
+    }
+
+    /// Block shared address space (`100.64.0.0/10`), used by carrier-grade
+    /// NAT and overlay networks such as Tailscale.
+    ///
+    /// Off in both built-in policies, so a base that allows only the
+    /// overlay need not allow the whole private network. Turn it on where
+    /// the range reaches internal services, with or without
+    /// [`block_private`](Self::block_private).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{BasePolicy, PolicyBuilder};
+    ///
+    /// let policy = PolicyBuilder::new(BasePolicy::builder().block_shared(true).build()).build();
+    /// assert!(policy.is_ip_allowed("100.101.102.103".parse().unwrap()).is_err());
+    /// assert!(policy.is_ip_allowed("93.184.216.34".parse().unwrap()).is_ok());
+    /// ```
+    pub fn block_shared(mut self, block: bool) -> Self {
+        self.flags.shared = block;
+        self
+    }
+
+    /// Reject every hostname no `allow_host` rule matches, and every IP
+    /// literal no `allow_cidr` rule contains, as [`Policy::BlockAll`] does.
+    ///
+    /// The other flags still apply to the addresses allowed hostnames
+    /// resolve to, so an allowlist of internal services can be combined
+    /// with [`block_private(false)`](Self::block_private).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{BasePolicy, PolicyBuilder};
+    ///
+    /// let base = BasePolicy::builder()
+    ///     .block_private(false)
+    ///     .allowlist_only(true)
+    ///     .build();
+    /// let policy = PolicyBuilder::new(base).allow_host("*.corp.internal").build();
+    /// assert!(policy.is_hostname_allowed("billing.corp.internal").is_ok());
+    /// assert!(policy.is_hostname_allowed("example.com").is_err());
+    /// assert!(policy.is_ip_allowed("10.0.0.1".parse().unwrap()).is_ok());
+    /// ```
+    pub fn allowlist_only(mut self, allowlist_only: bool) -> Self {
+        self.flags.allowlist_only = allowlist_only;
+        self
+    }
+
+    /// Build the base policy.
+    pub fn build(self) -> BasePolicy {
+        self.flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PolicyBuilder;
+
+    #[test]
+    fn test_flags_combine() {
+        let policy = PolicyBuilder::new(
+            BasePolicy::builder()
+                .block_private(false)
+                .block_link_local(false)
+                .build(),
+        )
+        .build();
+        for (addr, allowed) in [
+            ("10.0.0.1", true),
+            ("169.254.10.1", true),
+            ("169.254.169.254", false),
+            ("127.0.0.1", false),
+            ("0.0.0.0", false),
+        ] {
+            let result = policy.is_ip_allowed(addr.parse().unwrap());
+            assert_eq!(result.is_ok(), allowed, "{}: {:?}", addr, result);
+        }
+    }
+
+    #[test]
+    fn test_builtin_equivalents_keep_fingerprint() {
+        for policy in [Policy::PublicOnly, Policy::AllowPrivate] {
+            let from_flags = PolicyBuilder::new(BasePolicy::from(policy)).build();
+            let from_policy = PolicyBuilder::new(policy).build();
+            assert_eq!(from_flags.fingerprint(), from_policy.fingerprint());
+        }
+        let private = BasePolicy::builder().block_private(false).build();
+        assert_eq!(private.as_policy(), Some(Policy::AllowPrivate));
+
+        let dev = BasePolicy::builder().block_loopback(false).build();
+        assert_eq!(dev.as_policy(), None);
+        assert_eq!(dev.name(), "Flags(allow=loopback)");
+        assert_ne!(
+            PolicyBuilder::new(dev).build().fingerprint(),
+            PolicyBuilder::new(Policy::PublicOnly).build().fingerprint()
+        );
+
+        let shared = BasePolicy::builder().block_shared(true).build();
+        assert_eq!(shared.as_policy(), None);
+        assert_eq!(shared.name(), "PublicOnly+block=shared");
+        let dev_shared = BasePolicy::builder()
+            .block_loopback(false)
+            .block_shared(true)
+            .build();
+        assert_eq!(dev_shared.name(), "Flags(allow=loopback)+block=shared");
+    }
+
+    #[test]
+    fn test_shared_address_space() {
+        let cgnat = "100.64.1.2".parse().unwrap();
+        for policy in [Policy::PublicOnly, Policy::AllowPrivate] {
+            let base = BasePolicy::from(policy);
+            assert!(!base.blocks(IpClass::Shared));
+            assert!(PolicyBuilder::new(base)
+                .build()
+                .is_ip_allowed(cgnat)
+                .is_ok());
+        }
+
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .classifier(crate::DefaultClassifier)
+            .build();
+        assert!(policy.is_ip_allowed(cgnat).is_ok());
+
+        let overlay_blocked = BasePolicy::builder()
+            .block_private(false)
+            .block_shared(true)
+            .build();
+        let policy = PolicyBuilder::new(overlay_blocked)
+            .allow_cidr("100.64.1.0/24")
+            .build();
+        assert!(policy.is_ip_allowed("10.0.0.1".parse().unwrap()).is_ok());
+        assert!(policy.is_ip_allowed(cgnat).is_ok());
+        let err = policy
+            .is_ip_allowed("100.100.1.1".parse().unwrap())
+            .unwrap_err();
+        assert!(
+            err.to_string().starts_with("shared address space"),
+            "{}",
+            err
+        );
+        // Alibaba metadata sits in the range but stays blocked everywhere
+        let metadata = "100.100.100.200".parse().unwrap();
+        let policy = PolicyBuilder::new(Policy::AllowPrivate).build();
+        assert!(policy.is_ip_allowed(metadata).is_err());
+    }
+}
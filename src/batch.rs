@@ -0,0 +1,246 @@
+//! Batch validation.
+//!
+//! Multi-tenant batch jobs validate URLs that belong to different tenants,
+//! each with its own policy. [`validate_all`] takes `(url, policy)` pairs, so
+//! URLs need not be grouped by policy first; with `&dyn ValidationPolicy` a
+//! single batch can mix built-in and custom policies.
+//!
+//! [`validate_many`] validates a large batch under one policy with at most a
+//! given number of validations in flight, such as every webhook endpoint of
+//! a tenant at registration time.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Poll;
+
+use tokio::sync::Semaphore;
+
+use crate::error::Error;
+use crate::policy::ValidationPolicy;
+use crate::validate::{validate_with_options, ValidateOptions, Validated};
+
+/// Validate a batch of URLs, each against its own policy.
+///
+/// Validations run concurrently on the calling task (nothing is spawned, so
+/// policies may be borrowed); results are returned in input order.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use url_jail::{validate_all, Policy, PolicyBuilder, ValidationPolicy};
+///
+/// # async fn example() {
+/// let acme = PolicyBuilder::new(Policy::PublicOnly).block_host("*.acme-internal.example").build();
+/// let batch: Vec<(&str, &dyn ValidationPolicy)> = vec![
+///     ("https://hooks.acme.example/1", &acme),
+///     ("https://api.globex.example/cb", &Policy::PublicOnly),
+///     ("http://10.0.0.5/", &Policy::AllowPrivate),
+/// ];
+/// for result in validate_all(batch).await {
+///     match result {
+///         Ok(v) => println!("ok: {} -> {}", v.url, v.ip),
+///         Err(e) => println!("rejected: {}", e),
+///     }
+/// }
+/// # }
+/// ```
+pub async fn validate_all<'a, I, P>(items: I) -> Vec<Result<Validated, Error>>
+where
+    I: IntoIterator<Item = (&'a str, P)>,
+    P: ValidationPolicy,
+{
+    validate_all_with_options(items, ValidateOptions::default()).await
+}
+
+/// Validate a batch of URLs, each against its own policy, with `options`.
+///
+/// `options` (including any cache) is shared by every validation in the
+/// batch. See [`validate_all`].
+pub async fn validate_all_with_options<'a, I, P>(
+    items: I,
+    options: ValidateOptions,
+) -> Vec<Result<Validated, Error>>
+where
+    I: IntoIterator<Item = (&'a str, P)>,
+    P: ValidationPolicy,
+{
+    let validations: Vec<_> = items
+        .into_iter()
+        .map(|(url, policy)| validate_with_options(url, policy, options.clone()))
+        .collect();
+    join_all(validations).await
+}
+
+/// Validate `urls` against one policy, at most `max_concurrency` at a time.
+///
+/// Every URL gets its own result, in input order; a failure does not stop
+/// the rest. Validations run on the calling task, like [`validate_all`].
+/// `max_concurrency` is clamped to at least 1, and the process-wide lookup
+/// limit (see [`set_max_concurrent_lookups`](crate::set_max_concurrent_lookups))
+/// still applies.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use url_jail::{validate_many, Policy};
+///
+/// # async fn example() {
+/// let endpoints = ["https://hooks.acme.example/1", "https://hooks.acme.example/2"];
+/// let results = validate_many(endpoints, Policy::PublicOnly, 16).await;
+/// let rejected = results.iter().filter(|result| result.is_err()).count();
+/// println!("{} of {} endpoints rejected", rejected, results.len());
+/// # }
+/// ```
+pub async fn validate_many<I, P>(
+    urls: I,
+    policy: P,
+    max_concurrency: usize,
+) -> Vec<Result<Validated, Error>>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+    P: ValidationPolicy,
+{
+    validate_many_with_options(urls, policy, max_concurrency, ValidateOptions::default()).await
+}
+
+/// Validate `urls` against one policy with bounded concurrency and
+/// `options`.
+///
+/// `options` (including any cache) is shared by every validation. See
+/// [`validate_many`].
+pub async fn validate_many_with_options<I, P>(
+    urls: I,
+    policy: P,
+    max_concurrency: usize,
+    options: ValidateOptions,
+) -> Vec<Result<Validated, Error>>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+    P: ValidationPolicy,
+{
+    let limit = Semaphore::new(max_concurrency.clamp(1, Semaphore::MAX_PERMITS));
+    let (limit, policy) = (&limit, &policy);
+    let validations: Vec<_> = urls
+        .into_iter()
+        .map(|url| {
+            let options = options.clone();
+            async move {
+                let _permit = limit.acquire().await.expect("batch limit is never closed");
+                validate_with_options(url.as_ref(), policy, options).await
+            }
+        })
+        .collect();
+    join_all(validations).await
+}
+
+/// Poll `futures` concurrently and return their outputs in order.
+pub(crate) async fn join_all<F: Future>(futures: Vec<F>) -> Vec<F::Output> {
+    let mut futures: Vec<Pin<Box<F>>> = futures.into_iter().map(Box::pin).collect();
+    let mut outputs: Vec<Option<F::Output>> = futures.iter().map(|_| None).collect();
+
+    std::future::poll_fn(|cx| {
+        let mut pending = false;
+        for (future, output) in futures.iter_mut().zip(outputs.iter_mut()) {
+            if output.is_some() {
+                continue;
+            }
+            match future.as_mut().poll(cx) {
+                Poll::Ready(result) => *output = Some(result),
+                Poll::Pending => pending = true,
+            }
+        }
+        if pending {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    })
+    .await;
+
+    outputs
+        .into_iter()
+        .map(|output| output.expect("every future completed"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{Policy, PolicyBuilder, Resolve, ResolveFuture};
+
+    #[tokio::test]
+    async fn test_mixed_policies_in_input_order() {
+        let tenant = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_cidr("10.20.0.0/16")
+            .build();
+        let batch: Vec<(&str, &dyn ValidationPolicy)> = vec![
+            ("http://10.20.0.1/", &tenant),
+            ("http://10.20.0.1/", &Policy::PublicOnly),
+            ("http://10.30.0.1/", &Policy::AllowPrivate),
+            ("http://127.0.0.1/", &Policy::AllowPrivate),
+            ("not a url", &tenant),
+        ];
+
+        let results = validate_all(batch).await;
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0].as_ref().unwrap().ip.to_string(), "10.20.0.1");
+        assert!(results[1].as_ref().unwrap_err().is_blocked());
+        assert_eq!(results[2].as_ref().unwrap().ip.to_string(), "10.30.0.1");
+        assert!(results[3].as_ref().unwrap_err().is_blocked());
+        assert!(matches!(results[4], Err(Error::InvalidUrl { .. })));
+    }
+
+    /// Answers after a delay, tracking how many lookups overlap.
+    #[derive(Default)]
+    struct SlowResolver {
+        active: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    impl Resolve for SlowResolver {
+        fn lookup<'a>(&'a self, _host: &'a str) -> ResolveFuture<'a> {
+            Box::pin(async move {
+                let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+                self.peak.fetch_max(active, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                self.active.fetch_sub(1, Ordering::SeqCst);
+                Ok(vec!["93.184.216.34".parse().unwrap()])
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_many_bounds_concurrency() {
+        let resolver = Arc::new(SlowResolver::default());
+        let options = ValidateOptions {
+            resolver: Some(resolver.clone()),
+            ..Default::default()
+        };
+        let mut urls: Vec<String> = (0..20)
+            .map(|i| format!("https://hook{}.example/", i))
+            .collect();
+        urls.push("http://127.0.0.1/".to_string());
+
+        let results = validate_many_with_options(&urls, Policy::PublicOnly, 4, options).await;
+        assert_eq!(results.len(), 21);
+        assert!(results[..20].iter().all(Result::is_ok));
+        assert!(results[20].as_ref().unwrap_err().is_blocked());
+        assert_eq!(results[7].as_ref().unwrap().host, "hook7.example");
+        assert_eq!(resolver.peak.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_join_all_keeps_order() {
+        let delays = [30u64, 0, 10];
+        let futures = delays.iter().map(|ms| async move {
+            tokio::time::sleep(std::time::Duration::from_millis(*ms)).await;
+            *ms
+        });
+        assert_eq!(join_all(futures.collect()).await, delays);
+    }
+}
@@ -0,0 +1,261 @@
+//! Outbound budgets per validation context.
+//!
+//! A single user-triggered fetch should not be able to turn the server into
+//! a bandwidth amplifier: a redirect chain, a huge body, or a trickling
+//! response each keep a connection busy on the user's behalf. A
+//! [`FetchBudget`] caps what every [`Context`] may consume across all the
+//! fetches made under it: connections opened (one per hop), body bytes read,
+//! and time since its first connection.
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use url_jail::{BudgetLimits, Context, FetchBudget, FetchOptions};
+//!
+//! let budget = Arc::new(FetchBudget::new(BudgetLimits {
+//!     max_connections: Some(5),
+//!     max_bytes: Some(10 * 1024 * 1024),
+//!     max_duration: Some(Duration::from_secs(30)),
+//! }));
+//!
+//! // Per user request
+//! let opts = FetchOptions {
+//!     budget: Some(budget.clone()),
+//!     context: Context::new().tag("user", "u-123"),
+//!     ..Default::default()
+//! };
+//! ```
+//!
+//! Usage is kept until [`FetchBudget::forget`] drops it, so key contexts by
+//! whatever the budget is meant to cover, such as one user request.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::context::Context;
+use crate::error::Error;
+use crate::validate::Validated;
+
+/// Limits for a [`FetchBudget`]. `None` leaves a dimension unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BudgetLimits {
+    /// Connections a context may open, one per hop including redirects.
+    pub max_connections: Option<u32>,
+
+    /// Response body bytes a context may read.
+    pub max_bytes: Option<u64>,
+
+    /// Time a context may spend fetching, from its first connection.
+    pub max_duration: Option<Duration>,
+}
+
+/// What a context has consumed, from [`FetchBudget::usage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetUsage {
+    /// Connections opened.
+    pub connections: u32,
+
+    /// Response body bytes read.
+    pub bytes: u64,
+
+    /// Time since the first connection.
+    pub elapsed: Duration,
+}
+
+#[derive(Debug)]
+struct Usage {
+    connections: u32,
+    bytes: u64,
+    started: Instant,
+}
+
+/// Outbound limits shared by every fetch made under the same [`Context`].
+///
+/// Pass it as [`FetchOptions::budget`](crate::FetchOptions::budget), keyed
+/// by [`FetchOptions::context`](crate::FetchOptions::context). Bytes are
+/// counted by [`FetchResult::bytes`](crate::FetchResult::bytes),
+/// [`FetchResult::text`](crate::FetchResult::text) and
+/// [`fetch_stream`](crate::fetch_stream); reading
+/// [`FetchResult::response`](crate::FetchResult::response) directly skips
+/// the byte limit.
+#[derive(Debug, Default)]
+pub struct FetchBudget {
+    limits: BudgetLimits,
+    usage: Mutex<HashMap<Context, Usage>>,
+}
+
+impl FetchBudget {
+    /// A budget applying `limits` to each context.
+    pub fn new(limits: BudgetLimits) -> Self {
+        Self {
+            limits,
+            usage: Mutex::default(),
+        }
+    }
+
+    /// The limits applied to each context.
+    pub fn limits(&self) -> BudgetLimits {
+        self.limits
+    }
+
+    /// What `context` has consumed, or `None` if it has not connected yet.
+    pub fn usage(&self, context: &Context) -> Option<BudgetUsage> {
+        let usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        usage.get(context).map(|usage| BudgetUsage {
+            connections: usage.connections,
+            bytes: usage.bytes,
+            elapsed: usage.started.elapsed(),
+        })
+    }
+
+    /// Drop the usage of `context`, giving it a fresh budget.
+    pub fn forget(&self, context: &Context) {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        usage.remove(context);
+    }
+
+    /// Count a connection for the hop to `validated`.
+    ///
+    /// Returns the deadline for the context, if its duration is limited.
+    /// Refused connections are not counted.
+    pub(crate) fn connect(
+        &self,
+        context: &Context,
+        validated: &Validated,
+    ) -> Result<Option<Instant>, Error> {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let usage = usage.entry(context.clone()).or_insert_with(|| Usage {
+            connections: 0,
+            bytes: 0,
+            started: Instant::now(),
+        });
+        let refused = |reason: String| Error::SsrfBlocked {
+            url: validated.url.clone(),
+            ip: validated.ip,
+            reason: exhausted(context, reason),
+        };
+        if let Some(max) = self.limits.max_connections {
+            if usage.connections >= max {
+                return Err(refused(format!("{} connections", max)));
+            }
+        }
+        let deadline = self.deadline_of(usage);
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(refused(self.duration_reason()));
+        }
+        usage.connections += 1;
+        Ok(deadline)
+    }
+
+    /// Count `len` body bytes read from `url`.
+    pub(crate) fn consume(&self, context: &Context, url: &str, len: u64) -> Result<(), Error> {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(usage) = usage.get_mut(context) else {
+            // Forgotten mid-read: the fresh budget starts with this body
+            return Ok(());
+        };
+        usage.bytes += len;
+        match self.limits.max_bytes {
+            Some(max) if usage.bytes > max => Err(Error::ResponseBlocked {
+                url: url.to_string(),
+                reason: exhausted(context, format!("{} bytes", max)),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The error for a read still pending at the deadline.
+    pub(crate) fn timed_out(&self, context: &Context, url: &str) -> Error {
+        Error::ResponseBlocked {
+            url: url.to_string(),
+            reason: exhausted(context, self.duration_reason()),
+        }
+    }
+
+    fn deadline_of(&self, usage: &Usage) -> Option<Instant> {
+        self.limits.max_duration.map(|max| usage.started + max)
+    }
+
+    fn duration_reason(&self) -> String {
+        format!("{:?}", self.limits.max_duration.unwrap_or_default())
+    }
+}
+
+fn exhausted(context: &Context, limit: String) -> String {
+    if context.is_empty() {
+        format!("fetch budget exhausted ({})", limit)
+    } else {
+        format!("fetch budget exhausted for {} ({})", context, limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validated() -> Validated {
+        Validated {
+            ip: "93.184.216.34".parse().unwrap(),
+            ips: vec!["93.184.216.34".parse().unwrap()],
+            host: "example.com".to_string(),
+            port: 443,
+            url: "https://example.com/".to_string(),
+            https: true,
+            valid_until: None,
+            clock: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_limits_apply_per_context() {
+        let budget = FetchBudget::new(BudgetLimits {
+            max_connections: Some(2),
+            max_bytes: Some(100),
+            ..Default::default()
+        });
+        let alice = Context::new().tag("user", "alice");
+        let bob = Context::new().tag("user", "bob");
+
+        assert_eq!(budget.connect(&alice, &validated()).unwrap(), None);
+        budget.connect(&alice, &validated()).unwrap();
+        let err = budget.connect(&alice, &validated()).unwrap_err();
+        assert!(err.is_blocked());
+        assert!(
+            err.to_string()
+                .contains("fetch budget exhausted for user=alice (2 connections)"),
+            "{}",
+            err
+        );
+        budget.connect(&bob, &validated()).unwrap();
+
+        budget.consume(&alice, "https://example.com/", 60).unwrap();
+        let err = budget
+            .consume(&alice, "https://example.com/", 60)
+            .unwrap_err();
+        assert!(matches!(err, Error::ResponseBlocked { .. }));
+        budget.consume(&bob, "https://example.com/", 60).unwrap();
+        assert_eq!(budget.usage(&alice).unwrap().connections, 2);
+        assert_eq!(budget.usage(&alice).unwrap().bytes, 120);
+
+        budget.forget(&alice);
+        assert_eq!(budget.usage(&alice), None);
+        budget.connect(&alice, &validated()).unwrap();
+    }
+
+    #[test]
+    fn test_duration_limit_refuses_late_connections() {
+        let budget = FetchBudget::new(BudgetLimits {
+            max_duration: Some(Duration::ZERO),
+            ..Default::default()
+        });
+        let context = Context::new();
+        // The first connection starts the clock; a zero budget is spent
+        let err = budget.connect(&context, &validated()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "SSRF blocked: https://example.com/ resolved to 93.184.216.34 - fetch budget exhausted (0ns)"
+        );
+        assert_eq!(budget.usage(&context).unwrap().connections, 0);
+    }
+}
@@ -0,0 +1,732 @@
+//! Validation result caching.
+//!
+//! A [`ValidationCache`] remembers hostnames that resolved to allowed
+//! addresses so repeated validations skip DNS. Entries are **partitioned by
+//! policy fingerprint**: a host that passed under one policy is never served
+//! from the cache to a different (possibly stricter) policy.
+//!
+//! Only successful validations are cached. Hostname rules and the cloud
+//! metadata blocklist are still evaluated on every call, and cached addresses
+//! are re-checked against the policy, so the cache replaces only the DNS lookup.
+//!
+//! ## Storage
+//!
+//! Entries live in a [`CacheStore`]. The default is the in-process
+//! [`MemoryStore`]; implement the trait over Redis, memcached, or similar to
+//! share validation results across a horizontally scaled fleet. Keys render
+//! to stable strings and expiry is carried as wall-clock time, so entries
+//! written by one process are meaningful to another.
+//!
+//! ## Security
+//!
+//! Caching widens the time-of-check/time-of-use window to the cache TTL. Keep
+//! the TTL short (seconds to a few minutes) and always connect to the cached
+//! [`Validated::ip`](crate::Validated::ip). A shared store should only be
+//! writable by trusted services: re-checking against the policy prevents a
+//! poisoned entry from reaching a blocked address, but not from redirecting a
+//! host to some other allowed address.
+//!
+//! ## DNS TTLs
+//!
+//! By default every entry lives for the cache's fixed TTL, however short the
+//! DNS answer's own TTL. [`ValidationCache::with_dns_ttl`] makes entries
+//! expire with the answer instead, clamped between a floor and the cache
+//! TTL, so a hot hostname with a one-hour record stays cached while one
+//! whose operator set a five-second TTL is looked up again after five
+//! seconds.
+//!
+//! ## Jitter
+//!
+//! Entries for a popular host written at the same moment across a fleet
+//! would all expire, and re-resolve, at the same moment.
+//! [`ValidationCache::with_jitter`] shortens each entry's TTL by a random
+//! amount to spread the expiries out.
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::clock::{Clock, SystemClock};
+
+/// Default maximum number of entries in a [`MemoryStore`].
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// Boxed future returned by [`CacheStore`] methods.
+pub type CacheFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Key of a cached validation: policy fingerprint and normalized hostname.
+///
+/// Displays as `<fingerprint as 16 hex digits>:<host>`, suitable as a key in
+/// external stores.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// [`Policy::fingerprint`](crate::Policy::fingerprint) or
+    /// [`CustomPolicy::fingerprint`](crate::CustomPolicy::fingerprint).
+    pub fingerprint: u64,
+
+    /// Normalized (lowercase, punycode) hostname.
+    pub host: String,
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}:{}", self.fingerprint, self.host)
+    }
+}
+
+/// A cached validation result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheEntry {
+    /// The validated address.
+    pub ip: IpAddr,
+
+    /// When the entry stops being fresh.
+    pub expires_at: SystemTime,
+}
+
+/// Storage backend for a [`ValidationCache`].
+///
+/// Methods return boxed futures so implementations can perform network I/O
+/// and the trait stays object-safe.
+///
+/// Stores may keep entries past `ttl`: freshness is decided by the cache from
+/// [`CacheEntry::expires_at`], and retained entries can still be served by
+/// [`DnsFallback::ServeStale`](crate::DnsFallback::ServeStale). Errors should
+/// be treated as misses (`get` returns `None`, `set` does nothing); the cache
+/// is an optimization and validation falls back to DNS.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use url_jail::{CacheEntry, CacheFuture, CacheKey, CacheStore};
+///
+/// /// A store that never caches anything.
+/// struct NoopStore;
+///
+/// impl CacheStore for NoopStore {
+///     fn get<'a>(&'a self, _key: &'a CacheKey) -> CacheFuture<'a, Option<CacheEntry>> {
+///         Box::pin(async { None })
+///     }
+///
+///     fn set<'a>(&'a self, _key: CacheKey, _entry: CacheEntry, _ttl: Duration) -> CacheFuture<'a, ()> {
+///         Box::pin(async {})
+///     }
+/// }
+/// ```
+pub trait CacheStore: Send + Sync {
+    /// Fetch the entry for `key`, fresh or not.
+    fn get<'a>(&'a self, key: &'a CacheKey) -> CacheFuture<'a, Option<CacheEntry>>;
+
+    /// Store `entry` under `key`; `ttl` is its freshness lifetime.
+    fn set<'a>(&'a self, key: CacheKey, entry: CacheEntry, ttl: Duration) -> CacheFuture<'a, ()>;
+}
+
+/// In-process [`CacheStore`] backed by a mutex-protected map.
+///
+/// Holds at most a fixed number of entries. When full, expired entries are
+/// dropped first (including ones still usable by
+/// [`DnsFallback::ServeStale`](crate::DnsFallback::ServeStale)), then the
+/// entry closest to expiry.
+pub struct MemoryStore {
+    max_entries: usize,
+    entries: Mutex<HashMap<CacheKey, CacheEntry>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl MemoryStore {
+    /// Create a store holding up to 10,000 entries.
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_MAX_ENTRIES)
+    }
+
+    /// Create a store holding at most `max_entries` entries.
+    pub fn with_capacity(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            entries: Mutex::new(HashMap::new()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Use `clock` to decide which entries have expired when evicting.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Number of entries, including expired ones not yet evicted.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if the store has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all entries.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<CacheKey, CacheEntry>> {
+        // A panic while holding the lock cannot leave an entry half-written
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MemoryStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryStore")
+            .field("max_entries", &self.max_entries)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn get<'a>(&'a self, key: &'a CacheKey) -> CacheFuture<'a, Option<CacheEntry>> {
+        let entry = self.lock().get(key).copied();
+        Box::pin(async move { entry })
+    }
+
+    fn set<'a>(&'a self, key: CacheKey, entry: CacheEntry, _ttl: Duration) -> CacheFuture<'a, ()> {
+        if self.max_entries > 0 {
+            let now = self.clock.now();
+            let mut entries = self.lock();
+            if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+                entries.retain(|_, entry| entry.expires_at > now);
+                if entries.len() >= self.max_entries {
+                    if let Some(oldest) = entries
+                        .iter()
+                        .min_by_key(|(_, entry)| entry.expires_at)
+                        .map(|(key, _)| key.clone())
+                    {
+                        entries.remove(&oldest);
+                    }
+                }
+            }
+            entries.insert(key, entry);
+        }
+        Box::pin(async {})
+    }
+}
+
+/// Shared cache of successful validations, partitioned by policy.
+///
+/// Pass it to validation through [`ValidateOptions::cache`](crate::ValidateOptions::cache).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use url_jail::{validate_with_options, Policy, ValidateOptions, ValidationCache};
+///
+/// # async fn example() -> Result<(), url_jail::Error> {
+/// let cache = Arc::new(ValidationCache::new(Duration::from_secs(30)));
+/// let opts = ValidateOptions {
+///     cache: Some(cache.clone()),
+///     ..Default::default()
+/// };
+/// let first = validate_with_options("https://example.com/", Policy::PublicOnly, opts.clone()).await?;
+/// // Served from the cache: same policy, same host
+/// let second = validate_with_options("https://example.com/other", Policy::PublicOnly, opts).await?;
+/// assert_eq!(first.ip, second.ip);
+/// # Ok(())
+/// # }
+/// ```
+pub struct ValidationCache {
+    ttl: Duration,
+    /// Floor for DNS answer TTLs, if entries follow them.
+    min_dns_ttl: Option<Duration>,
+    jitter: Duration,
+    random: RandomState,
+    store: Arc<dyn CacheStore>,
+    clock: Arc<dyn Clock>,
+}
+
+impl ValidationCache {
+    /// Create an in-memory cache whose entries expire after `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_store(ttl, Arc::new(MemoryStore::new()))
+    }
+
+    /// Create a cache backed by `store`, whose entries expire after `ttl`.
+    pub fn with_store(ttl: Duration, store: Arc<dyn CacheStore>) -> Self {
+        Self {
+            ttl,
+            min_dns_ttl: None,
+            jitter: Duration::ZERO,
+            random: RandomState::new(),
+            store,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Shorten each new entry's TTL by a random amount of up to `max_jitter`.
+    ///
+    /// Entries live between `ttl - max_jitter` (at least zero) and `ttl`, so
+    /// jitter never keeps a result fresh longer than the configured TTL.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use url_jail::ValidationCache;
+    ///
+    /// // Entries expire 50 to 60 seconds after they are written
+    /// let cache = ValidationCache::new(Duration::from_secs(60)).with_jitter(Duration::from_secs(10));
+    /// ```
+    pub fn with_jitter(mut self, max_jitter: Duration) -> Self {
+        self.jitter = max_jitter.min(self.ttl);
+        self
+    }
+
+    /// Expire entries with the DNS answer they came from, clamped to at
+    /// least `min` and at most the cache TTL.
+    ///
+    /// The floor bounds lookups for records with very short TTLs; keep it
+    /// small, since an entry can then outlive its record by up to `min`.
+    /// Answers with no known TTL, such as those from a custom
+    /// [`Resolve`](crate::Resolve), use the cache TTL. Jitter applies to the
+    /// clamped TTL.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use url_jail::ValidationCache;
+    ///
+    /// // Follow record TTLs, but cache between 1 second and 5 minutes
+    /// let cache = ValidationCache::new(Duration::from_secs(300)).with_dns_ttl(Duration::from_secs(1));
+    /// ```
+    pub fn with_dns_ttl(mut self, min: Duration) -> Self {
+        self.min_dns_ttl = Some(min.min(self.ttl));
+        self
+    }
+
+    /// Use `clock` for entry expiry instead of the system clock.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use url_jail::{ManualClock, ValidationCache};
+    ///
+    /// let clock = Arc::new(ManualClock::default());
+    /// let cache = ValidationCache::new(Duration::from_secs(30)).with_clock(clock.clone());
+    /// // ... validate, then expire every entry without sleeping:
+    /// clock.advance(Duration::from_secs(31));
+    /// ```
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The clock entry expiry is measured on.
+    pub(crate) fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    /// Time-to-live of new entries, before jitter.
+    ///
+    /// With [`with_dns_ttl`](Self::with_dns_ttl), the longest an entry lives.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// The floor set by [`with_dns_ttl`](Self::with_dns_ttl), if entries
+    /// follow DNS TTLs.
+    pub fn min_dns_ttl(&self) -> Option<Duration> {
+        self.min_dns_ttl
+    }
+
+    /// Maximum amount by which [`with_jitter`](Self::with_jitter) shortens
+    /// an entry's TTL.
+    pub fn jitter(&self) -> Duration {
+        self.jitter
+    }
+
+    /// The TTL for an entry for `key` written at `now`, from a DNS answer
+    /// valid for `dns_ttl`.
+    fn entry_ttl(&self, key: &CacheKey, now: SystemTime, dns_ttl: Option<Duration>) -> Duration {
+        let ttl = match (self.min_dns_ttl, dns_ttl) {
+            (Some(min), Some(dns_ttl)) => dns_ttl.clamp(min, self.ttl),
+            _ => self.ttl,
+        };
+        let jitter_nanos = self.jitter.min(ttl).as_nanos() as u64;
+        if jitter_nanos == 0 {
+            return ttl;
+        }
+        // The per-cache random seed makes processes disagree, spreading
+        // expiries across a fleet and not just across hosts
+        let sample = self.random.hash_one((&key.host, key.fingerprint, now));
+        ttl - Duration::from_nanos(sample % (jitter_nanos + 1))
+    }
+
+    /// Look up a fresh entry for `host` under the policy `fingerprint`.
+    pub(crate) async fn get(&self, fingerprint: u64, host: &str) -> Option<IpAddr> {
+        self.get_stale(fingerprint, host, Duration::ZERO).await
+    }
+
+    /// Look up an entry that expired at most `max_staleness` ago.
+    pub(crate) async fn get_stale(
+        &self,
+        fingerprint: u64,
+        host: &str,
+        max_staleness: Duration,
+    ) -> Option<IpAddr> {
+        let key = CacheKey {
+            fingerprint,
+            host: host.to_string(),
+        };
+        let entry = self.store.get(&key).await?;
+        (entry.expires_at + max_staleness > self.clock.now()).then_some(entry.ip)
+    }
+
+    /// Record that `host` validated to `ip` under the policy `fingerprint`,
+    /// with no known DNS TTL.
+    #[cfg(test)]
+    pub(crate) async fn insert(&self, fingerprint: u64, host: &str, ip: IpAddr) {
+        self.insert_answer(fingerprint, host, ip, None).await;
+    }
+
+    /// Record that `host` validated to `ip` under the policy `fingerprint`,
+    /// from a DNS answer valid for `dns_ttl`, if known.
+    pub(crate) async fn insert_answer(
+        &self,
+        fingerprint: u64,
+        host: &str,
+        ip: IpAddr,
+        dns_ttl: Option<Duration>,
+    ) {
+        let key = CacheKey {
+            fingerprint,
+            host: host.to_string(),
+        };
+        let now = self.clock.now();
+        let ttl = self.entry_ttl(&key, now, dns_ttl);
+        let entry = CacheEntry {
+            ip,
+            expires_at: now + ttl,
+        };
+        self.store.set(key, entry, ttl).await;
+    }
+}
+
+impl fmt::Debug for ValidationCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValidationCache")
+            .field("ttl", &self.ttl)
+            .field("min_dns_ttl", &self.min_dns_ttl)
+            .field("jitter", &self.jitter)
+            .finish_non_exhaustive()
+    }
+}
+
+/// 64-bit FNV-1a hash.
+///
+/// Used for policy fingerprints because, unlike `DefaultHasher`, its output
+/// is stable across processes and Rust versions.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn key(host: &str) -> CacheKey {
+        CacheKey {
+            fingerprint: 1,
+            host: host.to_string(),
+        }
+    }
+
+    fn entry(s: &str) -> CacheEntry {
+        CacheEntry {
+            ip: ip(s),
+            expires_at: SystemTime::now() + Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn test_fnv1a_known_values() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_cache_key_display() {
+        let key = CacheKey {
+            fingerprint: 0xab,
+            host: "example.com".to_string(),
+        };
+        assert_eq!(key.to_string(), "00000000000000ab:example.com");
+    }
+
+    #[tokio::test]
+    async fn test_get_after_insert() {
+        let cache = ValidationCache::new(Duration::from_secs(60));
+        cache.insert(1, "example.com", ip("93.184.215.14")).await;
+        assert_eq!(cache.get(1, "example.com").await, Some(ip("93.184.215.14")));
+        assert_eq!(cache.get(1, "other.com").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_partitioned_by_fingerprint() {
+        let cache = ValidationCache::new(Duration::from_secs(60));
+        cache.insert(1, "example.com", ip("10.0.0.1")).await;
+        assert_eq!(cache.get(2, "example.com").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_not_served() {
+        let cache = ValidationCache::new(Duration::ZERO);
+        cache.insert(1, "example.com", ip("93.184.215.14")).await;
+        assert_eq!(cache.get(1, "example.com").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_stale_entries_within_bound() {
+        let cache = ValidationCache::new(Duration::ZERO);
+        cache.insert(1, "example.com", ip("93.184.215.14")).await;
+        assert_eq!(
+            cache
+                .get_stale(1, "example.com", Duration::from_secs(60))
+                .await,
+            Some(ip("93.184.215.14"))
+        );
+        assert_eq!(
+            cache
+                .get_stale(2, "example.com", Duration::from_secs(60))
+                .await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_jittered_expiry_within_bounds() {
+        let clock = Arc::new(ManualClock::default());
+        let store = Arc::new(MemoryStore::new());
+        let cache = ValidationCache::with_store(Duration::from_secs(100), store.clone())
+            .with_jitter(Duration::from_secs(50))
+            .with_clock(clock.clone());
+        let now = clock.now();
+
+        let mut expiries = std::collections::HashSet::new();
+        for i in 0..50 {
+            let host = format!("host{}.example.com", i);
+            cache.insert(1, &host, ip("93.184.215.14")).await;
+            let expires_at = store.get(&key(&host)).await.unwrap().expires_at;
+            assert!(expires_at >= now + Duration::from_secs(50));
+            assert!(expires_at <= now + Duration::from_secs(100));
+            expiries.insert(expires_at);
+        }
+        assert!(
+            expiries.len() > 40,
+            "expiries not spread: {}",
+            expiries.len()
+        );
+    }
+
+    #[test]
+    fn test_jitter_capped_at_ttl() {
+        let cache =
+            ValidationCache::new(Duration::from_secs(5)).with_jitter(Duration::from_secs(60));
+        assert_eq!(cache.jitter(), Duration::from_secs(5));
+        let key = key("example.com");
+        assert!(cache.entry_ttl(&key, SystemTime::UNIX_EPOCH, None) <= Duration::from_secs(5));
+        assert_eq!(
+            ValidationCache::new(Duration::from_secs(5)).entry_ttl(
+                &key,
+                SystemTime::UNIX_EPOCH,
+                None
+            ),
+            Duration::from_secs(5)
+        );
+    }
+
+    #[test]
+    fn test_dns_ttl_clamped() {
+        let key = key("example.com");
+        let at = |cache: &ValidationCache, dns_ttl: Option<u64>| {
+            cache
+                .entry_ttl(
+                    &key,
+                    SystemTime::UNIX_EPOCH,
+                    dns_ttl.map(Duration::from_secs),
+                )
+                .as_secs()
+        };
+        let fixed = ValidationCache::new(Duration::from_secs(300));
+        assert_eq!(at(&fixed, Some(5)), 300);
+
+        let cache = fixed.with_dns_ttl(Duration::from_secs(2));
+        assert_eq!(cache.min_dns_ttl(), Some(Duration::from_secs(2)));
+        for (dns_ttl, expected) in [(Some(60), 60), (Some(0), 2), (Some(3600), 300), (None, 300)] {
+            assert_eq!(at(&cache, dns_ttl), expected, "{:?}", dns_ttl);
+        }
+
+        // Jitter never stretches a short record
+        let jittered = ValidationCache::new(Duration::from_secs(300))
+            .with_jitter(Duration::from_secs(60))
+            .with_dns_ttl(Duration::ZERO);
+        assert!(at(&jittered, Some(10)) <= 10);
+    }
+
+    #[tokio::test]
+    async fn test_dns_ttl_entry_expires_with_record() {
+        let clock = Arc::new(ManualClock::default());
+        let cache = ValidationCache::new(Duration::from_secs(300))
+            .with_dns_ttl(Duration::from_secs(1))
+            .with_clock(clock.clone());
+        cache
+            .insert_answer(
+                1,
+                "example.com",
+                ip("93.184.215.14"),
+                Some(Duration::from_secs(10)),
+            )
+            .await;
+        clock.advance(Duration::from_secs(9));
+        assert!(cache.get(1, "example.com").await.is_some());
+        clock.advance(Duration::from_secs(1));
+        assert!(cache.get(1, "example.com").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stale_entries_past_bound() {
+        let clock = Arc::new(ManualClock::default());
+        let cache = ValidationCache::new(Duration::ZERO).with_clock(clock.clone());
+        cache.insert(1, "example.com", ip("93.184.215.14")).await;
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(
+            cache
+                .get_stale(1, "example.com", Duration::from_secs(1))
+                .await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_entry_expires_with_clock() {
+        let clock = Arc::new(ManualClock::default());
+        let cache = ValidationCache::new(Duration::from_secs(30)).with_clock(clock.clone());
+        cache.insert(1, "example.com", ip("93.184.215.14")).await;
+
+        clock.advance(Duration::from_secs(29));
+        assert_eq!(cache.get(1, "example.com").await, Some(ip("93.184.215.14")));
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(cache.get(1, "example.com").await, None);
+        assert_eq!(
+            cache
+                .get_stale(1, "example.com", Duration::from_secs(10))
+                .await,
+            Some(ip("93.184.215.14"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_evicts_expired_by_clock() {
+        let clock = Arc::new(ManualClock::default());
+        let store = MemoryStore::with_capacity(2).with_clock(clock.clone());
+        let expiring = CacheEntry {
+            ip: ip("1.1.1.1"),
+            expires_at: clock.now() + Duration::from_secs(10),
+        };
+        store.set(key("a.com"), expiring, Duration::ZERO).await;
+        store
+            .set(key("b.com"), entry("1.1.1.2"), Duration::ZERO)
+            .await;
+
+        // a.com expires; inserting c.com evicts it rather than b.com
+        clock.advance(Duration::from_secs(11));
+        store
+            .set(key("c.com"), entry("1.1.1.3"), Duration::ZERO)
+            .await;
+        assert!(store.get(&key("a.com")).await.is_none());
+        assert!(store.get(&key("b.com")).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_custom_store() {
+        let store = Arc::new(MemoryStore::new());
+        let cache = ValidationCache::with_store(Duration::from_secs(60), store.clone());
+        cache.insert(7, "example.com", ip("93.184.215.14")).await;
+
+        let stored = store
+            .get(&CacheKey {
+                fingerprint: 7,
+                host: "example.com".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(stored.ip, ip("93.184.215.14"));
+        assert!(stored.expires_at > SystemTime::now());
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_capacity_evicts() {
+        let store = MemoryStore::with_capacity(2);
+        store
+            .set(key("a.com"), entry("1.1.1.1"), Duration::ZERO)
+            .await;
+        store
+            .set(key("b.com"), entry("1.1.1.2"), Duration::ZERO)
+            .await;
+        store
+            .set(key("c.com"), entry("1.1.1.3"), Duration::ZERO)
+            .await;
+        assert_eq!(store.len(), 2);
+        assert_eq!(
+            store.get(&key("c.com")).await.map(|e| e.ip),
+            Some(ip("1.1.1.3"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_zero_capacity_caches_nothing() {
+        let store = MemoryStore::with_capacity(0);
+        store
+            .set(key("a.com"), entry("1.1.1.1"), Duration::ZERO)
+            .await;
+        assert!(store.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_clear() {
+        let store = MemoryStore::new();
+        store
+            .set(key("a.com"), entry("1.1.1.1"), Duration::ZERO)
+            .await;
+        store.clear();
+        assert!(store.is_empty());
+    }
+}
@@ -0,0 +1,270 @@
+//! Pluggable IP address classification.
+//!
+//! Built-in policies decide by address class:
+//! [`Policy::PublicOnly`](crate::Policy::PublicOnly) blocks everything but
+//! [`IpClass::Public`], and
+//! [`Policy::AllowPrivate`](crate::Policy::AllowPrivate) also permits
+//! [`IpClass::Private`]. [`DefaultClassifier`] is the built-in
+//! classification; an [`IpClassifier`] installed with
+//! [`PolicyBuilder::classifier`](crate::PolicyBuilder::classifier) can extend
+//! it, for example to treat a VPN range as private.
+//!
+//! A custom classifier can only make an address stricter: an address is
+//! blocked if the base policy blocks either its default class or the class
+//! the classifier reports, so loopback reported as public stays blocked. Use
+//! [`allow_cidr`](crate::PolicyBuilder::allow_cidr) to permit a range.
+
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use crate::base_policy::BasePolicy;
+use crate::blocklist::{classify_ip, unwrap_ipv4};
+
+pub use url_jail_core::IpClass;
+
+/// Decides which [`IpClass`] an address belongs to.
+///
+/// A policy passes IPv4-mapped and IPv4-compatible IPv6 addresses to the
+/// classifier as their embedded IPv4 address, so IPv4 ranges need no
+/// special handling.
+///
+/// # Example
+///
+/// ```rust
+/// use std::net::IpAddr;
+///
+/// use ipnet::IpNet;
+/// use url_jail::{DefaultClassifier, IpClass, IpClassifier, Policy, PolicyBuilder};
+///
+/// struct VpnIsPrivate(IpNet);
+///
+/// impl IpClassifier for VpnIsPrivate {
+///     fn classify(&self, ip: IpAddr) -> IpClass {
+///         if self.0.contains(&ip) {
+///             IpClass::Private
+///         } else {
+///             DefaultClassifier.classify(ip)
+///         }
+///     }
+/// }
+///
+/// let vpn = VpnIsPrivate("44.0.0.0/16".parse().unwrap());
+/// let policy = PolicyBuilder::new(Policy::PublicOnly).classifier(vpn).build();
+/// assert!(policy.is_ip_allowed("44.0.1.2".parse().unwrap()).is_err());
+/// assert!(policy.is_ip_allowed("::ffff:44.0.1.2".parse().unwrap()).is_err());
+/// assert!(policy.is_ip_allowed("93.184.216.34".parse().unwrap()).is_ok());
+/// ```
+pub trait IpClassifier: Send + Sync {
+    /// Classify `ip`.
+    fn classify(&self, ip: IpAddr) -> IpClass;
+
+    /// Identifies this classifier in policy
+    /// [fingerprints](crate::CustomPolicy::fingerprint) and `Debug` output.
+    ///
+    /// Defaults to the type name. Override it if one type can be configured
+    /// to classify differently, so that differently configured policies do
+    /// not share cache entries.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// The built-in classification used by [`Policy`](crate::Policy).
+///
+/// IPv4-mapped and IPv4-compatible IPv6 addresses are classified by their
+/// embedded IPv4 address.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultClassifier;
+
+impl IpClassifier for DefaultClassifier {
+    fn classify(&self, ip: IpAddr) -> IpClass {
+        classify_ip(ip).map_or(IpClass::Public, |(class, _)| class)
+    }
+}
+
+/// A custom classifier held by a [`CustomPolicy`](crate::CustomPolicy).
+#[derive(Clone)]
+pub(crate) struct SharedClassifier(Arc<dyn IpClassifier>);
+
+impl SharedClassifier {
+    pub(crate) fn new(classifier: impl IpClassifier + 'static) -> Self {
+        Self(Arc::new(classifier))
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    /// The base-policy block reason for `ip`, if `policy` blocks it.
+    ///
+    /// `ip` is blocked if `base` blocks its default class or the class this
+    /// classifier reports; the default class's reason comes first.
+    pub(crate) fn blocked(&self, ip: IpAddr, base: BasePolicy) -> Option<&'static str> {
+        if let Some((class, reason)) = classify_ip(ip) {
+            if base.blocks(class) {
+                return Some(reason);
+            }
+        }
+        // Custom classifiers are usually written for IPv4 ranges; a mapped
+        // or compatible form of the address must not slip past them
+        let custom = self.0.classify(unwrap_ipv4(ip));
+        base.blocks(custom).then(|| custom_reason(custom))
+    }
+}
+
+/// Block reason for an address a custom classifier put in this class.
+fn custom_reason(class: IpClass) -> &'static str {
+    match class {
+        IpClass::Unspecified => {
+            "classified as unspecified by custom IpClassifier - blocked in all policies"
+        }
+        IpClass::Loopback => {
+            "classified as loopback by custom IpClassifier - blocked in all policies"
+        }
+        IpClass::LinkLocal => {
+            "classified as link-local by custom IpClassifier - blocked in all policies"
+        }
+        IpClass::Metadata => {
+            "classified as cloud metadata by custom IpClassifier - blocked in all policies"
+        }
+        IpClass::Private => {
+            "classified as private by custom IpClassifier - use AllowPrivate policy to permit"
+        }
+        IpClass::Shared => "classified as shared address space by custom IpClassifier",
+        IpClass::Public => "classified as public by custom IpClassifier",
+        _ => "classified by custom IpClassifier",
+    }
+}
+
+impl fmt::Debug for SharedClassifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "IpClassifier({})", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Policy, PolicyBuilder};
+
+    struct Fixed(IpClass);
+
+    impl IpClassifier for Fixed {
+        fn classify(&self, _ip: IpAddr) -> IpClass {
+            self.0
+        }
+
+        fn name(&self) -> &str {
+            self.0.as_str()
+        }
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_default_classification() {
+        for (addr, class) in [
+            ("0.0.0.0", IpClass::Unspecified),
+            ("127.0.0.1", IpClass::Loopback),
+            ("::1", IpClass::Loopback),
+            ("169.254.1.1", IpClass::LinkLocal),
+            ("fe80::1", IpClass::LinkLocal),
+            ("169.254.169.254", IpClass::Metadata),
+            ("100.100.100.200", IpClass::Metadata),
+            ("fd00:ec2::254", IpClass::Metadata),
+            ("10.0.0.1", IpClass::Private),
+            ("::ffff:192.168.1.1", IpClass::Private),
+            ("fc00::1", IpClass::Private),
+            ("100.64.0.1", IpClass::Shared),
+            ("100.127.255.254", IpClass::Shared),
+            ("::ffff:100.100.1.1", IpClass::Shared),
+            ("100.128.0.1", IpClass::Public),
+            ("93.184.216.34", IpClass::Public),
+            ("2606:4700::1111", IpClass::Public),
+        ] {
+            assert_eq!(DefaultClassifier.classify(ip(addr)), class, "{}", addr);
+        }
+    }
+
+    #[test]
+    fn test_custom_classifier_can_only_tighten() {
+        let private = PolicyBuilder::new(Policy::PublicOnly)
+            .classifier(Fixed(IpClass::Private))
+            .build();
+        let err = private
+            .is_ip_allowed(ip("93.184.216.34"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("custom IpClassifier"), "{}", err);
+
+        // Loopback cannot be reclassified as public
+        let public = PolicyBuilder::new(Policy::AllowPrivate)
+            .classifier(Fixed(IpClass::Public))
+            .build();
+        let err = public
+            .is_ip_allowed(ip("127.0.0.1"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("loopback address"), "{}", err);
+        assert!(public.is_ip_allowed(ip("10.0.0.1")).is_ok());
+
+        // AllowPrivate still permits what the classifier marks private
+        let relaxed = PolicyBuilder::new(Policy::AllowPrivate)
+            .classifier(Fixed(IpClass::Private))
+            .build();
+        assert!(relaxed.is_ip_allowed(ip("93.184.216.34")).is_ok());
+        assert!(relaxed.is_ip_allowed(ip("169.254.169.254")).is_err());
+    }
+
+    #[test]
+    fn test_custom_classifier_cannot_escape_class_flags() {
+        use crate::BasePolicy;
+
+        // Private blocked, link-local not: private reported as link-local
+        let base = BasePolicy::builder()
+            .block_private(true)
+            .block_link_local(false)
+            .build();
+        let policy = PolicyBuilder::new(base)
+            .classifier(Fixed(IpClass::LinkLocal))
+            .build();
+        let err = policy.is_ip_allowed(ip("10.0.0.1")).unwrap_err();
+        assert!(!err.to_string().contains("custom IpClassifier"), "{}", err);
+        assert!(policy.is_ip_allowed(ip("93.184.216.34")).is_ok());
+
+        // Shared blocked, private not: shared reported as private
+        let base = BasePolicy::builder()
+            .block_shared(true)
+            .block_private(false)
+            .build();
+        let policy = PolicyBuilder::new(base)
+            .classifier(Fixed(IpClass::Private))
+            .build();
+        assert!(policy.is_ip_allowed(ip("100.64.0.1")).is_err());
+        assert!(policy.is_ip_allowed(ip("10.0.0.1")).is_ok());
+
+        // A class the base blocks still blocks through the classifier
+        let policy = PolicyBuilder::new(base)
+            .classifier(Fixed(IpClass::Shared))
+            .build();
+        let err = policy.is_ip_allowed(ip("10.0.0.1")).unwrap_err();
+        assert!(err.to_string().contains("custom IpClassifier"), "{}", err);
+    }
+
+    #[test]
+    fn test_classifier_changes_fingerprint() {
+        let plain = PolicyBuilder::new(Policy::PublicOnly).build();
+        let private = PolicyBuilder::new(Policy::PublicOnly)
+            .classifier(Fixed(IpClass::Private))
+            .build();
+        let metadata = PolicyBuilder::new(Policy::PublicOnly)
+            .classifier(Fixed(IpClass::Metadata))
+            .build();
+        assert_ne!(plain.fingerprint(), private.fingerprint());
+        assert_ne!(private.fingerprint(), metadata.fingerprint());
+        assert!(format!("{:?}", private).contains("IpClassifier(private)"));
+    }
+}
@@ -0,0 +1,257 @@
+//! Pinned reqwest clients for validated URLs.
+//!
+//! These helpers bridge a [`Validated`] result into a `reqwest` client whose
+//! DNS resolution is overridden to the validated address, closing the gap
+//! between validation and connection (DNS rebinding). [`SafeClient`] does
+//! both steps for every request.
+//!
+//! ## Security
+//!
+//! Pinned clients never follow redirects. A redirect would be resolved by
+//! reqwest's own DNS lookup and could change hosts, bypassing validation.
+//! Use [`fetch()`](crate::fetch) to follow redirects with per-hop validation.
+
+use std::time::Duration;
+
+use reqwest::redirect::Policy as RedirectPolicy;
+use reqwest::{Client, ClientBuilder, Method, RequestBuilder, Response};
+
+use crate::error::Error;
+use crate::policy::{Policy, ValidationPolicy};
+use crate::validate::{validate_with_options, ValidateOptions, Validated};
+
+/// Create a `reqwest` client builder pinned to a validated address.
+///
+/// The builder resolves the validated hostname to the validated IPs (see
+/// [`Validated::resolve_to_addrs`]) and has redirects disabled. Further configuration (timeouts, headers, TLS) can be
+/// applied before calling `build()`; do not re-enable redirects.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use url_jail::{pinned_client_builder, validate, Policy};
+/// use std::time::Duration;
+///
+/// let v = validate("https://example.com/api", Policy::PublicOnly).await?;
+/// let client = pinned_client_builder(&v)
+///     .timeout(Duration::from_secs(10))
+///     .build()?;
+/// let response = client.get(&v.url).send().await?;
+/// ```
+pub fn pinned_client_builder(validated: &Validated) -> ClientBuilder {
+    let (domain, addrs) = validated.resolve_to_addrs();
+    Client::builder()
+        .redirect(RedirectPolicy::none())
+        .resolve_to_addrs(domain, &addrs)
+}
+
+/// Create a one-shot `reqwest` client pinned to a validated address.
+///
+/// See [`pinned_client_builder`] for details.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use url_jail::{pinned_client, validate, Policy};
+///
+/// let v = validate("https://example.com/api", Policy::PublicOnly).await?;
+/// let response = pinned_client(&v)?.get(&v.url).send().await?;
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::HttpError`] if the client cannot be built.
+pub fn pinned_client(validated: &Validated) -> Result<Client, Error> {
+    pinned_client_builder(validated)
+        .build()
+        .map_err(|e| Error::HttpError {
+            url: validated.url.clone(),
+            message: e.to_string(),
+        })
+}
+
+/// An HTTP client that validates every URL and connects only to the
+/// validated address.
+///
+/// Each request validates its URL against the policy, then sends it through
+/// a client pinned to the validated IP, so no second DNS lookup happens at
+/// connect time. The URL's hostname is kept, so the `Host` header and TLS
+/// SNI (and certificate checks) are those of the original host.
+///
+/// Redirects are not followed; use [`fetch()`](crate::fetch) for per-hop
+/// validation. Each request uses a fresh connection.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use url_jail::{Policy, PolicyBuilder, SafeClient};
+///
+/// # async fn example() -> Result<(), url_jail::Error> {
+/// let client = SafeClient::new(Policy::PublicOnly).with_timeout(Duration::from_secs(10));
+/// let response = client.get("https://example.com/api").await?;
+///
+/// // Any ValidationPolicy works, and requests can be customized before sending
+/// let custom = PolicyBuilder::new(Policy::PublicOnly).block_host("*.corp.example").build();
+/// let client = SafeClient::new(custom);
+/// let (validated, request) = client.request(reqwest::Method::POST, "https://example.com/hook").await?;
+/// let response = request.body("{}").send().await;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SafeClient<P: ValidationPolicy = Policy> {
+    policy: P,
+    options: ValidateOptions,
+    timeout: Option<Duration>,
+}
+
+impl<P: ValidationPolicy> SafeClient<P> {
+    /// Create a client validating against `policy`.
+    pub fn new(policy: P) -> Self {
+        Self {
+            policy,
+            options: ValidateOptions::default(),
+            timeout: None,
+        }
+    }
+
+    /// Use `options` (DNS timeout, cache, ...) for validation.
+    pub fn with_options(mut self, options: ValidateOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set a total timeout for each HTTP request, after validation.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The policy requests are validated against.
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    /// Validate `url` and return a pinned request for it, to add headers or
+    /// a body before sending.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`validate`](crate::validate), or
+    /// [`Error::HttpError`] if the client cannot be built.
+    pub async fn request(
+        &self,
+        method: Method,
+        url: &str,
+    ) -> Result<(Validated, RequestBuilder), Error> {
+        let validated = validate_with_options(url, &self.policy, self.options.clone()).await?;
+        let mut builder = pinned_client_builder(&validated);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let client = builder.build().map_err(|e| Error::HttpError {
+            url: validated.url.clone(),
+            message: e.to_string(),
+        })?;
+        let request = client.request(method, &validated.url);
+        Ok((validated, request))
+    }
+
+    /// Validate `url` and send a `GET` request to the validated address.
+    ///
+    /// # Errors
+    ///
+    /// As [`request`](Self::request), plus [`Error::HttpError`] if the
+    /// request fails.
+    pub async fn get(&self, url: &str) -> Result<Response, Error> {
+        let (validated, request) = self.request(Method::GET, url).await?;
+        request.send().await.map_err(|e| Error::HttpError {
+            url: validated.url,
+            message: e.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{validate, Policy};
+
+    #[tokio::test]
+    async fn test_pinned_client_builds() {
+        let v = validate("http://10.0.0.1:8080/", Policy::AllowPrivate)
+            .await
+            .unwrap();
+        assert!(pinned_client(&v).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_pinned_client_connects_to_validated_ip() {
+        // Pin a hostname to a closed local port: the request must fail to
+        // connect rather than resolving the hostname via DNS.
+        let v = Validated {
+            ip: "127.0.0.1".parse().unwrap(),
+            ips: vec!["127.0.0.1".parse().unwrap()],
+            host: "pinned.invalid".to_string(),
+            port: 9,
+            url: "http://pinned.invalid:9/".to_string(),
+            https: false,
+            valid_until: None,
+            clock: Default::default(),
+        };
+        let err = pinned_client(&v)
+            .unwrap()
+            .get(&v.url)
+            .send()
+            .await
+            .unwrap_err();
+        assert!(err.is_connect(), "expected connect error, got {:?}", err);
+        assert!(
+            !format!("{:?}", err).contains("dns error"),
+            "hostname must not be resolved: {:?}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_safe_client_blocks_before_connecting() {
+        let client = SafeClient::new(Policy::PublicOnly);
+        let err = client.get("http://127.0.0.1:9/").await.unwrap_err();
+        assert!(err.is_blocked(), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_safe_client_connects_to_validated_ip() {
+        use crate::policy_builder::PolicyBuilder;
+        use crate::ValidationCache;
+        use std::sync::Arc;
+
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_cidr("127.0.0.1/32")
+            .build();
+        let cache = Arc::new(ValidationCache::new(Duration::from_secs(60)));
+        cache
+            .insert(
+                policy.fingerprint(),
+                "pinned.invalid",
+                "127.0.0.1".parse().unwrap(),
+            )
+            .await;
+        let client = SafeClient::new(&policy).with_options(ValidateOptions {
+            cache: Some(cache),
+            ..Default::default()
+        });
+
+        let (validated, _) = client
+            .request(Method::GET, "http://pinned.invalid:9/")
+            .await
+            .unwrap();
+        assert_eq!(validated.ip.to_string(), "127.0.0.1");
+
+        // The hostname does not resolve: a connect error proves pinning
+        let err = client.get("http://pinned.invalid:9/").await.unwrap_err();
+        assert!(matches!(err, Error::HttpError { .. }), "{:?}", err);
+        assert!(!err.to_string().contains("dns error"), "{}", err);
+    }
+}
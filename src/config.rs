@@ -0,0 +1,603 @@
+//! Policies as reviewable configuration.
+//!
+//! A [`PolicyConfig`] is the plain-data form of a
+//! [`CustomPolicy`](crate::CustomPolicy): the base policy plus lists of rules
+//! as strings, so a security team can review it in a config file rather than
+//! in code. [`PolicyConfig::build`] checks every rule strictly, unlike
+//! [`PolicyBuilder::build`](crate::PolicyBuilder::build), which skips
+//! unparseable CIDRs.
+//!
+//! With the `serde` feature, [`Policy`], [`BasePolicy`], [`PolicyConfig`] and
+//! [`CustomPolicy`](crate::CustomPolicy) implement `Serialize` and
+//! `Deserialize`, so any serde format loads a policy directly:
+//!
+//! ```toml
+//! base = "public_only"
+//! block_hosts = ["*.corp.example", { rule = "*.db.example", note = "Use the data API" }]
+//! allow_hosts = [{ rule = "wiki.corp.example", until = 1767225600 }]
+//! allow_cidrs = ["10.20.0.0/16"]
+//! block_ports = [25, 6379]
+//! allow_schemes = ["https"]
+//! ```
+//!
+//! ```rust,ignore
+//! let policy: CustomPolicy = toml::from_str(&std::fs::read_to_string("egress.toml")?)?;
+//! ```
+//!
+//! A flags base is written as a table of `block_*` booleans matching the
+//! [`BasePolicyBuilder`](crate::BasePolicyBuilder) methods; omitted flags
+//! stay blocked.
+//!
+//! # Round trips
+//!
+//! Building a config and converting the policy back with
+//! [`CustomPolicy::to_config`](crate::CustomPolicy::to_config) yields an
+//! equal config (after rules are normalized: lowercased hosts, canonical
+//! CIDRs). Converting a policy to config and back yields a policy with the
+//! same [`fingerprint`](crate::CustomPolicy::fingerprint), provided its
+//! `allow_host_until` deadlines are whole seconds.
+
+use std::time::{Duration, SystemTime};
+
+use crate::base_policy::BasePolicy;
+use crate::policy_builder::{self, CustomPolicy, PolicyBuilder};
+use crate::safe_url::SafeUrl;
+
+/// Plain-data form of a [`CustomPolicy`].
+///
+/// # Example
+///
+/// ```rust
+/// use url_jail::{Policy, PolicyConfig, RuleConfig};
+///
+/// let config = PolicyConfig {
+///     base: Policy::AllowPrivate.into(),
+///     block_cidrs: vec!["10.0.0.0/8".into()],
+///     allow_hosts: vec![RuleConfig::from("build.corp.example")],
+///     ..Default::default()
+/// };
+/// let policy = config.build().unwrap();
+/// assert!(policy.is_ip_allowed("10.1.2.3".parse().unwrap()).is_err());
+/// assert_eq!(policy.to_config().unwrap(), config);
+///
+/// let bad = PolicyConfig {
+///     block_cidrs: vec!["10.0.0/8".into()],
+///     ..Default::default()
+/// };
+/// assert!(bad.build().unwrap_err().starts_with("block_cidrs[0]: invalid CIDR"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct PolicyConfig {
+    /// The base policy.
+    pub base: BasePolicy,
+
+    /// CIDRs to block; a rule may carry a `note`.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub block_cidrs: Vec<RuleConfig>,
+
+    /// CIDRs to allow.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub allow_cidrs: Vec<RuleConfig>,
+
+    /// Hostname patterns to block; a rule may carry a `note`.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub block_hosts: Vec<RuleConfig>,
+
+    /// Hostname patterns to allow; a rule may carry an `until` deadline.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub allow_hosts: Vec<RuleConfig>,
+
+    /// See [`PolicyBuilder::allow_ports`].
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub allow_ports: Vec<u16>,
+
+    /// See [`PolicyBuilder::block_ports`].
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub block_ports: Vec<u16>,
+
+    /// See [`PolicyBuilder::allow_schemes`].
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub allow_schemes: Vec<String>,
+
+    /// See [`PolicyBuilder::allow_url_prefix`].
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub allow_url_prefixes: Vec<String>,
+
+    /// See [`PolicyBuilder::block_path_glob`].
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub block_paths: Vec<String>,
+
+    /// Registrable domains to restrict hosts to, with the `psl` feature;
+    /// see `PolicyBuilder::allow_sites`.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub allow_sites: Vec<String>,
+}
+
+/// One rule in a [`PolicyConfig`]: a bare string, or a table with a note
+/// or deadline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged, deny_unknown_fields))]
+pub enum RuleConfig {
+    /// A CIDR or hostname pattern.
+    Rule(String),
+
+    /// A rule with extras.
+    Detailed {
+        /// The CIDR or hostname pattern.
+        rule: String,
+
+        /// Remediation note, for block rules; see
+        /// [`PolicyBuilder::block_cidr_with_note`].
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        note: Option<String>,
+
+        /// Deadline in Unix seconds, for `allow_hosts`; see
+        /// [`PolicyBuilder::allow_host_until`].
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        until: Option<u64>,
+    },
+}
+
+impl RuleConfig {
+    /// The CIDR or hostname pattern.
+    pub fn rule(&self) -> &str {
+        match self {
+            RuleConfig::Rule(rule) | RuleConfig::Detailed { rule, .. } => rule,
+        }
+    }
+
+    fn note(&self) -> Option<&str> {
+        match self {
+            RuleConfig::Rule(_) => None,
+            RuleConfig::Detailed { note, .. } => note.as_deref(),
+        }
+    }
+
+    fn until(&self) -> Option<u64> {
+        match self {
+            RuleConfig::Rule(_) => None,
+            RuleConfig::Detailed { until, .. } => *until,
+        }
+    }
+
+    /// The simplest form of a rule with these parts.
+    pub(crate) fn new(rule: String, note: Option<String>, until: Option<u64>) -> Self {
+        if note.is_none() && until.is_none() {
+            RuleConfig::Rule(rule)
+        } else {
+            RuleConfig::Detailed { rule, note, until }
+        }
+    }
+}
+
+impl From<&str> for RuleConfig {
+    fn from(rule: &str) -> Self {
+        RuleConfig::Rule(rule.to_string())
+    }
+}
+
+impl From<String> for RuleConfig {
+    fn from(rule: String) -> Self {
+        RuleConfig::Rule(rule)
+    }
+}
+
+impl PolicyConfig {
+    /// Build the policy, checking every rule.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first invalid rule, prefixed with its
+    /// list and index (`block_cidrs[2]: invalid CIDR "10.0.0/8": ...`).
+    pub fn build(&self) -> Result<CustomPolicy, String> {
+        let mut builder = PolicyBuilder::new(self.base);
+
+        for (i, rule) in self.block_cidrs.iter().enumerate() {
+            let at = |e: String| format!("block_cidrs[{}]: {}", i, e);
+            check_rule(rule, false).map_err(at)?;
+            let cidr = parse_cidr(rule.rule()).map_err(at)?;
+            builder = match rule.note() {
+                Some(note) => builder.block_cidr_with_note(&cidr, note),
+                None => builder.block_cidr(&cidr),
+            };
+        }
+        for (i, rule) in self.allow_cidrs.iter().enumerate() {
+            let at = |e: String| format!("allow_cidrs[{}]: {}", i, e);
+            if rule.note().is_some() {
+                return Err(at("notes are only supported on block rules".to_string()));
+            }
+            check_rule(rule, false).map_err(at)?;
+            builder = builder.allow_cidr(&parse_cidr(rule.rule()).map_err(at)?);
+        }
+        for (i, rule) in self.block_hosts.iter().enumerate() {
+            let at = |e: String| format!("block_hosts[{}]: {}", i, e);
+            check_rule(rule, false).map_err(at)?;
+            check_pattern(rule.rule()).map_err(at)?;
+            builder = match rule.note() {
+                Some(note) => builder.block_host_with_note(rule.rule(), note),
+                None => builder.block_host(rule.rule()),
+            };
+        }
+        for (i, rule) in self.allow_hosts.iter().enumerate() {
+            let at = |e: String| format!("allow_hosts[{}]: {}", i, e);
+            if rule.note().is_some() {
+                return Err(at("notes are only supported on block rules".to_string()));
+            }
+            check_rule(rule, true).map_err(at)?;
+            check_pattern(rule.rule()).map_err(at)?;
+            builder = match rule.until() {
+                Some(secs) => builder.allow_host_until(
+                    rule.rule(),
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+                ),
+                None => builder.allow_host(rule.rule()),
+            };
+        }
+        for (i, scheme) in self.allow_schemes.iter().enumerate() {
+            if !matches!(scheme.to_ascii_lowercase().as_str(), "http" | "https") {
+                return Err(format!(
+                    "allow_schemes[{}]: unsupported scheme {:?} (only http and https)",
+                    i, scheme
+                ));
+            }
+        }
+
+        for (i, prefix) in self.allow_url_prefixes.iter().enumerate() {
+            SafeUrl::parse(prefix).map_err(|e| format!("allow_url_prefixes[{}]: {}", i, e))?;
+            builder = builder.allow_url_prefix(prefix);
+        }
+
+        for (i, glob) in self.block_paths.iter().enumerate() {
+            if !glob.starts_with(['/', '*']) {
+                return Err(format!(
+                    "block_paths[{}]: path glob {:?} must start with '/' or '*'",
+                    i, glob
+                ));
+            }
+            builder = builder.block_path_glob(glob);
+        }
+
+        #[cfg(feature = "psl")]
+        for (i, site) in self.allow_sites.iter().enumerate() {
+            crate::site::check_site(&site.to_lowercase())
+                .map_err(|e| format!("allow_sites[{}]: {:?}: {}", i, site, e))?;
+            builder = builder.allow_sites([site]);
+        }
+        #[cfg(not(feature = "psl"))]
+        if !self.allow_sites.is_empty() {
+            return Err("allow_sites: requires the psl feature".to_string());
+        }
+
+        Ok(builder
+            .allow_ports(self.allow_ports.iter().copied())
+            .block_ports(self.block_ports.iter().copied())
+            .allow_schemes(&self.allow_schemes)
+            .build())
+    }
+}
+
+fn check_rule(rule: &RuleConfig, until_allowed: bool) -> Result<(), String> {
+    if rule.until().is_some() && !until_allowed {
+        return Err("deadlines are only supported on allow_hosts rules".to_string());
+    }
+    Ok(())
+}
+
+/// Parse a CIDR strictly, returning it in canonical form.
+fn parse_cidr(cidr: &str) -> Result<String, String> {
+    policy_builder::parse_cidr(cidr)
+        .map(|net| net.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn check_pattern(pattern: &str) -> Result<(), String> {
+    policy_builder::check_host_pattern(pattern).map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "serde")]
+mod impls {
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::*;
+    use crate::policy::Policy;
+
+    /// Serialized form of a [`BasePolicy`]: a built-in policy name or flags.
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum BaseRepr {
+        Policy(Policy),
+        Flags(Flags),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(default, deny_unknown_fields)]
+    struct Flags {
+        block_unspecified: bool,
+        block_loopback: bool,
+        block_link_local: bool,
+        block_metadata: bool,
+        block_private: bool,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        block_shared: bool,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        allowlist_only: bool,
+    }
+
+    impl Default for Flags {
+        fn default() -> Self {
+            Flags {
+                block_unspecified: true,
+                block_loopback: true,
+                block_link_local: true,
+                block_metadata: true,
+                block_private: true,
+                block_shared: false,
+                allowlist_only: false,
+            }
+        }
+    }
+
+    impl Serialize for BasePolicy {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            use crate::classify::IpClass;
+
+            let repr = match self.as_policy() {
+                Some(policy) => BaseRepr::Policy(policy),
+                None => BaseRepr::Flags(Flags {
+                    block_unspecified: self.blocks(IpClass::Unspecified),
+                    block_loopback: self.blocks(IpClass::Loopback),
+                    block_link_local: self.blocks(IpClass::LinkLocal),
+                    block_metadata: self.blocks(IpClass::Metadata),
+                    block_private: self.blocks(IpClass::Private),
+                    block_shared: self.blocks(IpClass::Shared),
+                    allowlist_only: self.is_allowlist_only(),
+                }),
+            };
+            repr.serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for BasePolicy {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Ok(match BaseRepr::deserialize(deserializer)? {
+                BaseRepr::Policy(policy) => policy.into(),
+                BaseRepr::Flags(flags) => BasePolicy::builder()
+                    .block_unspecified(flags.block_unspecified)
+                    .block_loopback(flags.block_loopback)
+                    .block_link_local(flags.block_link_local)
+                    .block_metadata(flags.block_metadata)
+                    .block_private(flags.block_private)
+                    .block_shared(flags.block_shared)
+                    .allowlist_only(flags.allowlist_only)
+                    .build(),
+            })
+        }
+    }
+
+    impl Serialize for CustomPolicy {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.to_config()
+                .map_err(serde::ser::Error::custom)?
+                .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for CustomPolicy {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            PolicyConfig::deserialize(deserializer)?
+                .build()
+                .map_err(de::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::Policy;
+
+    fn rejects(config: PolicyConfig, expected: &str) {
+        let err = config.build().unwrap_err();
+        assert!(err.starts_with(expected), "{}", err);
+    }
+
+    #[test]
+    fn test_config_round_trip() {
+        let config = PolicyConfig {
+            base: BasePolicy::builder().block_loopback(false).build(),
+            block_cidrs: vec![
+                "10.0.0.0/8".into(),
+                RuleConfig::new("192.168.0.0/16".into(), Some("Use the VPN".into()), None),
+            ],
+            allow_cidrs: vec!["10.20.0.0/16".into()],
+            block_hosts: vec!["*.corp.example".into(), "cache.example:6379".into()],
+            allow_hosts: vec![RuleConfig::new(
+                "wiki.corp.example".into(),
+                None,
+                Some(1_767_225_600),
+            )],
+            allow_ports: vec![443, 8443],
+            block_ports: vec![25],
+            allow_schemes: vec!["https".into()],
+            allow_url_prefixes: vec!["https://api.partner.example/v1/".into()],
+            block_paths: vec!["*/admin/*".into()],
+            allow_sites: Vec::new(),
+        };
+        let policy = config.build().unwrap();
+        assert_eq!(policy.to_config().unwrap(), config);
+        assert_eq!(
+            policy.to_config().unwrap().build().unwrap().fingerprint(),
+            policy.fingerprint()
+        );
+        assert!(policy
+            .is_ip_allowed("192.168.1.1".parse().unwrap())
+            .unwrap_err()
+            .to_string()
+            .ends_with("(hint: Use the VPN)"));
+    }
+
+    #[cfg(feature = "psl")]
+    #[test]
+    fn test_site_rules() {
+        let config = PolicyConfig {
+            block_hosts: vec!["site:example.co.uk".into()],
+            allow_sites: vec!["example.com".into(), "example.co.uk".into()],
+            ..Default::default()
+        };
+        let policy = config.build().unwrap();
+        assert_eq!(policy.to_config().unwrap(), config);
+        assert!(policy.is_hostname_allowed("www.example.com").is_ok());
+        assert!(policy.is_hostname_allowed("www.example.co.uk").is_err());
+
+        rejects(
+            PolicyConfig {
+                allow_sites: vec!["example.com".into(), "co.uk".into()],
+                ..Default::default()
+            },
+            "allow_sites[1]: \"co.uk\": not a registrable domain",
+        );
+    }
+
+    #[cfg(not(feature = "psl"))]
+    #[test]
+    fn test_site_rules_require_psl() {
+        rejects(
+            PolicyConfig {
+                allow_sites: vec!["example.com".into()],
+                ..Default::default()
+            },
+            "allow_sites: requires the psl feature",
+        );
+    }
+
+    #[test]
+    fn test_build_normalizes_rules() {
+        let config = PolicyConfig {
+            block_cidrs: vec!["FE80:0:0::/10".into()],
+            block_hosts: vec!["API.Example.COM".into()],
+            ..Default::default()
+        };
+        let normalized = config.build().unwrap().to_config().unwrap();
+        assert_eq!(normalized.block_cidrs, vec!["fe80::/10".into()]);
+        assert_eq!(normalized.block_hosts, vec!["api.example.com".into()]);
+    }
+
+    #[test]
+    fn test_build_reports_bad_rules() {
+        rejects(
+            PolicyConfig {
+                block_cidrs: vec!["10.0.0.0/8".into(), "10.0.0/8".into()],
+                ..Default::default()
+            },
+            "block_cidrs[1]: invalid CIDR \"10.0.0/8\"",
+        );
+        rejects(
+            PolicyConfig {
+                allow_cidrs: vec!["fe80::/129".into()],
+                ..Default::default()
+            },
+            "allow_cidrs[0]: invalid CIDR",
+        );
+        for (pattern, reason) in [
+            (
+                "api.*.example",
+                "'*' is only allowed in the left-most label",
+            ),
+            ("", "empty hostname"),
+            ("a..example", "empty label"),
+            ("http://a.example", "invalid port"),
+            ("a.example/path", "contains characters"),
+            ("a.example:99999", "invalid port"),
+        ] {
+            let err = PolicyConfig {
+                block_hosts: vec![pattern.into()],
+                ..Default::default()
+            }
+            .build()
+            .unwrap_err();
+            assert!(err.contains(reason), "{}: {}", pattern, err);
+        }
+        rejects(
+            PolicyConfig {
+                allow_hosts: vec![RuleConfig::new("a.example".into(), Some("x".into()), None)],
+                ..Default::default()
+            },
+            "allow_hosts[0]: notes are only supported on block rules",
+        );
+        rejects(
+            PolicyConfig {
+                block_hosts: vec![RuleConfig::new("a.example".into(), None, Some(1))],
+                ..Default::default()
+            },
+            "block_hosts[0]: deadlines are only supported",
+        );
+        rejects(
+            PolicyConfig {
+                allow_schemes: vec!["ftp".into()],
+                ..Default::default()
+            },
+            "allow_schemes[0]: unsupported scheme",
+        );
+        rejects(
+            PolicyConfig {
+                allow_url_prefixes: vec!["ftp://files.example/".into()],
+                ..Default::default()
+            },
+            "allow_url_prefixes[0]: Invalid URL",
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_base() {
+        use serde::de::value::{Error, MapDeserializer, StrDeserializer};
+        use serde::Deserialize;
+
+        let named = BasePolicy::deserialize(StrDeserializer::<Error>::new("allow_private"));
+        assert_eq!(named.unwrap(), Policy::AllowPrivate.into());
+
+        let flags = MapDeserializer::<_, Error>::new([("block_loopback", false)].into_iter());
+        assert_eq!(
+            BasePolicy::deserialize(flags).unwrap(),
+            BasePolicy::builder().block_loopback(false).build()
+        );
+
+        let flags = MapDeserializer::<_, Error>::new([("block_shared", true)].into_iter());
+        assert_eq!(
+            BasePolicy::deserialize(flags).unwrap(),
+            BasePolicy::builder().block_shared(true).build()
+        );
+
+        let named = BasePolicy::deserialize(StrDeserializer::<Error>::new("block_all"));
+        assert!(named.unwrap().is_allowlist_only());
+
+        let typo = MapDeserializer::<_, Error>::new([("block_loop", false)].into_iter());
+        assert!(BasePolicy::deserialize(typo).is_err());
+    }
+
+    #[test]
+    fn test_classifier_has_no_config_form() {
+        use crate::{IpClass, IpClassifier};
+
+        struct Cgnat;
+        impl IpClassifier for Cgnat {
+            fn classify(&self, _ip: std::net::IpAddr) -> IpClass {
+                IpClass::Public
+            }
+        }
+
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .classifier(Cgnat)
+            .build();
+        assert!(policy.to_config().is_err());
+    }
+}
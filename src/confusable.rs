@@ -0,0 +1,268 @@
+//! IDN homograph detection.
+//!
+//! Internationalized hostnames can imitate other hostnames: `аpple.com` with
+//! a Cyrillic `а` (U+0430) is `xn--pple-43d.com`, a different domain that
+//! renders like `apple.com`. Link previews and webhook UIs that show the
+//! hostname to a person are the usual target.
+//!
+//! [`SafeUrl::confusable`](crate::SafeUrl::confusable) flags a hostname with
+//! a label that either
+//!
+//! - mixes scripts, such as Latin and Cyrillic in one label. Latin mixed
+//!   with Chinese, Japanese or Korean scripts is allowed, as browsers do; or
+//! - is written only with letters that look like Latin ones, such as an
+//!   all-Cyrillic `аррӏе`, or Latin lookalikes such as the dotless `ı`.
+//!
+//! Validation rejects flagged hostnames with [`Confusables::Reject`]. The
+//! tables cover the common Cyrillic and Greek lookalikes, not the full
+//! Unicode confusables list, so an unflagged hostname is not proof that it
+//! is unambiguous.
+
+/// How validation treats a hostname flagged by
+/// [`SafeUrl::confusable`](crate::SafeUrl::confusable).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Confusables {
+    /// Validate the hostname like any other.
+    #[default]
+    Allow,
+
+    /// Fail with [`Error::HostnameBlocked`](crate::Error::HostnameBlocked).
+    Reject,
+}
+
+/// The script of a character, as far as homograph detection cares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Thai,
+    Han,
+    Hiragana,
+    Katakana,
+    Bopomofo,
+    Hangul,
+    Other,
+}
+
+impl Script {
+    fn of(c: char) -> Option<Script> {
+        Some(match c {
+            // Digits, hyphens and combining marks belong to every script
+            '0'..='9' | '-' | '\u{0300}'..='\u{036F}' | '\u{30FC}' => return None,
+            'a'..='z'
+            | '\u{00DF}'..='\u{00F6}'
+            | '\u{00F8}'..='\u{02AF}'
+            | '\u{1D00}'..='\u{1DBF}'
+            | '\u{1E00}'..='\u{1EFF}'
+            | '\u{2C60}'..='\u{2C7F}'
+            | '\u{A720}'..='\u{A7FF}' => Script::Latin,
+            '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}' => Script::Greek,
+            '\u{0400}'..='\u{052F}'
+            | '\u{1C80}'..='\u{1C8F}'
+            | '\u{2DE0}'..='\u{2DFF}'
+            | '\u{A640}'..='\u{A69F}' => Script::Cyrillic,
+            '\u{0530}'..='\u{058F}' => Script::Armenian,
+            '\u{0590}'..='\u{05FF}' => Script::Hebrew,
+            '\u{0600}'..='\u{06FF}' | '\u{0750}'..='\u{077F}' => Script::Arabic,
+            '\u{0E00}'..='\u{0E7F}' => Script::Thai,
+            '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{20000}'..='\u{2FFFF}' => Script::Han,
+            '\u{3040}'..='\u{309F}' => Script::Hiragana,
+            '\u{30A0}'..='\u{30FF}' => Script::Katakana,
+            '\u{3100}'..='\u{312F}' => Script::Bopomofo,
+            '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' | '\u{AC00}'..='\u{D7AF}' => {
+                Script::Hangul
+            }
+            _ => Script::Other,
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Script::Latin => "Latin",
+            Script::Greek => "Greek",
+            Script::Cyrillic => "Cyrillic",
+            Script::Armenian => "Armenian",
+            Script::Hebrew => "Hebrew",
+            Script::Arabic => "Arabic",
+            Script::Thai => "Thai",
+            Script::Han => "Han",
+            Script::Hiragana => "Hiragana",
+            Script::Katakana => "Katakana",
+            Script::Bopomofo => "Bopomofo",
+            Script::Hangul => "Hangul",
+            Script::Other => "other",
+        }
+    }
+}
+
+/// Script mixes that are normal in one label: Latin with Japanese, Chinese
+/// or Korean writing.
+const ALLOWED_MIXES: &[&[Script]] = &[
+    &[
+        Script::Latin,
+        Script::Han,
+        Script::Hiragana,
+        Script::Katakana,
+    ],
+    &[Script::Latin, Script::Han, Script::Bopomofo],
+    &[Script::Latin, Script::Han, Script::Hangul],
+];
+
+/// Non-ASCII letters that render like an ASCII letter.
+const LOOKALIKES: &[(char, char)] = &[
+    // Cyrillic
+    ('а', 'a'),
+    ('с', 'c'),
+    ('ԁ', 'd'),
+    ('е', 'e'),
+    ('һ', 'h'),
+    ('і', 'i'),
+    ('ј', 'j'),
+    ('ӏ', 'l'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('ԛ', 'q'),
+    ('ѕ', 's'),
+    ('ԝ', 'w'),
+    ('х', 'x'),
+    ('у', 'y'),
+    // Greek
+    ('α', 'a'),
+    ('ι', 'i'),
+    ('κ', 'k'),
+    ('ν', 'v'),
+    ('ο', 'o'),
+    ('ρ', 'p'),
+    ('υ', 'u'),
+    ('χ', 'x'),
+    ('ω', 'w'),
+    // Latin
+    ('ɑ', 'a'),
+    ('ƅ', 'b'),
+    ('ɡ', 'g'),
+    ('ı', 'i'),
+    ('ɩ', 'i'),
+    ('ȷ', 'j'),
+    ('ʟ', 'l'),
+    ('ɴ', 'n'),
+    ('ⱺ', 'o'),
+    ('ʀ', 'r'),
+    ('ꜱ', 's'),
+    ('ᴠ', 'v'),
+    ('ᴡ', 'w'),
+    ('ʏ', 'y'),
+    ('ᴢ', 'z'),
+];
+
+/// The Unicode form of an ASCII (punycode) hostname.
+///
+/// Labels that are not valid punycode are kept as they are.
+pub(crate) fn to_unicode(host: &str) -> String {
+    let (unicode, result) = idna::domain_to_unicode(host);
+    match result {
+        Ok(()) => unicode,
+        Err(_) => host.to_string(),
+    }
+}
+
+/// Why the hostname `host` (ASCII or Unicode) could imitate another one, if
+/// it could.
+pub(crate) fn check_host(host: &str) -> Option<String> {
+    if !host.contains("xn--") && host.is_ascii() {
+        return None;
+    }
+    to_unicode(host).split('.').find_map(check_label)
+}
+
+fn check_label(label: &str) -> Option<String> {
+    if label.is_ascii() {
+        return None;
+    }
+    let mut scripts: Vec<Script> = label.chars().filter_map(Script::of).collect();
+    scripts.sort();
+    scripts.dedup();
+    if scripts.len() > 1
+        && !ALLOWED_MIXES
+            .iter()
+            .any(|mix| scripts.iter().all(|script| mix.contains(script)))
+    {
+        let names: Vec<&str> = scripts.iter().map(|script| script.name()).collect();
+        return Some(format!(
+            "label '{}' mixes {} scripts",
+            label,
+            names.join(" and ")
+        ));
+    }
+
+    // A label written only with Latin lookalikes reads as that Latin label
+    let skeleton: Option<String> = label
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '-' => Some(c),
+            _ => LOOKALIKES
+                .iter()
+                .find(|(lookalike, _)| *lookalike == c)
+                .map(|(_, ascii)| *ascii),
+        })
+        .collect();
+    skeleton.map(|skeleton| format!("label '{}' looks like '{}'", label, skeleton))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_homographs() {
+        for (host, reason) in [
+            (
+                "\u{0430}pple.com",
+                "label 'аpple' mixes Latin and Cyrillic scripts",
+            ),
+            (
+                "xn--pple-43d.com",
+                "label 'аpple' mixes Latin and Cyrillic scripts",
+            ),
+            ("www.аррӏе.com", "label 'аррӏе' looks like 'apple'"),
+            (
+                "pаypal.com",
+                "label 'pаypal' mixes Latin and Cyrillic scripts",
+            ),
+            ("gοοgle.com", "label 'gοοgle' mixes Latin and Greek scripts"),
+            ("gıthub.com", "label 'gıthub' looks like 'github'"),
+        ] {
+            assert_eq!(check_host(host).as_deref(), Some(reason), "{}", host);
+        }
+    }
+
+    #[test]
+    fn test_allows_ordinary_names() {
+        for host in [
+            "example.com",
+            "xn--mnchen-3ya.de",
+            "münchen.de",
+            "москва.рф",
+            "παράδειγμα.δοκιμή",
+            "日本語テスト.jp",
+            "ソニーstore.jp",
+            "한국abc.kr",
+            "例え.テスト",
+            "xn--invalid-.com",
+        ] {
+            assert_eq!(check_host(host), None, "{}", host);
+        }
+    }
+
+    #[test]
+    fn test_to_unicode() {
+        assert_eq!(to_unicode("xn--mnchen-3ya.de"), "münchen.de");
+        assert_eq!(to_unicode("example.com"), "example.com");
+    }
+}
@@ -0,0 +1,159 @@
+//! IP-pinning connector for hyper.
+//!
+//! [`PinnedConnector`] implements `tower::Service<Uri>`, the connector
+//! interface of `hyper_util`'s legacy client (and of TLS wrappers such as
+//! `hyper-rustls`). Each connection validates its target URI against the
+//! policy and dials the validated address directly, so raw hyper users get
+//! SSRF protection and DNS rebinding resistance without a connector of their
+//! own.
+//!
+//! The connector returns plain TCP streams. For HTTPS, wrap it in a TLS
+//! connector, which then uses the URI's hostname for SNI and certificate
+//! verification as usual.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use http::Uri;
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpStream;
+
+use crate::error::Error;
+use crate::policy::{Policy, ValidationPolicy};
+use crate::validate::{validate_with_options, ValidateOptions};
+
+/// A hyper connector that validates each target and connects to the
+/// validated IP.
+///
+/// Redirects are never followed by hyper itself, but each request a caller
+/// makes goes through the connector, so every new target is validated.
+/// Pooled connections are reused only for the same scheme and authority.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use hyper_util::client::legacy::Client;
+/// use hyper_util::rt::TokioExecutor;
+/// use url_jail::{PinnedConnector, Policy};
+///
+/// let client: Client<_, http_body_util::Empty<bytes::Bytes>> =
+///     Client::builder(TokioExecutor::new()).build(PinnedConnector::new(Policy::PublicOnly));
+/// let response = client.get("http://example.com/".parse()?).await?;
+/// ```
+pub struct PinnedConnector<P: ValidationPolicy = Policy> {
+    policy: Arc<P>,
+    options: ValidateOptions,
+}
+
+impl<P: ValidationPolicy> PinnedConnector<P> {
+    /// Create a connector validating against `policy`.
+    pub fn new(policy: P) -> Self {
+        Self {
+            policy: Arc::new(policy),
+            options: ValidateOptions::default(),
+        }
+    }
+
+    /// Use `options` (DNS timeout, cache, ...) for validation.
+    pub fn with_options(mut self, options: ValidateOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl<P: ValidationPolicy> Clone for PinnedConnector<P> {
+    fn clone(&self) -> Self {
+        Self {
+            policy: self.policy.clone(),
+            options: self.options.clone(),
+        }
+    }
+}
+
+impl<P: ValidationPolicy> fmt::Debug for PinnedConnector<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PinnedConnector")
+            .field("policy", &self.policy)
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl<P: ValidationPolicy + 'static> tower_service::Service<Uri> for PinnedConnector<P> {
+    type Response = TokioIo<TcpStream>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let policy = self.policy.clone();
+        let options = self.options.clone();
+        Box::pin(async move {
+            let url = uri.to_string();
+            let validated = validate_with_options(&url, &*policy, options).await?;
+            let stream = TcpStream::connect(validated.to_socket_addr())
+                .await
+                .map_err(|e| Error::HttpError {
+                    url: validated.url.clone(),
+                    message: format!("connect to {} failed: {}", validated.to_socket_addr(), e),
+                })?;
+            Ok(TokioIo::new(stream))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PolicyBuilder, ValidationCache};
+    use std::time::Duration;
+    use tower_service::Service;
+
+    #[tokio::test]
+    async fn test_blocked_target_never_dialed() {
+        let mut connector = PinnedConnector::new(Policy::PublicOnly);
+        let err = connector
+            .call("http://127.0.0.1:9/".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert!(err.is_blocked(), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_dials_validated_ip() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_cidr("127.0.0.1/32")
+            .build();
+        // The hostname does not resolve: only the cached validation can pin it
+        let cache = Arc::new(ValidationCache::new(Duration::from_secs(60)));
+        cache
+            .insert(
+                policy.fingerprint(),
+                "pinned.invalid",
+                "127.0.0.1".parse().unwrap(),
+            )
+            .await;
+        let mut connector = PinnedConnector::new(policy).with_options(ValidateOptions {
+            cache: Some(cache),
+            ..Default::default()
+        });
+
+        let uri: Uri = format!("http://pinned.invalid:{}/path", port)
+            .parse()
+            .unwrap();
+        let (stream, accepted) = tokio::join!(connector.call(uri), listener.accept());
+        let stream = stream.unwrap();
+        let (_, peer) = accepted.unwrap();
+        assert_eq!(stream.inner().peer_addr().unwrap().port(), port);
+        assert_eq!(peer.ip().to_string(), "127.0.0.1");
+    }
+}
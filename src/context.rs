@@ -0,0 +1,104 @@
+//! Caller-supplied validation metadata.
+//!
+//! A [`Context`] carries tags such as a tenant id, feature name, or request
+//! id through validation so that security logs can attribute blocked
+//! attempts. It is metadata only: policies never see it, and the same URL
+//! gets the same decision whatever the context says.
+//!
+//! Tags appear in `tracing` events (with the `tracing` feature) and are
+//! appended to error messages.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Tags describing who or what requested a validation.
+///
+/// Pass it through [`ValidateOptions::context`](crate::ValidateOptions::context).
+///
+/// # Example
+///
+/// ```rust
+/// use url_jail::{Context, ValidateOptions};
+///
+/// let opts = ValidateOptions {
+///     context: Context::new()
+///         .tag("tenant", "acme")
+///         .tag("feature", "webhooks")
+///         .tag("request_id", "req-42"),
+///     ..Default::default()
+/// };
+/// assert_eq!(opts.context.get("tenant"), Some("acme"));
+/// assert_eq!(
+///     opts.context.to_string(),
+///     "feature=webhooks request_id=req-42 tenant=acme"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Context {
+    tags: BTreeMap<String, String>,
+}
+
+impl Context {
+    /// Create an empty context.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a tag, replacing any previous value for `key`.
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.insert(key.into(), value.into());
+        self
+    }
+
+    /// The value of the tag `key`, if set.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.tags.get(key).map(String::as_str)
+    }
+
+    /// Returns `true` if no tags are set.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Iterate over the tags, sorted by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.tags.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+impl fmt::Display for Context {
+    /// Formats as space-separated `key=value` pairs, sorted by key.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags_replace_and_sort() {
+        let context = Context::new()
+            .tag("tenant", "acme")
+            .tag("feature", "import")
+            .tag("tenant", "globex");
+
+        assert_eq!(context.get("tenant"), Some("globex"));
+        assert_eq!(context.get("missing"), None);
+        assert_eq!(context.to_string(), "feature=import tenant=globex");
+    }
+
+    #[test]
+    fn test_empty_context() {
+        let context = Context::new();
+        assert!(context.is_empty());
+        assert_eq!(context.to_string(), "");
+    }
+}
@@ -0,0 +1,488 @@
+//! Known SSRF bypass payloads, as a self-audit harness.
+//!
+//! This is the corpus the crate is tested against: encoded IP addresses,
+//! metadata endpoints, IPv6 embeddings, parser-differential URLs, and DNS
+//! rebinding hostnames. Run it against your own policy with [`audit`] or
+//! [`audit_custom`] to find rules that let a known bypass through, or export
+//! it with [`to_json`] for other tools.
+//!
+//! A payload counts as a bypass only if validation succeeds. Payloads that
+//! could not be resolved (no network, DNS failure) are reported separately,
+//! since they were not actually exercised.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use url_jail::corpus;
+//! use url_jail::{PolicyBuilder, Policy};
+//!
+//! # async fn example() {
+//! let policy = PolicyBuilder::new(Policy::PublicOnly)
+//!     .allow_cidr("169.254.0.0/16") // oops
+//!     .build();
+//!
+//! let report = corpus::audit_custom(&policy).await;
+//! for payload in &report.bypasses {
+//!     eprintln!("BYPASS [{}] {} - {}", payload.category, payload.url, payload.description);
+//! }
+//! assert!(!report.is_clean());
+//! # }
+//! ```
+
+use std::fmt;
+
+use crate::error::Error;
+use crate::policy::Policy;
+use crate::policy_builder::CustomPolicy;
+use crate::validate::{validate, validate_custom, Validated};
+
+/// Kind of bypass a payload attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// Non-standard IPv4 notation (octal, hex, decimal, short-form).
+    IpEncoding,
+    /// Loopback and unspecified addresses.
+    Loopback,
+    /// Cloud metadata endpoints, by address or hostname.
+    Metadata,
+    /// RFC 1918 / ULA / link-local ranges.
+    Private,
+    /// IPv4 addresses embedded in IPv6.
+    Ipv6Embedding,
+    /// Hostname spelling tricks (case, trailing dots).
+    Hostname,
+    /// URLs that different parsers read differently.
+    ParserDifferential,
+    /// Non-HTTP schemes.
+    Scheme,
+    /// Hostnames that reach internal addresses through DNS (wildcard DNS,
+    /// rebinding, search domains). Needs DNS.
+    InternalDns,
+}
+
+impl Category {
+    /// Stable lowercase name, as used by [`to_json`].
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Category::IpEncoding => "ip_encoding",
+            Category::Loopback => "loopback",
+            Category::Metadata => "metadata",
+            Category::Private => "private",
+            Category::Ipv6Embedding => "ipv6_embedding",
+            Category::Hostname => "hostname",
+            Category::ParserDifferential => "parser_differential",
+            Category::Scheme => "scheme",
+            Category::InternalDns => "internal_dns",
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A known bypass payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Payload {
+    /// The URL to validate.
+    pub url: &'static str,
+
+    /// What kind of bypass it attempts.
+    pub category: Category,
+
+    /// What the payload actually targets.
+    pub description: &'static str,
+}
+
+const fn payload(url: &'static str, category: Category, description: &'static str) -> Payload {
+    Payload {
+        url,
+        category,
+        description,
+    }
+}
+
+use Category::*;
+
+static PAYLOADS: &[Payload] = &[
+    // IP encodings
+    payload("http://0177.0.0.1/", IpEncoding, "octal 127.0.0.1"),
+    payload("http://0x7f000001/", IpEncoding, "hex dword 127.0.0.1"),
+    payload("http://2130706433/", IpEncoding, "decimal dword 127.0.0.1"),
+    payload("http://127.1/", IpEncoding, "short-form 127.0.0.1"),
+    payload("http://0x7f.0.0.1/", IpEncoding, "mixed hex 127.0.0.1"),
+    payload("http://017700000001/", IpEncoding, "octal dword 127.0.0.1"),
+    payload(
+        "http://0251.0376.0251.0376/",
+        IpEncoding,
+        "octal 169.254.169.254",
+    ),
+    payload(
+        "http://0xa9fea9fe/",
+        IpEncoding,
+        "hex dword 169.254.169.254",
+    ),
+    payload(
+        "http://2852039166/",
+        IpEncoding,
+        "decimal dword 169.254.169.254",
+    ),
+    payload(
+        "http://169.254.43518/",
+        IpEncoding,
+        "short-form 169.254.169.254",
+    ),
+    payload("http://10.1/", IpEncoding, "short-form 10.0.0.1"),
+    // Loopback
+    payload("http://127.0.0.1/", Loopback, "IPv4 loopback"),
+    payload("http://127.255.255.254/", Loopback, "end of 127.0.0.0/8"),
+    payload("http://[::1]/", Loopback, "IPv6 loopback"),
+    payload(
+        "http://0.0.0.0/",
+        Loopback,
+        "unspecified address, reaches localhost on Linux",
+    ),
+    payload("http://[::]/", Loopback, "IPv6 unspecified address"),
+    payload("http://127.0.0.1:6379/", Loopback, "local Redis"),
+    // Metadata
+    payload(
+        "http://169.254.169.254/latest/meta-data/",
+        Metadata,
+        "AWS/GCP/Azure metadata",
+    ),
+    payload(
+        "http://169.254.170.2/v2/credentials",
+        Metadata,
+        "AWS ECS task credentials",
+    ),
+    payload(
+        "http://metadata.google.internal/",
+        Metadata,
+        "GCP metadata hostname",
+    ),
+    payload("http://[fd00:ec2::254]/", Metadata, "AWS IPv6 metadata"),
+    payload(
+        "http://100.100.100.200/",
+        Metadata,
+        "Alibaba Cloud metadata",
+    ),
+    payload("http://instance-data/", Metadata, "AWS metadata hostname"),
+    // Private
+    payload("http://10.0.0.1/", Private, "RFC 1918 10/8"),
+    payload("http://172.16.0.1/", Private, "RFC 1918 172.16/12"),
+    payload(
+        "http://192.168.0.1/",
+        Private,
+        "RFC 1918 192.168/16 (router admin)",
+    ),
+    payload("http://169.254.1.1/", Private, "IPv4 link-local"),
+    payload("http://[fc00::1]/", Private, "IPv6 unique local"),
+    payload("http://[fd12:3456::1]/", Private, "IPv6 unique local"),
+    payload("http://[fe80::1]/", Private, "IPv6 link-local"),
+    payload("http://[::ffff:10.0.0.1]/", Private, "IPv4-mapped 10/8"),
+    // IPv6 embeddings
+    payload(
+        "http://[::ffff:127.0.0.1]/",
+        Ipv6Embedding,
+        "IPv4-mapped loopback",
+    ),
+    payload(
+        "http://[::ffff:7f00:1]/",
+        Ipv6Embedding,
+        "IPv4-mapped loopback, hex",
+    ),
+    payload(
+        "http://[::ffff:169.254.169.254]/",
+        Ipv6Embedding,
+        "IPv4-mapped metadata",
+    ),
+    payload(
+        "http://[::ffff:a9fe:a9fe]/",
+        Ipv6Embedding,
+        "IPv4-mapped metadata, hex",
+    ),
+    payload(
+        "http://[::127.0.0.1]/",
+        Ipv6Embedding,
+        "IPv4-compatible loopback",
+    ),
+    payload(
+        "http://[::169.254.169.254]/",
+        Ipv6Embedding,
+        "IPv4-compatible metadata",
+    ),
+    payload(
+        "http://[64:ff9b::a9fe:a9fe]/",
+        Ipv6Embedding,
+        "NAT64 metadata",
+    ),
+    payload("http://[64:ff9b::7f00:1]/", Ipv6Embedding, "NAT64 loopback"),
+    payload("http://[2002:7f00:1::]/", Ipv6Embedding, "6to4 loopback"),
+    payload(
+        "http://[2002:a9fe:a9fe::1]/",
+        Ipv6Embedding,
+        "6to4 metadata",
+    ),
+    payload(
+        "http://[2001:0:4136:e378:8000:63bf:80ff:fffe]/",
+        Ipv6Embedding,
+        "Teredo client loopback",
+    ),
+    // Hostname tricks
+    payload("http://127.0.0.1./", Hostname, "trailing dot on IP"),
+    payload(
+        "http://METADATA.GOOGLE.INTERNAL./",
+        Hostname,
+        "metadata hostname, case and trailing dot",
+    ),
+    payload(
+        "http://example.com@127.0.0.1/",
+        Hostname,
+        "userinfo before loopback",
+    ),
+    // Parser differentials
+    payload(
+        "http://169.254.169.254%2F@example.com/",
+        ParserDifferential,
+        "encoded slash in authority",
+    ),
+    payload(
+        "http://127.0.0.1%23@example.com/",
+        ParserDifferential,
+        "encoded fragment in authority",
+    ),
+    payload(
+        "http:http://127.0.0.1/",
+        ParserDifferential,
+        "nested scheme",
+    ),
+    payload("http:/\\127.0.0.1/", ParserDifferential, "mixed slashes"),
+    payload(
+        "http://127.0.0.1\\@example.com/",
+        ParserDifferential,
+        "backslash before userinfo",
+    ),
+    payload(
+        "http://127.0.0.%31/",
+        ParserDifferential,
+        "percent-encoded host",
+    ),
+    payload(
+        "http://127.0.0.1\t/",
+        ParserDifferential,
+        "tab stripped by WHATWG parsers",
+    ),
+    payload(
+        "http://example.com:0/",
+        ParserDifferential,
+        "port 0, left to the OS by some clients",
+    ),
+    payload(
+        "http://example.com:0080/",
+        ParserDifferential,
+        "port with leading zeros, octal to some parsers",
+    ),
+    // Schemes
+    payload("file:///etc/passwd", Scheme, "local file read"),
+    payload("gopher://127.0.0.1:6379/_INFO", Scheme, "gopher to Redis"),
+    payload("dict://127.0.0.1:11211/stats", Scheme, "dict to memcached"),
+    payload("ftp://127.0.0.1/", Scheme, "FTP to loopback"),
+    // DNS rebinding / internal-resolving hostnames
+    payload("http://LOCALHOST/", InternalDns, "uppercase localhost"),
+    payload(
+        "http://metadata/",
+        InternalDns,
+        "GCP metadata via the search domain",
+    ),
+    payload(
+        "http://localtest.me/",
+        InternalDns,
+        "public name resolving to 127.0.0.1",
+    ),
+    payload(
+        "http://127.0.0.1.nip.io/",
+        InternalDns,
+        "wildcard DNS to 127.0.0.1",
+    ),
+    payload(
+        "http://169.254.169.254.nip.io/",
+        InternalDns,
+        "wildcard DNS to metadata",
+    ),
+    payload(
+        "http://10.0.0.1.sslip.io/",
+        InternalDns,
+        "wildcard DNS to 10.0.0.1",
+    ),
+    payload(
+        "http://7f000001.a9fea9fe.rbndr.us/",
+        InternalDns,
+        "rebinding between loopback and metadata",
+    ),
+];
+
+/// Every payload in the corpus.
+pub fn payloads() -> &'static [Payload] {
+    PAYLOADS
+}
+
+/// Result of running the corpus against a policy.
+#[derive(Debug)]
+pub struct AuditReport {
+    /// Number of payloads checked.
+    pub checked: usize,
+
+    /// Payloads that passed validation. Each one is a bypass.
+    pub bypasses: Vec<&'static Payload>,
+
+    /// Payloads that could not be exercised because DNS failed.
+    pub unresolved: Vec<(&'static Payload, Error)>,
+}
+
+impl AuditReport {
+    /// Returns `true` if no payload passed validation.
+    pub fn is_clean(&self) -> bool {
+        self.bypasses.is_empty()
+    }
+}
+
+/// Run the corpus against a built-in policy.
+pub async fn audit(policy: Policy) -> AuditReport {
+    let mut results = Vec::with_capacity(PAYLOADS.len());
+    for payload in PAYLOADS {
+        results.push((payload, validate(payload.url, policy).await));
+    }
+    report(results)
+}
+
+/// Run the corpus against a custom policy.
+pub async fn audit_custom(policy: &CustomPolicy) -> AuditReport {
+    let mut results = Vec::with_capacity(PAYLOADS.len());
+    for payload in PAYLOADS {
+        results.push((payload, validate_custom(payload.url, policy).await));
+    }
+    report(results)
+}
+
+fn report(results: Vec<(&'static Payload, Result<Validated, Error>)>) -> AuditReport {
+    let mut report = AuditReport {
+        checked: results.len(),
+        bypasses: Vec::new(),
+        unresolved: Vec::new(),
+    };
+    for (payload, result) in results {
+        match result {
+            Ok(_) => report.bypasses.push(payload),
+            Err(e @ (Error::DnsError { .. } | Error::Timeout { .. })) => {
+                report.unresolved.push((payload, e))
+            }
+            Err(_) => {}
+        }
+    }
+    report
+}
+
+/// The corpus as a JSON array of `{"url", "category", "description"}` objects.
+pub fn to_json() -> String {
+    fn string(out: &mut String, s: &str) {
+        out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+    }
+
+    let mut out = String::from("[\n");
+    for (i, payload) in PAYLOADS.iter().enumerate() {
+        out.push_str("  {\"url\": ");
+        string(&mut out, payload.url);
+        out.push_str(", \"category\": ");
+        string(&mut out, payload.category.as_str());
+        out.push_str(", \"description\": ");
+        string(&mut out, payload.description);
+        out.push('}');
+        if i + 1 < PAYLOADS.len() {
+            out.push(',');
+        }
+        out.push('\n');
+    }
+    out.push(']');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::{Step, ValidationMachine};
+
+    /// Everything except internal DNS payloads is decided without DNS, so the
+    /// built-in policies must reject all of them.
+    #[test]
+    fn test_builtin_policies_block_offline_corpus() {
+        for policy in [Policy::PublicOnly, Policy::AllowPrivate] {
+            for payload in PAYLOADS.iter().filter(|p| p.category != InternalDns) {
+                if policy == Policy::AllowPrivate && payload.category == Private {
+                    continue;
+                }
+                match ValidationMachine::new(payload.url, policy).start() {
+                    Step::Done(Err(_)) => {}
+                    Step::Done(Ok(v)) => panic!("{:?} bypassed {:?}: {:?}", payload, policy, v),
+                    Step::NeedDns(q) => panic!("{:?} needs DNS for {}", payload, q.host()),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_report_classifies_results() {
+        let results = vec![
+            (&PAYLOADS[0], Err(Error::invalid_url("u", "bad"))),
+            (&PAYLOADS[1], Err(Error::dns_error("h", "down"))),
+            (
+                &PAYLOADS[2],
+                Ok(Validated {
+                    ip: "127.0.0.1".parse().unwrap(),
+                    ips: vec!["127.0.0.1".parse().unwrap()],
+                    host: "h".into(),
+                    port: 80,
+                    url: "u".into(),
+                    https: false,
+                    valid_until: None,
+                    clock: Default::default(),
+                }),
+            ),
+        ];
+        let report = report(results);
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.bypasses, [&PAYLOADS[2]]);
+        assert_eq!(report.unresolved.len(), 1);
+        assert!(!report.is_clean());
+    }
+
+    #[tokio::test]
+    async fn test_audit_finds_allow_rule_bypass() {
+        let policy = crate::PolicyBuilder::new(Policy::PublicOnly)
+            .allow_cidr("10.0.0.0/8")
+            .build();
+        let report = audit_custom(&policy).await;
+        assert_eq!(report.checked, PAYLOADS.len());
+        assert!(report.bypasses.iter().any(|p| p.url == "http://10.0.0.1/"));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let json = to_json();
+        assert!(json
+            .starts_with("[\n  {\"url\": \"http://0177.0.0.1/\", \"category\": \"ip_encoding\""));
+        assert!(json.contains(r#""http:/\\127.0.0.1/""#));
+        assert!(json.contains(r#""http://127.0.0.1\u0009/""#));
+        assert_eq!(json.matches("\"url\"").count(), PAYLOADS.len());
+    }
+}
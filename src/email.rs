@@ -0,0 +1,293 @@
+//! Email domain validation for signup flows.
+//!
+//! A user-supplied email address makes the server talk to the domain's mail
+//! exchangers (verification emails, bounce handling), so a domain whose MX
+//! records point at internal or metadata addresses is an SSRF vector.
+//! [`validate_email_domain`] resolves the mail hosts and applies the policy to
+//! every one of them.
+
+use std::net::IpAddr;
+use std::time::Duration;
+
+use hickory_resolver::proto::rr::rdata::MX;
+
+use crate::error::Error;
+use crate::machine::{Step, ValidationMachine};
+use crate::policy::Policy;
+use crate::resolver::{build_resolver, lookup_permit};
+use crate::validate::{validate_with_options, ValidateOptions};
+
+/// An email domain whose mail hosts all passed validation.
+#[derive(Debug, Clone)]
+pub struct EmailDomain {
+    /// The normalized domain (lowercase, punycode).
+    pub domain: String,
+
+    /// Mail hosts in preference order (lowest preference value first).
+    ///
+    /// If the domain has no MX records, this is the domain itself (the
+    /// implicit MX of RFC 5321).
+    pub mail_hosts: Vec<MailHost>,
+}
+
+/// A validated mail exchanger.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailHost {
+    /// Exchanger hostname (or address literal).
+    pub host: String,
+
+    /// MX preference; lower is preferred.
+    pub preference: u16,
+
+    /// The verified IP address to connect to.
+    pub ip: IpAddr,
+}
+
+/// Validate the domain of an email address (or a bare domain).
+///
+/// Resolves the domain's MX records, falling back to the domain itself when
+/// there are none, and validates every mail host against `policy`. One
+/// internal exchanger fails the whole domain, so an attacker cannot hide it
+/// behind a legitimate primary MX.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use url_jail::{validate_email_domain, Policy};
+///
+/// # async fn example() -> Result<(), url_jail::Error> {
+/// let domain = validate_email_domain("Alice <alice@Example.com>", Policy::PublicOnly).await?;
+/// assert_eq!(domain.domain, "example.com");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidUrl`] if no valid domain can be extracted,
+/// [`Error::DnsError`] if the domain publishes a null MX (accepts no mail) or
+/// does not resolve, and the errors of [`validate`](crate::validate) for any
+/// mail host.
+pub async fn validate_email_domain(
+    addr_or_domain: &str,
+    policy: Policy,
+) -> Result<EmailDomain, Error> {
+    let host = extract_domain(addr_or_domain)?;
+    let url = format!("http://{}/", host);
+
+    // Parsing, the hostname blocklist, and address literals are all decided
+    // without DNS
+    let domain = match ValidationMachine::new(&url, policy).start() {
+        Step::Done(Err(e)) => return Err(e),
+        Step::Done(Ok(literal)) => {
+            return Ok(EmailDomain {
+                domain: literal.host.clone(),
+                mail_hosts: vec![MailHost {
+                    host: literal.host,
+                    preference: 0,
+                    ip: literal.ip,
+                }],
+            })
+        }
+        Step::NeedDns(query) => query.host().to_string(),
+    };
+
+    let options = ValidateOptions::default();
+    let exchangers = match lookup_mx(&domain, options.dns_timeout).await? {
+        Some(exchangers) => exchangers,
+        // No MX records: the domain itself is the implicit MX
+        None => vec![(0, domain.clone())],
+    };
+
+    let mut mail_hosts = Vec::with_capacity(exchangers.len());
+    for (preference, host) in exchangers {
+        let url = format!("http://{}/", host);
+        let validated = validate_with_options(&url, policy, options.clone()).await?;
+        mail_hosts.push(MailHost {
+            host,
+            preference,
+            ip: validated.ip,
+        });
+    }
+
+    Ok(EmailDomain { domain, mail_hosts })
+}
+
+/// Extract the domain from `alice@example.com`, `Alice <alice@example.com>`,
+/// or a bare `example.com`, in a form usable as a URL host.
+///
+/// RFC 5321 address literals (`[192.0.2.1]`, `[IPv6:2001:db8::1]`) become
+/// `192.0.2.1` and `[2001:db8::1]`.
+fn extract_domain(input: &str) -> Result<String, Error> {
+    let trimmed = input.trim();
+    let addr = match (trimmed.rfind('<'), trimmed.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &trimmed[start + 1..end],
+        _ => trimmed,
+    };
+    let domain = addr.rsplit_once('@').map_or(addr, |(_, domain)| domain);
+
+    if let Some(literal) = domain.strip_prefix('[').and_then(|d| d.strip_suffix(']')) {
+        let ip = literal
+            .strip_prefix("IPv6:")
+            .or_else(|| literal.strip_prefix("ipv6:"))
+            .unwrap_or(literal);
+        return match ip.parse::<IpAddr>() {
+            Ok(IpAddr::V4(v4)) => Ok(v4.to_string()),
+            Ok(IpAddr::V6(v6)) => Ok(format!("[{}]", v6)),
+            Err(_) => Err(Error::invalid_url(input, "invalid email address literal")),
+        };
+    }
+
+    // Anything that would change how the domain parses as a URL host
+    let forbidden = |c: char| c.is_whitespace() || "/?#\\:[]<>@%".contains(c);
+    if domain.is_empty() || domain.contains(forbidden) {
+        return Err(Error::invalid_url(input, "no valid email domain"));
+    }
+    Ok(domain.to_string())
+}
+
+/// Look up MX records, sorted by preference.
+///
+/// Returns `None` if the domain has no MX records.
+async fn lookup_mx(domain: &str, timeout: Duration) -> Result<Option<Vec<(u16, String)>>, Error> {
+    let lookup = async {
+        let _permit = lookup_permit().await;
+        let resolver = build_resolver(domain)?;
+        match resolver.mx_lookup(domain).await {
+            Ok(response) => Ok(Some(response.iter().cloned().collect::<Vec<MX>>())),
+            Err(e) if e.is_no_records_found() => Ok(None),
+            Err(e) => Err(Error::dns_error(domain, e.to_string())),
+        }
+    };
+    let records = tokio::time::timeout(timeout, lookup)
+        .await
+        .map_err(|_| Error::Timeout {
+            message: format!("MX lookup for {} timed out after {:?}", domain, timeout),
+        })??;
+
+    let Some(records) = records else {
+        return Ok(None);
+    };
+    mail_exchangers(domain, records).map(Some)
+}
+
+/// Turn MX records into `(preference, host)` pairs, detecting null MX.
+fn mail_exchangers(domain: &str, records: Vec<MX>) -> Result<Vec<(u16, String)>, Error> {
+    let mut exchangers: Vec<(u16, String)> = records
+        .iter()
+        .map(|mx| {
+            let host = mx.exchange().to_ascii();
+            (mx.preference(), host.trim_end_matches('.').to_lowercase())
+        })
+        .collect();
+
+    // RFC 7505 null MX: a single "." exchanger means no mail is accepted
+    if exchangers.iter().any(|(_, host)| host.is_empty()) {
+        return Err(Error::dns_error(
+            domain,
+            "domain does not accept mail (null MX)",
+        ));
+    }
+    if exchangers.is_empty() {
+        return Err(Error::dns_error(domain, "no MX records found"));
+    }
+
+    exchangers.sort();
+    exchangers.dedup();
+    Ok(exchangers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_resolver::proto::rr::Name;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_extract_domain() {
+        assert_eq!(extract_domain("alice@Example.com").unwrap(), "Example.com");
+        assert_eq!(
+            extract_domain("Alice <alice@example.com>").unwrap(),
+            "example.com"
+        );
+        assert_eq!(extract_domain(" example.com ").unwrap(), "example.com");
+        assert_eq!(
+            extract_domain("\"a@b\"@example.com").unwrap(),
+            "example.com"
+        );
+        assert_eq!(extract_domain("a@[192.0.2.1]").unwrap(), "192.0.2.1");
+        assert_eq!(
+            extract_domain("a@[IPv6:2001:db8::1]").unwrap(),
+            "[2001:db8::1]"
+        );
+    }
+
+    #[test]
+    fn test_extract_domain_rejects_url_syntax() {
+        for input in [
+            "a@",
+            "a@evil.com/x",
+            "a@evil.com:25",
+            "a@evil.com#",
+            "a@ex ample.com",
+            "a@[not-an-ip]",
+            "a@ex%61mple.com",
+        ] {
+            assert!(
+                matches!(extract_domain(input), Err(Error::InvalidUrl { .. })),
+                "{} accepted",
+                input
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocked_domains_rejected_without_dns() {
+        let err = validate_email_domain("a@metadata.google.internal", Policy::PublicOnly)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::HostnameBlocked { .. }));
+
+        let err = validate_email_domain("a@[127.0.0.1]", Policy::PublicOnly)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::SsrfBlocked { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_address_literal_allowed() {
+        let domain = validate_email_domain("a@[8.8.8.8]", Policy::PublicOnly)
+            .await
+            .unwrap();
+        assert_eq!(domain.domain, "8.8.8.8");
+        assert_eq!(domain.mail_hosts.len(), 1);
+        assert_eq!(domain.mail_hosts[0].ip.to_string(), "8.8.8.8");
+    }
+
+    fn mx(preference: u16, exchange: &str) -> MX {
+        MX::new(preference, Name::from_str(exchange).unwrap())
+    }
+
+    #[test]
+    fn test_mail_exchangers_sorted() {
+        let hosts = mail_exchangers(
+            "example.com",
+            vec![mx(20, "MX2.example.com."), mx(10, "mx1.example.com.")],
+        )
+        .unwrap();
+        assert_eq!(
+            hosts,
+            [
+                (10, "mx1.example.com".to_string()),
+                (20, "mx2.example.com".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_null_mx_rejected() {
+        let err = mail_exchangers("example.com", vec![mx(0, ".")]).unwrap_err();
+        assert!(matches!(err, Error::DnsError { .. }));
+        assert!(err.to_string().contains("null MX"));
+    }
+}
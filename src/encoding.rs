@@ -0,0 +1,256 @@
+//! Strict percent-encoding rules per URL component.
+//!
+//! url_jail already rejects all percent-encoding in the scheme and authority
+//! (see [`SafeUrl::parse`](crate::SafeUrl::parse)). The path and query are
+//! passed through as given, which is usually right, but a downstream proxy or
+//! server may decode them differently from the HTTP client: `/%2e%2e/admin`
+//! or `/public%2F..%2Fadmin` can slip past a path-based ACL that only sees
+//! the literal form. A [`PercentEncoding`] in
+//! [`ValidateOptions::percent_encoding`](crate::ValidateOptions::percent_encoding)
+//! rejects such URLs with [`Error::AmbiguousUrl`] before any DNS lookup.
+
+use crate::error::Error;
+
+/// Percent-encoding rules for one URL component.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentEncoding {
+    /// Bytes that must not appear percent-encoded.
+    pub forbidden: Vec<u8>,
+
+    /// Allow encoded characters that never need encoding (`%61` for `a`).
+    pub allow_unreserved: bool,
+
+    /// Allow an encoded `%` (`%252F`), which decodes to another escape.
+    pub allow_double_encoding: bool,
+}
+
+impl ComponentEncoding {
+    /// Rules that accept every well-formed escape.
+    pub fn permissive() -> Self {
+        Self {
+            forbidden: Vec::new(),
+            allow_unreserved: true,
+            allow_double_encoding: true,
+        }
+    }
+
+    fn check(&self, url: &str, component: &str, raw: &str) -> Result<(), Error> {
+        let bytes = raw.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] != b'%' {
+                i += 1;
+                continue;
+            }
+            let Some(byte) = raw
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            else {
+                return Err(Error::ambiguous_url(
+                    url,
+                    format!("malformed percent-escape in {}", component),
+                ));
+            };
+            let reason = if self.forbidden.contains(&byte) {
+                Some("percent-encoded")
+            } else if byte == b'%' && !self.allow_double_encoding {
+                Some("double percent-encoded")
+            } else if is_unreserved(byte) && !self.allow_unreserved {
+                Some("needlessly percent-encoded")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                return Err(Error::ambiguous_url(
+                    url,
+                    format!("{} {} in {}", reason, describe(byte), component),
+                ));
+            }
+            i += 3;
+        }
+        Ok(())
+    }
+}
+
+/// Which characters may appear percent-encoded in the path and query.
+///
+/// The default is strict where canonicalization confusion is known to
+/// matter:
+///
+/// | Component | Rejected when encoded |
+/// |-----------|-----------------------|
+/// | Path | `/`, `\`, `.`, `;`, control characters, `%` (double encoding), unreserved characters |
+/// | Query | control characters |
+///
+/// Malformed escapes (`%zz`, a trailing `%`) are always rejected.
+///
+/// # Example
+///
+/// ```rust
+/// use url_jail::PercentEncoding;
+///
+/// let rules = PercentEncoding::default();
+/// assert!(rules.check("https://example.com/files/a%20b?q=x%2Fy").is_ok());
+/// assert!(rules.check("https://example.com/public/%2e%2e/admin").is_err());
+/// assert!(rules.check("https://example.com/%61dmin").is_err());
+///
+/// // APIs that put encoded slashes in path segments
+/// let mut gitlab = PercentEncoding::default();
+/// gitlab.path.forbidden.retain(|&b| b != b'/');
+/// assert!(gitlab.check("https://gitlab.example/api/v4/projects/group%2Frepo").is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PercentEncoding {
+    /// Rules for the path.
+    pub path: ComponentEncoding,
+
+    /// Rules for the query string.
+    pub query: ComponentEncoding,
+}
+
+impl Default for PercentEncoding {
+    fn default() -> Self {
+        Self {
+            path: ComponentEncoding {
+                forbidden: [b'/', b'\\', b'.', b';']
+                    .into_iter()
+                    .chain(control_bytes())
+                    .collect(),
+                allow_unreserved: false,
+                allow_double_encoding: false,
+            },
+            query: ComponentEncoding {
+                forbidden: control_bytes().collect(),
+                ..ComponentEncoding::permissive()
+            },
+        }
+    }
+}
+
+impl PercentEncoding {
+    /// Check the path and query of `url` against these rules.
+    ///
+    /// Only the path and query are checked; the fragment is never sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AmbiguousUrl`] naming the offending escape.
+    pub fn check(&self, url: &str) -> Result<(), Error> {
+        let Some((_, rest)) = url.split_once("://") else {
+            // Not a hierarchical URL; the parser rejects it
+            return Ok(());
+        };
+        let rest = rest.split('#').next().unwrap_or_default();
+        let Some(path_start) = rest.find(['/', '?']) else {
+            return Ok(());
+        };
+        let rest = &rest[path_start..];
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        self.path.check(url, "path", path)?;
+        self.query.check(url, "query", query)
+    }
+}
+
+fn control_bytes() -> impl Iterator<Item = u8> {
+    (0x00..=0x1f).chain([0x7f])
+}
+
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+}
+
+fn describe(byte: u8) -> String {
+    if byte.is_ascii_graphic() {
+        format!("'{}'", byte as char)
+    } else {
+        format!("byte 0x{:02x}", byte)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(rules: &PercentEncoding, url: &str) -> String {
+        match rules.check(url) {
+            Err(Error::AmbiguousUrl { reason, .. }) => reason,
+            other => panic!("{} not rejected: {:?}", url, other),
+        }
+    }
+
+    #[test]
+    fn test_default_rejects_confusing_path_escapes() {
+        let rules = PercentEncoding::default();
+        for (url, expected) in [
+            (
+                "https://a.example/x%2F..%2Fadmin",
+                "percent-encoded '/' in path",
+            ),
+            ("https://a.example/%5cadmin", "percent-encoded '\\' in path"),
+            (
+                "https://a.example/%2e%2e/admin",
+                "percent-encoded '.' in path",
+            ),
+            ("https://a.example/admin%3bx", "percent-encoded ';' in path"),
+            (
+                "https://a.example/a%00.txt",
+                "percent-encoded byte 0x00 in path",
+            ),
+            (
+                "https://a.example/a%252F",
+                "double percent-encoded '%' in path",
+            ),
+            (
+                "https://a.example/%41dmin",
+                "needlessly percent-encoded 'A' in path",
+            ),
+            ("https://a.example/a%zz", "malformed percent-escape in path"),
+            (
+                "https://a.example/?q=%0d%0aX:1",
+                "percent-encoded byte 0x0d in query",
+            ),
+            (
+                "https://a.example/?q=100%",
+                "malformed percent-escape in query",
+            ),
+        ] {
+            assert_eq!(reason(&rules, url), expected, "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_default_accepts_ordinary_urls() {
+        let rules = PercentEncoding::default();
+        for url in [
+            "https://a.example",
+            "https://a.example/files/na%C3%AFve%20name.pdf",
+            "https://a.example/?next=http%3A%2F%2Fb.example%2F&u=http%253A",
+            "https://a.example/a#%2e%2e%2F",
+            "https://a.example?q=%41",
+        ] {
+            assert!(rules.check(url).is_ok(), "{} rejected", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_applied_by_validate_options() {
+        use crate::{validate_with_options, Policy, ValidateOptions};
+
+        let url = "http://93.184.216.34/%2e%2e/admin";
+        let opts = |percent_encoding| ValidateOptions {
+            percent_encoding,
+            ..Default::default()
+        };
+        assert!(validate_with_options(url, Policy::PublicOnly, opts(None))
+            .await
+            .is_ok());
+        let err = validate_with_options(
+            url,
+            Policy::PublicOnly,
+            opts(Some(PercentEncoding::default())),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.is_blocked(), "{:?}", err);
+    }
+}
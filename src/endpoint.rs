@@ -0,0 +1,374 @@
+//! Validation of infrastructure endpoint configs.
+//!
+//! Telemetry exporters, collectors, and metrics sinks are configured with
+//! outbound endpoints just like user-facing features are, and a misconfigured
+//! (or attacker-influenced) endpoint can point them at metadata services or
+//! internal hosts. These helpers validate such endpoints once at startup and
+//! return the addresses to pin the exporter to.
+//!
+//! Accepted forms:
+//!
+//! | Form | Example | Notes |
+//! |------|---------|-------|
+//! | HTTP(S) URL | `https://otlp.example.com:4318/v1/traces` | OTLP/HTTP, OTLP/gRPC |
+//! | `host:port` | `statsd.example.com:8125` | Port required |
+//! | `udp://` / `tcp://` / `grpc://` / `grpcs://` | `udp://10.0.0.5:8125` | Port required |
+//!
+//! Collectors often live on private networks; use [`Policy::AllowPrivate`] or
+//! a [`CustomPolicy`] that allows their ranges.
+//!
+//! Targets that come as a separate host and port, such as SMTP relays and
+//! database hosts, go through [`validate_host`] instead.
+
+use std::net::Ipv6Addr;
+use std::time::Instant;
+
+use crate::context::Context;
+use crate::error::Error;
+use crate::policy::{Policy, ValidationPolicy};
+use crate::policy_builder::CustomPolicy;
+use crate::validate::{
+    drive, validate_custom_with_options, validate_with_options, ValidateOptions, Validated,
+};
+
+/// Non-HTTP schemes accepted for `scheme://host:port` endpoints.
+const SOCKET_SCHEMES: &[&str] = &["udp", "tcp", "grpc", "grpcs"];
+
+/// Validate an exporter or collector endpoint.
+///
+/// Returns the address the exporter should connect to. For `host:port` and
+/// socket-scheme endpoints, [`Validated::url`] is a synthetic `http://host:port/`
+/// URL; use [`Validated::to_socket_addr`] for the pinned address.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use url_jail::{validate_endpoint, Policy};
+///
+/// # async fn example() -> Result<(), url_jail::Error> {
+/// let statsd = validate_endpoint("statsd.example.com:8125", Policy::PublicOnly).await?;
+/// let socket = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+/// socket.connect(statsd.to_socket_addr()).unwrap();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidUrl`] if the endpoint is malformed or lacks a
+/// required port, plus any error from [`validate`](crate::validate).
+pub async fn validate_endpoint(endpoint: &str, policy: Policy) -> Result<Validated, Error> {
+    let url = endpoint_url(endpoint)?;
+    validate_with_options(&url, policy, ValidateOptions::default()).await
+}
+
+/// Validate an exporter or collector endpoint against a custom policy.
+///
+/// See [`validate_endpoint`] for the accepted forms.
+pub async fn validate_endpoint_custom(
+    endpoint: &str,
+    policy: &CustomPolicy,
+) -> Result<Validated, Error> {
+    let url = endpoint_url(endpoint)?;
+    validate_custom_with_options(&url, policy, ValidateOptions::default()).await
+}
+
+/// Validate a set of named endpoints at startup, failing on the first bad one.
+///
+/// Each error is tagged with the endpoint's name (`endpoint=<name>`), so a
+/// startup failure says which config entry to fix.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use url_jail::{validate_endpoints, Policy};
+///
+/// # async fn example() -> Result<(), url_jail::Error> {
+/// let pinned = validate_endpoints(
+///     [
+///         ("otlp_traces", "https://otlp.example.com:4318/v1/traces"),
+///         ("statsd", "udp://statsd.example.com:8125"),
+///     ],
+///     Policy::AllowPrivate,
+/// )
+/// .await?;
+/// for (name, validated) in &pinned {
+///     println!("{} -> {}", name, validated.to_socket_addr());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn validate_endpoints<'a, I>(
+    endpoints: I,
+    policy: Policy,
+) -> Result<Vec<(String, Validated)>, Error>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let mut pinned = Vec::new();
+    for (name, endpoint) in endpoints {
+        let context = Context::new().tag("endpoint", name);
+        let url = endpoint_url(endpoint).map_err(|e| e.with_context(&context))?;
+        let options = ValidateOptions {
+            context,
+            ..Default::default()
+        };
+        let validated = validate_with_options(&url, policy, options).await?;
+        pinned.push((name.to_string(), validated));
+    }
+    Ok(pinned)
+}
+
+/// Validate a host and port that are not part of a URL.
+///
+/// Runs the hostname checks, DNS resolution and address checks of
+/// [`validate`](crate::validate), for targets such as SMTP relays and
+/// database hosts from user configuration. `host` is a hostname or an IP
+/// address; IPv6 addresses may be bracketed. Custom policy rules about the
+/// URL itself (schemes, URL prefixes, paths) do not apply; host and port
+/// rules do.
+///
+/// [`Validated::url`] is a synthetic `http://host:port/` URL; connect to
+/// [`Validated::to_socket_addr`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use url_jail::{validate_host, Policy};
+///
+/// # async fn example() -> Result<(), url_jail::Error> {
+/// let relay = validate_host("smtp.example.com", 587, Policy::PublicOnly).await?;
+/// let stream = std::net::TcpStream::connect(relay.to_socket_addr()).unwrap();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidUrl`] if `host` is not a hostname or IP address,
+/// plus any error from [`validate`](crate::validate).
+pub async fn validate_host<P: ValidationPolicy>(
+    host: &str,
+    port: u16,
+    policy: P,
+) -> Result<Validated, Error> {
+    validate_host_with_options(host, port, policy, ValidateOptions::default()).await
+}
+
+/// Validate a host and port with custom options.
+///
+/// Options about URL syntax ([`percent_encoding`](ValidateOptions::percent_encoding),
+/// [`cross_check_parsers`](ValidateOptions::cross_check_parsers)) do not
+/// apply. See [`validate_host`].
+pub async fn validate_host_with_options<P: ValidationPolicy>(
+    host: &str,
+    port: u16,
+    policy: P,
+    options: ValidateOptions,
+) -> Result<Validated, Error> {
+    let started = Instant::now();
+    let url = host_url(host, port).map_err(|e| e.with_context(&options.context))?;
+    let mut machine = policy.machine(&url).host_only();
+    if options.collect_violations {
+        machine = machine.collect_violations();
+    }
+    let result = drive(machine.start(), &options, policy.fingerprint()).await;
+    if let Some(budget) = &options.latency_budget {
+        budget.observe(&url, started.elapsed(), result.is_ok());
+    }
+    result
+}
+
+/// The synthetic URL validated for a bare host and port.
+fn host_url(host: &str, port: u16) -> Result<String, Error> {
+    let host = host.trim();
+    let unbracketed = host
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .unwrap_or(host);
+    if let Ok(v6) = unbracketed.parse::<Ipv6Addr>() {
+        return Ok(format!("http://[{}]:{}/", v6, port));
+    }
+    let delimiter = |c: char| c.is_whitespace() || ":/?#@[]\\%".contains(c);
+    if host.is_empty() || host.contains(delimiter) {
+        return Err(Error::invalid_url(
+            host,
+            "host must be a hostname or IP address",
+        ));
+    }
+    Ok(format!("http://{}:{}/", host, port))
+}
+
+/// Convert an endpoint config value into a URL for validation.
+fn endpoint_url(endpoint: &str) -> Result<String, Error> {
+    let endpoint = endpoint.trim();
+    let authority = match endpoint.split_once("://") {
+        Some((scheme, _)) if scheme.eq_ignore_ascii_case("http") => return Ok(endpoint.into()),
+        Some((scheme, _)) if scheme.eq_ignore_ascii_case("https") => return Ok(endpoint.into()),
+        Some((scheme, rest))
+            if SOCKET_SCHEMES
+                .iter()
+                .any(|s| scheme.eq_ignore_ascii_case(s)) =>
+        {
+            rest.strip_suffix('/').unwrap_or(rest)
+        }
+        // Let URL parsing report the forbidden scheme
+        Some(_) => return Ok(endpoint.into()),
+        None => endpoint,
+    };
+
+    if authority.contains(['/', '?', '#', '@']) {
+        return Err(Error::invalid_url(
+            endpoint,
+            "endpoint must be host:port without a path",
+        ));
+    }
+    if !has_port(authority) {
+        return Err(Error::invalid_url(endpoint, "endpoint must include a port"));
+    }
+    Ok(format!("http://{}/", authority))
+}
+
+/// Whether a `host:port` authority ends in a numeric port.
+fn has_port(authority: &str) -> bool {
+    let port = match authority.strip_prefix('[') {
+        Some(bracketed) => bracketed.split_once("]:").map(|(_, port)| port),
+        None => authority.rsplit_once(':').map(|(_, port)| port),
+    };
+    port.is_some_and(|port| port.parse::<u16>().is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_url_forms() {
+        assert_eq!(
+            endpoint_url("https://otlp.example.com:4318/v1/traces").unwrap(),
+            "https://otlp.example.com:4318/v1/traces"
+        );
+        assert_eq!(
+            endpoint_url("statsd.example.com:8125").unwrap(),
+            "http://statsd.example.com:8125/"
+        );
+        assert_eq!(
+            endpoint_url("UDP://10.0.0.5:8125").unwrap(),
+            "http://10.0.0.5:8125/"
+        );
+        assert_eq!(
+            endpoint_url("grpc://[2001:db8::1]:4317/").unwrap(),
+            "http://[2001:db8::1]:4317/"
+        );
+    }
+
+    #[test]
+    fn test_host_url() {
+        assert_eq!(
+            host_url("smtp.example.com", 587).unwrap(),
+            "http://smtp.example.com:587/"
+        );
+        assert_eq!(host_url("::1", 5432).unwrap(), "http://[::1]:5432/");
+        assert_eq!(
+            host_url(" [2001:db8::1] ", 25).unwrap(),
+            "http://[2001:db8::1]:25/"
+        );
+        for host in ["", "db/admin", "db:5432", "user@db", "db%2f", "a b", "[db]"] {
+            assert!(
+                matches!(host_url(host, 1), Err(Error::InvalidUrl { .. })),
+                "{:?} accepted",
+                host
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_host_skips_url_rules() {
+        let policy = crate::PolicyBuilder::new(Policy::AllowPrivate)
+            .allow_schemes(["https"])
+            .block_path_glob("/*")
+            .block_ports([25])
+            .build();
+        let db = validate_host("10.0.0.5", 5432, &policy).await.unwrap();
+        assert_eq!(db.to_socket_addr(), "10.0.0.5:5432".parse().unwrap());
+        assert_eq!(db.url, "http://10.0.0.5:5432/");
+
+        let err = validate_host("10.0.0.5", 25, &policy).await.unwrap_err();
+        assert!(err.is_blocked(), "{:?}", err);
+        for host in ["::1", "metadata.google.internal", "169.254.169.254"] {
+            let err = validate_host(host, 5432, &policy).await.unwrap_err();
+            assert!(err.is_blocked(), "{}: {:?}", host, err);
+        }
+    }
+
+    #[test]
+    fn test_endpoint_url_requires_port() {
+        for endpoint in [
+            "statsd.example.com",
+            "udp://statsd.example.com",
+            "[::1]",
+            "host:abc",
+        ] {
+            assert!(
+                matches!(endpoint_url(endpoint), Err(Error::InvalidUrl { .. })),
+                "{} accepted",
+                endpoint
+            );
+        }
+        assert!(endpoint_url("tcp://host:1/path").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_endpoint_pins_address() {
+        let v = validate_endpoint("8.8.8.8:8125", Policy::PublicOnly)
+            .await
+            .unwrap();
+        assert_eq!(v.to_socket_addr().to_string(), "8.8.8.8:8125");
+    }
+
+    #[tokio::test]
+    async fn test_validate_endpoint_blocks_metadata() {
+        let err = validate_endpoint("udp://169.254.169.254:8125", Policy::AllowPrivate)
+            .await
+            .unwrap_err();
+        assert!(err.is_blocked());
+
+        let err = validate_endpoint("unix:///var/run/statsd.sock", Policy::AllowPrivate)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidUrl { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_validate_endpoints_names_failure() {
+        let err = validate_endpoints(
+            [
+                ("statsd", "10.0.0.5:8125"),
+                ("otlp", "http://127.0.0.1:4318/"),
+            ],
+            Policy::AllowPrivate,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().ends_with("[endpoint=otlp]"), "{}", err);
+
+        let pinned = validate_endpoints([("statsd", "10.0.0.5:8125")], Policy::AllowPrivate)
+            .await
+            .unwrap();
+        assert_eq!(pinned[0].0, "statsd");
+        assert_eq!(pinned[0].1.port, 8125);
+    }
+
+    #[tokio::test]
+    async fn test_validate_endpoint_custom() {
+        let policy = crate::PolicyBuilder::new(Policy::PublicOnly)
+            .allow_cidr("10.20.0.0/16")
+            .build();
+        assert!(validate_endpoint_custom("10.20.1.1:4317", &policy)
+            .await
+            .is_ok());
+        assert!(validate_endpoint_custom("10.30.1.1:4317", &policy)
+            .await
+            .is_err());
+    }
+}
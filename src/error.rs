@@ -3,6 +3,8 @@
 use std::net::IpAddr;
 use thiserror::Error;
 
+use crate::context::Context;
+
 /// Errors that can occur during URL validation.
 #[derive(Debug, Error)]
 pub enum Error {
@@ -22,10 +24,25 @@ pub enum Error {
         reason: String,
     },
 
+    /// URL scheme is not allowed by a custom policy's scheme allowlist.
+    #[error("Scheme blocked: {url} uses '{scheme}' - {reason}")]
+    SchemeBlocked {
+        url: String,
+        scheme: String,
+        reason: String,
+    },
+
     /// Invalid URL syntax or forbidden scheme.
     #[error("Invalid URL '{url}': {reason}")]
     InvalidUrl { url: String, reason: String },
 
+    /// URL syntax that different parsers interpret differently.
+    ///
+    /// Raised for parser-differential tricks such as percent-encoded
+    /// delimiters in the authority, nested schemes, or missing `//`.
+    #[error("Ambiguous URL '{url}': {reason}")]
+    AmbiguousUrl { url: String, reason: String },
+
     /// DNS resolution failed.
     #[error("DNS error for {host}: {message}")]
     DnsError { host: String, message: String },
@@ -44,6 +61,11 @@ pub enum Error {
     #[error("Too many redirects from {url} (limit: {max})")]
     TooManyRedirects { url: String, max: u8 },
 
+    /// The final response violated the fetch response policy.
+    #[cfg(feature = "fetch")]
+    #[error("Response blocked for {url}: {reason}")]
+    ResponseBlocked { url: String, reason: String },
+
     /// HTTP request failed.
     #[cfg(feature = "fetch")]
     #[error("HTTP error for {url}: {message}")]
@@ -52,15 +74,129 @@ pub enum Error {
     /// Operation timed out.
     #[error("Timeout: {message}")]
     Timeout { message: String },
+
+    /// More than one check rejected the URL.
+    ///
+    /// Only returned when collecting violations, with
+    /// [`ValidateOptions::collect_violations`](crate::ValidateOptions::collect_violations)
+    /// or [`ValidationMachine::collect_violations`](crate::ValidationMachine::collect_violations).
+    /// `errors` lists each failure in pipeline order.
+    #[error("{} violations for {url}: {}", .errors.len(), join(.errors))]
+    Violations { url: String, errors: Vec<Error> },
+}
+
+fn join(errors: &[Error]) -> String {
+    errors
+        .iter()
+        .map(Error::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// The kind of an [`Error`](crate::Error), as a stable identifier.
+///
+/// Every kind exists whatever features are enabled, so tables keyed by kind
+/// (such as a [`DisplayPolicy`](crate::DisplayPolicy)) compile the same way
+/// everywhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// [`Error::SsrfBlocked`](crate::Error::SsrfBlocked)
+    SsrfBlocked,
+    /// [`Error::HostnameBlocked`](crate::Error::HostnameBlocked)
+    HostnameBlocked,
+    /// [`Error::SchemeBlocked`](crate::Error::SchemeBlocked)
+    SchemeBlocked,
+    /// [`Error::InvalidUrl`](crate::Error::InvalidUrl)
+    InvalidUrl,
+    /// [`Error::AmbiguousUrl`](crate::Error::AmbiguousUrl)
+    AmbiguousUrl,
+    /// [`Error::DnsError`](crate::Error::DnsError)
+    DnsError,
+    /// `Error::RedirectBlocked` (with the `fetch` feature)
+    RedirectBlocked,
+    /// `Error::TooManyRedirects` (with the `fetch` feature)
+    TooManyRedirects,
+    /// `Error::ResponseBlocked` (with the `fetch` feature)
+    ResponseBlocked,
+    /// `Error::HttpError` (with the `fetch` feature)
+    HttpError,
+    /// [`Error::Timeout`](crate::Error::Timeout)
+    Timeout,
+    /// [`Error::Violations`](crate::Error::Violations)
+    Violations,
+}
+
+impl ErrorKind {
+    /// A stable snake_case code, e.g. `ssrf_blocked`, for use as a
+    /// translation key or metrics label.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::SsrfBlocked => "ssrf_blocked",
+            ErrorKind::HostnameBlocked => "hostname_blocked",
+            ErrorKind::SchemeBlocked => "scheme_blocked",
+            ErrorKind::InvalidUrl => "invalid_url",
+            ErrorKind::AmbiguousUrl => "ambiguous_url",
+            ErrorKind::DnsError => "dns_error",
+            ErrorKind::RedirectBlocked => "redirect_blocked",
+            ErrorKind::TooManyRedirects => "too_many_redirects",
+            ErrorKind::ResponseBlocked => "response_blocked",
+            ErrorKind::HttpError => "http_error",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Violations => "violations",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 impl Error {
+    /// The kind of this error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{validate_sync, ErrorKind, Policy};
+    ///
+    /// let err = validate_sync("http://127.0.0.1/", Policy::PublicOnly).unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::SsrfBlocked);
+    /// assert_eq!(err.kind().as_str(), "ssrf_blocked");
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::SsrfBlocked { .. } => ErrorKind::SsrfBlocked,
+            Error::HostnameBlocked { .. } => ErrorKind::HostnameBlocked,
+            Error::SchemeBlocked { .. } => ErrorKind::SchemeBlocked,
+            Error::InvalidUrl { .. } => ErrorKind::InvalidUrl,
+            Error::AmbiguousUrl { .. } => ErrorKind::AmbiguousUrl,
+            Error::DnsError { .. } => ErrorKind::DnsError,
+            Error::Timeout { .. } => ErrorKind::Timeout,
+            Error::Violations { .. } => ErrorKind::Violations,
+            #[cfg(feature = "fetch")]
+            Error::RedirectBlocked { .. } => ErrorKind::RedirectBlocked,
+            #[cfg(feature = "fetch")]
+            Error::TooManyRedirects { .. } => ErrorKind::TooManyRedirects,
+            #[cfg(feature = "fetch")]
+            Error::ResponseBlocked { .. } => ErrorKind::ResponseBlocked,
+            #[cfg(feature = "fetch")]
+            Error::HttpError { .. } => ErrorKind::HttpError,
+        }
+    }
+
     /// Returns `true` if this error represents a security block.
     ///
     /// This includes:
     /// - `SsrfBlocked` - IP address blocked by policy
     /// - `HostnameBlocked` - Hostname blocked by policy  
+    /// - `SchemeBlocked` - Scheme not in a custom policy's scheme allowlist
+    /// - `AmbiguousUrl` - URL crafted to be parsed differently by different parsers
     /// - `RedirectBlocked` - Redirect to blocked URL (with `fetch` feature)
+    /// - `ResponseBlocked` - Response violated the fetch response policy (with `fetch` feature)
+    /// - `Violations` - If any of its errors is a security block
     ///
     /// Use this to distinguish security rejections from other errors like DNS failures.
     ///
@@ -77,13 +213,22 @@ impl Error {
     /// }
     /// ```
     pub fn is_blocked(&self) -> bool {
+        if let Error::Violations { errors, .. } = self {
+            return errors.iter().any(Error::is_blocked);
+        }
         matches!(
             self,
-            Error::SsrfBlocked { .. } | Error::HostnameBlocked { .. }
+            Error::SsrfBlocked { .. }
+                | Error::HostnameBlocked { .. }
+                | Error::SchemeBlocked { .. }
+                | Error::AmbiguousUrl { .. }
         ) || {
             #[cfg(feature = "fetch")]
             {
-                matches!(self, Error::RedirectBlocked { .. })
+                matches!(
+                    self,
+                    Error::RedirectBlocked { .. } | Error::ResponseBlocked { .. }
+                )
             }
             #[cfg(not(feature = "fetch"))]
             {
@@ -115,6 +260,9 @@ impl Error {
     /// }
     /// ```
     pub fn is_retriable(&self) -> bool {
+        if let Error::Violations { errors, .. } = self {
+            return errors.iter().all(Error::is_retriable);
+        }
         matches!(self, Error::DnsError { .. } | Error::Timeout { .. }) || {
             #[cfg(feature = "fetch")]
             {
@@ -147,18 +295,46 @@ impl Error {
         match self {
             Error::SsrfBlocked { url, .. } => Some(url),
             Error::HostnameBlocked { url, .. } => Some(url),
+            Error::SchemeBlocked { url, .. } => Some(url),
             Error::InvalidUrl { url, .. } => Some(url),
+            Error::AmbiguousUrl { url, .. } => Some(url),
             Error::DnsError { .. } => None, // Only has host, not full URL
             Error::Timeout { .. } => None,
+            Error::Violations { url, .. } => Some(url),
             #[cfg(feature = "fetch")]
             Error::RedirectBlocked { original_url, .. } => Some(original_url),
             #[cfg(feature = "fetch")]
             Error::TooManyRedirects { url, .. } => Some(url),
             #[cfg(feature = "fetch")]
+            Error::ResponseBlocked { url, .. } => Some(url),
+            #[cfg(feature = "fetch")]
             Error::HttpError { url, .. } => Some(url),
         }
     }
 
+    /// The individual failures: the errors of [`Error::Violations`], or
+    /// this error alone.
+    ///
+    /// Lets code that reports every problem (such as a form showing why a
+    /// webhook URL was rejected) handle both cases the same way.
+    pub fn violations(&self) -> &[Error] {
+        match self {
+            Error::Violations { errors, .. } => errors,
+            _ => std::slice::from_ref(self),
+        }
+    }
+
+    /// One violation as is, or several as [`Error::Violations`].
+    pub(crate) fn collected(url: impl Into<String>, mut errors: Vec<Error>) -> Self {
+        if errors.len() == 1 {
+            return errors.remove(0);
+        }
+        Self::Violations {
+            url: url.into(),
+            errors,
+        }
+    }
+
     pub(crate) fn ssrf_blocked(
         url: impl Into<String>,
         ip: IpAddr,
@@ -190,12 +366,49 @@ impl Error {
         }
     }
 
+    pub(crate) fn ambiguous_url(url: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self::AmbiguousUrl {
+            url: url.into(),
+            reason: reason.into(),
+        }
+    }
+
     pub(crate) fn dns_error(host: impl Into<String>, message: impl Into<String>) -> Self {
         Self::DnsError {
             host: host.into(),
             message: message.into(),
         }
     }
+
+    /// Append the caller's context tags to the error's reason or message.
+    pub(crate) fn with_context(mut self, context: &Context) -> Self {
+        if context.is_empty() {
+            return self;
+        }
+        let detail = match &mut self {
+            Error::SsrfBlocked { reason, .. }
+            | Error::HostnameBlocked { reason, .. }
+            | Error::SchemeBlocked { reason, .. }
+            | Error::InvalidUrl { reason, .. }
+            | Error::AmbiguousUrl { reason, .. } => reason,
+            Error::DnsError { message, .. } | Error::Timeout { message } => message,
+            #[cfg(feature = "fetch")]
+            Error::RedirectBlocked { reason, .. } | Error::ResponseBlocked { reason, .. } => reason,
+            #[cfg(feature = "fetch")]
+            Error::HttpError { message, .. } => message,
+            #[cfg(feature = "fetch")]
+            Error::TooManyRedirects { .. } => return self,
+            Error::Violations { url, errors } => {
+                let errors = errors.drain(..).map(|e| e.with_context(context)).collect();
+                return Error::Violations {
+                    url: std::mem::take(url),
+                    errors,
+                };
+            }
+        };
+        detail.push_str(&format!(" [{}]", context));
+        self
+    }
 }
 
 #[cfg(test)]
@@ -203,6 +416,40 @@ mod tests {
     use super::*;
     use std::net::Ipv4Addr;
 
+    #[test]
+    fn test_kind_codes_are_distinct() {
+        let kinds = [
+            ErrorKind::SsrfBlocked,
+            ErrorKind::HostnameBlocked,
+            ErrorKind::SchemeBlocked,
+            ErrorKind::InvalidUrl,
+            ErrorKind::AmbiguousUrl,
+            ErrorKind::DnsError,
+            ErrorKind::RedirectBlocked,
+            ErrorKind::TooManyRedirects,
+            ErrorKind::ResponseBlocked,
+            ErrorKind::HttpError,
+            ErrorKind::Timeout,
+            ErrorKind::Violations,
+        ];
+        let codes: std::collections::HashSet<_> = kinds.iter().map(ErrorKind::as_str).collect();
+        assert_eq!(codes.len(), kinds.len());
+        assert_eq!(Error::dns_error("a", "b").kind(), ErrorKind::DnsError);
+    }
+
+    #[test]
+    fn test_with_context_appends_tags() {
+        let err = Error::ssrf_blocked("http://127.0.0.1/", Ipv4Addr::LOCALHOST.into(), "loopback")
+            .with_context(&Context::new().tag("tenant", "acme"));
+        assert_eq!(
+            err.to_string(),
+            "SSRF blocked: http://127.0.0.1/ resolved to 127.0.0.1 - loopback [tenant=acme]"
+        );
+
+        let err = Error::dns_error("example.com", "timed out").with_context(&Context::new());
+        assert_eq!(err.to_string(), "DNS error for example.com: timed out");
+    }
+
     #[test]
     fn test_is_blocked_ssrf() {
         let err = Error::SsrfBlocked {
@@ -271,6 +518,18 @@ mod tests {
         assert_eq!(err.url(), None);
     }
 
+    #[cfg(feature = "fetch")]
+    #[test]
+    fn test_is_blocked_response() {
+        let err = Error::ResponseBlocked {
+            url: "https://example.com/".into(),
+            reason: "content type not allowed".into(),
+        };
+        assert!(err.is_blocked());
+        assert!(!err.is_retriable());
+        assert_eq!(err.url(), Some("https://example.com/"));
+    }
+
     #[test]
     fn test_invalid_url_not_blocked_or_retriable() {
         let err = Error::InvalidUrl {
@@ -0,0 +1,582 @@
+//! Batched export of validation decisions to an HTTP endpoint.
+//!
+//! Teams that centralize security telemetry often cannot run a metrics or
+//! log agent next to every service. A [`DecisionExporter`] in
+//! [`ValidateOptions::exporter`](crate::ValidateOptions::exporter) queues a
+//! [`DecisionEvent`] for every validation and POSTs them in batches to a
+//! collector, as a JSON array:
+//!
+//! ```json
+//! [
+//!   {"timestamp_ms": 1700000000000, "url": "http://10.0.0.1/", "context": {"user": "u-123"},
+//!    "decision": "block", "reason": "SSRF blocked: ..."},
+//!   {"timestamp_ms": 1700000000250, "url": "https://example.com/", "context": {},
+//!    "decision": "allow", "ip": "93.184.216.34"}
+//! ]
+//! ```
+//!
+//! Exporting never slows validation down. Events wait in a bounded queue;
+//! a background task sends a batch once it is full or once the flush
+//! interval passes. A request that fails with a transport error, a 429 or a
+//! 5xx status is retried with exponential backoff, and its batch is dropped
+//! after the last retry. While the collector is down the queue fills up,
+//! and events arriving at a full queue are dropped. Both kinds of loss are
+//! counted in [`ExportStats`].
+//!
+//! The endpoint is trusted configuration, typically an internal address,
+//! so it is not checked against any policy. Redirects are not followed.
+//!
+//! ```rust,no_run
+//! use url_jail::{DecisionExporter, Policy, ValidateOptions, validate_with_options};
+//!
+//! # async fn example() -> Result<(), url_jail::Error> {
+//! let exporter = DecisionExporter::new("http://telemetry.internal:4318/ssrf")?.spawn();
+//! let opts = ValidateOptions {
+//!     exporter: Some(exporter.clone()),
+//!     ..Default::default()
+//! };
+//! let _ = validate_with_options("https://example.com/", Policy::PublicOnly, opts).await;
+//!
+//! // At shutdown
+//! exporter.flush().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy as RedirectPolicy;
+use reqwest::StatusCode;
+use tokio::sync::Notify;
+
+use crate::context::Context;
+use crate::error::Error;
+use crate::recorder::Decision;
+use crate::validate::Validated;
+
+/// One validation outcome, as exported by a [`DecisionExporter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecisionEvent {
+    /// When the decision was made.
+    pub timestamp: SystemTime,
+
+    /// The URL as passed to validation.
+    pub url: String,
+
+    /// The [`ValidateOptions::context`](crate::ValidateOptions::context) of
+    /// the validation.
+    pub context: Context,
+
+    /// What validation decided.
+    pub decision: Decision,
+}
+
+/// Batching, queueing and retry settings for a [`DecisionExporter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// Events per request.
+    ///
+    /// Default: 100.
+    pub max_batch: usize,
+
+    /// Longest an event waits in the queue before it is sent.
+    ///
+    /// Default: 5 seconds.
+    pub flush_interval: Duration,
+
+    /// Events held while the endpoint is slow or down. Events arriving at a
+    /// full queue are dropped.
+    ///
+    /// Default: 10,000.
+    pub max_queue: usize,
+
+    /// Retries of a failed request before its batch is dropped.
+    ///
+    /// Default: 3.
+    pub max_retries: u32,
+
+    /// Delay before the first retry, doubled for each retry after it.
+    ///
+    /// Default: 500 milliseconds.
+    pub retry_backoff: Duration,
+
+    /// Timeout per request.
+    ///
+    /// Default: 10 seconds.
+    pub timeout: Duration,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            max_batch: 100,
+            flush_interval: Duration::from_secs(5),
+            max_queue: 10_000,
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Counters of a [`DecisionExporter`], from [`DecisionExporter::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportStats {
+    /// Events waiting to be sent.
+    pub queued: usize,
+
+    /// Events the endpoint accepted.
+    pub exported: u64,
+
+    /// Events dropped because the queue was full.
+    pub dropped: u64,
+
+    /// Events dropped because their batch failed after every retry.
+    pub failed: u64,
+}
+
+struct Inner {
+    endpoint: String,
+    options: ExportOptions,
+    client: reqwest::Client,
+    queue: Mutex<VecDeque<DecisionEvent>>,
+    exported: AtomicU64,
+    dropped: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Queues validation decisions and POSTs them in batches to a collector.
+///
+/// Attach it with
+/// [`ValidateOptions::exporter`](crate::ValidateOptions::exporter), and
+/// [`spawn`](Self::spawn) the task that sends batches in the background, or
+/// call [`flush`](Self::flush) yourself.
+///
+/// Clones share the queue and counters.
+#[derive(Clone)]
+pub struct DecisionExporter {
+    inner: Arc<Inner>,
+    /// Wakes the background task when a batch is full.
+    ready: Arc<Notify>,
+}
+
+impl DecisionExporter {
+    /// Export to the `http` or `https` URL `endpoint` with default options.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidUrl`] if `endpoint` is not an `http` or
+    /// `https` URL.
+    pub fn new(endpoint: &str) -> Result<Self, Error> {
+        Self::with_options(endpoint, ExportOptions::default())
+    }
+
+    /// Export to `endpoint` with `options`.
+    ///
+    /// # Errors
+    ///
+    /// As [`new`](Self::new), or [`Error::HttpError`] if the HTTP client
+    /// cannot be built.
+    pub fn with_options(endpoint: &str, options: ExportOptions) -> Result<Self, Error> {
+        let url =
+            url::Url::parse(endpoint).map_err(|e| Error::invalid_url(endpoint, e.to_string()))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(Error::invalid_url(
+                endpoint,
+                format!("unsupported export scheme '{}'", url.scheme()),
+            ));
+        }
+        let client = reqwest::Client::builder()
+            .timeout(options.timeout)
+            .redirect(RedirectPolicy::none())
+            .build()
+            .map_err(|e| Error::HttpError {
+                url: endpoint.to_string(),
+                message: e.to_string(),
+            })?;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                endpoint: url.to_string(),
+                options,
+                client,
+                queue: Mutex::default(),
+                exported: AtomicU64::new(0),
+                dropped: AtomicU64::new(0),
+                failed: AtomicU64::new(0),
+            }),
+            ready: Arc::new(Notify::new()),
+        })
+    }
+
+    /// Send batches on a background Tokio task: whenever a batch fills up,
+    /// and every [`flush_interval`](ExportOptions::flush_interval).
+    ///
+    /// The task stops once every clone of the exporter is dropped; call
+    /// [`flush`](Self::flush) before that to send what is still queued.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a Tokio runtime.
+    pub fn spawn(self) -> Self {
+        let inner = Arc::downgrade(&self.inner);
+        let ready = self.ready.clone();
+        let interval = self.inner.options.flush_interval;
+        tokio::spawn(async move {
+            loop {
+                let _ = tokio::time::timeout(interval, ready.notified()).await;
+                let Some(inner) = inner.upgrade() else {
+                    return;
+                };
+                let exporter = DecisionExporter {
+                    inner,
+                    ready: ready.clone(),
+                };
+                let _ = exporter.flush().await;
+            }
+        });
+        self
+    }
+
+    /// Queue `event` for export.
+    ///
+    /// The event is dropped, and counted in [`ExportStats::dropped`], if the
+    /// queue is full.
+    pub fn export(&self, event: DecisionEvent) {
+        let mut queue = self.queue();
+        if queue.len() >= self.inner.options.max_queue {
+            self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                endpoint = %self.inner.endpoint,
+                "decision export queue full, dropping event"
+            );
+            return;
+        }
+        queue.push_back(event);
+        if queue.len() % self.inner.options.max_batch.max(1) == 0 {
+            self.ready.notify_one();
+        }
+    }
+
+    /// Send every queued event now, in batches.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::HttpError`] for the first batch that still failed
+    /// after every retry. Later batches are still attempted.
+    pub async fn flush(&self) -> Result<(), Error> {
+        let mut first_error = None;
+        loop {
+            let batch: Vec<DecisionEvent> = {
+                let mut queue = self.queue();
+                let len = queue.len().min(self.inner.options.max_batch.max(1));
+                queue.drain(..len).collect()
+            };
+            if batch.is_empty() {
+                return first_error.map_or(Ok(()), Err);
+            }
+            let count = batch.len() as u64;
+            match self.send(&batch).await {
+                Ok(()) => {
+                    self.inner.exported.fetch_add(count, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    self.inner.failed.fetch_add(count, Ordering::Relaxed);
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(
+                        endpoint = %self.inner.endpoint,
+                        events = count,
+                        error = %e,
+                        "decision export failed, dropping batch"
+                    );
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+    }
+
+    /// Current counters.
+    pub fn stats(&self) -> ExportStats {
+        ExportStats {
+            queued: self.queue().len(),
+            exported: self.inner.exported.load(Ordering::Relaxed),
+            dropped: self.inner.dropped.load(Ordering::Relaxed),
+            failed: self.inner.failed.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The endpoint events are sent to.
+    pub fn endpoint(&self) -> &str {
+        &self.inner.endpoint
+    }
+
+    /// Queue the outcome of validating `url`.
+    pub(crate) fn observe(&self, url: &str, context: &Context, result: &Result<Validated, Error>) {
+        self.export(DecisionEvent {
+            timestamp: SystemTime::now(),
+            url: url.to_string(),
+            context: context.clone(),
+            decision: Decision::from_result(result),
+        });
+    }
+
+    /// POST `batch`, retrying transport errors, 429 and 5xx statuses.
+    async fn send(&self, batch: &[DecisionEvent]) -> Result<(), Error> {
+        let options = &self.inner.options;
+        let body = to_json(batch);
+        let mut backoff = options.retry_backoff;
+        let mut retries = 0;
+        loop {
+            let sent = self
+                .inner
+                .client
+                .post(&self.inner.endpoint)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.clone())
+                .send()
+                .await;
+            let (message, retryable) = match sent {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    (
+                        format!("export rejected with status {}", status),
+                        status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+                    )
+                }
+                Err(e) => (e.to_string(), true),
+            };
+            if !retryable || retries >= options.max_retries {
+                return Err(Error::HttpError {
+                    url: self.inner.endpoint.clone(),
+                    message,
+                });
+            }
+            retries += 1;
+            tokio::time::sleep(backoff).await;
+            backoff = backoff.saturating_mul(2);
+        }
+    }
+
+    fn queue(&self) -> MutexGuard<'_, VecDeque<DecisionEvent>> {
+        self.inner.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl fmt::Debug for DecisionExporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecisionExporter")
+            .field("endpoint", &self.inner.endpoint)
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+/// `batch` as the JSON array described in the module docs.
+fn to_json(batch: &[DecisionEvent]) -> String {
+    fn string(out: &mut String, s: &str) {
+        out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+    }
+
+    let mut out = String::from("[");
+    for (i, event) in batch.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let millis = event
+            .timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        out.push_str(&format!("{{\"timestamp_ms\":{},\"url\":", millis));
+        string(&mut out, &event.url);
+        out.push_str(",\"context\":{");
+        for (j, (key, value)) in event.context.iter().enumerate() {
+            if j > 0 {
+                out.push(',');
+            }
+            string(&mut out, key);
+            out.push(':');
+            string(&mut out, value);
+        }
+        out.push_str("},");
+        match &event.decision {
+            Decision::Allowed { ip } => {
+                out.push_str(&format!("\"decision\":\"allow\",\"ip\":\"{}\"", ip));
+            }
+            Decision::Blocked { reason } => {
+                out.push_str("\"decision\":\"block\",\"reason\":");
+                string(&mut out, reason);
+            }
+        }
+        out.push('}');
+    }
+    out.push(']');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{validate_with_options, Policy, ValidateOptions};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn event(url: &str, decision: Decision) -> DecisionEvent {
+        DecisionEvent {
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_millis(1500),
+            url: url.to_string(),
+            context: Context::new().tag("user", "u-\"1\""),
+            decision,
+        }
+    }
+
+    /// Answer each request on `listener` with the next of `statuses`,
+    /// returning the request bodies.
+    async fn serve(listener: TcpListener, statuses: &[u16]) -> Vec<String> {
+        let mut bodies = Vec::new();
+        for status in statuses {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let len: usize = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length: "))
+                        .map_or(0, |len| len.parse().unwrap());
+                    if body.len() >= len {
+                        bodies.push(body.to_string());
+                        break;
+                    }
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 {} X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                status
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+        bodies
+    }
+
+    fn options() -> ExportOptions {
+        ExportOptions {
+            max_batch: 2,
+            max_queue: 3,
+            max_retries: 1,
+            retry_backoff: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_to_json() {
+        let json = to_json(&[
+            event(
+                "http://a.example/",
+                Decision::Blocked {
+                    reason: "line\nbreak".to_string(),
+                },
+            ),
+            event(
+                "https://b.example/",
+                Decision::Allowed {
+                    ip: "93.184.216.34".parse().unwrap(),
+                },
+            ),
+        ]);
+        assert_eq!(
+            json,
+            "[{\"timestamp_ms\":1500,\"url\":\"http://a.example/\",\"context\":{\"user\":\"u-\\\"1\\\"\"},\
+             \"decision\":\"block\",\"reason\":\"line\\u000abreak\"},\
+             {\"timestamp_ms\":1500,\"url\":\"https://b.example/\",\"context\":{\"user\":\"u-\\\"1\\\"\"},\
+             \"decision\":\"allow\",\"ip\":\"93.184.216.34\"}]"
+        );
+    }
+
+    #[test]
+    fn test_rejects_bad_endpoints() {
+        assert!(matches!(
+            DecisionExporter::new("ftp://collector.internal/").unwrap_err(),
+            Error::InvalidUrl { .. }
+        ));
+        assert!(DecisionExporter::new("not a url").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_drops_new_events() {
+        let exporter = DecisionExporter::with_options("http://127.0.0.1:9/", options()).unwrap();
+        let allowed = Decision::Allowed {
+            ip: "93.184.216.34".parse().unwrap(),
+        };
+        for _ in 0..5 {
+            exporter.export(event("https://example.com/", allowed.clone()));
+        }
+        let stats = exporter.stats();
+        assert_eq!((stats.queued, stats.dropped), (3, 2));
+    }
+
+    #[tokio::test]
+    async fn test_flush_batches_and_retries() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/events", listener.local_addr().unwrap());
+        let exporter = DecisionExporter::with_options(&endpoint, options()).unwrap();
+        let opts = ValidateOptions {
+            exporter: Some(exporter.clone()),
+            context: Context::new().tag("user", "u-123"),
+            ..Default::default()
+        };
+        for url in [
+            "http://10.0.0.1/",
+            "http://127.0.0.1/",
+            "ftp://example.com/",
+        ] {
+            let _ = validate_with_options(url, Policy::PublicOnly, opts.clone()).await;
+        }
+        assert_eq!(exporter.stats().queued, 3);
+
+        // First batch: 503, then accepted on retry. Second batch: 400, dropped
+        let server = tokio::spawn(serve(listener, &[503, 200, 400]));
+        let err = exporter.flush().await.unwrap_err();
+        assert!(err.to_string().contains("status 400"), "{}", err);
+        let bodies = server.await.unwrap();
+
+        assert_eq!(bodies.len(), 3);
+        assert_eq!(bodies[0], bodies[1]);
+        assert!(
+            bodies[0].starts_with("[{\"timestamp_ms\":"),
+            "{}",
+            bodies[0]
+        );
+        assert!(bodies[0].contains("\"url\":\"http://10.0.0.1/\""));
+        assert!(bodies[0].contains("\"context\":{\"user\":\"u-123\"}"));
+        assert!(bodies[0].contains("\"decision\":\"block\""));
+        assert!(bodies[2].contains("ftp://example.com/"));
+        assert_eq!(
+            exporter.stats(),
+            ExportStats {
+                queued: 0,
+                exported: 2,
+                dropped: 0,
+                failed: 1,
+            }
+        );
+    }
+}
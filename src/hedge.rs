@@ -0,0 +1,799 @@
+//! Hedged DNS lookups across two resolver groups.
+//!
+//! A single slow or lossy resolver sets the tail latency of every
+//! validation. A [`HedgedResolver`] asks a primary [`Resolve`] first and,
+//! if it has not answered after a short delay, asks a secondary too, using
+//! whichever answers first. Each group is usually a [`NameserverGroup`],
+//! such as the local caching resolver and a pair of upstream ones:
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use url_jail::{HedgedResolver, NameserverGroup, ValidateOptions};
+//!
+//! let local = NameserverGroup::new(["127.0.0.53"]).unwrap();
+//! let upstream = NameserverGroup::new(["10.0.0.2", "[fd00::2]:5353"]).unwrap();
+//! let options = ValidateOptions {
+//!     resolver: Some(Arc::new(
+//!         HedgedResolver::new(local, upstream).hedge_after(Duration::from_millis(50)),
+//!     )),
+//!     ..Default::default()
+//! };
+//! ```
+//!
+//! With [`cross_check`](HedgedResolver::cross_check), both groups are asked
+//! every time and every address either returns is checked against the
+//! policy, so one tampered resolver cannot point a hostname at an internal
+//! address without the validation failing.
+//!
+//! Answers that put the host in different address classes, one public and
+//! one private, are a strong sign of rebinding or cache poisoning even when
+//! the policy allows both. [`ClassMismatch`] chooses whether such lookups
+//! fail or only warn.
+
+use std::fmt;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
+
+use hickory_resolver::name_server::TokioConnectionProvider;
+use hickory_resolver::TokioResolver;
+
+use crate::classify::{DefaultClassifier, IpClass, IpClassifier};
+use crate::error::Error;
+use crate::resolver::{
+    override_config, parse_nameservers, Resolve, ResolveFuture, Transport, Upstream, DNS_PORT,
+};
+
+/// Default delay before the secondary group is asked.
+const DEFAULT_HEDGE_DELAY: Duration = Duration::from_millis(100);
+
+/// A [`Resolve`] querying a fixed set of nameservers.
+///
+/// Search domains and resolver options come from the system configuration,
+/// as with [`set_nameservers`](crate::set_nameservers), but this group is
+/// used only where it is passed rather than process-wide, so one service can
+/// resolve through an internal DNS server whatever `resolv.conf` says:
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use url_jail::{NameserverGroup, ValidateOptions};
+///
+/// let internal = NameserverGroup::new(["10.96.0.10"])
+///     .unwrap()
+///     .timeout(Duration::from_secs(2))
+///     .attempts(3);
+/// let options = ValidateOptions {
+///     resolver: Some(Arc::new(internal)),
+///     ..Default::default()
+/// };
+/// ```
+///
+/// Like the system resolver, a group builds a resolver for each lookup, so
+/// it works from any runtime, including the temporary one
+/// [`validate_sync`](crate::validate_sync) may start. Answers are not cached
+/// between lookups; use [`ValidateOptions::cache`](crate::ValidateOptions::cache)
+/// for that.
+#[derive(Clone)]
+pub struct NameserverGroup {
+    upstream: Upstream,
+}
+
+impl NameserverGroup {
+    /// A group of `nameservers`, each an IP address with an optional port.
+    ///
+    /// See [`set_nameservers`](crate::set_nameservers) for the format.
+    ///
+    /// # Errors
+    ///
+    /// Returns a reason if a nameserver is invalid or the list is empty.
+    pub fn new<I>(nameservers: I) -> Result<Self, String>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        Self::with_upstream(parse_nameservers(nameservers, DNS_PORT)?, Transport::Plain)
+    }
+
+    /// A group of DNS-over-TLS `nameservers`, whose certificates must be
+    /// valid for `server_name`.
+    ///
+    /// See [`set_tls_nameservers`](crate::set_tls_nameservers); the port
+    /// defaults to 853.
+    ///
+    /// # Errors
+    ///
+    /// As [`new`](Self::new), or if `server_name` is empty.
+    #[cfg(feature = "dns-over-tls")]
+    pub fn tls<I>(nameservers: I, server_name: &str) -> Result<Self, String>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let server_name = crate::resolver::tls_server_name(server_name)?;
+        let addrs = parse_nameservers(nameservers, crate::resolver::DOT_PORT)?;
+        Self::with_upstream(addrs, Transport::Tls(server_name))
+    }
+
+    /// A group of DNS-over-HTTPS `nameservers`, whose certificates must be
+    /// valid for `server_name`.
+    ///
+    /// See [`set_https_nameservers`](crate::set_https_nameservers); the port
+    /// defaults to 443.
+    ///
+    /// # Errors
+    ///
+    /// As [`tls`](Self::tls).
+    #[cfg(feature = "dns-over-tls")]
+    pub fn https<I>(nameservers: I, server_name: &str) -> Result<Self, String>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let server_name = crate::resolver::tls_server_name(server_name)?;
+        let addrs = parse_nameservers(nameservers, crate::resolver::DOH_PORT)?;
+        Self::with_upstream(addrs, Transport::Https(server_name))
+    }
+
+    fn with_upstream(addrs: Vec<SocketAddr>, transport: Transport) -> Result<Self, String> {
+        if addrs.is_empty() {
+            return Err("a nameserver group needs at least one nameserver".to_string());
+        }
+        Ok(Self {
+            upstream: Upstream {
+                addrs,
+                transport,
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Wait at most `timeout` for each query, instead of the system
+    /// `options timeout:`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.upstream.timeout = Some(timeout);
+        self
+    }
+
+    /// Send each query up to `attempts` times, instead of the system
+    /// `options attempts:`. Clamped to at least 1.
+    pub fn attempts(mut self, attempts: usize) -> Self {
+        self.upstream.attempts = Some(attempts.max(1));
+        self
+    }
+
+    /// The nameservers in this group.
+    pub fn nameservers(&self) -> &[SocketAddr] {
+        &self.upstream.addrs
+    }
+
+    /// A resolver bound to the current runtime; hickory resolvers cannot
+    /// be shared across runtimes.
+    fn resolver(&self) -> TokioResolver {
+        let (config, opts) = override_config(&self.upstream);
+        TokioResolver::builder_with_config(config, TokioConnectionProvider::default())
+            .with_options(opts)
+            .build()
+    }
+}
+
+impl fmt::Debug for NameserverGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NameserverGroup")
+            .field("nameservers", &self.upstream.addrs)
+            .field("transport", &self.upstream.transport)
+            .field("timeout", &self.upstream.timeout)
+            .field("attempts", &self.upstream.attempts)
+            .finish()
+    }
+}
+
+impl Resolve for NameserverGroup {
+    fn lookup<'a>(&'a self, host: &'a str) -> ResolveFuture<'a> {
+        Box::pin(async move {
+            let response = self.resolver().lookup_ip(host).await.map_err(|e| {
+                if e.is_no_records_found() {
+                    Error::dns_error(host, e.to_string())
+                } else {
+                    // Unreachable, as far as DnsFallback is concerned
+                    Error::Timeout {
+                        message: format!(
+                            "DNS resolution for {} via {:?} failed: {}",
+                            host, self.upstream.addrs, e
+                        ),
+                    }
+                }
+            })?;
+            Ok(response.iter().collect())
+        })
+    }
+}
+
+/// What a cross-checking [`HedgedResolver`] does when the two groups'
+/// answers fall in different [`IpClass`]es.
+///
+/// Classes come from [`DefaultClassifier`]: an answer of `93.184.216.34`
+/// from one group and `10.0.0.5` from the other is a mismatch, two
+/// different public addresses are not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClassMismatch {
+    /// Count the mismatch, log a warning with the `tracing` feature, and
+    /// check every address against the policy as usual.
+    #[default]
+    Warn,
+
+    /// Fail the lookup with [`Error::DnsError`], even if the policy allows
+    /// every address.
+    Fail,
+}
+
+/// A [`Resolve`] that hedges lookups across a primary and a secondary.
+///
+/// The secondary is asked once the primary has not answered within the
+/// hedge delay, or at once if the primary fails as unreachable. The first
+/// answer wins. If both fail, a "no such host" answer is preferred over an
+/// unreachable resolver.
+///
+/// Validation's [`dns_timeout`](crate::ValidateOptions::dns_timeout) covers
+/// the hedged lookup as a whole.
+pub struct HedgedResolver {
+    primary: Arc<dyn Resolve>,
+    secondary: Arc<dyn Resolve>,
+    delay: Duration,
+    cross_check: bool,
+    on_class_mismatch: ClassMismatch,
+    disagreements: AtomicU64,
+    class_mismatches: AtomicU64,
+}
+
+impl HedgedResolver {
+    /// Hedge lookups from `primary` to `secondary` after 100 ms.
+    pub fn new(primary: impl Resolve + 'static, secondary: impl Resolve + 'static) -> Self {
+        Self {
+            primary: Arc::new(primary),
+            secondary: Arc::new(secondary),
+            delay: DEFAULT_HEDGE_DELAY,
+            cross_check: false,
+            on_class_mismatch: ClassMismatch::default(),
+            disagreements: AtomicU64::new(0),
+            class_mismatches: AtomicU64::new(0),
+        }
+    }
+
+    /// Ask the secondary once the primary has not answered after `delay`.
+    ///
+    /// With cross-checking, how long to wait for the second answer once the
+    /// first is in.
+    pub fn hedge_after(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Ask both groups every time and return every address either answers.
+    ///
+    /// Each address is then checked against the policy, so a resolver that
+    /// answers with an internal address fails the validation even when the
+    /// other answers with a public one. Answers that differ are counted in
+    /// [`disagreements`](Self::disagreements); CDNs rotating addresses make
+    /// some disagreement normal, so alert on a change in rate rather than on
+    /// any disagreement.
+    ///
+    /// If the second answer does not arrive within the hedge delay of the
+    /// first, the first is used alone.
+    pub fn cross_check(mut self, enabled: bool) -> Self {
+        self.cross_check = enabled;
+        self
+    }
+
+    /// Cross-check, and handle answers in different address classes as
+    /// `action` says.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{ClassMismatch, HedgedResolver, NameserverGroup};
+    ///
+    /// let resolver = HedgedResolver::new(
+    ///     NameserverGroup::new(["10.0.0.2"]).unwrap(),
+    ///     NameserverGroup::new(["192.0.2.53"]).unwrap(),
+    /// )
+    /// .on_class_mismatch(ClassMismatch::Fail);
+    /// ```
+    pub fn on_class_mismatch(mut self, action: ClassMismatch) -> Self {
+        self.cross_check = true;
+        self.on_class_mismatch = action;
+        self
+    }
+
+    /// Cross-checked lookups where the two groups answered differently.
+    pub fn disagreements(&self) -> u64 {
+        self.disagreements.load(Ordering::Relaxed)
+    }
+
+    /// Cross-checked lookups whose answers fell in different address
+    /// classes, whether or not they failed.
+    pub fn class_mismatches(&self) -> u64 {
+        self.class_mismatches.load(Ordering::Relaxed)
+    }
+
+    /// Combine the answers of a cross-checked lookup.
+    fn merge(
+        &self,
+        host: &str,
+        primary: Result<Vec<IpAddr>, Error>,
+        secondary: Option<Result<Vec<IpAddr>, Error>>,
+    ) -> Result<Vec<IpAddr>, Error> {
+        let (primary, secondary) = match (primary, secondary) {
+            (Ok(mut primary), Some(Ok(secondary))) => {
+                let differ = primary.len() != secondary.len()
+                    || secondary.iter().any(|ip| !primary.contains(ip));
+                if differ {
+                    self.disagree(host);
+                    let (ours, theirs) = (classes(&primary), classes(&secondary));
+                    if ours != theirs {
+                        self.class_mismatches.fetch_add(1, Ordering::Relaxed);
+                        let message = format!(
+                            "resolvers disagree: primary answered {}, secondary answered {}",
+                            describe(&ours),
+                            describe(&theirs)
+                        );
+
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(host, %message, "hedged resolvers disagree on address class");
+
+                        if self.on_class_mismatch == ClassMismatch::Fail {
+                            return Err(Error::dns_error(host, message));
+                        }
+                    }
+                }
+                for ip in secondary {
+                    if !primary.contains(&ip) {
+                        primary.push(ip);
+                    }
+                }
+                return Ok(primary);
+            }
+            (primary, None) => return primary,
+            (primary, Some(secondary)) => (primary, secondary),
+        };
+        // One answered with addresses and the other did not
+        if matches!(
+            (&primary, &secondary),
+            (Ok(_), Err(Error::DnsError { .. })) | (Err(Error::DnsError { .. }), Ok(_))
+        ) {
+            self.disagree(host);
+        }
+        either(primary, secondary)
+    }
+
+    fn disagree(&self, host: &str) {
+        self.disagreements.fetch_add(1, Ordering::Relaxed);
+
+        #[cfg(feature = "tracing")]
+        tracing::warn!(host, "hedged resolvers disagree");
+        #[cfg(not(feature = "tracing"))]
+        let _ = host;
+    }
+}
+
+impl Resolve for HedgedResolver {
+    fn lookup<'a>(&'a self, host: &'a str) -> ResolveFuture<'a> {
+        Box::pin(async move {
+            let mut primary = self.primary.lookup(host);
+            let mut secondary = self.cross_check.then(|| self.secondary.lookup(host));
+            let mut timer = Box::pin(tokio::time::sleep(self.delay));
+            let mut answers: [Option<Result<Vec<IpAddr>, Error>>; 2] = [None, None];
+
+            std::future::poll_fn(|cx| {
+                let answered = answers.iter().any(|a| matches!(a, Some(Ok(_))));
+                if answers[0].is_none() {
+                    if let Poll::Ready(answer) = primary.as_mut().poll(cx) {
+                        answers[0] = Some(answer);
+                    }
+                }
+                if secondary.is_none() {
+                    let primary_down = matches!(answers[0], Some(Err(Error::Timeout { .. })));
+                    let slow = answers[0].is_none() && timer.as_mut().poll(cx).is_ready();
+                    if primary_down || slow {
+                        secondary = Some(self.secondary.lookup(host));
+                    }
+                }
+                if let (Some(lookup), None) = (&mut secondary, &answers[1]) {
+                    if let Poll::Ready(answer) = lookup.as_mut().poll(cx) {
+                        answers[1] = Some(answer);
+                    }
+                }
+
+                let both = answers.iter().all(Option::is_some);
+                if !self.cross_check {
+                    let definitive = |a: &Option<Result<Vec<IpAddr>, Error>>| {
+                        matches!(a, Some(Ok(_)) | Some(Err(Error::DnsError { .. })))
+                    };
+                    let done = matches!(answers[1], Some(Ok(_))) || definitive(&answers[0]);
+                    return if done || both {
+                        Poll::Ready(())
+                    } else {
+                        Poll::Pending
+                    };
+                }
+                if both {
+                    return Poll::Ready(());
+                }
+                // Once one group has answered, the other gets one more hedge
+                // delay before the answer is used alone
+                if answers.iter().any(|a| matches!(a, Some(Ok(_)))) {
+                    if !answered {
+                        timer
+                            .as_mut()
+                            .reset(tokio::time::Instant::now() + self.delay);
+                    }
+                    if timer.as_mut().poll(cx).is_ready() {
+                        return Poll::Ready(());
+                    }
+                }
+                Poll::Pending
+            })
+            .await;
+
+            match answers {
+                [Some(primary), secondary] if self.cross_check => {
+                    self.merge(host, primary, secondary)
+                }
+                [None, Some(secondary)] => secondary,
+                [Some(Ok(ips)), _] | [_, Some(Ok(ips))] => Ok(ips),
+                [Some(primary), Some(secondary)] => either(primary, secondary),
+                [Some(primary), None] => primary,
+                [None, None] => unreachable!("hedged lookup finished without an answer"),
+            }
+        })
+    }
+}
+
+impl fmt::Debug for HedgedResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HedgedResolver")
+            .field("delay", &self.delay)
+            .field("cross_check", &self.cross_check)
+            .field("on_class_mismatch", &self.on_class_mismatch)
+            .field("disagreements", &self.disagreements())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The distinct classes of `ips`, in first-seen order.
+fn classes(ips: &[IpAddr]) -> Vec<IpClass> {
+    let mut classes = Vec::new();
+    for ip in ips {
+        let class = DefaultClassifier.classify(*ip);
+        if !classes.contains(&class) {
+            classes.push(class);
+        }
+    }
+    classes.sort_by_key(IpClass::as_str);
+    classes
+}
+
+fn describe(classes: &[IpClass]) -> String {
+    let names: Vec<&str> = classes.iter().map(IpClass::as_str).collect();
+    names.join("+")
+}
+
+/// The first successful answer, or the more definitive failure: "no such
+/// host" over an unreachable resolver.
+fn either(
+    primary: Result<Vec<IpAddr>, Error>,
+    secondary: Result<Vec<IpAddr>, Error>,
+) -> Result<Vec<IpAddr>, Error> {
+    match (primary, secondary) {
+        (Ok(ips), _) | (_, Ok(ips)) => Ok(ips),
+        (Err(Error::Timeout { .. }), Err(e)) => Err(e),
+        (Err(e), Err(_)) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Instant;
+
+    use super::*;
+    use crate::{validate_with_resolver, Policy};
+
+    /// Answers after `delay`, counting lookups.
+    struct Delayed {
+        delay: Duration,
+        answer: Option<&'static str>,
+        down: bool,
+        lookups: Arc<AtomicUsize>,
+    }
+
+    fn delayed(ms: u64, answer: Option<&'static str>) -> Delayed {
+        Delayed {
+            delay: Duration::from_millis(ms),
+            answer,
+            down: false,
+            lookups: Arc::default(),
+        }
+    }
+
+    impl Resolve for Delayed {
+        fn lookup<'a>(&'a self, host: &'a str) -> ResolveFuture<'a> {
+            self.lookups.fetch_add(1, Ordering::Relaxed);
+            Box::pin(async move {
+                tokio::time::sleep(self.delay).await;
+                match self.answer {
+                    Some(ip) => Ok(vec![ip.parse().unwrap()]),
+                    None if self.down => Err(Error::Timeout {
+                        message: "unreachable".into(),
+                    }),
+                    None => Err(Error::dns_error(host, "no such host")),
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hedges_to_secondary_when_primary_is_slow() {
+        let fast = delayed(0, Some("93.184.216.34"));
+        let fast_lookups = fast.lookups.clone();
+        let resolver = HedgedResolver::new(fast, delayed(0, Some("203.0.113.1")))
+            .hedge_after(Duration::from_millis(20));
+        let ips = resolver.lookup("example.com").await.unwrap();
+        assert_eq!(ips, ["93.184.216.34".parse::<IpAddr>().unwrap()]);
+        assert_eq!(fast_lookups.load(Ordering::Relaxed), 1);
+
+        let secondary = delayed(0, Some("203.0.113.1"));
+        let secondary_lookups = secondary.lookups.clone();
+        let resolver = HedgedResolver::new(delayed(2_000, Some("93.184.216.34")), secondary)
+            .hedge_after(Duration::from_millis(20));
+        let start = Instant::now();
+        let ips = resolver.lookup("example.com").await.unwrap();
+        assert_eq!(ips, ["203.0.113.1".parse::<IpAddr>().unwrap()]);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(secondary_lookups.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failures_prefer_no_such_host() {
+        let down = Delayed {
+            down: true,
+            ..delayed(0, None)
+        };
+        // An unreachable primary hedges at once, with no delay
+        let resolver = HedgedResolver::new(down, delayed(0, Some("203.0.113.1")))
+            .hedge_after(Duration::from_secs(60));
+        assert!(resolver.lookup("example.com").await.is_ok());
+
+        let down = Delayed {
+            down: true,
+            ..delayed(0, None)
+        };
+        let resolver = HedgedResolver::new(down, delayed(0, None));
+        let err = resolver.lookup("example.com").await.unwrap_err();
+        assert!(matches!(err, Error::DnsError { .. }), "{:?}", err);
+
+        // "No such host" from the primary is final
+        let secondary = delayed(0, Some("203.0.113.1"));
+        let secondary_lookups = secondary.lookups.clone();
+        let resolver = HedgedResolver::new(delayed(0, None), secondary);
+        assert!(resolver.lookup("example.com").await.is_err());
+        assert_eq!(secondary_lookups.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cross_check_checks_every_answer() {
+        let resolver = Arc::new(
+            HedgedResolver::new(
+                delayed(0, Some("93.184.216.34")),
+                delayed(5, Some("10.0.0.5")),
+            )
+            .cross_check(true),
+        );
+        let ips = resolver.lookup("example.com").await.unwrap();
+        assert_eq!(ips.len(), 2);
+        assert_eq!(resolver.disagreements(), 1);
+
+        let err =
+            validate_with_resolver("https://example.com/", Policy::PublicOnly, resolver.clone())
+                .await
+                .unwrap_err();
+        assert!(err.is_blocked(), "{:?}", err);
+
+        // A silent secondary delays the answer by at most the hedge delay
+        let resolver = HedgedResolver::new(
+            delayed(0, Some("93.184.216.34")),
+            delayed(2_000, Some("93.184.216.34")),
+        )
+        .hedge_after(Duration::from_millis(20))
+        .cross_check(true);
+        let start = Instant::now();
+        assert_eq!(resolver.lookup("example.com").await.unwrap().len(), 1);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(resolver.disagreements(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_class_mismatch_fails_or_warns() {
+        let mismatch = || {
+            (
+                delayed(0, Some("93.184.216.34")),
+                delayed(0, Some("10.0.0.5")),
+            )
+        };
+
+        let (primary, secondary) = mismatch();
+        let resolver = Arc::new(
+            HedgedResolver::new(primary, secondary).on_class_mismatch(ClassMismatch::Fail),
+        );
+        let err = resolver.lookup("example.com").await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "DNS error for example.com: resolvers disagree: primary answered public, \
+             secondary answered private"
+        );
+        // Fails even where the policy would allow both answers
+        let err = validate_with_resolver(
+            "https://example.com/",
+            Policy::AllowPrivate,
+            resolver.clone(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, Error::DnsError { .. }), "{:?}", err);
+        assert_eq!(resolver.class_mismatches(), 2);
+
+        let (primary, secondary) = mismatch();
+        let resolver =
+            HedgedResolver::new(primary, secondary).on_class_mismatch(ClassMismatch::Warn);
+        assert_eq!(resolver.lookup("example.com").await.unwrap().len(), 2);
+        assert_eq!(resolver.class_mismatches(), 1);
+
+        // Different public addresses are only a disagreement
+        let resolver = HedgedResolver::new(
+            delayed(0, Some("93.184.216.34")),
+            delayed(0, Some("93.184.216.35")),
+        )
+        .on_class_mismatch(ClassMismatch::Fail);
+        assert_eq!(resolver.lookup("example.com").await.unwrap().len(), 2);
+        assert_eq!(
+            (resolver.disagreements(), resolver.class_mismatches()),
+            (1, 0)
+        );
+    }
+
+    #[test]
+    fn test_nameserver_group_parses_addresses() {
+        let group = NameserverGroup::new(["10.0.0.53", "[fd00::53]:5353"]).unwrap();
+        assert_eq!(
+            group.nameservers(),
+            [
+                "10.0.0.53:53".parse::<SocketAddr>().unwrap(),
+                "[fd00::53]:5353".parse().unwrap()
+            ]
+        );
+        assert!(NameserverGroup::new(Vec::<&str>::new()).is_err());
+        assert!(NameserverGroup::new(["dns.internal"]).is_err());
+
+        let group = group.timeout(Duration::from_secs(2)).attempts(0);
+        assert_eq!(group.upstream.timeout, Some(Duration::from_secs(2)));
+        assert_eq!(group.upstream.attempts, Some(1));
+    }
+
+    /// The response to a DNS `query`: `93.184.216.34` for an A query, no
+    /// records otherwise, and only the truncated bit if `truncated`.
+    fn dns_response(query: &[u8], truncated: bool) -> Option<Vec<u8>> {
+        // Header, then the question: labels, type, class
+        let question_end = 12 + query.get(12..)?.iter().position(|&b| b == 0)? + 5;
+        let qtype = query.get(question_end - 4..question_end - 2)?;
+        let answer = qtype == [0, 1] && !truncated;
+        let mut response = query[..question_end].to_vec();
+        response[2] = if truncated { 0x83 } else { 0x81 };
+        response[3] = 0x80;
+        response[6..12].copy_from_slice(&[0, answer as u8, 0, 0, 0, 0]);
+        if answer {
+            response.extend_from_slice(&[0xc0, 0x0c, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+            response.extend_from_slice(&[93, 184, 216, 34]);
+        }
+        Some(response)
+    }
+
+    /// A local nameserver that truncates every UDP answer, so clients use
+    /// TCP, whose connections hickory keeps open between queries.
+    fn spawn_dns_server() -> SocketAddr {
+        use std::io::{Read, Write};
+
+        let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = tcp.local_addr().unwrap();
+        let udp = std::net::UdpSocket::bind(addr).unwrap();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            while let Ok((len, peer)) = udp.recv_from(&mut buf) {
+                if let Some(response) = dns_response(&buf[..len], true) {
+                    let _ = udp.send_to(&response, peer);
+                }
+            }
+        });
+        std::thread::spawn(move || {
+            for mut stream in tcp.incoming().flatten() {
+                std::thread::spawn(move || loop {
+                    let mut len = [0u8; 2];
+                    if stream.read_exact(&mut len).is_err() {
+                        return;
+                    }
+                    let mut query = vec![0u8; u16::from_be_bytes(len) as usize];
+                    if stream.read_exact(&mut query).is_err() {
+                        return;
+                    }
+                    let Some(response) = dns_response(&query, false) else {
+                        return;
+                    };
+                    let mut framed = (response.len() as u16).to_be_bytes().to_vec();
+                    framed.extend_from_slice(&response);
+                    if stream.write_all(&framed).is_err() {
+                        return;
+                    }
+                });
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_nameserver_group_works_across_runtimes() {
+        let server = spawn_dns_server();
+        let group = NameserverGroup::new([server.to_string()])
+            .unwrap()
+            .timeout(Duration::from_secs(1))
+            .attempts(1);
+        // Both runtimes stay alive, but only the one calling drives tasks
+        let runtimes: Vec<tokio::runtime::Runtime> = (0..2)
+            .map(|_| {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+        for (runtime, host) in runtimes.iter().zip(["a.example.com", "b.example.com"]) {
+            let ips = runtime
+                .block_on(async {
+                    tokio::time::timeout(Duration::from_secs(5), group.lookup(host))
+                        .await
+                        .expect("lookup hung")
+                })
+                .unwrap();
+            assert_eq!(ips, ["93.184.216.34".parse::<IpAddr>().unwrap()]);
+        }
+    }
+
+    #[cfg(feature = "dns-over-tls")]
+    #[test]
+    fn test_tls_nameserver_group() {
+        let group = NameserverGroup::tls(["1.1.1.1"], "cloudflare-dns.com").unwrap();
+        assert_eq!(
+            group.nameservers(),
+            ["1.1.1.1:853".parse::<SocketAddr>().unwrap()]
+        );
+        assert!(NameserverGroup::tls(["1.1.1.1"], "").is_err());
+        assert!(NameserverGroup::tls(Vec::<&str>::new(), "cloudflare-dns.com").is_err());
+    }
+
+    #[cfg(feature = "dns-over-tls")]
+    #[test]
+    fn test_https_nameserver_group() {
+        let group = NameserverGroup::https(["1.1.1.1", "9.9.9.9:5053"], "1.1.1.1").unwrap();
+        assert_eq!(
+            group.nameservers(),
+            [
+                "1.1.1.1:443".parse::<SocketAddr>().unwrap(),
+                "9.9.9.9:5053".parse().unwrap(),
+            ]
+        );
+        assert!(format!("{:?}", group).contains("Https(\"1.1.1.1\")"));
+        assert!(NameserverGroup::https(["1.1.1.1"], " ").is_err());
+    }
+}
@@ -0,0 +1,196 @@
+//! Inbound `Host` header validation.
+//!
+//! Services often build outbound URLs (password reset links, webhooks,
+//! OAuth redirects) from the `Host` or `X-Forwarded-Host` of the incoming
+//! request. Those headers are client-controlled, so [`HostAllowlist`] checks
+//! them against the hostnames the service actually answers to. Patterns use
+//! the same syntax as [`PolicyBuilder::allow_host`](crate::PolicyBuilder::allow_host).
+//!
+//! No DNS is performed: the check is purely syntactic.
+
+use crate::error::Error;
+use crate::policy_builder::HostPattern;
+use crate::safe_url::SafeUrl;
+
+/// Allowlist for inbound `Host` / `X-Forwarded-Host` values.
+///
+/// # Example
+///
+/// ```rust
+/// use url_jail::HostAllowlist;
+///
+/// let hosts = HostAllowlist::new(["example.com", "*.example.com", "localhost:8080"]);
+///
+/// let host = hosts.check("API.example.com").unwrap();
+/// assert_eq!(host.host, "api.example.com");
+/// assert!(hosts.check("localhost:8080").is_ok());
+/// assert!(hosts.check("localhost").is_err());
+/// assert!(hosts.check("evil.com").is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct HostAllowlist {
+    patterns: Vec<HostPattern>,
+}
+
+/// A `Host` value that passed [`HostAllowlist::check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InboundHost {
+    /// Normalized hostname (lowercase, no trailing dot, punycode).
+    /// IPv6 literals keep their brackets.
+    pub host: String,
+
+    /// Explicit port, if the value had one.
+    pub port: Option<u16>,
+}
+
+impl HostAllowlist {
+    /// Create an allowlist from hostname patterns.
+    ///
+    /// Supports wildcards (`*.example.com`) and `:port` suffixes. A pattern
+    /// with a port only matches values with that explicit port.
+    pub fn new<I, S>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            patterns: patterns
+                .into_iter()
+                .map(|p| HostPattern::parse(p.as_ref()))
+                .collect(),
+        }
+    }
+
+    /// Check a `Host` header value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidUrl`] if the value is not a valid `host[:port]`,
+    /// and [`Error::HostnameBlocked`] if it is not on the allowlist.
+    pub fn check(&self, value: &str) -> Result<InboundHost, Error> {
+        let inbound = parse_host_header(value)?;
+        if self
+            .patterns
+            .iter()
+            .any(|pattern| pattern.matches(&inbound.host, inbound.port))
+        {
+            Ok(inbound)
+        } else {
+            Err(Error::hostname_blocked(
+                value,
+                inbound.host,
+                "not in inbound host allowlist",
+            ))
+        }
+    }
+
+    /// Check an `X-Forwarded-Host` header value.
+    ///
+    /// Every comma-separated entry must be allowed, since proxies append to
+    /// the list and any entry may be client-supplied. Returns the first
+    /// (client-facing) entry.
+    pub fn check_forwarded(&self, value: &str) -> Result<InboundHost, Error> {
+        let mut first = None;
+        for entry in value.split(',') {
+            let host = self.check(entry.trim())?;
+            first.get_or_insert(host);
+        }
+        first.ok_or_else(|| Error::invalid_url(value, "empty X-Forwarded-Host"))
+    }
+}
+
+/// Parse and normalize a `host[:port]` header value.
+fn parse_host_header(value: &str) -> Result<InboundHost, Error> {
+    let invalid = |reason: &str| Error::invalid_url(value, reason);
+
+    if value.is_empty() || value.contains(|c: char| c.is_whitespace() || "/?#\\@%".contains(c)) {
+        return Err(invalid("not a valid host[:port]"));
+    }
+
+    let (host, port) = match value.strip_prefix('[') {
+        Some(rest) => match rest.split_once(']') {
+            Some((ip, "")) => (format!("[{}]", ip), None),
+            Some((ip, port)) => (format!("[{}]", ip), Some(port)),
+            None => return Err(invalid("unterminated IPv6 literal")),
+        },
+        None => match value.find(':') {
+            Some(colon) => (value[..colon].to_string(), Some(&value[colon..])),
+            None => (value.to_string(), None),
+        },
+    };
+    let port = match port {
+        None => None,
+        Some(port) => {
+            let digits = port
+                .strip_prefix(':')
+                .filter(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()))
+                .ok_or_else(|| invalid("invalid port"))?;
+            Some(digits.parse::<u16>().map_err(|_| invalid("invalid port"))?)
+        }
+    };
+
+    // Reuse URL host normalization (case, trailing dot, IDNA, IP encodings)
+    let url = SafeUrl::parse(&format!("http://{}/", host))?;
+    Ok(InboundHost {
+        host: url.host().to_string(),
+        port,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_host_header() {
+        assert_eq!(
+            parse_host_header("Example.COM.:8443").unwrap(),
+            InboundHost {
+                host: "example.com".into(),
+                port: Some(8443)
+            }
+        );
+        assert_eq!(parse_host_header("[::1]:80").unwrap().host, "[::1]");
+        assert_eq!(parse_host_header("[::1]").unwrap().port, None);
+
+        for bad in [
+            "",
+            "example.com:",
+            "example.com:+1",
+            "example.com:99999",
+            "example.com/evil",
+            "evil.com@example.com",
+            "a b",
+            "[::1",
+            "0x7f000001",
+        ] {
+            assert!(parse_host_header(bad).is_err(), "{:?} accepted", bad);
+        }
+    }
+
+    #[test]
+    fn test_allowlist_matching() {
+        let hosts = HostAllowlist::new(["example.com", "*.example.com", "admin.example.org:8443"]);
+
+        assert!(hosts.check("example.com").is_ok());
+        assert!(hosts.check("a.b.example.com:3000").is_ok());
+        assert!(hosts.check("admin.example.org:8443").is_ok());
+        assert!(hosts.check("admin.example.org").is_err());
+        assert!(matches!(
+            hosts.check("example.com.evil.net"),
+            Err(Error::HostnameBlocked { .. })
+        ));
+    }
+
+    #[test]
+    fn test_forwarded_requires_every_entry() {
+        let hosts = HostAllowlist::new(["*.example.com"]);
+
+        let host = hosts
+            .check_forwarded("www.example.com, edge.example.com")
+            .unwrap();
+        assert_eq!(host.host, "www.example.com");
+        assert!(hosts.check_forwarded("www.example.com, evil.com").is_err());
+        assert!(hosts.check_forwarded("").is_err());
+    }
+}
@@ -0,0 +1,179 @@
+//! Latency budget for validations.
+//!
+//! A degraded resolver rarely fails outright; it gets slow, and every
+//! request that validates a URL gets slow with it. A [`LatencyBudget`] in
+//! [`ValidateOptions::latency_budget`](crate::ValidateOptions::latency_budget)
+//! flags each validation that takes longer than the budget, so operators
+//! see the degradation before their users do.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A validation that exceeded its [`LatencyBudget`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowValidation {
+    /// The URL that was validated.
+    pub url: String,
+
+    /// How long validation took.
+    pub elapsed: Duration,
+
+    /// The budget it exceeded.
+    pub budget: Duration,
+
+    /// Whether the URL was allowed.
+    pub allowed: bool,
+}
+
+type SlowFn = dyn Fn(&SlowValidation) + Send + Sync;
+
+/// A per-validation latency budget with an optional callback.
+///
+/// Every validation that takes longer than the budget increments
+/// [`exceeded_count`](Self::exceeded_count), emits a `tracing` warning (with
+/// the `tracing` feature), and calls the [`on_exceeded`](Self::on_exceeded)
+/// callback. The decision itself is unaffected; use
+/// [`ValidateOptions::dns_timeout`](crate::ValidateOptions::dns_timeout) to
+/// bound latency.
+///
+/// Clones share the counter and callback.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use url_jail::{LatencyBudget, ValidateOptions};
+///
+/// let budget = LatencyBudget::new(Duration::from_millis(50)).on_exceeded(|slow| {
+///     eprintln!("validating {} took {:?}", slow.url, slow.elapsed);
+/// });
+/// let opts = ValidateOptions {
+///     latency_budget: Some(budget.clone()),
+///     ..Default::default()
+/// };
+/// // Export as a metric
+/// let slow_validations = budget.exceeded_count();
+/// ```
+#[derive(Clone)]
+pub struct LatencyBudget {
+    budget: Duration,
+    on_exceeded: Option<Arc<SlowFn>>,
+    exceeded: Arc<AtomicU64>,
+}
+
+impl LatencyBudget {
+    /// Create a budget of `budget` per validation.
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            on_exceeded: None,
+            exceeded: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Call `callback` for every validation over budget.
+    ///
+    /// The callback runs on the validating task, so it should be quick.
+    pub fn on_exceeded<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&SlowValidation) + Send + Sync + 'static,
+    {
+        self.on_exceeded = Some(Arc::new(callback));
+        self
+    }
+
+    /// The budget per validation.
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// How many validations have exceeded the budget.
+    pub fn exceeded_count(&self) -> u64 {
+        self.exceeded.load(Ordering::Relaxed)
+    }
+
+    /// Report a finished validation.
+    pub(crate) fn observe(&self, url: &str, elapsed: Duration, allowed: bool) {
+        if elapsed <= self.budget {
+            return;
+        }
+        self.exceeded.fetch_add(1, Ordering::Relaxed);
+
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            url,
+            elapsed_ms = elapsed.as_millis() as u64,
+            budget_ms = self.budget.as_millis() as u64,
+            "validation exceeded latency budget"
+        );
+
+        if let Some(callback) = &self.on_exceeded {
+            callback(&SlowValidation {
+                url: url.to_string(),
+                elapsed,
+                budget: self.budget,
+                allowed,
+            });
+        }
+    }
+}
+
+impl fmt::Debug for LatencyBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LatencyBudget")
+            .field("budget", &self.budget)
+            .field("exceeded", &self.exceeded_count())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{validate_with_options, Policy, ValidateOptions};
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_only_over_budget_is_reported() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let budget = LatencyBudget::new(Duration::from_millis(10))
+            .on_exceeded(move |slow| log.lock().unwrap().push(slow.clone()));
+
+        budget.observe("http://fast/", Duration::from_millis(10), true);
+        budget.observe("http://slow/", Duration::from_millis(11), false);
+
+        assert_eq!(budget.clone().exceeded_count(), 1);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [SlowValidation {
+                url: "http://slow/".into(),
+                elapsed: Duration::from_millis(11),
+                budget: Duration::from_millis(10),
+                allowed: false,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validation_observed() {
+        let budget = LatencyBudget::new(Duration::ZERO);
+        let opts = || ValidateOptions {
+            latency_budget: Some(budget.clone()),
+            ..Default::default()
+        };
+        assert!(
+            validate_with_options("http://8.8.8.8/", Policy::PublicOnly, opts())
+                .await
+                .is_ok()
+        );
+        assert!(
+            validate_with_options("http://127.0.0.1/", Policy::PublicOnly, opts())
+                .await
+                .is_err()
+        );
+        assert_eq!(budget.exceeded_count(), 2);
+    }
+}
@@ -0,0 +1,159 @@
+//! IP-pinning connector for hyper 0.14.
+//!
+//! [`LegacyPinnedConnector`] is the hyper 0.14 counterpart of
+//! [`PinnedConnector`](crate::PinnedConnector): it implements
+//! `tower::Service<hyper::Uri>` over hyper 0.14's `Uri` (from `http` 0.2) and
+//! returns a `TcpStream`, which hyper 0.14 accepts as a connection. Codebases
+//! migrating between hyper versions can enable both features and share one
+//! policy.
+//!
+//! As with `PinnedConnector`, the streams are plain TCP. Wrap the connector in
+//! a TLS connector (such as `hyper-rustls` 0.24) for HTTPS.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use hyper014::Uri;
+use tokio::net::TcpStream;
+
+use crate::error::Error;
+use crate::policy::{Policy, ValidationPolicy};
+use crate::validate::{validate_with_options, ValidateOptions};
+
+/// A hyper 0.14 connector that validates each target and connects to the
+/// validated IP.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use url_jail::{LegacyPinnedConnector, Policy};
+///
+/// let client = hyper::Client::builder()
+///     .build::<_, hyper::Body>(LegacyPinnedConnector::new(Policy::PublicOnly));
+/// let response = client.get("http://example.com/".parse()?).await?;
+/// ```
+pub struct LegacyPinnedConnector<P: ValidationPolicy = Policy> {
+    policy: Arc<P>,
+    options: ValidateOptions,
+}
+
+impl<P: ValidationPolicy> LegacyPinnedConnector<P> {
+    /// Create a connector validating against `policy`.
+    pub fn new(policy: P) -> Self {
+        Self {
+            policy: Arc::new(policy),
+            options: ValidateOptions::default(),
+        }
+    }
+
+    /// Use `options` (DNS timeout, cache, ...) for validation.
+    pub fn with_options(mut self, options: ValidateOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl<P: ValidationPolicy> Clone for LegacyPinnedConnector<P> {
+    fn clone(&self) -> Self {
+        Self {
+            policy: self.policy.clone(),
+            options: self.options.clone(),
+        }
+    }
+}
+
+impl<P: ValidationPolicy> fmt::Debug for LegacyPinnedConnector<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LegacyPinnedConnector")
+            .field("policy", &self.policy)
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl<P: ValidationPolicy + 'static> tower_service::Service<Uri> for LegacyPinnedConnector<P> {
+    type Response = TcpStream;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let policy = self.policy.clone();
+        let options = self.options.clone();
+        Box::pin(async move {
+            let url = uri.to_string();
+            let validated = validate_with_options(&url, &*policy, options).await?;
+            TcpStream::connect(validated.to_socket_addr())
+                .await
+                .map_err(|e| Error::HttpError {
+                    url: validated.url.clone(),
+                    message: format!("connect to {} failed: {}", validated.to_socket_addr(), e),
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PolicyBuilder, ValidationCache};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tower_service::Service;
+
+    #[tokio::test]
+    async fn test_blocked_target_never_dialed() {
+        let mut connector = LegacyPinnedConnector::new(Policy::PublicOnly);
+        let err = connector
+            .call("http://127.0.0.1:9/".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert!(err.is_blocked(), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_hyper_client_dials_validated_ip() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_cidr("127.0.0.1/32")
+            .build();
+        // The hostname does not resolve: only the cached validation can pin it
+        let cache = Arc::new(ValidationCache::new(Duration::from_secs(60)));
+        cache
+            .insert(
+                policy.fingerprint(),
+                "pinned.invalid",
+                "127.0.0.1".parse().unwrap(),
+            )
+            .await;
+        let connector = LegacyPinnedConnector::new(policy).with_options(ValidateOptions {
+            cache: Some(cache),
+            ..Default::default()
+        });
+
+        let client = hyper014::Client::builder().build::<_, hyper014::Body>(connector);
+        let uri: Uri = format!("http://pinned.invalid:{}/path", port)
+            .parse()
+            .unwrap();
+        let response = client.get(uri).await.unwrap();
+        assert_eq!(response.status(), 204);
+        server.await.unwrap();
+    }
+}
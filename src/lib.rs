@@ -32,6 +32,9 @@
 //! # }
 //! ```
 //!
+//! Projects that only need a yes or no can use [`simple::is_safe`], which
+//! fixes every setting to a safe default.
+//!
 //! ## Using with reqwest
 //!
 //! The returned [`Validated`] struct contains the verified IP address. Use it with
@@ -53,6 +56,9 @@
 //! }
 //! ```
 //!
+//! With the `fetch` feature, `pinned_client()` builds such a client in one step,
+//! with redirects disabled (reqwest would resolve redirect targets itself).
+//!
 //! ## Fetch with Redirect Validation
 //!
 //! For the safest approach, use the `fetch` feature which validates each redirect:
@@ -65,12 +71,27 @@
 //! println!("Redirect chain: {} hops", result.chain.len());
 //! ```
 //!
+//! ## Startup Readiness
+//!
+//! Call [`init()`] during startup to load the resolver configuration and resolve
+//! a canary hostname, so a broken resolver fails the service fast instead of
+//! failing the first user request:
+//!
+//! ```rust,no_run
+//! # async fn example() -> Result<(), url_jail::Error> {
+//! let readiness = url_jail::init().await?;
+//! println!("DNS ready in {:?}", readiness.elapsed);
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! ## Policies
 //!
 //! | Policy | Allows | Blocks |
 //! |--------|--------|--------|
 //! | [`Policy::PublicOnly`] | Public IPs only | Private, loopback, link-local, metadata |
 //! | [`Policy::AllowPrivate`] | Private + public | Loopback, metadata (for internal services) |
+//! | [`Policy::BlockAll`] | Only what a custom policy's allow rules name | Everything else |
 //!
 //! ## Custom Policies
 //!
@@ -98,21 +119,41 @@
 //! - Private IPv4: `10.0.0.0/8`, `172.16.0.0/12`, `192.168.0.0/16`
 //! - Private IPv6: `fc00::/7` (Unique Local Addresses)
 //!
+//! ### Opt-In
+//! - Shared address space: `100.64.0.0/10` (carrier-grade NAT, Tailscale), with
+//!   [`BasePolicyBuilder::block_shared`]
+//!
 //! ### IP Encoding Tricks Rejected
 //! - Octal: `0177.0.0.1` (= 127.0.0.1)
 //! - Decimal: `2130706433` (= 127.0.0.1)
 //! - Hexadecimal: `0x7f000001` (= 127.0.0.1)
 //! - Short-form: `127.1` (= 127.0.0.1)
 //! - IPv4-mapped IPv6: `::ffff:127.0.0.1`
+//! - IPv4 embedded in NAT64, 6to4 or Teredo addresses: `64:ff9b::7f00:1`, `2002:7f00:1::`
 //!
 //! ## Features
 //!
 //! | Feature | Description |
 //! |---------|-------------|
-//! | `fetch` | `fetch()`, `fetch_sync()` with redirect chain validation |
+//! | `fetch` | `fetch()`, `fetch_sync()`, `fetch_stream()` with redirect chain validation; `DecisionExporter` |
+//! | `preview` | `preview()` link previews (title, description, validated `og:image`) |
+//! | `hyper` | `PinnedConnector` for hyper-util clients (implies `fetch`) |
+//! | `hyper-014` | `LegacyPinnedConnector` for hyper 0.14 clients (implies `fetch`) |
+//! | `corpus` | `corpus` module: known bypass payloads and a policy self-audit harness |
+//! | `tower` | `tower` module: `ValidateLayer` middleware for outbound request stacks |
+//! | `serde` | `Serialize`/`Deserialize` for policies and [`PolicyConfig`] |
+//! | `token` | Signed `Validated` tokens (`Validated::to_token`) for passing results between services |
+//! | `dns-over-tls` | `set_tls_nameservers()`, `set_https_nameservers()` and `NameserverGroup::tls()` / `https()` for DNS-over-TLS and DNS-over-HTTPS upstreams |
+//! | `psl` | Registrable-domain host rules (`site:example.com`, `PolicyBuilder::allow_sites()`) from the Public Suffix List |
+//! | `notify` | `PolicyWatcher::watch()` reloads on filesystem notifications, not only by polling |
+//! | `test-utils` | `MockResolver`, fixed DNS answers for deterministic tests |
 //! | `tracing` | Debug/warn logs for validation decisions |
 //! | `python` | Python bindings via PyO3 |
 //!
+//! The built-in classification, [`Policy`] and [`IpClass`] come from the
+//! `url_jail_core` crate, which builds without `std` for embedded gateways
+//! and packet filters that need only the rules.
+//!
 //! ## Error Handling
 //!
 //! All errors are returned via the [`Error`] enum. Use helper methods to categorize errors:
@@ -140,7 +181,7 @@
 //!
 //! | Method | Returns `true` for |
 //! |--------|-------------------|
-//! | [`Error::is_blocked()`] | Security rejections (`SsrfBlocked`, `HostnameBlocked`, `RedirectBlocked`) |
+//! | [`Error::is_blocked()`] | Security rejections (`SsrfBlocked`, `HostnameBlocked`, `SchemeBlocked`, `RedirectBlocked`, `ResponseBlocked`, and `Violations` containing one) |
 //! | [`Error::is_retriable()`] | Temporary errors (`DnsError`, `Timeout`, `HttpError`) |
 //! | [`Error::url()`] | Returns the URL that caused the error (if available) |
 //!
@@ -156,27 +197,155 @@
 //!
 //! See [SECURITY.md](https://github.com/tenuo-ai/url_jail/blob/main/SECURITY.md) for full details.
 
+mod acl;
+mod allowlist;
+mod base_policy;
+mod batch;
 mod blocklist;
+mod cache;
+mod classify;
+mod clock;
+mod config;
+mod confusable;
+mod context;
+mod email;
+mod encoding;
+mod endpoint;
 mod error;
+mod hedge;
+mod host_header;
+mod latency;
+mod local_addresses;
+mod localize;
+mod machine;
+mod metadata_group;
 mod policy;
 mod policy_builder;
+mod policy_handle;
+mod policy_watcher;
+mod preflight;
+mod profile;
+mod recorder;
+mod redirect;
+mod resolver;
 mod safe_url;
+mod self_test;
+pub mod simple;
+mod simulate;
+mod unix_socket;
 mod validate;
 
+#[cfg(feature = "fetch")]
+mod budget;
+#[cfg(feature = "fetch")]
+mod client;
+#[cfg(feature = "hyper")]
+mod connector;
+#[cfg(feature = "corpus")]
+pub mod corpus;
+#[cfg(feature = "fetch")]
+mod export;
 #[cfg(feature = "fetch")]
 mod fetch;
+#[cfg(feature = "hyper-014")]
+mod legacy_connector;
+#[cfg(feature = "test-utils")]
+mod mock_resolver;
+#[cfg(feature = "preview")]
+mod preview;
+#[cfg(feature = "psl")]
+mod site;
+#[cfg(feature = "fetch")]
+mod stream;
+#[cfg(feature = "token")]
+mod token;
+#[cfg(feature = "tower")]
+pub mod tower;
 
-pub use error::Error;
-pub use policy::Policy;
-pub use policy_builder::{CustomPolicy, PolicyBuilder};
-pub use safe_url::SafeUrl;
+pub use acl::AclAction;
+pub use allowlist::{AllowlistChange, AllowlistIssue, AllowlistMonitor, AllowlistStatus};
+pub use base_policy::{BasePolicy, BasePolicyBuilder};
+pub use batch::{
+    validate_all, validate_all_with_options, validate_many, validate_many_with_options,
+};
+pub use cache::{CacheEntry, CacheFuture, CacheKey, CacheStore, MemoryStore, ValidationCache};
+pub use classify::{DefaultClassifier, IpClass, IpClassifier};
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use config::{PolicyConfig, RuleConfig};
+pub use confusable::Confusables;
+pub use context::Context;
+pub use email::{validate_email_domain, EmailDomain, MailHost};
+pub use encoding::{ComponentEncoding, PercentEncoding};
+pub use endpoint::{
+    validate_endpoint, validate_endpoint_custom, validate_endpoints, validate_host,
+    validate_host_with_options,
+};
+pub use error::{Error, ErrorKind};
+pub use hedge::{ClassMismatch, HedgedResolver, NameserverGroup};
+pub use host_header::{HostAllowlist, InboundHost};
+pub use latency::{LatencyBudget, SlowValidation};
+pub use local_addresses::LocalAddresses;
+pub use localize::DisplayPolicy;
+pub use machine::{DnsQuery, Step, ValidationMachine};
+pub use metadata_group::MetadataGroup;
+pub use policy::{Policy, ValidationPolicy};
+pub use policy_builder::{
+    BlockReason, CustomPolicy, LintWarning, PolicyBuilder, PolicyError, RuleMatch, RuleStat,
+};
+pub use policy_handle::{PolicyHandle, RelaxGuard, Relaxation};
+pub use policy_watcher::PolicyWatcher;
+pub use preflight::{
+    prefetch, prefetch_with_options, preflight, validate_many_hosts, validate_many_hosts_custom,
+    HostCheck,
+};
+pub use profile::PolicyProfile;
+pub use recorder::{
+    parse_log, replay, replay_custom, Decision, Recorder, ReplayOutcome, Resolution,
+};
+pub use redirect::{validate_redirect, validate_redirect_with_options};
+pub use resolver::{
+    init, init_with_options, max_concurrent_lookups, nameservers, set_max_concurrent_lookups,
+    set_nameservers, InitOptions, Readiness, Resolve, ResolveFuture,
+};
+pub use safe_url::{SafeUrl, ZoneIds};
+pub use self_test::{self_test, self_test_with_options, SelfTestCheck, SelfTestReport};
+pub use simulate::{simulate, Destination, Simulation, Tally};
+pub use unix_socket::{
+    validate_target, validate_target_with_options, Target, UnixSocketPolicy, ValidatedSocket,
+};
 pub use validate::{
-    validate, validate_custom, validate_custom_with_options, validate_sync, validate_with_options,
-    ValidateOptions, Validated,
+    validate, validate_custom, validate_custom_sync, validate_custom_with_options, validate_sync,
+    validate_sync_with_timeout, validate_with_options, validate_with_resolver, DnsFallback,
+    TargetKey, ValidateOptions, Validated,
 };
 
 #[cfg(feature = "fetch")]
-pub use fetch::{fetch, fetch_sync, FetchResult};
+pub use budget::{BudgetLimits, BudgetUsage, FetchBudget};
+#[cfg(feature = "fetch")]
+pub use client::{pinned_client, pinned_client_builder, SafeClient};
+#[cfg(feature = "hyper")]
+pub use connector::PinnedConnector;
+#[cfg(feature = "fetch")]
+pub use export::{DecisionEvent, DecisionExporter, ExportOptions, ExportStats};
+#[cfg(feature = "fetch")]
+pub use fetch::{
+    fetch, fetch_sync, fetch_with_options, FetchOptions, FetchResult, HeaderPolicy,
+    MetadataHeaders, PreConnectHook,
+};
+#[cfg(feature = "hyper-014")]
+pub use legacy_connector::LegacyPinnedConnector;
+#[cfg(feature = "test-utils")]
+pub use mock_resolver::MockResolver;
+#[cfg(feature = "preview")]
+pub use preview::{preview, preview_with_options, Preview, PreviewOptions};
+#[cfg(feature = "dns-over-tls")]
+pub use resolver::{set_https_nameservers, set_tls_nameservers};
+#[cfg(feature = "psl")]
+pub use site::registrable_domain;
+#[cfg(feature = "fetch")]
+pub use stream::{fetch_stream, FetchStream};
+#[cfg(feature = "token")]
+pub use token::TokenKey;
 
 #[cfg(feature = "python")]
 mod python;
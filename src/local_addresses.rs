@@ -0,0 +1,222 @@
+//! The local machine's own interface addresses.
+//!
+//! Loopback is blocked by every built-in policy, but services also listen on
+//! the machine's other addresses: an admin port bound to `10.0.0.5`, or a
+//! sidecar on the pod IP. A policy that allows the private network lets a
+//! request reach them by the machine's own address. [`LocalAddresses`]
+//! records those addresses so
+//! [`PolicyBuilder::block_local_addresses`](crate::PolicyBuilder::block_local_addresses)
+//! can block them, even inside an allowed range.
+//!
+//! Detection reads `/proc/net` on Linux, which reflects the caller's network
+//! namespace (the container's addresses, not the host's). Elsewhere, supply
+//! the addresses with [`LocalAddresses::from_addrs`].
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::{Arc, RwLock};
+
+use crate::blocklist::unwrap_ipv4;
+
+/// A shared, refreshable set of the machine's interface addresses.
+///
+/// Clones share the set: a [`refresh`](Self::refresh) through one updates
+/// every policy built with another.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use url_jail::{LocalAddresses, Policy, PolicyBuilder};
+///
+/// let local = LocalAddresses::detect().expect("interface addresses");
+/// let policy = PolicyBuilder::new(Policy::AllowPrivate)
+///     .block_local_addresses(local.clone())
+///     .build();
+///
+/// // After a network change, e.g. on a timer
+/// local.refresh().expect("interface addresses");
+/// ```
+#[derive(Clone, Default)]
+pub struct LocalAddresses {
+    addrs: Arc<RwLock<Vec<IpAddr>>>,
+}
+
+impl LocalAddresses {
+    /// Read the addresses of every interface of this machine.
+    ///
+    /// # Errors
+    ///
+    /// Returns a reason if the interfaces cannot be read, including on
+    /// platforms other than Linux.
+    pub fn detect() -> Result<Self, String> {
+        Ok(Self::from_addrs(detect()?))
+    }
+
+    /// A fixed set of addresses, for platforms without detection or for
+    /// tests.
+    pub fn from_addrs(addrs: impl IntoIterator<Item = IpAddr>) -> Self {
+        let set = Self::default();
+        set.replace(addrs.into_iter().collect());
+        set
+    }
+
+    /// Read the interface addresses again, replacing the current set.
+    ///
+    /// On failure the current set stays in effect.
+    ///
+    /// # Errors
+    ///
+    /// As [`detect`](Self::detect).
+    pub fn refresh(&self) -> Result<(), String> {
+        self.replace(detect()?);
+        Ok(())
+    }
+
+    /// Returns `true` if `ip` is one of the addresses.
+    ///
+    /// IPv6 addresses with an embedded IPv4 address (IPv4-mapped,
+    /// IPv4-compatible, NAT64, 6to4, Teredo) match that IPv4 address as well
+    /// as themselves.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let addrs = self.read();
+        addrs.contains(&ip.to_canonical()) || addrs.contains(&unwrap_ipv4(ip))
+    }
+
+    /// The current addresses.
+    pub fn addrs(&self) -> Vec<IpAddr> {
+        self.read().clone()
+    }
+
+    pub(crate) fn replace(&self, mut addrs: Vec<IpAddr>) {
+        addrs.iter_mut().for_each(|ip| *ip = ip.to_canonical());
+        addrs.sort();
+        addrs.dedup();
+        *self.addrs.write().unwrap_or_else(|e| e.into_inner()) = addrs;
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Vec<IpAddr>> {
+        self.addrs.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl fmt::Debug for LocalAddresses {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LocalAddresses")
+            .field(&*self.read())
+            .finish()
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn detect() -> Result<Vec<IpAddr>, String> {
+    let read = |path: &str| std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e));
+    let mut addrs = parse_fib_trie(&read("/proc/net/fib_trie")?);
+    // Absent when IPv6 is disabled
+    if let Ok(if_inet6) = std::fs::read_to_string("/proc/net/if_inet6") {
+        addrs.extend(parse_if_inet6(&if_inet6));
+    }
+    Ok(addrs)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn detect() -> Result<Vec<IpAddr>, String> {
+    Err("interface address detection is only supported on Linux; \
+         use LocalAddresses::from_addrs"
+        .to_string())
+}
+
+/// The IPv4 addresses the kernel routes as local: `/32 host LOCAL` leaves.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_fib_trie(contents: &str) -> Vec<IpAddr> {
+    let mut addrs = Vec::new();
+    let mut leaf = None;
+    for line in contents.lines().map(str::trim) {
+        if let Some(ip) = line.strip_prefix("|-- ") {
+            leaf = ip.parse::<Ipv4Addr>().ok();
+        } else if line.starts_with("/32 host LOCAL") {
+            addrs.extend(leaf.take().map(IpAddr::V4));
+        }
+    }
+    addrs
+}
+
+/// The IPv6 addresses in `/proc/net/if_inet6`, one per line as 32 hex
+/// digits.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_if_inet6(contents: &str) -> Vec<IpAddr> {
+    contents
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter_map(|hex| u128::from_str_radix(hex, 16).ok())
+        .map(|bits| IpAddr::V6(Ipv6Addr::from(bits)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_net() {
+        let fib_trie = "\
+Main:
+  +-- 0.0.0.0/0 3 0 5
+     |-- 0.0.0.0
+        /0 universe UNICAST
+     +-- 10.0.0.0/24 2 0 2
+        |-- 10.0.0.0
+           /24 link UNICAST
+        |-- 10.0.0.5
+           /32 host LOCAL
+        |-- 10.0.0.255
+           /32 link BROADCAST
+     |-- 127.0.0.0
+        /8 host LOCAL
+";
+        assert_eq!(
+            parse_fib_trie(fib_trie),
+            ["10.0.0.5".parse::<IpAddr>().unwrap()]
+        );
+
+        let if_inet6 = "\
+00000000000000000000000000000001 01 80 10 80       lo
+fd000000000000000000000000000002 04 40 00 82     eth0
+";
+        assert_eq!(
+            parse_if_inet6(if_inet6),
+            ["::1".parse::<IpAddr>().unwrap(), "fd00::2".parse().unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_clones_share_refreshes() {
+        let local = LocalAddresses::from_addrs(["10.0.0.5".parse().unwrap()]);
+        let shared = local.clone();
+        assert!(shared.contains("::ffff:10.0.0.5".parse().unwrap()));
+        assert!(shared.contains("::10.0.0.5".parse().unwrap()));
+        local.replace(vec!["10.0.0.6".parse().unwrap()]);
+        assert!(!shared.contains("10.0.0.5".parse().unwrap()));
+        assert!(shared.contains("10.0.0.6".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_embedded_ipv4_addresses_match_themselves() {
+        for ip in [
+            "2002:c000:204::1",                     // 6to4
+            "2001:0:4136:e378:8000:63bf:3fff:fdd2", // Teredo
+            "64:ff9b::c000:221",                    // NAT64
+            "::c000:221",                           // IPv4-compatible
+        ] {
+            let ip: IpAddr = ip.parse().unwrap();
+            let local = LocalAddresses::from_addrs([ip]);
+            assert!(local.contains(ip), "{}", ip);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_detect_includes_loopback() {
+        let local = LocalAddresses::detect().unwrap();
+        assert!(local.contains("127.0.0.1".parse().unwrap()), "{:?}", local);
+    }
+}
@@ -0,0 +1,154 @@
+//! User-facing error messages.
+//!
+//! An [`Error`]'s `Display` output is written for logs: it is English, names
+//! internal ranges, and can change between releases. Product UIs should
+//! render errors through a [`DisplayPolicy`] instead, which maps each
+//! [`ErrorKind`] to an integrator-supplied message in any language.
+
+use std::collections::HashMap;
+
+use crate::error::{Error, ErrorKind};
+
+/// Integrator-supplied messages for errors, keyed by [`ErrorKind`].
+///
+/// Messages are templates that may contain these placeholders:
+///
+/// | Placeholder | Value |
+/// |-------------|-------|
+/// | `{host}` | The hostname involved, if known |
+/// | `{ip}` | The blocked address, for [`ErrorKind::SsrfBlocked`] |
+/// | `{url}` | The URL involved, if known |
+/// | `{code}` | The kind's [`as_str`](ErrorKind::as_str) code |
+///
+/// Placeholders without a value render as the empty string. The crate's own
+/// reason text is never included, so nothing untranslated leaks into the UI.
+///
+/// # Example
+///
+/// ```rust
+/// use url_jail::{validate_sync, DisplayPolicy, ErrorKind, Policy};
+///
+/// let german = DisplayPolicy::new("Diese Adresse kann nicht verwendet werden.")
+///     .message(ErrorKind::SsrfBlocked, "{host} verweist auf ein internes Netzwerk.")
+///     .message(ErrorKind::DnsError, "{host} konnte nicht aufgelöst werden.");
+///
+/// let err = validate_sync("http://127.0.0.1/", Policy::PublicOnly).unwrap_err();
+/// assert_eq!(german.render(&err), "127.0.0.1 verweist auf ein internes Netzwerk.");
+/// ```
+#[derive(Debug, Clone)]
+pub struct DisplayPolicy {
+    messages: HashMap<ErrorKind, String>,
+    fallback: String,
+}
+
+impl DisplayPolicy {
+    /// Create a policy that renders every kind as `fallback`.
+    pub fn new(fallback: impl Into<String>) -> Self {
+        Self {
+            messages: HashMap::new(),
+            fallback: fallback.into(),
+        }
+    }
+
+    /// Set the message template for `kind`.
+    pub fn message(mut self, kind: ErrorKind, template: impl Into<String>) -> Self {
+        self.messages.insert(kind, template.into());
+        self
+    }
+
+    /// Render `error` as a user-facing message.
+    pub fn render(&self, error: &Error) -> String {
+        let kind = error.kind();
+        let template = self.messages.get(&kind).unwrap_or(&self.fallback);
+        let ip = match error {
+            Error::SsrfBlocked { ip, .. } => ip.to_string(),
+            _ => String::new(),
+        };
+
+        template
+            .replace("{host}", host_of(error).unwrap_or_default())
+            .replace("{ip}", &ip)
+            .replace("{url}", error.url().unwrap_or_default())
+            .replace("{code}", kind.as_str())
+    }
+}
+
+/// The hostname an error is about, if it has one.
+fn host_of(error: &Error) -> Option<&str> {
+    match error {
+        Error::HostnameBlocked { host, .. } | Error::DnsError { host, .. } => Some(host),
+        _ => {
+            let url = error.url()?;
+            // Built-in policy IP blocks carry the bare host in `url`
+            match url.split_once("://") {
+                Some((_, rest)) => {
+                    let authority = rest.split(['/', '?', '#']).next()?;
+                    let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+                    match host.strip_prefix('[') {
+                        Some(v6) => v6.split_once(']').map(|(v6, _)| v6),
+                        None => Some(host.split(':').next().unwrap_or(host)),
+                    }
+                }
+                None => Some(url),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> DisplayPolicy {
+        DisplayPolicy::new("Adresse ungültig ({code})")
+            .message(ErrorKind::SsrfBlocked, "{host} ({ip}) ist intern")
+            .message(ErrorKind::HostnameBlocked, "{host} ist gesperrt")
+    }
+
+    #[test]
+    fn test_render_by_kind() {
+        let err = Error::hostname_blocked(
+            "http://metadata.google.internal/",
+            "metadata.google.internal",
+            "matches blocked pattern",
+        );
+        assert_eq!(
+            policy().render(&err),
+            "metadata.google.internal ist gesperrt"
+        );
+
+        let err = Error::ssrf_blocked(
+            "https://user@[::1]:8443/x",
+            "::1".parse().unwrap(),
+            "loopback",
+        );
+        assert_eq!(policy().render(&err), "::1 (::1) ist intern");
+    }
+
+    #[test]
+    fn test_fallback_never_leaks_reason() {
+        let err = Error::invalid_url("ftp://x/", "scheme 'ftp' not allowed");
+        let rendered = policy().render(&err);
+        assert_eq!(rendered, "Adresse ungültig (invalid_url)");
+        assert!(!rendered.contains("scheme"));
+    }
+
+    #[test]
+    fn test_host_of() {
+        let ip = "10.0.0.1".parse().unwrap();
+        for (url, host) in [
+            ("internal.example", "internal.example"),
+            ("http://internal.example:8080/path", "internal.example"),
+            ("http://a@internal.example?q", "internal.example"),
+        ] {
+            assert_eq!(
+                host_of(&Error::ssrf_blocked(url, ip, "private")),
+                Some(host)
+            );
+        }
+        let timeout = Error::Timeout {
+            message: "slow".into(),
+        };
+        assert_eq!(host_of(&timeout), None);
+    }
+}
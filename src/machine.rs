@@ -0,0 +1,608 @@
+//! Sans-IO validation core.
+//!
+//! [`ValidationMachine`] contains the whole validation pipeline (URL parsing,
+//! hostname rules, IP checks) without performing any I/O. Where the pipeline
+//! needs DNS it yields [`Step::NeedDns`] and waits for the caller to supply
+//! the answer, so it can be driven from any event loop or runtime.
+//!
+//! [`validate()`](crate::validate) and [`validate_custom()`](crate::validate_custom)
+//! are frontends over this machine that add Tokio-based DNS resolution,
+//! timeouts, caching, and [`DnsFallback`](crate::DnsFallback).
+
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::blocklist::{is_hostname_blocked, is_ip_blocked, BLOCK_ALL};
+use crate::clock::{Clock, SharedClock};
+use crate::confusable::{self, Confusables};
+use crate::error::Error;
+use crate::policy::Policy;
+use crate::policy_builder::CustomPolicy;
+use crate::safe_url::{SafeUrl, ZoneIds};
+use crate::validate::Validated;
+
+/// Policy a machine validates against.
+#[derive(Debug, Clone, Copy)]
+enum PolicyRef<'a> {
+    Builtin(Policy),
+    Custom(&'a CustomPolicy),
+}
+
+/// A URL validation in progress, independent of any I/O.
+///
+/// Create one with [`new`](Self::new) or [`custom`](Self::custom) and drive
+/// it with [`start`](Self::start).
+///
+/// # Example
+///
+/// A blocking frontend using the standard library resolver:
+///
+/// ```rust,no_run
+/// use std::net::ToSocketAddrs;
+/// use url_jail::{Error, Policy, Step, ValidationMachine};
+///
+/// fn validate_blocking(url: &str) -> Result<url_jail::Validated, Error> {
+///     let mut step = ValidationMachine::new(url, Policy::PublicOnly).start();
+///     loop {
+///         match step {
+///             Step::Done(result) => return result,
+///             Step::NeedDns(query) => {
+///                 let answer = (query.host(), 0)
+///                     .to_socket_addrs()
+///                     .map(|addrs| addrs.map(|a| a.ip()).collect())
+///                     .map_err(|e| Error::DnsError {
+///                         host: query.host().to_string(),
+///                         message: e.to_string(),
+///                     });
+///                 step = query.respond(answer);
+///             }
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ValidationMachine<'a> {
+    url: String,
+    policy: PolicyRef<'a>,
+    collect: bool,
+    /// Skip the rules about the URL itself (scheme, prefix, path).
+    host_only: bool,
+    zone_ids: ZoneIds,
+    confusables: Confusables,
+    clock: SharedClock,
+}
+
+/// The next thing a [`ValidationMachine`] needs, or its result.
+#[derive(Debug)]
+pub enum Step<'a> {
+    /// Resolve a hostname and pass the answer to [`DnsQuery::respond`].
+    NeedDns(DnsQuery<'a>),
+
+    /// Validation finished.
+    Done(Result<Validated, Error>),
+}
+
+/// A pending DNS lookup requested by a [`ValidationMachine`].
+#[derive(Debug)]
+pub struct DnsQuery<'a> {
+    url: String,
+    safe_url: SafeUrl,
+    policy: PolicyRef<'a>,
+    collect: bool,
+    /// Rules already violated before DNS, when collecting.
+    violations: Vec<Error>,
+    clock: SharedClock,
+}
+
+impl<'a> ValidationMachine<'a> {
+    /// Validate `url` against a built-in policy.
+    pub fn new(url: &str, policy: Policy) -> Self {
+        Self {
+            url: url.to_string(),
+            policy: PolicyRef::Builtin(policy),
+            collect: false,
+            host_only: false,
+            zone_ids: ZoneIds::Reject,
+            confusables: Confusables::Allow,
+            clock: SharedClock::default(),
+        }
+    }
+
+    /// Validate `url` against a custom policy.
+    pub fn custom(url: &str, policy: &'a CustomPolicy) -> Self {
+        Self {
+            url: url.to_string(),
+            policy: PolicyRef::Custom(policy),
+            collect: false,
+            host_only: false,
+            zone_ids: ZoneIds::Reject,
+            confusables: Confusables::Allow,
+            clock: SharedClock::default(),
+        }
+    }
+
+    /// Evaluate every check instead of stopping at the first violation.
+    ///
+    /// If more than one check fails, the result is an
+    /// [`Error::Violations`] listing each failure in pipeline order (scheme,
+    /// URL and path rules, host, then every resolved address); a single
+    /// failure is returned as is. The hostname is resolved even when a
+    /// hostname rule already rejects it, so its addresses are checked too.
+    ///
+    /// A URL that does not parse still fails with that one error, since no
+    /// other check can run. A failed lookup is only reported when no rule
+    /// was violated: a blocked internal name usually does not resolve.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{Error, Policy, PolicyBuilder, Step, ValidationMachine};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly)
+    ///     .allow_schemes(["https"])
+    ///     .block_ports([8080])
+    ///     .build();
+    /// let step = ValidationMachine::custom("http://127.0.0.1:8080/", &policy)
+    ///     .collect_violations()
+    ///     .start();
+    /// let Step::Done(Err(err)) = step else { unreachable!() };
+    /// assert!(matches!(err, Error::Violations { .. }));
+    /// assert_eq!(err.violations().len(), 3);
+    /// ```
+    pub fn collect_violations(mut self) -> Self {
+        self.collect = true;
+        self
+    }
+
+    /// Handle IPv6 zone identifiers (`http://[fe80::1%25eth0]/`) as
+    /// `zone_ids` says. See [`SafeUrl::parse_with_zone_ids`].
+    ///
+    /// Default: [`ZoneIds::Reject`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{Policy, Step, ValidationMachine, ZoneIds};
+    ///
+    /// let step = ValidationMachine::new("http://[fe80::1%25eth0]/", Policy::PublicOnly)
+    ///     .zone_ids(ZoneIds::Strip)
+    ///     .start();
+    /// // Checked as fe80::1, which is link-local
+    /// let Step::Done(Err(err)) = step else { unreachable!() };
+    /// assert!(err.is_blocked());
+    /// ```
+    pub fn zone_ids(mut self, zone_ids: ZoneIds) -> Self {
+        self.zone_ids = zone_ids;
+        self
+    }
+
+    /// Handle hostnames that could imitate another hostname, such as
+    /// `аpple.com` with a Cyrillic `а`, as `confusables` says. See
+    /// [`SafeUrl::confusable`].
+    ///
+    /// Default: [`Confusables::Allow`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{Confusables, Policy, Step, ValidationMachine};
+    ///
+    /// let step = ValidationMachine::new("https://\u{0430}pple.com/", Policy::PublicOnly)
+    ///     .confusables(Confusables::Reject)
+    ///     .start();
+    /// let Step::Done(Err(err)) = step else { unreachable!() };
+    /// assert!(err.to_string().contains("mixes Latin and Cyrillic scripts"));
+    /// ```
+    pub fn confusables(mut self, confusables: Confusables) -> Self {
+        self.confusables = confusables;
+        self
+    }
+
+    /// Read the current time from `clock` when setting
+    /// [`Validated::valid_until`], so [`Validated::is_expired`] follows the
+    /// same clock. Default: the system clock.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = SharedClock::new(clock);
+        self
+    }
+
+    /// Check only the host and port of the URL, for targets that are not
+    /// URLs at all, such as an SMTP relay.
+    pub(crate) fn host_only(mut self) -> Self {
+        self.host_only = true;
+        self
+    }
+
+    /// Run the checks that need no I/O.
+    ///
+    /// Returns [`Step::Done`] if the URL is rejected (or is an IP literal),
+    /// otherwise [`Step::NeedDns`] for the URL's hostname.
+    pub fn start(self) -> Step<'a> {
+        let safe_url = match SafeUrl::parse_with_zone_ids(&self.url, self.zone_ids) {
+            Ok(safe_url) => safe_url,
+            Err(e) => return Step::Done(Err(e)),
+        };
+
+        let mut violations = Vec::new();
+        self.check_before_dns(&safe_url, &mut violations);
+        if !self.collect {
+            if let Some(e) = violations.pop() {
+                return Step::Done(Err(e));
+            }
+        }
+
+        let query = DnsQuery {
+            url: self.url,
+            safe_url,
+            policy: self.policy,
+            collect: self.collect,
+            violations,
+            clock: self.clock,
+        };
+
+        // Literal IPs need no lookup
+        let literal = query.host().trim_start_matches('[').trim_end_matches(']');
+        if let Ok(ip) = literal.parse::<IpAddr>() {
+            return Step::Done(query.finish(Ok(vec![ip]), None));
+        }
+
+        Step::NeedDns(query)
+    }
+}
+
+impl ValidationMachine<'_> {
+    /// Push the checks that fail before DNS onto `violations`, stopping at
+    /// the first unless collecting.
+    fn check_before_dns(&self, safe_url: &SafeUrl, violations: &mut Vec<Error>) {
+        let host = safe_url.host();
+        let mut fail = |e: Error| {
+            violations.push(e);
+            !self.collect
+        };
+
+        // Nothing can pass, so don't spend a DNS lookup on it
+        if let PolicyRef::Builtin(Policy::BlockAll) = self.policy {
+            fail(Error::hostname_blocked(&self.url, host, BLOCK_ALL));
+            return;
+        }
+
+        if let PolicyRef::Custom(policy) = self.policy {
+            let scheme = if safe_url.is_https() { "https" } else { "http" };
+            let scheme_allowed = if self.host_only {
+                Ok(())
+            } else {
+                policy.is_scheme_allowed(scheme)
+            };
+            if let Err(reason) = scheme_allowed {
+                let e = Error::SchemeBlocked {
+                    url: self.url.clone(),
+                    scheme: scheme.to_string(),
+                    reason: reason.to_string(),
+                };
+                if fail(e) {
+                    return;
+                }
+            }
+
+            let url_checks = if self.host_only {
+                [Ok(()), Ok(())]
+            } else {
+                [
+                    policy.is_url_allowed(safe_url),
+                    policy.is_path_allowed(safe_url.path()),
+                ]
+            };
+            // Check hostname against custom policy
+            let host_check = policy.is_host_allowed(host, safe_url.port());
+            for check in url_checks.into_iter().chain([host_check]) {
+                if let Err(reason) = check {
+                    if fail(Error::hostname_blocked(&self.url, host, reason.to_string())) {
+                        return;
+                    }
+                }
+            }
+        }
+
+        if self.confusables == Confusables::Reject {
+            if let Some(reason) = confusable::check_host(host) {
+                let reason = format!("confusable hostname: {}", reason);
+                if fail(Error::hostname_blocked(&self.url, host, reason)) {
+                    return;
+                }
+            }
+        }
+
+        // Check built-in hostname blocklist
+        if let Some(blocked_host) = is_hostname_blocked(host) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(host, "hostname blocked");
+            fail(Error::hostname_blocked(
+                &self.url,
+                host,
+                format!(
+                    "matches blocked pattern '{}' (cloud metadata)",
+                    blocked_host
+                ),
+            ));
+        }
+    }
+}
+
+impl<'a> DnsQuery<'a> {
+    /// The hostname to resolve (normalized, lowercase, punycode).
+    pub fn host(&self) -> &str {
+        self.safe_url.host()
+    }
+
+    /// The URL being validated, as passed to the machine.
+    pub(crate) fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Supply the DNS answer and continue validation.
+    ///
+    /// Pass every address the resolver returned: all of them must be
+    /// allowed, so a blocked address cannot hide among allowed ones. An empty answer fails with [`Error::DnsError`]; an `Err`
+    /// is returned as the validation result.
+    ///
+    /// The answer's TTL is unknown, so the result is already expired (see
+    /// [`Validated::valid_until`]); use [`respond_with_ttl`](Self::respond_with_ttl)
+    /// if the resolver reports one.
+    pub fn respond(self, answer: Result<Vec<IpAddr>, Error>) -> Step<'a> {
+        let now = self.clock.now();
+        Step::Done(self.finish(answer, Some(now)))
+    }
+
+    /// Supply a DNS answer valid for `ttl` and continue validation.
+    ///
+    /// See [`respond`](Self::respond).
+    pub fn respond_with_ttl(self, answer: Result<Vec<IpAddr>, Error>, ttl: Duration) -> Step<'a> {
+        let until = self.clock.now() + ttl;
+        Step::Done(self.finish(answer, Some(until)))
+    }
+
+    fn finish(
+        mut self,
+        answer: Result<Vec<IpAddr>, Error>,
+        valid_until: Option<SystemTime>,
+    ) -> Result<Validated, Error> {
+        let host = self.safe_url.host();
+        let ips = match answer {
+            Ok(ips) if ips.is_empty() => Err(Error::dns_error(host, "no IP addresses found")),
+            answer => answer,
+        };
+        let ips = match ips {
+            Ok(ips) => ips,
+            // Rule violations already explain why the host is unusable
+            Err(e) if self.violations.is_empty() => return Err(e),
+            Err(_) => return Err(Error::collected(self.url, self.violations)),
+        };
+        let ip = ips[0];
+
+        // Check ALL resolved IPs - if ANY is blocked, fail
+        // This prevents attackers from hiding a blocked IP among allowed ones
+        for ip in &ips {
+            let blocked = match self.policy {
+                PolicyRef::Builtin(policy) => {
+                    is_ip_blocked(*ip, policy).map(|reason| Error::ssrf_blocked(host, *ip, reason))
+                }
+                // Check ALL resolved IPs against the custom policy, as above
+                PolicyRef::Custom(policy) => policy
+                    .is_ip_allowed(*ip)
+                    .err()
+                    .map(|reason| Error::ssrf_blocked(&self.url, *ip, reason.to_string())),
+            };
+            if let Some(e) = blocked {
+                self.violations.push(e);
+                if !self.collect {
+                    break;
+                }
+            }
+        }
+        if !self.violations.is_empty() {
+            return Err(Error::collected(self.url, self.violations));
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(%ip, host, "URL validated successfully");
+
+        Ok(Validated {
+            ip,
+            ips,
+            host: host.to_string(),
+            port: self.safe_url.port(),
+            url: self.safe_url.as_str().to_string(),
+            https: self.safe_url.is_https(),
+            valid_until,
+            clock: self.clock,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::policy_builder::PolicyBuilder;
+
+    fn ips(list: &[&str]) -> Vec<IpAddr> {
+        list.iter().map(|ip| ip.parse().unwrap()).collect()
+    }
+
+    fn expect_query(step: Step<'_>) -> DnsQuery<'_> {
+        match step {
+            Step::NeedDns(query) => query,
+            Step::Done(result) => panic!("expected DNS query, got {:?}", result),
+        }
+    }
+
+    fn expect_done(step: Step<'_>) -> Result<Validated, Error> {
+        match step {
+            Step::Done(result) => result,
+            Step::NeedDns(query) => panic!("unexpected DNS query for {}", query.host()),
+        }
+    }
+
+    #[test]
+    fn test_hostname_yields_dns_query() {
+        let query = expect_query(
+            ValidationMachine::new("https://Example.COM/a", Policy::PublicOnly).start(),
+        );
+        assert_eq!(query.host(), "example.com");
+
+        let v = expect_done(query.respond(Ok(ips(&["93.184.215.14"])))).unwrap();
+        assert_eq!(v.ip.to_string(), "93.184.215.14");
+        assert_eq!(v.host, "example.com");
+        assert_eq!(v.port, 443);
+        assert!(v.https);
+    }
+
+    #[test]
+    fn test_ip_literal_needs_no_dns() {
+        let v = expect_done(ValidationMachine::new("http://8.8.8.8/", Policy::PublicOnly).start());
+        assert_eq!(v.unwrap().ip.to_string(), "8.8.8.8");
+
+        let result =
+            expect_done(ValidationMachine::new("http://[::1]/", Policy::PublicOnly).start());
+        assert!(matches!(result, Err(Error::SsrfBlocked { .. })));
+    }
+
+    #[test]
+    fn test_rejections_before_dns() {
+        let result =
+            expect_done(ValidationMachine::new("file:///etc/passwd", Policy::PublicOnly).start());
+        assert!(matches!(result, Err(Error::InvalidUrl { .. })));
+
+        let result = expect_done(
+            ValidationMachine::new("http://metadata.google.internal/", Policy::PublicOnly).start(),
+        );
+        assert!(matches!(result, Err(Error::HostnameBlocked { .. })));
+    }
+
+    #[test]
+    fn test_any_blocked_answer_fails() {
+        let query = expect_query(
+            ValidationMachine::new("https://rebind.example/", Policy::PublicOnly).start(),
+        );
+        let result = expect_done(query.respond(Ok(ips(&["93.184.215.14", "127.0.0.1"]))));
+        assert!(matches!(result, Err(Error::SsrfBlocked { .. })));
+    }
+
+    #[test]
+    fn test_empty_and_failed_answers() {
+        let query = expect_query(
+            ValidationMachine::new("https://example.com/", Policy::PublicOnly).start(),
+        );
+        let result = expect_done(query.respond(Ok(Vec::new())));
+        assert!(matches!(result, Err(Error::DnsError { .. })));
+
+        let query = expect_query(
+            ValidationMachine::new("https://example.com/", Policy::PublicOnly).start(),
+        );
+        let result = expect_done(query.respond(Err(Error::Timeout {
+            message: "slow".into(),
+        })));
+        assert!(matches!(result, Err(Error::Timeout { .. })));
+    }
+
+    #[test]
+    fn test_collect_violations() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_schemes(["https"])
+            .block_path_glob("/admin*")
+            .block_host("*.blocked.example")
+            .build();
+        let machine = || ValidationMachine::custom("http://a.blocked.example/admin", &policy);
+
+        // Halting mode stops at the scheme
+        let err = expect_done(machine().start()).unwrap_err();
+        assert!(matches!(err, Error::SchemeBlocked { .. }), "{:?}", err);
+
+        let query = expect_query(machine().collect_violations().start());
+        let err =
+            expect_done(query.respond(Ok(ips(&["10.0.0.1", "93.184.215.14", "::1"])))).unwrap_err();
+        let kinds: Vec<_> = err.violations().iter().map(Error::kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ErrorKind::SchemeBlocked,
+                ErrorKind::HostnameBlocked,
+                ErrorKind::HostnameBlocked,
+                ErrorKind::SsrfBlocked,
+                ErrorKind::SsrfBlocked,
+            ]
+        );
+        assert!(err.is_blocked());
+        assert!(
+            err.to_string().starts_with("5 violations for http://"),
+            "{}",
+            err
+        );
+
+        // A failed lookup does not add to rule violations
+        let query = expect_query(machine().collect_violations().start());
+        let err = expect_done(query.respond(Ok(Vec::new()))).unwrap_err();
+        assert_eq!(err.violations().len(), 3);
+
+        // One violation is returned as is
+        let query = expect_query(
+            ValidationMachine::new("https://example.com/", Policy::PublicOnly)
+                .collect_violations()
+                .start(),
+        );
+        let err = expect_done(query.respond(Ok(ips(&["127.0.0.1"])))).unwrap_err();
+        assert!(matches!(err, Error::SsrfBlocked { .. }), "{:?}", err);
+        let query = expect_query(
+            ValidationMachine::new("https://example.com/", Policy::PublicOnly)
+                .collect_violations()
+                .start(),
+        );
+        assert!(expect_done(query.respond(Ok(ips(&["93.184.215.14"])))).is_ok());
+    }
+
+    #[test]
+    fn test_custom_policy() {
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("10.0.0.0/8")
+            .block_host("*.blocked.example")
+            .build();
+
+        let result =
+            expect_done(ValidationMachine::custom("https://a.blocked.example/", &policy).start());
+        assert!(matches!(result, Err(Error::HostnameBlocked { .. })));
+
+        let query =
+            expect_query(ValidationMachine::custom("https://internal.example/", &policy).start());
+        let result = expect_done(query.respond(Ok(ips(&["10.1.2.3"]))));
+        assert!(matches!(result, Err(Error::SsrfBlocked { .. })));
+
+        let query =
+            expect_query(ValidationMachine::custom("https://internal.example/", &policy).start());
+        let result = expect_done(query.respond(Ok(ips(&["192.168.1.1"]))));
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_custom_policy_checks_every_answer() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_cidr("192.168.1.0/24")
+            .build();
+
+        for answer in [["192.168.1.1", "10.0.0.1"], ["10.0.0.1", "192.168.1.1"]] {
+            let query =
+                expect_query(ValidationMachine::custom("https://mixed.example/", &policy).start());
+            match expect_done(query.respond(Ok(ips(&answer)))) {
+                Err(Error::SsrfBlocked { ip, .. }) => assert_eq!(ip.to_string(), "10.0.0.1"),
+                other => panic!("{:?} not blocked: {:?}", answer, other),
+            }
+        }
+
+        let query =
+            expect_query(ValidationMachine::custom("https://lan.example/", &policy).start());
+        let validated =
+            expect_done(query.respond(Ok(ips(&["192.168.1.2", "192.168.1.1"])))).unwrap();
+        assert_eq!(validated.ip.to_string(), "192.168.1.2");
+        assert_eq!(validated.ips, ips(&["192.168.1.2", "192.168.1.1"]));
+    }
+}
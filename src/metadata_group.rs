@@ -0,0 +1,280 @@
+//! Platform metadata and control-plane endpoints, in named groups.
+//!
+//! The built-in blocklist covers the classic instance metadata service at
+//! `169.254.169.254` and its well-known hostnames. Container and PaaS
+//! platforms add their own: task credential endpoints, control-plane APIs,
+//! and hostnames that reach the host machine. Some sit outside link-local
+//! space, such as Azure's WireServer at a public-looking `168.63.129.16`.
+//!
+//! A [`MetadataGroup`] bundles one platform's endpoints. Groups are opt-in,
+//! since a service may legitimately talk to its own platform, and each rule
+//! carries a note naming the group. A cloud's group also lists the
+//! instance metadata addresses the built-in blocklist covers, so it keeps
+//! blocking them under a [`BasePolicy`](crate::BasePolicy) that unblocks the
+//! metadata class:
+//!
+//! ```rust
+//! use url_jail::{MetadataGroup, Policy, PolicyBuilder};
+//!
+//! let policy = PolicyBuilder::new(Policy::PublicOnly)
+//!     .block_metadata_groups(MetadataGroup::ALL)
+//!     .build();
+//! assert!(policy.is_ip_allowed("168.63.129.16".parse().unwrap()).is_err());
+//! assert!(policy.is_hostname_allowed("host.docker.internal").is_err());
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The service range of a default `kubeadm` cluster.
+const KUBERNETES_SERVICE_CIDR: &str = "10.96.0.0/12";
+
+/// A platform's metadata, credential and control-plane endpoints.
+///
+/// Enable a group with
+/// [`PolicyBuilder::block_metadata_group`](crate::PolicyBuilder::block_metadata_group).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetadataGroup {
+    /// Alibaba Cloud ECS instance metadata (`100.100.100.200`).
+    Alibaba,
+
+    /// Amazon EC2 instance metadata (`169.254.169.254`, `fd00:ec2::254`).
+    Aws,
+
+    /// Amazon ECS task metadata and credentials (`169.254.170.2`) and the
+    /// EKS Pod Identity agent (`169.254.170.23`, `fd00:ec2::23`).
+    AwsContainers,
+
+    /// Azure instance metadata (`169.254.169.254`) and WireServer
+    /// (`168.63.129.16`), which serves VM agent configuration and extension
+    /// settings.
+    Azure,
+
+    /// Hostnames Docker and Podman resolve to the host machine, such as
+    /// `host.docker.internal`.
+    ContainerHost,
+
+    /// DigitalOcean Droplet metadata (`169.254.169.254`).
+    DigitalOcean,
+
+    /// The Fly.io Machines API (`_api.internal`).
+    FlyIo,
+
+    /// Google Compute Engine metadata (`169.254.169.254`) and the short
+    /// `metadata` hostname, which resolves to it.
+    Gcp,
+
+    /// The Kubernetes API server (`kubernetes.default.svc`), cluster
+    /// service names (`*.svc`, `*.cluster.local`), the default service
+    /// range `10.96.0.0/12`, which holds the API server's virtual IP
+    /// `10.96.0.1`, and the kubelet ports 10250 and 10255.
+    ///
+    /// Clusters with another service range should use
+    /// [`PolicyBuilder::block_kubernetes`](crate::PolicyBuilder::block_kubernetes).
+    Kubernetes,
+
+    /// Oracle Cloud instance metadata (`169.254.169.254`, and the legacy
+    /// `192.0.0.192`).
+    Oracle,
+
+    /// Tencent Cloud metadata (`metadata.tencentyun.com`).
+    TencentCloud,
+}
+
+impl MetadataGroup {
+    /// Every group.
+    pub const ALL: [MetadataGroup; 11] = [
+        MetadataGroup::Alibaba,
+        MetadataGroup::Aws,
+        MetadataGroup::AwsContainers,
+        MetadataGroup::Azure,
+        MetadataGroup::ContainerHost,
+        MetadataGroup::DigitalOcean,
+        MetadataGroup::FlyIo,
+        MetadataGroup::Gcp,
+        MetadataGroup::Kubernetes,
+        MetadataGroup::Oracle,
+        MetadataGroup::TencentCloud,
+    ];
+
+    /// Stable name, as accepted by [`FromStr`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetadataGroup::Alibaba => "alibaba",
+            MetadataGroup::Aws => "aws",
+            MetadataGroup::AwsContainers => "aws-containers",
+            MetadataGroup::Azure => "azure",
+            MetadataGroup::ContainerHost => "container-host",
+            MetadataGroup::DigitalOcean => "digital-ocean",
+            MetadataGroup::FlyIo => "fly-io",
+            MetadataGroup::Gcp => "gcp",
+            MetadataGroup::Kubernetes => "kubernetes",
+            MetadataGroup::Oracle => "oracle",
+            MetadataGroup::TencentCloud => "tencent-cloud",
+        }
+    }
+
+    /// Host patterns in this group, as for
+    /// [`PolicyBuilder::block_host`](crate::PolicyBuilder::block_host).
+    pub fn hosts(&self) -> &'static [&'static str] {
+        match self {
+            MetadataGroup::Alibaba
+            | MetadataGroup::Aws
+            | MetadataGroup::AwsContainers
+            | MetadataGroup::Azure
+            | MetadataGroup::DigitalOcean
+            | MetadataGroup::Oracle => &[],
+            MetadataGroup::ContainerHost => &[
+                "host.docker.internal",
+                "gateway.docker.internal",
+                "host.containers.internal",
+            ],
+            MetadataGroup::FlyIo => &["_api.internal"],
+            MetadataGroup::Gcp => &["metadata"],
+            MetadataGroup::Kubernetes => &[
+                "kubernetes",
+                "kubernetes.default",
+                "kubernetes.default.svc",
+                "*.svc",
+                "*.cluster.local",
+            ],
+            MetadataGroup::TencentCloud => &["metadata.tencentyun.com"],
+        }
+    }
+
+    /// Address ranges in this group, as for
+    /// [`PolicyBuilder::block_cidr`](crate::PolicyBuilder::block_cidr).
+    pub fn cidrs(&self) -> &'static [&'static str] {
+        match self {
+            MetadataGroup::Alibaba => &["100.100.100.200/32"],
+            MetadataGroup::Aws => &["169.254.169.254/32", "fd00:ec2::254/128"],
+            MetadataGroup::AwsContainers => {
+                &["169.254.170.2/32", "169.254.170.23/32", "fd00:ec2::23/128"]
+            }
+            MetadataGroup::Azure => &["169.254.169.254/32", "168.63.129.16/32"],
+            MetadataGroup::DigitalOcean | MetadataGroup::Gcp => &["169.254.169.254/32"],
+            MetadataGroup::Kubernetes => &[KUBERNETES_SERVICE_CIDR],
+            MetadataGroup::Oracle => &["169.254.169.254/32", "192.0.0.192/32"],
+            _ => &[],
+        }
+    }
+
+    /// Ports in this group, as for
+    /// [`PolicyBuilder::block_ports`](crate::PolicyBuilder::block_ports).
+    /// They are blocked on every host.
+    pub fn ports(&self) -> &'static [u16] {
+        match self {
+            // Kubelet API and read-only port
+            MetadataGroup::Kubernetes => &[10250, 10255],
+            _ => &[],
+        }
+    }
+
+    /// The note attached to this group's rules.
+    pub(crate) fn note(&self) -> String {
+        format!("{} metadata group", self.as_str())
+    }
+}
+
+impl fmt::Display for MetadataGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MetadataGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MetadataGroup::ALL
+            .into_iter()
+            .find(|group| group.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("unknown metadata group {:?}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy_builder::check_host_pattern;
+    use crate::{BasePolicy, Policy, PolicyBuilder};
+
+    #[test]
+    fn test_group_rules_are_valid() {
+        for group in MetadataGroup::ALL {
+            assert_eq!(group.as_str().parse::<MetadataGroup>(), Ok(group));
+            assert!(!group.hosts().is_empty() || !group.cidrs().is_empty());
+            assert!(group.ports().is_empty() || group == MetadataGroup::Kubernetes);
+            for host in group.hosts() {
+                assert!(check_host_pattern(host).is_ok(), "{}", host);
+            }
+            let policy = PolicyBuilder::new(Policy::AllowPrivate)
+                .block_metadata_group(group)
+                .try_build();
+            assert!(policy.is_ok(), "{}", group);
+        }
+        assert!("heroku".parse::<MetadataGroup>().is_err());
+    }
+
+    #[test]
+    fn test_groups_block_with_note() {
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_metadata_group(MetadataGroup::AwsContainers)
+            .block_metadata_group(MetadataGroup::Kubernetes)
+            .build();
+
+        let err = policy
+            .is_ip_allowed("169.254.170.2".parse().unwrap())
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("aws-containers metadata group"),
+            "{}",
+            err
+        );
+        assert!(policy
+            .is_ip_allowed("::ffff:169.254.170.23".parse().unwrap())
+            .is_err());
+        assert!(policy
+            .is_hostname_allowed("kubernetes.default.svc")
+            .is_err());
+        assert!(policy
+            .is_hostname_allowed("redis.prod.svc.cluster.local")
+            .is_err());
+        assert!(policy.is_hostname_allowed("redis.prod.svc").is_err());
+        assert!(policy.is_ip_allowed("10.96.0.1".parse().unwrap()).is_err());
+        assert!(policy.is_host_allowed("10.0.0.7", 10250).is_err());
+        // Other private addresses and groups are untouched
+        assert!(policy.is_ip_allowed("10.0.0.1".parse().unwrap()).is_ok());
+        assert!(policy.is_hostname_allowed("host.docker.internal").is_ok());
+    }
+
+    #[test]
+    fn test_cloud_groups_survive_unblocked_metadata_class() {
+        let base = BasePolicy::builder()
+            .block_metadata(false)
+            .block_link_local(false)
+            .block_shared(false)
+            .build();
+        let policy = PolicyBuilder::new(base)
+            .block_metadata_groups([MetadataGroup::Aws, MetadataGroup::Alibaba])
+            .block_metadata_group(MetadataGroup::Oracle)
+            .build();
+
+        for (ip, group) in [
+            ("169.254.169.254", "aws"),
+            ("fd00:ec2::254", "aws"),
+            ("100.100.100.200", "alibaba"),
+            ("192.0.0.192", "oracle"),
+        ] {
+            let err = policy.is_ip_allowed(ip.parse().unwrap()).unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains(&format!("{} metadata group", group)),
+                "{}: {}",
+                ip,
+                err
+            );
+        }
+        assert!(policy.is_ip_allowed("169.254.1.1".parse().unwrap()).is_ok());
+    }
+}
@@ -0,0 +1,149 @@
+//! A fixed-answer resolver for tests.
+//!
+//! [`MockResolver`] maps hostnames to addresses chosen by the test, so code
+//! that validates URLs can be tested without real DNS: "this hostname
+//! resolves to 169.254.169.254 and must be blocked" becomes a deterministic
+//! unit test. Pass it to [`validate_with_resolver`](crate::validate_with_resolver)
+//! or [`ValidateOptions::resolver`](crate::ValidateOptions::resolver).
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use url_jail::{validate_with_resolver, MockResolver, Policy};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let dns = Arc::new(
+//!     MockResolver::new()
+//!         .host("hooks.example.com", ["93.184.216.34".parse().unwrap()])
+//!         .host("rebind.example.com", ["169.254.169.254".parse().unwrap()]),
+//! );
+//!
+//! let ok = validate_with_resolver("https://hooks.example.com/", Policy::PublicOnly, dns.clone()).await;
+//! assert!(ok.is_ok());
+//! let blocked =
+//!     validate_with_resolver("https://rebind.example.com/", Policy::PublicOnly, dns.clone()).await;
+//! assert!(blocked.unwrap_err().is_blocked());
+//! assert_eq!(dns.queries(), ["hooks.example.com", "rebind.example.com"]);
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+use crate::error::Error;
+use crate::resolver::{Resolve, ResolveFuture};
+
+/// What a [`MockResolver`] answers for one hostname.
+#[derive(Debug, Clone)]
+enum Answer {
+    Addresses(Vec<IpAddr>),
+    Unreachable,
+}
+
+/// A [`Resolve`] implementation answering from a fixed table.
+///
+/// Hostnames are matched case-insensitively, ignoring a trailing dot.
+/// Unknown hostnames fail with [`Error::DnsError`], like a name that does
+/// not exist.
+#[derive(Debug, Default)]
+pub struct MockResolver {
+    answers: HashMap<String, Answer>,
+    queries: Mutex<Vec<String>>,
+}
+
+impl MockResolver {
+    /// Create a resolver that knows no hostnames.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolve `host` to `ips`.
+    ///
+    /// An empty list behaves like a name with no addresses.
+    pub fn host(mut self, host: &str, ips: impl IntoIterator<Item = IpAddr>) -> Self {
+        self.answers.insert(
+            normalize(host),
+            Answer::Addresses(ips.into_iter().collect()),
+        );
+        self
+    }
+
+    /// Fail lookups of `host` with [`Error::Timeout`], as if the resolver
+    /// were unreachable.
+    ///
+    /// Use it to test [`DnsFallback`](crate::DnsFallback) handling.
+    pub fn unreachable(mut self, host: &str) -> Self {
+        self.answers.insert(normalize(host), Answer::Unreachable);
+        self
+    }
+
+    /// The hostnames looked up so far, in order.
+    pub fn queries(&self) -> Vec<String> {
+        self.queries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+impl Resolve for MockResolver {
+    fn lookup<'a>(&'a self, host: &'a str) -> ResolveFuture<'a> {
+        self.queries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(host.to_string());
+        let answer = match self.answers.get(&normalize(host)) {
+            Some(Answer::Addresses(ips)) => Ok(ips.clone()),
+            Some(Answer::Unreachable) => Err(Error::Timeout {
+                message: format!("DNS resolution for {} timed out (MockResolver)", host),
+            }),
+            None => Err(Error::dns_error(host, "no such host (MockResolver)")),
+        };
+        Box::pin(async move { answer })
+    }
+}
+
+fn normalize(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{validate_with_options, DnsFallback, Policy, ValidateOptions};
+
+    #[tokio::test]
+    async fn test_answers_and_failures() {
+        let dns = Arc::new(
+            MockResolver::new()
+                .host("API.Example.com.", ["93.184.216.34".parse().unwrap()])
+                .host("empty.example.com", [])
+                .unreachable("down.example.com"),
+        );
+        let opts = ValidateOptions {
+            resolver: Some(dns.clone()),
+            dns_fallback: DnsFallback::FailOpen {
+                hosts: HashMap::from([(
+                    "down.example.com".to_string(),
+                    "93.184.216.35".parse().unwrap(),
+                )]),
+            },
+            ..Default::default()
+        };
+        let validate =
+            |url: &'static str| validate_with_options(url, Policy::PublicOnly, opts.clone());
+
+        assert!(validate("https://api.example.com/").await.is_ok());
+        let v = validate("https://down.example.com/").await.unwrap();
+        assert_eq!(v.ip.to_string(), "93.184.216.35");
+        for url in ["https://empty.example.com/", "https://unknown.example.com/"] {
+            let err = validate(url).await.unwrap_err();
+            assert!(matches!(err, Error::DnsError { .. }), "{}: {:?}", url, err);
+        }
+        assert_eq!(dns.queries().len(), 4);
+    }
+}
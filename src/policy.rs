@@ -4,11 +4,15 @@
 //!
 //! Policies are **pure validation constraints**:
 //!
-//! - Based solely on IP addresses and hostnames
+//! - Based solely on the URL: the addresses its hostname resolves to, and,
+//!   for [`CustomPolicy`](crate::CustomPolicy), the hostname, port, scheme,
+//!   URL prefix and path
 //! - No user identity or authentication
 //! - No request context or headers
-//! - No time-based logic
-//! - No delegation or inheritance
+//! - No time-based logic, except that
+//!   [`allow_host_until`](crate::PolicyBuilder::allow_host_until) rules stop
+//!   matching at a fixed deadline
+//! - No delegation: policies never defer a decision to an external service
 //!
 //! This separation ensures policies remain simple, auditable, and composable.
 //! Authorization logic (who can access what) should be handled separately
@@ -18,26 +22,185 @@
 //!
 //! [`Policy`] is `Copy` and cannot be mutated. [`CustomPolicy`](crate::CustomPolicy)
 //! is created via [`PolicyBuilder`](crate::PolicyBuilder) and is immutable once built.
+//!
+//! Both implement [`ValidationPolicy`], so code can accept either.
+
+use std::fmt;
+use std::net::IpAddr;
+
+use crate::blocklist::{is_hostname_blocked, is_ip_blocked, BLOCK_ALL};
+use crate::machine::ValidationMachine;
+use crate::policy_builder::{CustomPolicy, RuleMatch};
+
+pub use url_jail_core::Policy;
+
+/// Reason reported for hostnames on the built-in metadata blocklist.
+const METADATA_HOSTNAME: &str = "cloud metadata hostname - blocked in all policies";
 
-/// Validation policy that controls which IP ranges are allowed.
+/// A policy URLs can be validated against: [`Policy`] or [`CustomPolicy`].
 ///
-/// Policies are pure validation constraints based solely on IP addresses.
-/// They do not consider user identity, request context, or time.
+/// [`validate()`](crate::validate) and the other validation entry points
+/// are generic over this trait, so downstream code can accept any policy
+/// without duplicating call sites. References to a policy implement it too.
 ///
-/// This enum is `Copy` and immutable.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
-pub enum Policy {
-    /// Block private IPs, loopback, link-local, and metadata endpoints.
-    ///
-    /// This is the default and recommended for most use cases where you're
-    /// fetching URLs from untrusted sources (user input, webhooks, etc.).
-    #[default]
-    PublicOnly,
+/// This trait is sealed: validation semantics are defined by this crate, so
+/// it cannot be implemented outside it.
+///
+/// # Example
+///
+/// ```rust
+/// use url_jail::{Policy, PolicyBuilder, RuleMatch, ValidationPolicy};
+///
+/// fn allows_metadata(policy: impl ValidationPolicy) -> bool {
+///     policy.check_ip("169.254.169.254".parse().unwrap()).is_allowed()
+/// }
+///
+/// let custom = PolicyBuilder::new(Policy::AllowPrivate)
+///     .allow_cidr("169.254.169.254/32")
+///     .build();
+/// assert!(!allows_metadata(Policy::AllowPrivate));
+/// assert!(allows_metadata(&custom));
+/// ```
+pub trait ValidationPolicy: sealed::Sealed + fmt::Debug + Send + Sync {
+    /// Evaluate an IP address and report the rule that decided.
+    fn check_ip(&self, ip: IpAddr) -> RuleMatch;
 
-    /// Allow private IPs, but still block loopback and metadata endpoints.
+    /// Evaluate a hostname, and port if known, before DNS resolution.
     ///
-    /// Use for internal service-to-service calls within a trusted network
-    /// where you need to access private IP ranges but still want protection
-    /// against localhost and cloud metadata attacks.
-    AllowPrivate,
+    /// Includes the built-in cloud metadata hostname blocklist, which applies
+    /// under every policy. (The inherent
+    /// [`CustomPolicy::check_host`] reports only the custom rules.)
+    fn check_host(&self, host: &str, port: Option<u16>) -> RuleMatch;
+
+    /// Stable identifier for this policy's rules, used to partition caches.
+    fn fingerprint(&self) -> u64;
+}
+
+pub(crate) mod sealed {
+    use crate::machine::ValidationMachine;
+
+    pub trait Sealed {
+        /// A validation machine for `url` under this policy.
+        fn machine<'a>(&'a self, url: &str) -> ValidationMachine<'a>;
+    }
+}
+
+impl ValidationPolicy for Policy {
+    fn check_ip(&self, ip: IpAddr) -> RuleMatch {
+        match is_ip_blocked(ip, *self) {
+            Some(reason) => RuleMatch::BasePolicy(reason),
+            None => RuleMatch::NoMatch,
+        }
+    }
+
+    fn check_host(&self, host: &str, _port: Option<u16>) -> RuleMatch {
+        if *self == Policy::BlockAll {
+            return RuleMatch::BasePolicy(BLOCK_ALL);
+        }
+        match is_hostname_blocked(host) {
+            Some(_) => RuleMatch::BasePolicy(METADATA_HOSTNAME),
+            None => RuleMatch::NoMatch,
+        }
+    }
+
+    fn fingerprint(&self) -> u64 {
+        Policy::fingerprint(*self)
+    }
+}
+
+impl sealed::Sealed for Policy {
+    fn machine<'a>(&'a self, url: &str) -> ValidationMachine<'a> {
+        ValidationMachine::new(url, *self)
+    }
+}
+
+impl ValidationPolicy for CustomPolicy {
+    fn check_ip(&self, ip: IpAddr) -> RuleMatch {
+        CustomPolicy::check_ip(self, ip)
+    }
+
+    fn check_host(&self, host: &str, port: Option<u16>) -> RuleMatch {
+        let rule = CustomPolicy::check_host(self, host, port);
+        if rule.is_allowed() && is_hostname_blocked(host).is_some() {
+            return RuleMatch::BasePolicy(METADATA_HOSTNAME);
+        }
+        rule
+    }
+
+    fn fingerprint(&self) -> u64 {
+        CustomPolicy::fingerprint(self)
+    }
+}
+
+impl sealed::Sealed for CustomPolicy {
+    fn machine<'a>(&'a self, url: &str) -> ValidationMachine<'a> {
+        ValidationMachine::custom(url, self)
+    }
+}
+
+impl<P: ValidationPolicy + ?Sized> ValidationPolicy for &P {
+    fn check_ip(&self, ip: IpAddr) -> RuleMatch {
+        (**self).check_ip(ip)
+    }
+
+    fn check_host(&self, host: &str, port: Option<u16>) -> RuleMatch {
+        (**self).check_host(host, port)
+    }
+
+    fn fingerprint(&self) -> u64 {
+        (**self).fingerprint()
+    }
+}
+
+impl<P: sealed::Sealed + ?Sized> sealed::Sealed for &P {
+    fn machine<'a>(&'a self, url: &str) -> ValidationMachine<'a> {
+        (**self).machine(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PolicyBuilder;
+
+    fn check<P: ValidationPolicy>(policy: P, ip: &str, host: &str) -> (bool, bool) {
+        (
+            policy.check_ip(ip.parse().unwrap()).is_allowed(),
+            policy.check_host(host, Some(443)).is_allowed(),
+        )
+    }
+
+    #[test]
+    fn test_builtin_and_custom_through_trait() {
+        let custom = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.corp.example")
+            .allow_cidr("10.0.0.0/8")
+            .build();
+
+        assert_eq!(
+            check(Policy::PublicOnly, "10.0.0.1", "a.corp.example"),
+            (false, true)
+        );
+        assert_eq!(check(&custom, "10.0.0.1", "a.corp.example"), (true, false));
+        assert_eq!(
+            check(Policy::AllowPrivate, "10.0.0.1", "metadata.google.internal"),
+            (true, false)
+        );
+        assert_eq!(
+            ValidationPolicy::fingerprint(&&custom),
+            custom.fingerprint()
+        );
+    }
+
+    #[test]
+    fn test_metadata_hostname_not_overridable_through_trait() {
+        let custom = PolicyBuilder::new(Policy::AllowPrivate)
+            .allow_host("metadata.google.internal")
+            .build();
+        assert!(CustomPolicy::check_host(&custom, "metadata.google.internal", None).is_allowed());
+        assert_eq!(
+            ValidationPolicy::check_host(&custom, "metadata.google.internal", None),
+            RuleMatch::BasePolicy(METADATA_HOSTNAME)
+        );
+    }
 }
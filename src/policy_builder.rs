@@ -4,10 +4,13 @@
 //!
 //! Custom policies follow the same principles as built-in policies:
 //!
-//! - **Pure validation constraints**: Based solely on IP addresses and hostnames
+//! - **Pure validation constraints**: Based solely on the URL: resolved IP
+//!   addresses, hostname, port, scheme, URL prefix and path
 //! - **No user identity**: Policies don't know who is making the request
 //! - **No context**: Policies don't consider headers, methods, or request bodies
-//! - **No time**: Policies don't change based on time of day or rate limits
+//! - **No time**: Policies don't change based on time of day or rate limits.
+//!   The one exception is [`PolicyBuilder::allow_host_until()`]: a break-glass
+//!   allow rule that stops matching at a fixed deadline
 //! - **No delegation**: Policies can't defer to external services
 //!
 //! ## Immutability
@@ -30,17 +33,27 @@
 //!
 //! Only use `allow_*` methods when you have a specific, audited use case.
 
+use std::collections::HashMap;
 use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
 
 use ipnet::IpNet;
 
-use crate::blocklist::is_ip_blocked;
-use crate::policy::Policy;
+use crate::base_policy::BasePolicy;
+use crate::blocklist::{classify_ip, unwrap_ipv4};
+use crate::classify::{IpClassifier, SharedClassifier};
+use crate::clock::{Clock, SharedClock};
+use crate::config::{PolicyConfig, RuleConfig};
+use crate::local_addresses::LocalAddresses;
+use crate::metadata_group::MetadataGroup;
+use crate::safe_url::SafeUrl;
 
 /// A custom policy with user-defined blocklists and allowlists.
 ///
 /// Created via [`PolicyBuilder`]. This allows fine-grained control over which
-/// IPs and hostnames are allowed or blocked, beyond the built-in [`Policy`] options.
+/// IPs and hostnames are allowed or blocked, beyond the built-in [`Policy`](crate::Policy) options.
 ///
 /// # Immutability
 ///
@@ -56,10 +69,11 @@ use crate::policy::Policy;
 ///
 /// # Scope
 ///
-/// Like [`Policy`], custom policies are pure validation constraints:
+/// Like [`Policy`](crate::Policy), custom policies are pure validation constraints:
 /// - No user identity or authentication
 /// - No request context
-/// - No time-based logic
+/// - No time-based logic, except that
+///   [`allow_host_until`](PolicyBuilder::allow_host_until) rules expire
 ///
 /// # Example
 ///
@@ -78,352 +92,3660 @@ use crate::policy::Policy;
 /// ```
 #[derive(Debug, Clone)]
 pub struct CustomPolicy {
-    base: Policy,
+    base: BasePolicy,
     blocked_cidrs: Vec<IpNet>,
     allowed_cidrs: Vec<IpNet>,
-    blocked_hosts: Vec<String>,
-    allowed_hosts: Vec<String>,
+    blocked_hosts: Vec<HostPattern>,
+    allowed_hosts: Vec<HostPattern>,
+    allowed_ports: Option<Vec<u16>>,
+    blocked_ports: Vec<u16>,
+    allowed_schemes: Option<Vec<String>>,
+    allowed_url_prefixes: Option<Vec<String>>,
+    blocked_paths: Vec<String>,
+    allowed_sites: Option<Vec<String>>,
+    notes: Vec<(RuleMatch, String)>,
+    classifier: Option<SharedClassifier>,
+    local_addresses: Option<LocalAddresses>,
+    clock: SharedClock,
+    /// Policies from [`intersect`](CustomPolicy::intersect) that must also allow.
+    required: Vec<CustomPolicy>,
+    hits: Arc<RuleHits>,
 }
 
 impl CustomPolicy {
     /// Check if an IP address is allowed by this policy.
     ///
     /// Returns `Ok(())` if allowed, `Err(reason)` if blocked.
-    pub fn is_ip_allowed(&self, ip: IpAddr) -> Result<(), String> {
-        // Check explicit allowlist first
-        for cidr in &self.allowed_cidrs {
-            if cidr.contains(&ip) {
-                return Ok(());
+    /// Use [`check_ip`](Self::check_ip) to find out which rule decided.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{BlockReason, PolicyBuilder, Policy};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::AllowPrivate)
+    ///     .block_cidr("10.0.0.0/8")
+    ///     .build();
+    ///
+    /// match policy.is_ip_allowed("10.1.2.3".parse().unwrap()) {
+    ///     Err(BlockReason::BlockedCidr { rule, .. }) => assert_eq!(rule, "10.0.0.0/8"),
+    ///     other => panic!("unexpected {:?}", other),
+    /// }
+    /// ```
+    pub fn is_ip_allowed(&self, ip: IpAddr) -> Result<(), BlockReason> {
+        self.decide(self.check_ip(ip))
+    }
+
+    /// Evaluate an IP address and report the rule that decided.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{PolicyBuilder, Policy, RuleMatch};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::AllowPrivate)
+    ///     .block_cidr("10.0.0.0/8")
+    ///     .allow_cidr("10.1.0.0/16")
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     policy.check_ip("10.1.2.3".parse().unwrap()),
+    ///     RuleMatch::AllowCidr("10.1.0.0/16".to_string())
+    /// );
+    /// assert_eq!(
+    ///     policy.check_ip("10.2.0.1".parse().unwrap()),
+    ///     RuleMatch::BlockCidr("10.0.0.0/8".to_string())
+    /// );
+    /// assert_eq!(policy.check_ip("192.168.1.1".parse().unwrap()), RuleMatch::NoMatch);
+    /// ```
+    pub fn check_ip(&self, ip: IpAddr) -> RuleMatch {
+        let rule = self.match_ip(ip);
+        self.record(&rule);
+        rule
+    }
+
+    fn match_ip(&self, ip: IpAddr) -> RuleMatch {
+        let rule = self.match_own_ip(ip);
+        self.require(rule, |policy| policy.match_ip(ip))
+    }
+
+    fn match_own_ip(&self, ip: IpAddr) -> RuleMatch {
+        // The machine's own addresses, even inside an allowed range
+        if let Some(local) = &self.local_addresses {
+            if local.contains(ip) {
+                return RuleMatch::LocalAddress(ip);
             }
         }
 
+        // Check explicit allowlist
+        if let Some(cidr) = self
+            .allowed_cidrs
+            .iter()
+            .find(|cidr| cidr_matches(cidr, ip))
+        {
+            return RuleMatch::AllowCidr(cidr.to_string());
+        }
+
         // Check explicit blocklist
-        for cidr in &self.blocked_cidrs {
-            if cidr.contains(&ip) {
-                return Err(format!("blocked by custom policy CIDR rule: {}", cidr));
-            }
+        if let Some(cidr) = self
+            .blocked_cidrs
+            .iter()
+            .find(|cidr| cidr_matches(cidr, ip))
+        {
+            return RuleMatch::BlockCidr(cidr.to_string());
         }
 
         // Fall back to base policy
-        if let Some(reason) = is_ip_blocked(ip, self.base) {
-            return Err(reason.to_string());
+        match self.base_blocks(ip) {
+            Some(reason) => RuleMatch::BasePolicy(reason),
+            None => RuleMatch::NoMatch,
         }
-
-        Ok(())
     }
 
     /// Check if a hostname is allowed by this policy.
     ///
     /// Returns `Ok(())` if allowed, `Err(reason)` if blocked.
     /// Hostname matching is case-insensitive.
-    pub fn is_hostname_allowed(&self, host: &str) -> Result<(), String> {
-        let host_lower = host.to_lowercase();
+    ///
+    /// Port-scoped rules (e.g. `*.example.com:8443`) never match here because
+    /// no port is known; use [`is_host_allowed`](Self::is_host_allowed) to
+    /// evaluate them.
+    pub fn is_hostname_allowed(&self, host: &str) -> Result<(), BlockReason> {
+        self.decide(self.check_host(host, None))
+    }
 
-        // Check explicit allowlist first
-        for pattern in &self.allowed_hosts {
-            if matches_hostname_pattern(&host_lower, pattern) {
-                return Ok(());
+    /// Check if a hostname and port are allowed by this policy.
+    ///
+    /// Like [`is_hostname_allowed`](Self::is_hostname_allowed), but also
+    /// evaluates port-scoped rules. Rules without a port match any port.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly)
+    ///     .block_host("*.example.com")
+    ///     .allow_host("api.example.com:8443")
+    ///     .build();
+    ///
+    /// assert!(policy.is_host_allowed("api.example.com", 8443).is_ok());
+    /// assert!(policy.is_host_allowed("api.example.com", 443).is_err());
+    /// ```
+    pub fn is_host_allowed(&self, host: &str, port: u16) -> Result<(), BlockReason> {
+        self.decide(self.check_host(host, Some(port)))
+    }
+
+    /// Check if a URL scheme is allowed by this policy's scheme allowlist.
+    ///
+    /// Every scheme is allowed if no allowlist is set. Schemes compare
+    /// case-insensitively. This never widens what validation accepts: only
+    /// `http` and `https` URLs are ever valid.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly).allow_schemes(["https"]).build();
+    /// assert!(policy.is_scheme_allowed("HTTPS").is_ok());
+    /// assert!(policy.is_scheme_allowed("http").is_err());
+    /// ```
+    pub fn is_scheme_allowed(&self, scheme: &str) -> Result<(), BlockReason> {
+        let scheme = scheme.to_ascii_lowercase();
+        if let Some(allowed) = &self.allowed_schemes {
+            if !allowed.contains(&scheme) {
+                return Err(BlockReason::SchemeNotAllowed {
+                    scheme,
+                    allowed: allowed.clone(),
+                });
             }
         }
+        self.required
+            .iter()
+            .try_for_each(|policy| policy.is_scheme_allowed(&scheme))
+    }
 
-        // Check explicit blocklist
-        for pattern in &self.blocked_hosts {
-            if matches_hostname_pattern(&host_lower, pattern) {
-                return Err(format!(
-                    "blocked by custom policy hostname rule: {}",
-                    pattern
-                ));
+    /// Check a URL against this policy's URL prefix allowlist.
+    ///
+    /// Every URL is allowed if no prefix is set. Otherwise the normalized URL
+    /// must start with an allowed prefix, ending at a path boundary: prefix
+    /// `https://api.partner.example/v1` covers `/v1` and `/v1/orders` but not
+    /// `/v10`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{PolicyBuilder, Policy, SafeUrl};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly)
+    ///     .allow_url_prefix("https://api.partner.example/v1/")
+    ///     .build();
+    /// let url = |s| SafeUrl::parse(s).unwrap();
+    /// assert!(policy.is_url_allowed(&url("https://API.partner.example/v1/orders")).is_ok());
+    /// assert!(policy.is_url_allowed(&url("https://api.partner.example/v1/../admin")).is_err());
+    /// assert!(policy.is_url_allowed(&url("https://api.partner.example:8443/v1/")).is_err());
+    /// ```
+    pub fn is_url_allowed(&self, url: &SafeUrl) -> Result<(), BlockReason> {
+        if let Some(prefixes) = &self.allowed_url_prefixes {
+            let canonical = url.canonical();
+            if !prefixes
+                .iter()
+                .any(|prefix| prefix_covers(prefix, &canonical))
+            {
+                return Err(BlockReason::UrlNotAllowed {
+                    allowed: prefixes.clone(),
+                });
             }
         }
+        self.required
+            .iter()
+            .try_for_each(|policy| policy.is_url_allowed(url))
+    }
 
-        Ok(())
+    /// Check a URL path against this policy's `block_path_glob` rules.
+    ///
+    /// `path` should be normalized, as by [`SafeUrl::path`]. Matching is
+    /// ASCII case-insensitive, and the percent-decoded path is checked too,
+    /// so `/%61dmin/` cannot slip past `*/admin/*`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly)
+    ///     .block_path_glob("*/admin/*")
+    ///     .build();
+    /// assert!(policy.is_path_allowed("/v1/orders").is_ok());
+    /// assert!(policy.is_path_allowed("/v1/Admin/users").is_err());
+    /// assert!(policy.is_path_allowed("/v1/%61dmin/users").is_err());
+    /// ```
+    pub fn is_path_allowed(&self, path: &str) -> Result<(), BlockReason> {
+        if !self.blocked_paths.is_empty() {
+            let lower = path.to_ascii_lowercase();
+            let decoded = percent_decode_lossy(&lower).to_ascii_lowercase();
+            if let Some(glob) = self
+                .blocked_paths
+                .iter()
+                .find(|glob| path_glob_matches(&lower, glob) || path_glob_matches(&decoded, glob))
+            {
+                return Err(BlockReason::BlockedPath { rule: glob.clone() });
+            }
+        }
+        self.required
+            .iter()
+            .try_for_each(|policy| policy.is_path_allowed(path))
     }
-}
 
-/// Builder for creating custom policies.
-#[derive(Debug, Clone, Default)]
-pub struct PolicyBuilder {
-    base: Policy,
-    blocked_cidrs: Vec<IpNet>,
-    allowed_cidrs: Vec<IpNet>,
-    blocked_hosts: Vec<String>,
-    allowed_hosts: Vec<String>,
-}
+    /// Stable identifier for this policy's rules, used to partition caches.
+    ///
+    /// Two policies with the same base and the same rules (in any order) have
+    /// the same fingerprint; any difference in rules changes it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let a = PolicyBuilder::new(Policy::PublicOnly).block_host("a.com").build();
+    /// let b = PolicyBuilder::new(Policy::PublicOnly).block_host("b.com").build();
+    /// assert_ne!(a.fingerprint(), b.fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        fn section<T: std::fmt::Display>(out: &mut String, name: &str, items: &[T]) {
+            let mut items: Vec<String> = items.iter().map(|i| i.to_string()).collect();
+            items.sort();
+            items.dedup();
+            out.push_str(&format!(";{}={}", name, items.join(",")));
+        }
 
-impl PolicyBuilder {
-    /// Create a new builder with the given base policy.
-    pub fn new(base: Policy) -> Self {
-        Self {
-            base,
-            ..Default::default()
+        let mut canonical = format!("custom:{}", self.base.name());
+        section(&mut canonical, "allow_cidr", &self.allowed_cidrs);
+        section(&mut canonical, "block_cidr", &self.blocked_cidrs);
+        let allowed_hosts: Vec<String> = self
+            .allowed_hosts
+            .iter()
+            .map(|pattern| match pattern.until {
+                Some(until) => format!("{}@{:?}", pattern, until),
+                None => pattern.to_string(),
+            })
+            .collect();
+        section(&mut canonical, "allow_host", &allowed_hosts);
+        section(&mut canonical, "block_host", &self.blocked_hosts);
+        // Port sections only when set, so fingerprints of existing policies
+        // stay the same
+        if let Some(ports) = &self.allowed_ports {
+            section(&mut canonical, "allow_port", ports);
+        }
+        if !self.blocked_ports.is_empty() {
+            section(&mut canonical, "block_port", &self.blocked_ports);
+        }
+        if let Some(schemes) = &self.allowed_schemes {
+            section(&mut canonical, "allow_scheme", schemes);
+        }
+        if let Some(prefixes) = &self.allowed_url_prefixes {
+            section(&mut canonical, "allow_url_prefix", prefixes);
+        }
+        if !self.blocked_paths.is_empty() {
+            section(&mut canonical, "block_path", &self.blocked_paths);
         }
+        if let Some(sites) = &self.allowed_sites {
+            section(&mut canonical, "allow_site", sites);
+        }
+        if let Some(classifier) = &self.classifier {
+            canonical.push_str(&format!(";classifier={}", classifier.name()));
+        }
+        // The addresses change on refresh; cached results are re-checked
+        if self.local_addresses.is_some() {
+            canonical.push_str(";block_local");
+        }
+        if !self.required.is_empty() {
+            let required: Vec<String> = self
+                .required
+                .iter()
+                .map(|policy| format!("{:016x}", policy.fingerprint()))
+                .collect();
+            section(&mut canonical, "intersect", &required);
+        }
+        crate::cache::fnv1a(canonical.as_bytes())
     }
 
-    /// Block an IP range (CIDR notation).
+    /// The plain-data form of this policy, for review or serialization.
+    ///
+    /// See [`PolicyConfig`](crate::PolicyConfig) for the round-trip
+    /// guarantees. `allow_host_until` deadlines are truncated to whole
+    /// seconds.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the policy uses a custom
+    /// [`classifier`](PolicyBuilder::classifier) or
+    /// [`block_local_addresses`](PolicyBuilder::block_local_addresses), or
+    /// is an [`intersect`](Self::intersect)ion, which have no config form.
+    pub fn to_config(&self) -> Result<PolicyConfig, String> {
+        if let Some(classifier) = &self.classifier {
+            return Err(format!(
+                "custom classifier {} cannot be represented as config",
+                classifier.name()
+            ));
+        }
+        if self.local_addresses.is_some() {
+            return Err("local address blocking cannot be represented as config".to_string());
+        }
+        if !self.required.is_empty() {
+            return Err("intersected policies cannot be represented as config".to_string());
+        }
+        // An empty allowlist allows nothing, but empty config lists mean no
+        // restriction
+        for (list, empty) in [
+            (
+                "port",
+                self.allowed_ports.as_ref().is_some_and(Vec::is_empty),
+            ),
+            (
+                "scheme",
+                self.allowed_schemes.as_ref().is_some_and(Vec::is_empty),
+            ),
+            (
+                "URL prefix",
+                self.allowed_url_prefixes
+                    .as_ref()
+                    .is_some_and(Vec::is_empty),
+            ),
+            (
+                "site",
+                self.allowed_sites.as_ref().is_some_and(Vec::is_empty),
+            ),
+        ] {
+            if empty {
+                return Err(format!(
+                    "an empty {} allowlist cannot be represented as config",
+                    list
+                ));
+            }
+        }
+        let note = |rule: RuleMatch| self.note_for(&rule).map(str::to_string);
+        Ok(PolicyConfig {
+            base: self.base,
+            block_cidrs: self
+                .blocked_cidrs
+                .iter()
+                .map(|cidr| {
+                    let rule = cidr.to_string();
+                    RuleConfig::new(rule.clone(), note(RuleMatch::BlockCidr(rule)), None)
+                })
+                .collect(),
+            allow_cidrs: self
+                .allowed_cidrs
+                .iter()
+                .map(|cidr| RuleConfig::from(cidr.to_string()))
+                .collect(),
+            block_hosts: self
+                .blocked_hosts
+                .iter()
+                .map(|pattern| {
+                    let rule = pattern.to_string();
+                    RuleConfig::new(rule.clone(), note(RuleMatch::BlockHost(rule)), None)
+                })
+                .collect(),
+            allow_hosts: self
+                .allowed_hosts
+                .iter()
+                .map(|pattern| {
+                    let until = pattern.until.map(|until| {
+                        until
+                            .duration_since(SystemTime::UNIX_EPOCH)
+                            .map_or(0, |d| d.as_secs())
+                    });
+                    RuleConfig::new(pattern.to_string(), None, until)
+                })
+                .collect(),
+            allow_ports: self.allowed_ports.clone().unwrap_or_default(),
+            block_ports: self.blocked_ports.clone(),
+            allow_schemes: self.allowed_schemes.clone().unwrap_or_default(),
+            allow_url_prefixes: self.allowed_url_prefixes.clone().unwrap_or_default(),
+            block_paths: self.blocked_paths.clone(),
+            allow_sites: self.allowed_sites.clone().unwrap_or_default(),
+        })
+    }
+
+    /// Combine this policy with `other` into one policy holding the rules of
+    /// both.
+    ///
+    /// Use it to layer a per-service override on an org-wide baseline:
+    /// `baseline.merge(&service)`. The usual [precedence](CustomPolicy#precedence)
+    /// applies to the combined rules, so allow rules from either side win
+    /// over block rules from either side. That is how an override works, and
+    /// it means `merge` can widen the baseline: a service's `allow_cidr` or
+    /// `allow_host` opens a range or host the baseline blocks. Use
+    /// [`intersect`](Self::intersect) when the baseline must never be
+    /// widened.
+    ///
+    /// Everything else only narrows:
+    ///
+    /// - Block rules, allow rules and notes are concatenated; duplicates are
+    ///   kept once. Where both sides note the same rule, `other`'s note wins.
+    /// - Allowlists of ports, schemes, sites and URL prefixes are
+    ///   intersected when both sides set one, so neither side can add a
+    ///   scheme or port the other excludes. An intersection with no entries
+    ///   allows nothing.
+    /// - The base policy blocks every address class either side's base
+    ///   blocks, and is allowlist-only if either side's is.
+    /// - [`block_local_addresses`](PolicyBuilder::block_local_addresses)
+    ///   applies if either side sets it, and `other`'s
+    ///   [`classifier`](PolicyBuilder::classifier) replaces this policy's
+    ///   when it sets one.
+    ///
+    /// The merged policy has its own [`rule_stats`](Self::rule_stats)
+    /// counters, starting at zero.
     ///
     /// # Example
-    /// ```
-    /// use url_jail::{PolicyBuilder, Policy};
     ///
-    /// let policy = PolicyBuilder::new(Policy::AllowPrivate)
+    /// ```rust
+    /// use url_jail::{Policy, PolicyBuilder};
+    ///
+    /// let baseline = PolicyBuilder::new(Policy::AllowPrivate)
     ///     .block_cidr("10.0.0.0/8")
+    ///     .allow_schemes(["https"])
     ///     .build();
+    /// let service = PolicyBuilder::new(Policy::AllowPrivate)
+    ///     .allow_cidr("10.20.0.0/16")
+    ///     .block_cidr("192.168.0.0/16")
+    ///     .allow_schemes(["http", "https"])
+    ///     .build();
+    ///
+    /// let policy = baseline.merge(&service);
+    /// assert!(policy.is_ip_allowed("10.20.1.1".parse().unwrap()).is_ok());
+    /// assert!(policy.is_ip_allowed("10.1.1.1".parse().unwrap()).is_err());
+    /// assert!(policy.is_ip_allowed("192.168.1.1".parse().unwrap()).is_err());
+    /// assert!(policy.is_scheme_allowed("http").is_err());
     /// ```
-    pub fn block_cidr(mut self, cidr: &str) -> Self {
-        if let Ok(net) = cidr.parse() {
-            self.blocked_cidrs.push(net);
+    pub fn merge(&self, other: &CustomPolicy) -> CustomPolicy {
+        fn join<T: Clone + PartialEq>(into: &mut Vec<T>, items: &[T]) {
+            for item in items {
+                if !into.contains(item) {
+                    into.push(item.clone());
+                }
+            }
         }
-        self
+
+        /// Keep the entries of `into` that `other` allows, and then the
+        /// entries of `other` that `into` allowed.
+        fn restrict<T: Clone + PartialEq>(
+            into: &mut Option<Vec<T>>,
+            other: &Option<Vec<T>>,
+            covers: impl Fn(&T, &T) -> bool,
+        ) {
+            match (into.as_mut(), other) {
+                (Some(ours), Some(theirs)) => {
+                    let mut both: Vec<T> = ours
+                        .iter()
+                        .filter(|item| theirs.iter().any(|t| covers(t, item)))
+                        .cloned()
+                        .collect();
+                    let narrower: Vec<T> = theirs
+                        .iter()
+                        .filter(|item| ours.iter().any(|o| covers(o, item)))
+                        .cloned()
+                        .collect();
+                    join(&mut both, &narrower);
+                    *ours = both;
+                }
+                (None, Some(_)) => into.clone_from(other),
+                (_, None) => {}
+            }
+        }
+
+        let mut builder = self.to_builder();
+        builder.base = self.base.strictest(other.base);
+        join(&mut builder.blocked_cidrs, &other.blocked_cidrs);
+        join(&mut builder.allowed_cidrs, &other.allowed_cidrs);
+        join(&mut builder.blocked_hosts, &other.blocked_hosts);
+        join(&mut builder.allowed_hosts, &other.allowed_hosts);
+        join(&mut builder.blocked_ports, &other.blocked_ports);
+        join(&mut builder.blocked_paths, &other.blocked_paths);
+        restrict(
+            &mut builder.allowed_ports,
+            &other.allowed_ports,
+            PartialEq::eq,
+        );
+        restrict(
+            &mut builder.allowed_schemes,
+            &other.allowed_schemes,
+            PartialEq::eq,
+        );
+        restrict(
+            &mut builder.allowed_url_prefixes,
+            &other.allowed_url_prefixes,
+            |prefix, narrower| prefix_covers(prefix, narrower),
+        );
+        restrict(
+            &mut builder.allowed_sites,
+            &other.allowed_sites,
+            PartialEq::eq,
+        );
+        // `note_for` takes the first note for a rule
+        let mut notes = other.notes.clone();
+        for (rule, note) in &self.notes {
+            if !notes.iter().any(|(noted, _)| noted == rule) {
+                notes.push((rule.clone(), note.clone()));
+            }
+        }
+        builder.notes = notes;
+        if other.classifier.is_some() {
+            builder.classifier = other.classifier.clone();
+        }
+        if other.local_addresses.is_some() {
+            builder.local_addresses = other.local_addresses.clone();
+        }
+        for policy in &other.required {
+            builder.required.push(policy.to_builder().build());
+        }
+        builder.build()
     }
 
-    /// Allow an IP range (CIDR notation), overriding base policy.
-    pub fn allow_cidr(mut self, cidr: &str) -> Self {
-        if let Ok(net) = cidr.parse() {
-            self.allowed_cidrs.push(net);
+    /// A policy that allows only what both this policy and `other` allow.
+    ///
+    /// Each policy is evaluated on its own, with its own base policy and
+    /// precedence, and the first to block decides. So no allow rule in one
+    /// policy can open something the other blocks: an org-wide baseline
+    /// intersected with a service policy stays in force whatever the
+    /// service adds. [`check_ip`](Self::check_ip) and
+    /// [`check_host`](Self::check_host) report this policy's rule when both
+    /// allow, and notes from either policy appear in block reasons.
+    ///
+    /// The result cannot be turned back into a
+    /// [`PolicyConfig`](crate::PolicyConfig), and has its own
+    /// [`rule_stats`](Self::rule_stats) counters, starting at zero. Its
+    /// [`lint`](Self::lint) warnings are those of both policies.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{Policy, PolicyBuilder, RuleMatch};
+    ///
+    /// let baseline = PolicyBuilder::new(Policy::AllowPrivate)
+    ///     .block_cidr_with_note("10.0.0.0/8", "use the egress gateway")
+    ///     .build();
+    /// let service = PolicyBuilder::new(Policy::PublicOnly)
+    ///     .allow_cidr("10.20.0.0/16")
+    ///     .build();
+    ///
+    /// let policy = baseline.intersect(&service);
+    /// assert_eq!(
+    ///     policy.check_ip("10.20.1.1".parse().unwrap()),
+    ///     RuleMatch::BlockCidr("10.0.0.0/8".to_string())
+    /// );
+    /// // The service's base policy still blocks loopback
+    /// assert!(policy.is_ip_allowed("127.0.0.1".parse().unwrap()).is_err());
+    /// assert!(policy.is_ip_allowed("93.184.216.34".parse().unwrap()).is_ok());
+    /// ```
+    pub fn intersect(&self, other: &CustomPolicy) -> CustomPolicy {
+        let mut builder = self.to_builder();
+        builder.required.push(other.to_builder().build());
+        builder.build()
+    }
+
+    /// A builder holding this policy's rules.
+    fn to_builder(&self) -> PolicyBuilder {
+        PolicyBuilder {
+            base: self.base,
+            blocked_cidrs: self.blocked_cidrs.clone(),
+            allowed_cidrs: self.allowed_cidrs.clone(),
+            blocked_hosts: self.blocked_hosts.clone(),
+            allowed_hosts: self.allowed_hosts.clone(),
+            allowed_ports: self.allowed_ports.clone(),
+            blocked_ports: self.blocked_ports.clone(),
+            allowed_schemes: self.allowed_schemes.clone(),
+            allowed_url_prefixes: self.allowed_url_prefixes.clone(),
+            blocked_paths: self.blocked_paths.clone(),
+            allowed_sites: self.allowed_sites.clone(),
+            notes: self.notes.clone(),
+            classifier: self.classifier.clone(),
+            local_addresses: self.local_addresses.clone(),
+            clock: self.clock.clone(),
+            required: self
+                .required
+                .iter()
+                .map(|policy| policy.to_builder().build())
+                .collect(),
+            errors: Vec::new(),
         }
-        self
     }
 
-    /// Block a hostname or pattern.
+    /// The remediation note attached to the block rule that produced `rule`.
     ///
-    /// Supports wildcards: `*.internal.example.com`
-    pub fn block_host(mut self, pattern: &str) -> Self {
-        self.blocked_hosts.push(pattern.to_lowercase());
-        self
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::AllowPrivate)
+    ///     .block_cidr_with_note("10.0.0.0/8", "Use the internal egress gateway instead")
+    ///     .build();
+    ///
+    /// let rule = policy.check_ip("10.1.2.3".parse().unwrap());
+    /// assert_eq!(policy.note_for(&rule), Some("Use the internal egress gateway instead"));
+    /// assert!(policy
+    ///     .is_ip_allowed("10.1.2.3".parse().unwrap())
+    ///     .unwrap_err()
+    ///     .to_string()
+    ///     .ends_with("(hint: Use the internal egress gateway instead)"));
+    /// ```
+    pub fn note_for(&self, rule: &RuleMatch) -> Option<&str> {
+        self.notes
+            .iter()
+            .find(|(noted, _)| noted == rule)
+            .map(|(_, note)| note.as_str())
+            .or_else(|| {
+                self.required
+                    .iter()
+                    .find_map(|policy| policy.note_for(rule))
+            })
     }
 
-    /// Allow a hostname or pattern, overriding base blocklist.
-    pub fn allow_host(mut self, pattern: &str) -> Self {
-        self.allowed_hosts.push(pattern.to_lowercase());
-        self
+    /// Turn a rule match into the `is_*_allowed` result, appending any note.
+    fn decide(&self, rule: RuleMatch) -> Result<(), BlockReason> {
+        let note = self.note_for(&rule).map(str::to_string);
+        match rule.into_block_reason(note) {
+            Some(reason) => Err(reason),
+            None => Ok(()),
+        }
     }
 
-    /// Build the custom policy.
-    pub fn build(self) -> CustomPolicy {
-        CustomPolicy {
-            base: self.base,
-            blocked_cidrs: self.blocked_cidrs,
-            allowed_cidrs: self.allowed_cidrs,
-            blocked_hosts: self.blocked_hosts,
-            allowed_hosts: self.allowed_hosts,
+    /// The base policy's block reason for `ip`, using the custom classifier
+    /// if one is set.
+    fn base_blocks(&self, ip: IpAddr) -> Option<&'static str> {
+        match &self.classifier {
+            Some(classifier) => classifier.blocked(ip, self.base),
+            None => classify_ip(ip)
+                .filter(|(class, _)| self.base.blocks(*class))
+                .map(|(_, reason)| reason),
         }
     }
-}
 
-/// Match a hostname against a pattern (supports * wildcard).
-fn matches_hostname_pattern(host: &str, pattern: &str) -> bool {
-    if pattern.starts_with("*.") {
-        let suffix = &pattern[1..]; // ".example.com"
-        host.ends_with(suffix) || host == &pattern[2..]
-    } else {
-        host == pattern
+    /// This policy with extra allow rules taking precedence over its own.
+    pub(crate) fn relaxed(&self, cidrs: &[IpNet], hosts: &[HostPattern]) -> CustomPolicy {
+        let mut relaxed = self.clone();
+        relaxed.allowed_cidrs.splice(0..0, cidrs.iter().copied());
+        relaxed.allowed_hosts.splice(0..0, hosts.iter().cloned());
+        for policy in &mut relaxed.required {
+            *policy = policy.relaxed(cidrs, hosts);
+        }
+        relaxed
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Flag risky or ineffective rules, for use as a CI gate.
+    ///
+    /// An empty result means no known problems; it does not prove the policy
+    /// is safe. Public suffix detection uses a short built-in list, or the
+    /// full Public Suffix List with the `psl` feature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{LintWarning, PolicyBuilder, Policy};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly)
+    ///     .allow_cidr("0.0.0.0/0")
+    ///     .build();
+    ///
+    /// let warnings = policy.lint();
+    /// assert!(warnings.contains(&LintWarning::AllowAll { rule: "0.0.0.0/0".into() }));
+    /// for warning in &warnings {
+    ///     eprintln!("policy lint: {}", warning);
+    /// }
+    /// ```
+    pub fn lint(&self) -> Vec<LintWarning> {
+        let mut warnings = Vec::new();
 
-    // ==================== CIDR blocking tests ====================
+        for cidr in &self.allowed_cidrs {
+            let rule = cidr.to_string();
+            if cidr.prefix_len() == 0 {
+                warnings.push(LintWarning::AllowAll { rule: rule.clone() });
+            }
+            if METADATA_IPS
+                .iter()
+                .any(|ip| cidr_matches(cidr, ip.parse().expect("valid metadata IP")))
+            {
+                warnings.push(LintWarning::AllowsMetadata { rule: rule.clone() });
+            }
+            let overrides_block = self.blocked_cidrs.iter().any(|b| cidrs_overlap(cidr, b))
+                || self.base_blocks(cidr.network()).is_some()
+                || self.base_blocks(cidr.broadcast()).is_some();
+            if !overrides_block {
+                warnings.push(LintWarning::RedundantAllow { rule });
+            }
+        }
 
-    #[test]
-    fn test_block_cidr() {
-        let policy = PolicyBuilder::new(Policy::AllowPrivate)
-            .block_cidr("10.0.0.0/8")
-            .build();
+        for block in &self.blocked_cidrs {
+            if let Some(allow) = self.allowed_cidrs.iter().find(|a| a.contains(block)) {
+                warnings.push(LintWarning::ShadowedBlock {
+                    rule: block.to_string(),
+                    by: allow.to_string(),
+                });
+            }
+        }
 
-        assert!(policy.is_ip_allowed("10.1.2.3".parse().unwrap()).is_err());
-        assert!(policy.is_ip_allowed("192.168.1.1".parse().unwrap()).is_ok());
+        let now = self.clock.now();
+        for pattern in &self.allowed_hosts {
+            let rule = pattern.to_string();
+            if !pattern.is_active(now) {
+                warnings.push(LintWarning::ExpiredAllow { rule: rule.clone() });
+            }
+            if !self.blocked_hosts.iter().any(|b| pattern.overlaps(b)) {
+                warnings.push(LintWarning::RedundantAllow { rule });
+            }
+        }
+
+        for pattern in self.allowed_hosts.iter().chain(&self.blocked_hosts) {
+            if pattern.is_public_suffix_wildcard() {
+                warnings.push(LintWarning::PublicSuffixWildcard {
+                    rule: pattern.to_string(),
+                });
+            }
+        }
+
+        for block in &self.blocked_hosts {
+            if let Some(allow) = self
+                .allowed_hosts
+                .iter()
+                .find(|a| a.until.is_none() && a.covers(block))
+            {
+                warnings.push(LintWarning::ShadowedBlock {
+                    rule: block.to_string(),
+                    by: allow.to_string(),
+                });
+            }
+        }
+
+        for policy in &self.required {
+            for warning in policy.lint() {
+                if !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+            }
+        }
+
+        warnings
     }
 
-    #[test]
-    fn test_block_multiple_cidrs() {
-        let policy = PolicyBuilder::new(Policy::AllowPrivate)
-            .block_cidr("10.0.0.0/8")
-            .block_cidr("172.16.0.0/12")
-            .build();
+    /// Evaluate a hostname (and port, if known) and report the rule that decided.
+    ///
+    /// Host rules never consult the base policy, so the result is one of
+    /// [`RuleMatch::AllowHost`], [`RuleMatch::BlockHost`], or [`RuleMatch::NoMatch`].
+    /// Port-scoped rules only match when `port` is given.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{PolicyBuilder, Policy, RuleMatch};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly)
+    ///     .block_host("*.internal.example.com")
+    ///     .build();
+    ///
+    /// let rule = policy.check_host("db.internal.example.com", Some(5432));
+    /// assert_eq!(rule, RuleMatch::BlockHost("*.internal.example.com".to_string()));
+    /// assert!(!rule.is_allowed());
+    /// ```
+    pub fn check_host(&self, host: &str, port: Option<u16>) -> RuleMatch {
+        self.check_host_at(host, port, self.clock.now())
+    }
 
-        assert!(policy.is_ip_allowed("10.1.2.3".parse().unwrap()).is_err());
-        assert!(policy.is_ip_allowed("172.20.1.1".parse().unwrap()).is_err());
-        assert!(policy.is_ip_allowed("192.168.1.1".parse().unwrap()).is_ok());
+    /// Like [`check_host`](Self::check_host), evaluating expiring allow rules
+    /// as of `now` instead of the policy's [clock](PolicyBuilder::with_clock).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::time::{Duration, SystemTime};
+    /// use url_jail::{PolicyBuilder, Policy, RuleMatch};
+    ///
+    /// let deadline = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly)
+    ///     .block_host("*.corp.example")
+    ///     .allow_host_until("jira.corp.example", deadline)
+    ///     .build();
+    ///
+    /// let before = deadline - Duration::from_secs(1);
+    /// assert!(policy.check_host_at("jira.corp.example", None, before).is_allowed());
+    /// assert!(!policy.check_host_at("jira.corp.example", None, deadline).is_allowed());
+    /// ```
+    pub fn check_host_at(&self, host: &str, port: Option<u16>, now: SystemTime) -> RuleMatch {
+        let rule = self.match_host(host, port, now);
+        self.record(&rule);
+        rule
     }
 
-    #[test]
-    fn test_block_single_ip_cidr() {
+    /// How often each CIDR and host rule has decided a check, in the order
+    /// the rules were added: block CIDRs, allow CIDRs, block hosts, then
+    /// allow hosts, followed by the rules of any
+    /// [`intersect`](Self::intersect)ed policies.
+    ///
+    /// Every [`check_ip`](Self::check_ip) and [`check_host`](Self::check_host)
+    /// counts, including those made by validation; only the first matching
+    /// rule of a check is counted. Clones of a policy share its counters. A
+    /// rule with no hits after representative traffic is a candidate for
+    /// removal.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{Policy, PolicyBuilder, RuleMatch};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::AllowPrivate)
+    ///     .block_cidr("10.0.0.0/8")
+    ///     .block_host("*.corp.example")
+    ///     .build();
+    /// let _ = policy.is_ip_allowed("10.1.2.3".parse().unwrap());
+    /// let _ = policy.is_ip_allowed("10.4.5.6".parse().unwrap());
+    ///
+    /// let stats = policy.rule_stats();
+    /// assert_eq!(stats[0].rule, RuleMatch::BlockCidr("10.0.0.0/8".into()));
+    /// assert_eq!(stats[0].hits, 2);
+    /// assert_eq!(stats[1].hits, 0);
+    /// ```
+    pub fn rule_stats(&self) -> Vec<RuleStat> {
+        let own = self.hits.rules.iter().map(|(rule, hits)| RuleStat {
+            rule: rule.clone(),
+            hits: hits.load(Ordering::Relaxed),
+        });
+        own.chain(self.required.iter().flat_map(CustomPolicy::rule_stats))
+            .collect()
+    }
+
+    /// Active allow-host rules naming a single host, as `(rule, host)`.
+    pub(crate) fn exact_allowed_hosts(&self) -> Vec<(String, String)> {
+        let now = self.clock.now();
+        self.allowed_hosts
+            .iter()
+            .filter(|pattern| pattern.is_active(now) && pattern.is_exact())
+            .map(|pattern| (pattern.to_string(), pattern.host.clone()))
+            .collect()
+    }
+
+    /// Like [`is_ip_allowed`](Self::is_ip_allowed), without counting a hit.
+    pub(crate) fn is_ip_allowed_uncounted(&self, ip: IpAddr) -> Result<(), BlockReason> {
+        self.decide(self.match_ip(ip))
+    }
+
+    fn match_host(&self, host: &str, port: Option<u16>, now: SystemTime) -> RuleMatch {
+        let rule = self.match_own_host(host, port, now);
+        self.require(rule, |policy| policy.match_host(host, port, now))
+    }
+
+    /// `rule` unless a policy from [`intersect`](Self::intersect) blocks,
+    /// in which case the first blocking rule.
+    fn require(&self, rule: RuleMatch, check: impl Fn(&CustomPolicy) -> RuleMatch) -> RuleMatch {
+        if !rule.is_allowed() {
+            return rule;
+        }
+        self.required
+            .iter()
+            .map(check)
+            .find(|rule| !rule.is_allowed())
+            .unwrap_or(rule)
+    }
+
+    /// Count a hit for `rule` here and in every intersected policy with it.
+    fn record(&self, rule: &RuleMatch) {
+        self.hits.record(rule);
+        for policy in &self.required {
+            policy.record(rule);
+        }
+    }
+
+    fn match_own_host(&self, host: &str, port: Option<u16>, now: SystemTime) -> RuleMatch {
+        // Port restrictions apply whatever the host rules say
+        if let Some(port) = port {
+            if self.blocked_ports.contains(&port) {
+                return RuleMatch::BlockPort(port);
+            }
+            if self
+                .allowed_ports
+                .as_ref()
+                .is_some_and(|allowed| !allowed.contains(&port))
+            {
+                return RuleMatch::PortNotAllowed(port);
+            }
+        }
+
+        let host_lower = host.to_lowercase();
+
+        if let Some(sites) = &self.allowed_sites {
+            if !sites.iter().any(|site| matches_site(&host_lower, site)) {
+                return RuleMatch::SiteNotAllowed(host_lower);
+            }
+        }
+
+        // Check explicit allowlist first, skipping expired rules
+        if let Some(pattern) = self
+            .allowed_hosts
+            .iter()
+            .find(|pattern| pattern.is_active(now) && pattern.matches(&host_lower, port))
+        {
+            return RuleMatch::AllowHost(pattern.to_string());
+        }
+
+        // Check explicit blocklist
+        if let Some(pattern) = self
+            .blocked_hosts
+            .iter()
+            .find(|pattern| pattern.matches(&host_lower, port))
+        {
+            return RuleMatch::BlockHost(pattern.to_string());
+        }
+
+        // Default deny: an IP literal is left to the allow_cidr rules
+        if self.base.is_allowlist_only() {
+            let literal = host_lower.trim_start_matches('[').trim_end_matches(']');
+            let allowed = literal
+                .parse::<IpAddr>()
+                .is_ok_and(|ip| self.allowed_cidrs.iter().any(|cidr| cidr_matches(cidr, ip)));
+            if !allowed {
+                return RuleMatch::HostNotAllowed(host_lower);
+            }
+        }
+
+        RuleMatch::NoMatch
+    }
+}
+
+/// Builder for creating custom policies.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyBuilder {
+    base: BasePolicy,
+    blocked_cidrs: Vec<IpNet>,
+    allowed_cidrs: Vec<IpNet>,
+    blocked_hosts: Vec<HostPattern>,
+    allowed_hosts: Vec<HostPattern>,
+    allowed_ports: Option<Vec<u16>>,
+    blocked_ports: Vec<u16>,
+    allowed_schemes: Option<Vec<String>>,
+    allowed_url_prefixes: Option<Vec<String>>,
+    blocked_paths: Vec<String>,
+    allowed_sites: Option<Vec<String>>,
+    notes: Vec<(RuleMatch, String)>,
+    classifier: Option<SharedClassifier>,
+    local_addresses: Option<LocalAddresses>,
+    clock: SharedClock,
+    required: Vec<CustomPolicy>,
+    errors: Vec<PolicyError>,
+}
+
+impl PolicyBuilder {
+    /// Create a new builder with the given base policy.
+    ///
+    /// `base` is a [`Policy`](crate::Policy) or, for finer control over which address
+    /// classes are blocked, a [`BasePolicy`](crate::BasePolicy).
+    pub fn new(base: impl Into<BasePolicy>) -> Self {
+        Self {
+            base: base.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Block an IP range (CIDR notation).
+    ///
+    /// IPv4 ranges also match the IPv4-mapped IPv6 form of their addresses
+    /// (`::ffff:a.b.c.d`), and mapped IPv6 ranges match plain IPv4.
+    ///
+    /// An unparseable CIDR adds no rule; it is reported by
+    /// [`try_build`](Self::try_build). Use
+    /// [`try_block_cidr`](Self::try_block_cidr) to fail at the call instead.
+    ///
+    /// # Example
+    /// ```
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::AllowPrivate)
+    ///     .block_cidr("10.0.0.0/8")
+    ///     .build();
+    /// ```
+    pub fn block_cidr(mut self, cidr: &str) -> Self {
+        match parse_cidr(cidr) {
+            Ok(net) => self.blocked_cidrs.push(net),
+            Err(e) => self.errors.push(e),
+        }
+        self
+    }
+
+    /// Like [`block_cidr`](Self::block_cidr), but fails on an invalid CIDR.
+    ///
+    /// # Example
+    /// ```
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let builder = PolicyBuilder::new(Policy::AllowPrivate);
+    /// assert!(builder.clone().try_block_cidr("10.0.0.0/8").is_ok());
+    /// assert!(builder.try_block_cidr("10.0.0/8").is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PolicyError::InvalidCidr`] if `cidr` does not parse.
+    pub fn try_block_cidr(self, cidr: &str) -> Result<Self, PolicyError> {
+        parse_cidr(cidr)?;
+        Ok(self.block_cidr(cidr))
+    }
+
+    /// Block an IP range with a remediation note shown to whoever hits it.
+    ///
+    /// The note is appended to the block reason as `(hint: <note>)`, so it
+    /// appears in the resulting [`Error`](crate::Error). It can be advice or a
+    /// link to documentation; it never affects decisions.
+    ///
+    /// # Example
+    /// ```
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::AllowPrivate)
+    ///     .block_cidr_with_note("10.0.0.0/8", "Use the internal egress gateway instead")
+    ///     .build();
+    /// ```
+    pub fn block_cidr_with_note(mut self, cidr: &str, note: &str) -> Self {
+        match parse_cidr(cidr) {
+            Ok(net) => {
+                self.notes
+                    .push((RuleMatch::BlockCidr(net.to_string()), note.to_string()));
+                self.blocked_cidrs.push(net);
+            }
+            Err(e) => self.errors.push(e),
+        }
+        self
+    }
+
+    /// Allow an IP range (CIDR notation), overriding base policy.
+    ///
+    /// Like [`block_cidr`](Self::block_cidr), matches both IPv4 and
+    /// IPv4-mapped IPv6 forms.
+    pub fn allow_cidr(mut self, cidr: &str) -> Self {
+        match parse_cidr(cidr) {
+            Ok(net) => self.allowed_cidrs.push(net),
+            Err(e) => self.errors.push(e),
+        }
+        self
+    }
+
+    /// Like [`allow_cidr`](Self::allow_cidr), but fails on an invalid CIDR.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PolicyError::InvalidCidr`] if `cidr` does not parse.
+    pub fn try_allow_cidr(self, cidr: &str) -> Result<Self, PolicyError> {
+        parse_cidr(cidr)?;
+        Ok(self.allow_cidr(cidr))
+    }
+
+    /// Block a hostname or pattern.
+    ///
+    /// Supports wildcards: `*.internal.example.com`
+    ///
+    /// A `*` inside the left-most label globs within that one label:
+    /// `api-*.example.com` matches `api-1.example.com` and `api-eu.example.com`,
+    /// but not `api-1.eu.example.com`.
+    ///
+    /// A `:port` suffix scopes the rule to one port: `*.internal.example.com:6379`
+    ///
+    /// With the `psl` feature, `site:example.com` matches every host whose
+    /// registrable domain is `example.com`, by the Public Suffix List. The
+    /// rule must name a registrable domain, not `site:co.uk` or
+    /// `site:www.example.com`.
+    ///
+    /// A malformed pattern (`*`, `a..example`, `http://a.example`) is kept
+    /// but can never match; it is reported by [`try_build`](Self::try_build).
+    pub fn block_host(mut self, pattern: &str) -> Self {
+        self.check_host_pattern(pattern);
+        self.blocked_hosts.push(HostPattern::parse(pattern));
+        self
+    }
+
+    /// Like [`block_host`](Self::block_host), but fails on a malformed
+    /// pattern.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PolicyError::InvalidHostPattern`] describing the problem.
+    pub fn try_block_host(self, pattern: &str) -> Result<Self, PolicyError> {
+        check_host_pattern(pattern)?;
+        Ok(self.block_host(pattern))
+    }
+
+    /// Block a hostname or pattern with a remediation note.
+    ///
+    /// See [`block_cidr_with_note`](Self::block_cidr_with_note).
+    pub fn block_host_with_note(mut self, pattern: &str, note: &str) -> Self {
+        self.check_host_pattern(pattern);
+        let pattern = HostPattern::parse(pattern);
+        self.notes
+            .push((RuleMatch::BlockHost(pattern.to_string()), note.to_string()));
+        self.blocked_hosts.push(pattern);
+        self
+    }
+
+    /// Allow a hostname or pattern, overriding base blocklist.
+    ///
+    /// Patterns are as for [`block_host`](Self::block_host), including
+    /// single-label globs such as `api-*.example.com`.
+    ///
+    /// A `:port` suffix scopes the rule to one port: `api.example.com:8443`
+    pub fn allow_host(mut self, pattern: &str) -> Self {
+        self.check_host_pattern(pattern);
+        self.allowed_hosts.push(HostPattern::parse(pattern));
+        self
+    }
+
+    /// Like [`allow_host`](Self::allow_host), but fails on a malformed
+    /// pattern.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PolicyError::InvalidHostPattern`] describing the problem.
+    pub fn try_allow_host(self, pattern: &str) -> Result<Self, PolicyError> {
+        check_host_pattern(pattern)?;
+        Ok(self.allow_host(pattern))
+    }
+
+    /// Allow a hostname or pattern until `deadline`, for break-glass exceptions.
+    ///
+    /// Behaves like [`allow_host`](Self::allow_host) before `deadline` and
+    /// matches nothing from `deadline` on, so a temporary exception cannot
+    /// linger in config forever.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::{Duration, SystemTime};
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly)
+    ///     .block_host("*.corp.example")
+    ///     .allow_host_until("wiki.corp.example", SystemTime::now() + Duration::from_secs(3600))
+    ///     .build();
+    /// assert!(policy.is_hostname_allowed("wiki.corp.example").is_ok());
+    /// ```
+    pub fn allow_host_until(mut self, pattern: &str, deadline: SystemTime) -> Self {
+        self.check_host_pattern(pattern);
+        self.allowed_hosts.push(HostPattern {
+            until: Some(deadline),
+            ..HostPattern::parse(pattern)
+        });
+        self
+    }
+
+    /// Decide whether [`allow_host_until`](Self::allow_host_until) rules have
+    /// expired by `clock` instead of the system clock.
+    ///
+    /// Validation, [`check_host`](CustomPolicy::check_host) and
+    /// [`lint`](CustomPolicy::lint) all read the time from `clock`, so tests
+    /// can move a break-glass rule past its deadline with a
+    /// [`ManualClock`](crate::ManualClock). Policies from
+    /// [`intersect`](CustomPolicy::intersect) are evaluated at this policy's
+    /// time.
+    ///
+    /// # Example
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use url_jail::{Clock, ManualClock, PolicyBuilder, Policy};
+    ///
+    /// let clock = Arc::new(ManualClock::default());
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly)
+    ///     .block_host("*.corp.example")
+    ///     .allow_host_until("wiki.corp.example", clock.now() + Duration::from_secs(3600))
+    ///     .with_clock(clock.clone())
+    ///     .build();
+    /// assert!(policy.is_hostname_allowed("wiki.corp.example").is_ok());
+    ///
+    /// clock.advance(Duration::from_secs(3600));
+    /// assert!(policy.is_hostname_allowed("wiki.corp.example").is_err());
+    /// ```
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = SharedClock::new(clock);
+        self
+    }
+
+    /// Only allow these ports; every other port is blocked.
+    ///
+    /// Calls accumulate. Port restrictions are checked before host rules,
+    /// so an `allow_host` rule cannot reopen a port. The port is the URL's
+    /// explicit port or the scheme default (80, 443).
+    ///
+    /// # Example
+    /// ```
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly)
+    ///     .allow_ports([443, 8443])
+    ///     .build();
+    /// assert!(policy.is_host_allowed("api.example.com", 443).is_ok());
+    /// assert!(policy.is_host_allowed("api.example.com", 22).is_err());
+    /// ```
+    pub fn allow_ports(mut self, ports: impl IntoIterator<Item = u16>) -> Self {
+        let ports: Vec<u16> = ports.into_iter().collect();
+        if !ports.is_empty() {
+            self.allowed_ports
+                .get_or_insert_with(Vec::new)
+                .extend(ports);
+        }
+        self
+    }
+
+    /// Block these ports, for example mail and data stores that SSRF
+    /// exploits pivot through.
+    ///
+    /// Takes precedence over [`allow_ports`](Self::allow_ports) and host
+    /// rules.
+    ///
+    /// # Example
+    /// ```
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly)
+    ///     .block_ports([25, 6379, 11211])
+    ///     .build();
+    /// assert!(policy.is_host_allowed("cache.example.com", 6379).is_err());
+    /// ```
+    pub fn block_ports(mut self, ports: impl IntoIterator<Item = u16>) -> Self {
+        self.blocked_ports.extend(ports);
+        self
+    }
+
+    /// Only allow hosts whose registrable domain is one of `sites`; every
+    /// other host is blocked.
+    ///
+    /// `allow_sites(["example.com"])` allows `example.com` and
+    /// `api.eu.example.com`, but not `evilexample.com` or
+    /// `example.com.evil.net`. Hosts without a registrable domain, such as
+    /// IP literals and `localhost`, are blocked. Registrable domains come
+    /// from the [Public Suffix List](https://publicsuffix.org/).
+    ///
+    /// Calls accumulate. Like port restrictions, the site allowlist is
+    /// checked before host rules, so an `allow_host` rule cannot reopen
+    /// another site. A site that is not a registrable domain itself, such
+    /// as `co.uk` or `www.example.com`, adds no rule but still turns the
+    /// allowlist on, so a policy whose only sites are rejected allows no
+    /// host. It is reported by [`try_build`](Self::try_build).
+    ///
+    /// # Example
+    /// ```
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly)
+    ///     .allow_sites(["example.com", "example.org"])
+    ///     .build();
+    /// assert!(policy.is_hostname_allowed("cdn.example.org").is_ok());
+    /// assert!(policy.is_hostname_allowed("evilexample.com").is_err());
+    /// ```
+    #[cfg(feature = "psl")]
+    pub fn allow_sites<S: AsRef<str>>(mut self, sites: impl IntoIterator<Item = S>) -> Self {
+        for site in sites {
+            let allowed = self.allowed_sites.get_or_insert_with(Vec::new);
+            let site = site.as_ref().to_lowercase();
+            match crate::site::check_site(&site) {
+                Ok(()) => allowed.push(site),
+                Err(reason) => self.errors.push(PolicyError::InvalidHostPattern {
+                    pattern: site,
+                    reason,
+                }),
+            }
+        }
+        self
+    }
+
+    /// Only allow these URL schemes, e.g. `["https"]` for untrusted input.
+    ///
+    /// URLs with any other scheme fail validation with
+    /// [`Error::SchemeBlocked`](crate::Error::SchemeBlocked). Schemes other
+    /// than `http` and `https` are never valid, so listing them has no
+    /// effect. Calls accumulate.
+    ///
+    /// # Example
+    /// ```
+    /// use url_jail::{validate_sync, Error, PolicyBuilder, Policy};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly).allow_schemes(["https"]).build();
+    /// let err = validate_sync("http://93.184.216.34/", &policy).unwrap_err();
+    /// assert!(matches!(err, Error::SchemeBlocked { .. }));
+    /// ```
+    pub fn allow_schemes<S: AsRef<str>>(mut self, schemes: impl IntoIterator<Item = S>) -> Self {
+        let schemes: Vec<String> = schemes
+            .into_iter()
+            .map(|scheme| scheme.as_ref().to_ascii_lowercase())
+            .collect();
+        if !schemes.is_empty() {
+            self.allowed_schemes
+                .get_or_insert_with(Vec::new)
+                .extend(schemes);
+        }
+        self
+    }
+
+    /// Only allow URLs under this prefix, for integrations that should only
+    /// ever call specific endpoints.
+    ///
+    /// Calls accumulate; a URL must fall under at least one prefix. Both the
+    /// prefix and each validated URL are normalized first (case, default
+    /// port, `.`/`..` segments), and a prefix only matches at a path
+    /// boundary; see [`CustomPolicy::is_url_allowed`]. Other URLs fail with
+    /// [`Error::HostnameBlocked`](crate::Error::HostnameBlocked). Every other
+    /// rule still applies, including the IP checks after DNS.
+    ///
+    /// A prefix that is not a valid `http`/`https` URL adds no rule but
+    /// still turns the allowlist on, so a policy whose only prefixes are
+    /// invalid allows no URL. It is reported by
+    /// [`try_build`](Self::try_build); use
+    /// [`try_allow_url_prefix`](Self::try_allow_url_prefix) to fail at the
+    /// call instead.
+    ///
+    /// # Example
+    /// ```
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly)
+    ///     .allow_url_prefix("https://api.partner.example/v1/")
+    ///     .allow_url_prefix("https://hooks.partner.example/notify")
+    ///     .build();
+    /// ```
+    pub fn allow_url_prefix(mut self, prefix: &str) -> Self {
+        let prefixes = self.allowed_url_prefixes.get_or_insert_with(Vec::new);
+        match parse_url_prefix(prefix) {
+            Ok(prefix) => prefixes.push(prefix),
+            Err(e) => self.errors.push(e),
+        }
+        self
+    }
+
+    /// Like [`allow_url_prefix`](Self::allow_url_prefix), but fails on an
+    /// invalid prefix.
+    ///
+    /// # Example
+    /// ```
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let builder = PolicyBuilder::new(Policy::PublicOnly);
+    /// assert!(builder.clone().try_allow_url_prefix("https://api.partner.example/").is_ok());
+    /// assert!(builder.try_allow_url_prefix("htps://api.partner.example/").is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PolicyError::InvalidUrlPrefix`] if `prefix` is not a valid
+    /// `http` or `https` URL.
+    pub fn try_allow_url_prefix(self, prefix: &str) -> Result<Self, PolicyError> {
+        parse_url_prefix(prefix)?;
+        Ok(self.allow_url_prefix(prefix))
+    }
+
+    /// Block URL paths matching `glob`, for endpoint families that must stay
+    /// unreachable even on allowed hosts.
+    ///
+    /// `*` matches any run of characters, including `/`; a glob without `*`
+    /// matches the exact path. Globs match the normalized path only, not the
+    /// query. Path rules apply to every host, and no allow rule overrides
+    /// them. Blocked URLs fail with
+    /// [`Error::HostnameBlocked`](crate::Error::HostnameBlocked). See
+    /// [`CustomPolicy::is_path_allowed`].
+    ///
+    /// # Example
+    /// ```
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::PublicOnly)
+    ///     .allow_url_prefix("https://api.partner.example/")
+    ///     .block_path_glob("*/admin/*")
+    ///     .block_path_glob("/internal*")
+    ///     .build();
+    /// ```
+    pub fn block_path_glob(mut self, glob: &str) -> Self {
+        self.blocked_paths.push(glob.to_ascii_lowercase());
+        self
+    }
+
+    /// Classify addresses with `classifier` when applying the base policy.
+    ///
+    /// The classifier can mark more addresses as private or always-blocked
+    /// than the default classification does, never fewer; see the
+    /// [`IpClassifier`](crate::IpClassifier) example. Explicit allow and
+    /// block rules still take precedence.
+    pub fn classifier(mut self, classifier: impl IpClassifier + 'static) -> Self {
+        self.classifier = Some(SharedClassifier::new(classifier));
+        self
+    }
+
+    /// Block the machine's own interface addresses.
+    ///
+    /// Catches requests to services listening on a non-loopback local
+    /// address, which a policy allowing the private network would otherwise
+    /// let through. Takes precedence over `allow_cidr` rules. The policy
+    /// follows [`LocalAddresses::refresh`](crate::LocalAddresses::refresh).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{LocalAddresses, Policy, PolicyBuilder};
+    ///
+    /// let local = LocalAddresses::from_addrs(["10.0.0.5".parse().unwrap()]);
+    /// let policy = PolicyBuilder::new(Policy::AllowPrivate)
+    ///     .allow_cidr("10.0.0.0/24")
+    ///     .block_local_addresses(local)
+    ///     .build();
+    ///
+    /// assert!(policy.is_ip_allowed("10.0.0.6".parse().unwrap()).is_ok());
+    /// assert!(policy.is_ip_allowed("10.0.0.5".parse().unwrap()).is_err());
+    /// ```
+    pub fn block_local_addresses(mut self, local: LocalAddresses) -> Self {
+        self.local_addresses = Some(local);
+        self
+    }
+
+    /// Block a platform's metadata and control-plane endpoints.
+    ///
+    /// Adds the group's [`hosts`](MetadataGroup::hosts) and
+    /// [`cidrs`](MetadataGroup::cidrs) as block rules, each with a note
+    /// naming the group. Like other block rules, they yield to matching
+    /// `allow_host` and `allow_cidr` rules.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{MetadataGroup, Policy, PolicyBuilder};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::AllowPrivate)
+    ///     .block_metadata_group(MetadataGroup::Kubernetes)
+    ///     .build();
+    /// assert!(policy.is_hostname_allowed("kubernetes.default.svc").is_err());
+    /// ```
+    pub fn block_metadata_group(self, group: MetadataGroup) -> Self {
+        self.block_group(group, group.cidrs().iter().copied())
+    }
+
+    /// Block the [`Kubernetes`](MetadataGroup::Kubernetes) group with the
+    /// cluster's own service ranges in place of the default
+    /// `10.96.0.0/12`.
+    ///
+    /// The API server's virtual IP is the first address of the service
+    /// range, so it is covered too. Pass both ranges of a dual-stack
+    /// cluster.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{Policy, PolicyBuilder};
+    ///
+    /// let policy = PolicyBuilder::new(Policy::AllowPrivate)
+    ///     .block_kubernetes(["172.20.0.0/16", "fd00:20::/108"])
+    ///     .build();
+    /// assert!(policy.is_ip_allowed("172.20.0.1".parse().unwrap()).is_err());
+    /// assert!(policy.is_ip_allowed("10.96.0.1".parse().unwrap()).is_ok());
+    /// assert!(policy.is_hostname_allowed("payments.prod.svc").is_err());
+    /// ```
+    pub fn block_kubernetes<'s>(self, service_cidrs: impl IntoIterator<Item = &'s str>) -> Self {
+        self.block_group(MetadataGroup::Kubernetes, service_cidrs)
+    }
+
+    fn block_group<'s>(
+        self,
+        group: MetadataGroup,
+        cidrs: impl IntoIterator<Item = &'s str>,
+    ) -> Self {
+        let note = group.note();
+        let builder = group.hosts().iter().fold(self, |builder, host| {
+            builder.block_host_with_note(host, &note)
+        });
+        cidrs
+            .into_iter()
+            .fold(builder, |builder, cidr| {
+                builder.block_cidr_with_note(cidr, &note)
+            })
+            .block_ports(group.ports().iter().copied())
+    }
+
+    /// Block every group in `groups`; see
+    /// [`block_metadata_group`](Self::block_metadata_group).
+    pub fn block_metadata_groups(self, groups: impl IntoIterator<Item = MetadataGroup>) -> Self {
+        groups
+            .into_iter()
+            .fold(self, |builder, group| builder.block_metadata_group(group))
+    }
+
+    /// Build the policy, failing if any rule was invalid.
+    ///
+    /// [`build`](Self::build) keeps going past invalid rules: a typo such as
+    /// `block_cidr("10.0.0/8")` silently adds no rule. `try_build` reports
+    /// every such rule instead, in the order they were added.
+    ///
+    /// # Example
+    /// ```
+    /// use url_jail::{PolicyBuilder, Policy};
+    ///
+    /// let errors = PolicyBuilder::new(Policy::AllowPrivate)
+    ///     .block_cidr("10.0.0/8")
+    ///     .block_host("*.corp.example")
+    ///     .allow_host("http://wiki.corp.example")
+    ///     .try_build()
+    ///     .unwrap_err();
+    /// assert_eq!(errors.len(), 2);
+    /// assert!(errors[0].to_string().starts_with("invalid CIDR \"10.0.0/8\""));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns every invalid CIDR and host pattern passed to the builder.
+    pub fn try_build(mut self) -> Result<CustomPolicy, Vec<PolicyError>> {
+        match std::mem::take(&mut self.errors) {
+            errors if errors.is_empty() => Ok(self.build()),
+            errors => Err(errors),
+        }
+    }
+
+    /// Build the custom policy.
+    ///
+    /// Invalid rules are skipped; see [`try_build`](Self::try_build).
+    pub fn build(self) -> CustomPolicy {
+        let hits = Arc::new(RuleHits::new(
+            self.blocked_cidrs
+                .iter()
+                .map(|net| RuleMatch::BlockCidr(net.to_string()))
+                .chain(
+                    self.allowed_cidrs
+                        .iter()
+                        .map(|net| RuleMatch::AllowCidr(net.to_string())),
+                )
+                .chain(
+                    self.blocked_hosts
+                        .iter()
+                        .map(|pattern| RuleMatch::BlockHost(pattern.to_string())),
+                )
+                .chain(
+                    self.allowed_hosts
+                        .iter()
+                        .map(|pattern| RuleMatch::AllowHost(pattern.to_string())),
+                ),
+        ));
+        CustomPolicy {
+            base: self.base,
+            blocked_cidrs: self.blocked_cidrs,
+            allowed_cidrs: self.allowed_cidrs,
+            blocked_hosts: self.blocked_hosts,
+            allowed_hosts: self.allowed_hosts,
+            allowed_ports: self.allowed_ports,
+            blocked_ports: self.blocked_ports,
+            allowed_schemes: self.allowed_schemes,
+            allowed_url_prefixes: self.allowed_url_prefixes,
+            blocked_paths: self.blocked_paths,
+            allowed_sites: self.allowed_sites,
+            notes: self.notes,
+            classifier: self.classifier,
+            local_addresses: self.local_addresses,
+            clock: self.clock,
+            required: self.required,
+            hits,
+        }
+    }
+}
+
+/// Hit counters for a policy's CIDR and host rules, shared by its clones.
+///
+/// Rules are fixed when the policy is built, so counting needs no lock.
+/// Rules added later, such as a [`PolicyHandle`](crate::PolicyHandle)
+/// relaxation, are not counted.
+#[derive(Default)]
+struct RuleHits {
+    rules: Vec<(RuleMatch, AtomicU64)>,
+    index: HashMap<RuleMatch, usize>,
+}
+
+impl RuleHits {
+    fn new(rules: impl IntoIterator<Item = RuleMatch>) -> Self {
+        let mut hits = Self::default();
+        for rule in rules {
+            // Duplicate rules can never both match first; count them once
+            if !hits.index.contains_key(&rule) {
+                hits.index.insert(rule.clone(), hits.rules.len());
+                hits.rules.push((rule, AtomicU64::new(0)));
+            }
+        }
+        hits
+    }
+
+    fn record(&self, rule: &RuleMatch) {
+        if let Some(&i) = self.index.get(rule) {
+            self.rules[i].1.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl std::fmt::Debug for RuleHits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RuleHits({} rules)", self.rules.len())
+    }
+}
+
+/// How often one rule has matched, from [`CustomPolicy::rule_stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleStat {
+    /// The rule, as reported by [`CustomPolicy::check_ip`] and
+    /// [`CustomPolicy::check_host`].
+    pub rule: RuleMatch,
+
+    /// Checks this rule has decided.
+    pub hits: u64,
+}
+
+impl PolicyBuilder {
+    fn check_host_pattern(&mut self, pattern: &str) {
+        if let Err(e) = check_host_pattern(pattern) {
+            self.errors.push(e);
+        }
+    }
+}
+
+/// An invalid rule passed to a [`PolicyBuilder`].
+///
+/// Returned by the `try_*` builder methods and
+/// [`PolicyBuilder::try_build`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyError {
+    /// A CIDR that does not parse.
+    InvalidCidr {
+        /// The CIDR as given.
+        cidr: String,
+        /// Why it does not parse.
+        reason: String,
+    },
+
+    /// A host pattern that can never match a hostname.
+    InvalidHostPattern {
+        /// The pattern as given.
+        pattern: String,
+        /// What is wrong with it.
+        reason: String,
+    },
+
+    /// A URL prefix that is not a valid `http` or `https` URL.
+    InvalidUrlPrefix {
+        /// The prefix as given.
+        prefix: String,
+        /// Why it does not parse.
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicyError::InvalidCidr { cidr, reason } => {
+                write!(f, "invalid CIDR {:?}: {}", cidr, reason)
+            }
+            PolicyError::InvalidHostPattern { pattern, reason } => {
+                write!(f, "invalid host pattern {:?}: {}", pattern, reason)
+            }
+            PolicyError::InvalidUrlPrefix { prefix, reason } => {
+                write!(f, "invalid URL prefix {:?}: {}", prefix, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+/// The normalized form of a URL prefix, as matched by `is_url_allowed`.
+fn parse_url_prefix(prefix: &str) -> Result<String, PolicyError> {
+    SafeUrl::parse(prefix)
+        .map(|url| url.canonical())
+        .map_err(|e| PolicyError::InvalidUrlPrefix {
+            prefix: prefix.to_string(),
+            reason: e.to_string(),
+        })
+}
+
+/// Whether the normalized URL or prefix `url` falls under `prefix`, ending
+/// at a path boundary.
+fn prefix_covers(prefix: &str, url: &str) -> bool {
+    url.strip_prefix(prefix).is_some_and(|rest| {
+        prefix.ends_with('/') || rest.is_empty() || rest.starts_with(['/', '?', '#'])
+    })
+}
+
+pub(crate) fn parse_cidr(cidr: &str) -> Result<IpNet, PolicyError> {
+    cidr.parse()
+        .map_err(|e: ipnet::AddrParseError| PolicyError::InvalidCidr {
+            cidr: cidr.to_string(),
+            reason: e.to_string(),
+        })
+}
+
+/// Check that a hostname pattern is something [`PolicyBuilder::block_host`]
+/// can match.
+pub(crate) fn check_host_pattern(pattern: &str) -> Result<(), PolicyError> {
+    let invalid = |reason: &str| {
+        Err(PolicyError::InvalidHostPattern {
+            pattern: pattern.to_string(),
+            reason: reason.to_string(),
+        })
+    };
+    let site = pattern.strip_prefix(SITE_PREFIX);
+    let unprefixed = site.unwrap_or(pattern);
+    let host = match unprefixed.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+            if port.parse::<u16>().is_err() {
+                return invalid("invalid port");
+            }
+            host
+        }
+        _ => unprefixed,
+    };
+    if host.is_empty() {
+        return invalid("empty hostname");
+    }
+    if site.is_some() {
+        #[cfg(feature = "psl")]
+        return crate::site::check_site(&host.to_lowercase()).or_else(|reason| invalid(&reason));
+        #[cfg(not(feature = "psl"))]
+        return invalid("site: patterns require the psl feature");
+    }
+    if host
+        .chars()
+        .any(|c| c.is_whitespace() || matches!(c, '/' | '?' | '#' | '@'))
+    {
+        return invalid("contains characters not allowed in a hostname");
+    }
+    if host.split('.').any(str::is_empty) {
+        return invalid("empty label");
+    }
+    if host.split('.').skip(1).any(|label| label.contains('*')) {
+        return invalid("'*' is only allowed in the left-most label");
+    }
+    if host == "*" {
+        return invalid("'*' alone matches nothing; use '*.domain'");
+    }
+    Ok(())
+}
+
+/// Whether `cidr` contains `ip` in any of its IPv4 / IPv4-mapped IPv6 forms.
+///
+/// A `127.0.0.0/8` rule also matches `::ffff:127.0.0.1` and the
+/// IPv4-compatible `::127.0.0.1`, and a `::ffff:10.0.0.0/104` rule also
+/// matches `10.1.2.3`, so a rule written in one form cannot be bypassed with
+/// another.
+fn cidr_matches(cidr: &IpNet, ip: IpAddr) -> bool {
+    if cidr.contains(&ip) {
+        return true;
+    }
+    match unwrap_ipv4(ip) {
+        IpAddr::V4(v4) => {
+            cidr.contains(&IpAddr::V4(v4)) || cidr.contains(&IpAddr::V6(v4.to_ipv6_mapped()))
+        }
+        IpAddr::V6(_) => false,
+    }
+}
+
+/// The rule that decided a [`CustomPolicy`] check.
+///
+/// Returned by [`CustomPolicy::check_ip`] and [`CustomPolicy::check_host`] so
+/// callers can log precise rule hits. `Display` gives the same reason text as
+/// the `is_*_allowed` methods for blocks.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RuleMatch {
+    /// Allowed by this `allow_cidr` rule.
+    AllowCidr(String),
+
+    /// Blocked by this `block_cidr` rule.
+    BlockCidr(String),
+
+    /// Allowed by this `allow_host` pattern.
+    AllowHost(String),
+
+    /// Blocked by this `block_host` pattern.
+    BlockHost(String),
+
+    /// Blocked by a `block_ports` rule.
+    BlockPort(u16),
+
+    /// Blocked because `allow_ports` does not list this port.
+    PortNotAllowed(u16),
+
+    /// Blocked because `allow_sites` does not list the registrable domain
+    /// of this host.
+    SiteNotAllowed(String),
+
+    /// Blocked because the base policy is allowlist-only and no allow rule
+    /// names this host.
+    HostNotAllowed(String),
+
+    /// Blocked by the base policy, with its reason.
+    BasePolicy(&'static str),
+
+    /// Blocked as an address of the local machine.
+    LocalAddress(IpAddr),
+
+    /// No rule matched and the base policy allows it.
+    NoMatch,
+}
+
+impl RuleMatch {
+    /// Returns `true` unless the match blocks.
+    pub fn is_allowed(&self) -> bool {
+        !matches!(
+            self,
+            RuleMatch::BlockCidr(_)
+                | RuleMatch::BlockHost(_)
+                | RuleMatch::BlockPort(_)
+                | RuleMatch::PortNotAllowed(_)
+                | RuleMatch::SiteNotAllowed(_)
+                | RuleMatch::HostNotAllowed(_)
+                | RuleMatch::BasePolicy(_)
+                | RuleMatch::LocalAddress(_)
+        )
+    }
+
+    fn into_block_reason(self, note: Option<String>) -> Option<BlockReason> {
+        Some(match self {
+            RuleMatch::BlockCidr(rule) => BlockReason::BlockedCidr { rule, note },
+            RuleMatch::BlockHost(rule) => BlockReason::BlockedHostPattern { rule, note },
+            RuleMatch::BlockPort(port) => BlockReason::BlockedPort(port),
+            RuleMatch::PortNotAllowed(port) => BlockReason::PortNotAllowed(port),
+            RuleMatch::SiteNotAllowed(host) => BlockReason::SiteNotAllowed(host),
+            RuleMatch::HostNotAllowed(host) => BlockReason::HostNotAllowed(host),
+            RuleMatch::BasePolicy(reason) => BlockReason::BasePolicy { reason },
+            RuleMatch::LocalAddress(ip) => BlockReason::LocalAddress(ip),
+            RuleMatch::AllowCidr(_) | RuleMatch::AllowHost(_) | RuleMatch::NoMatch => return None,
+        })
+    }
+}
+
+impl std::fmt::Display for RuleMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleMatch::AllowCidr(cidr) => write!(f, "allowed by custom policy CIDR rule: {}", cidr),
+            RuleMatch::BlockCidr(cidr) => write!(f, "blocked by custom policy CIDR rule: {}", cidr),
+            RuleMatch::AllowHost(pattern) => {
+                write!(f, "allowed by custom policy hostname rule: {}", pattern)
+            }
+            RuleMatch::BlockHost(pattern) => {
+                write!(f, "blocked by custom policy hostname rule: {}", pattern)
+            }
+            RuleMatch::BlockPort(port) => write!(f, "blocked by custom policy port rule: {}", port),
+            RuleMatch::PortNotAllowed(port) => {
+                write!(f, "port {} not in custom policy port allowlist", port)
+            }
+            RuleMatch::SiteNotAllowed(host) => {
+                write!(f, "host {} not under any custom policy site", host)
+            }
+            RuleMatch::HostNotAllowed(host) => {
+                write!(f, "host {} not in custom policy allowlist", host)
+            }
+            RuleMatch::BasePolicy(reason) => f.write_str(reason),
+            RuleMatch::LocalAddress(ip) => write!(f, "address of a local interface: {}", ip),
+            RuleMatch::NoMatch => f.write_str("no rule matched"),
+        }
+    }
+}
+
+/// Why a [`CustomPolicy`] blocked an address, host, or scheme.
+///
+/// Returned by the `is_*_allowed` methods. Match on it to handle specific
+/// rules; `Display` gives the reason text used in [`Error`](crate::Error)
+/// messages, including any remediation note as `(hint: <note>)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockReason {
+    /// Blocked by this `block_cidr` rule.
+    BlockedCidr {
+        /// The CIDR, as normalized by the policy.
+        rule: String,
+        /// The rule's remediation note, if any.
+        note: Option<String>,
+    },
+
+    /// Blocked by this `block_host` pattern.
+    BlockedHostPattern {
+        /// The pattern, as normalized by the policy.
+        rule: String,
+        /// The rule's remediation note, if any.
+        note: Option<String>,
+    },
+
+    /// Blocked by a `block_ports` rule.
+    BlockedPort(u16),
+
+    /// Blocked because `allow_ports` does not list this port.
+    PortNotAllowed(u16),
+
+    /// Blocked because `allow_sites` does not list the registrable domain
+    /// of this host.
+    SiteNotAllowed(String),
+
+    /// Blocked because the base policy is allowlist-only and no allow rule
+    /// names this host.
+    HostNotAllowed(String),
+
+    /// Blocked because `allow_schemes` does not list this scheme.
+    SchemeNotAllowed {
+        /// The rejected scheme, lowercased.
+        scheme: String,
+        /// The schemes the policy allows.
+        allowed: Vec<String>,
+    },
+
+    /// Blocked by this `block_path_glob` rule.
+    BlockedPath {
+        /// The glob, lowercased.
+        rule: String,
+    },
+
+    /// Blocked because the URL is not under any `allow_url_prefix` prefix.
+    UrlNotAllowed {
+        /// The allowed prefixes, normalized.
+        allowed: Vec<String>,
+    },
+
+    /// Blocked by the base policy.
+    BasePolicy {
+        /// The base policy's reason, e.g. `"private network (RFC 1918)"`.
+        reason: &'static str,
+    },
+
+    /// Blocked by `block_local_addresses` as this machine's own address.
+    LocalAddress(IpAddr),
+}
+
+impl BlockReason {
+    /// The remediation note attached to the matched rule, if any.
+    pub fn note(&self) -> Option<&str> {
+        match self {
+            BlockReason::BlockedCidr { note, .. }
+            | BlockReason::BlockedHostPattern { note, .. } => note.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for BlockReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockReason::BlockedCidr { rule, .. } => {
+                write!(f, "{}", RuleMatch::BlockCidr(rule.clone()))?
+            }
+            BlockReason::BlockedHostPattern { rule, .. } => {
+                write!(f, "{}", RuleMatch::BlockHost(rule.clone()))?
+            }
+            BlockReason::BlockedPort(port) => write!(f, "{}", RuleMatch::BlockPort(*port))?,
+            BlockReason::PortNotAllowed(port) => write!(f, "{}", RuleMatch::PortNotAllowed(*port))?,
+            BlockReason::SiteNotAllowed(host) => {
+                write!(f, "{}", RuleMatch::SiteNotAllowed(host.clone()))?
+            }
+            BlockReason::HostNotAllowed(host) => {
+                write!(f, "{}", RuleMatch::HostNotAllowed(host.clone()))?
+            }
+            BlockReason::SchemeNotAllowed { allowed, .. } => write!(
+                f,
+                "not in custom policy scheme allowlist ({})",
+                allowed.join(", ")
+            )?,
+            BlockReason::BlockedPath { rule } => {
+                write!(f, "blocked by custom policy path rule: {}", rule)?
+            }
+            BlockReason::UrlNotAllowed { allowed } => write!(
+                f,
+                "URL not under any custom policy URL prefix ({})",
+                allowed.join(", ")
+            )?,
+            BlockReason::BasePolicy { reason } => f.write_str(reason)?,
+            BlockReason::LocalAddress(ip) => write!(f, "{}", RuleMatch::LocalAddress(*ip))?,
+        }
+        match self.note() {
+            Some(note) => write!(f, " (hint: {})", note),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for BlockReason {}
+
+/// A problem found by [`CustomPolicy::lint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintWarning {
+    /// An allow rule covering every address (`0.0.0.0/0`, `::/0`).
+    AllowAll { rule: String },
+
+    /// An allow rule covering a cloud metadata address.
+    AllowsMetadata { rule: String },
+
+    /// An allow rule that overrides nothing: no block rule or base policy
+    /// block overlaps it.
+    RedundantAllow { rule: String },
+
+    /// An `allow_host_until` rule whose deadline has passed.
+    ExpiredAllow { rule: String },
+
+    /// A wildcard host rule covering a public suffix, such as `*.com` or
+    /// `*.co.uk`, which matches hosts run by unrelated parties.
+    PublicSuffixWildcard { rule: String },
+
+    /// A block rule that never applies because an allow rule covers it.
+    ShadowedBlock { rule: String, by: String },
+}
+
+impl LintWarning {
+    /// The rule the warning is about.
+    pub fn rule(&self) -> &str {
+        match self {
+            LintWarning::AllowAll { rule }
+            | LintWarning::AllowsMetadata { rule }
+            | LintWarning::RedundantAllow { rule }
+            | LintWarning::ExpiredAllow { rule }
+            | LintWarning::PublicSuffixWildcard { rule }
+            | LintWarning::ShadowedBlock { rule, .. } => rule,
+        }
+    }
+}
+
+impl std::fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LintWarning::AllowAll { rule } => {
+                write!(f, "allow rule {} covers every address", rule)
+            }
+            LintWarning::AllowsMetadata { rule } => {
+                write!(f, "allow rule {} covers a cloud metadata address", rule)
+            }
+            LintWarning::RedundantAllow { rule } => {
+                write!(f, "allow rule {} does not override any block", rule)
+            }
+            LintWarning::ExpiredAllow { rule } => {
+                write!(f, "allow rule {} has expired", rule)
+            }
+            LintWarning::PublicSuffixWildcard { rule } => {
+                write!(f, "host rule {} covers a public suffix", rule)
+            }
+            LintWarning::ShadowedBlock { rule, by } => {
+                write!(f, "block rule {} is overridden by allow rule {}", rule, by)
+            }
+        }
+    }
+}
+
+/// Cloud metadata addresses that allow rules should not cover.
+const METADATA_IPS: &[&str] = &["169.254.169.254", "100.100.100.200", "fd00:ec2::254"];
+
+/// Multi-label public suffixes commonly mistaken for organization domains.
+///
+/// Every single-label suffix (`*.com`, `*.internal`) is also treated as public.
+const PUBLIC_SUFFIXES: &[&str] = &[
+    "co.uk",
+    "org.uk",
+    "ac.uk",
+    "gov.uk",
+    "com.au",
+    "net.au",
+    "org.au",
+    "co.jp",
+    "co.nz",
+    "co.in",
+    "com.br",
+    "com.cn",
+    "amazonaws.com",
+    "cloudfront.net",
+    "appspot.com",
+    "azurewebsites.net",
+    "herokuapp.com",
+    "github.io",
+    "netlify.app",
+    "vercel.app",
+    "pages.dev",
+    "workers.dev",
+];
+
+/// Whether two CIDRs share any address.
+fn cidrs_overlap(a: &IpNet, b: &IpNet) -> bool {
+    a.contains(&b.network()) || b.contains(&a.network())
+}
+
+/// A hostname pattern, optionally scoped to a single port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HostPattern {
+    host: String,
+    port: Option<u16>,
+    /// Deadline after which the rule stops matching.
+    until: Option<SystemTime>,
+}
+
+impl HostPattern {
+    /// Parse `host`, `*.host`, or either with a `:port` suffix.
+    ///
+    /// A suffix that is not a valid port is kept as part of the host, so the
+    /// pattern behaves exactly as it did before port scoping existed.
+    pub(crate) fn parse(pattern: &str) -> Self {
+        let pattern = pattern.to_lowercase();
+        let unprefixed = pattern.strip_prefix(SITE_PREFIX).unwrap_or(&pattern);
+        if let Some((host, port)) = unprefixed.rsplit_once(':') {
+            // Unbracketed IPv6 literals contain colons but no port
+            if !host.contains(':') || host.ends_with(']') {
+                if let Ok(number) = port.parse::<u16>() {
+                    // Keep any `site:` prefix on the host
+                    return Self {
+                        host: pattern[..pattern.len() - port.len() - 1].to_string(),
+                        port: Some(number),
+                        until: None,
+                    };
+                }
+            }
+        }
+        Self {
+            host: pattern,
+            port: None,
+            until: None,
+        }
+    }
+
+    /// Whether every host and port this pattern could match is also matched
+    /// by `self`.
+    fn covers(&self, other: &HostPattern) -> bool {
+        if self.port.is_some() && self.port != other.port {
+            return false;
+        }
+        if let Some(suffix) = other.host.strip_prefix("*.") {
+            return (self.host.starts_with("*.") || self.host.starts_with(SITE_PREFIX))
+                && matches_hostname_pattern(suffix, &self.host);
+        }
+        if let Some((_, rest)) = label_glob(&other.host) {
+            // Only a subdomain wildcard over `rest` (or the same glob) covers
+            // every label the glob can produce
+            return self.host == other.host
+                || ((self.host.starts_with("*.") || self.host.starts_with(SITE_PREFIX))
+                    && matches_hostname_pattern(rest, &self.host));
+        }
+        if let Some(site) = other.host.strip_prefix(SITE_PREFIX) {
+            // Every host of a site is under the site itself
+            return self.host == other.host
+                || (self.host.starts_with("*.") && matches_hostname_pattern(site, &self.host));
+        }
+        matches_hostname_pattern(&other.host, &self.host)
+    }
+
+    /// Whether some host and port is matched by both patterns.
+    fn overlaps(&self, other: &HostPattern) -> bool {
+        let ports_overlap = self.port.is_none() || other.port.is_none() || self.port == other.port;
+        let (a, b) = (
+            Self {
+                port: None,
+                ..self.clone()
+            },
+            Self {
+                port: None,
+                ..other.clone()
+            },
+        );
+        ports_overlap && (a.covers(&b) || b.covers(&a))
+    }
+
+    /// Whether this is a wildcard over a public suffix.
+    fn is_public_suffix_wildcard(&self) -> bool {
+        let suffix = match self.host.strip_prefix("*.") {
+            Some(suffix) => suffix,
+            None => match label_glob(&self.host) {
+                Some((_, rest)) => rest,
+                None => return false,
+            },
+        };
+        !suffix.contains('.') || PUBLIC_SUFFIXES.contains(&suffix) || in_public_suffix_list(suffix)
+    }
+
+    /// Whether the pattern names one host, rather than a wildcard, glob or
+    /// site.
+    fn is_exact(&self) -> bool {
+        !self.host.contains('*') && !self.host.starts_with(SITE_PREFIX)
+    }
+
+    /// Whether the rule is still in force at `now`.
+    fn is_active(&self, now: SystemTime) -> bool {
+        self.until.is_none_or(|until| now < until)
+    }
+
+    /// Check a lowercased hostname and optional port against this pattern.
+    pub(crate) fn matches(&self, host: &str, port: Option<u16>) -> bool {
+        if let Some(rule_port) = self.port {
+            if port != Some(rule_port) {
+                return false;
+            }
+        }
+        matches_hostname_pattern(host, &self.host)
+    }
+}
+
+impl std::fmt::Display for HostPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.port {
+            Some(port) => write!(f, "{}:{}", self.host, port),
+            None => f.write_str(&self.host),
+        }
+    }
+}
+
+/// Prefix of host patterns matching a registrable domain.
+const SITE_PREFIX: &str = "site:";
+
+/// Match a hostname against a pattern (supports * wildcard).
+fn matches_hostname_pattern(host: &str, pattern: &str) -> bool {
+    if let Some(site) = pattern.strip_prefix(SITE_PREFIX) {
+        matches_site(host, site)
+    } else if pattern.starts_with("*.") {
+        let suffix = &pattern[1..]; // ".example.com"
+        host.ends_with(suffix) || host == &pattern[2..]
+    } else if let Some((glob, rest)) = label_glob(pattern) {
+        match host.split_once('.') {
+            Some((label, host_rest)) => host_rest == rest && glob_matches(label, glob),
+            None => false,
+        }
+    } else {
+        host == pattern
+    }
+}
+
+/// Whether the registrable domain of `host` is `site`.
+///
+/// Never matches without the `psl` feature, like any other invalid pattern.
+fn matches_site(host: &str, site: &str) -> bool {
+    #[cfg(feature = "psl")]
+    {
+        crate::site::registrable_domain(host).as_deref() == Some(site)
+    }
+    #[cfg(not(feature = "psl"))]
+    {
+        let _ = (host, site);
+        false
+    }
+}
+
+/// Whether the Public Suffix List names `domain`; always `false` without
+/// the `psl` feature.
+fn in_public_suffix_list(domain: &str) -> bool {
+    #[cfg(feature = "psl")]
+    {
+        crate::site::is_public_suffix(domain)
+    }
+    #[cfg(not(feature = "psl"))]
+    {
+        let _ = domain;
+        false
+    }
+}
+
+/// Split `api-*.example.com` into the left-most label glob and the rest.
+///
+/// Returns `None` unless the first label contains `*` and a suffix follows.
+fn label_glob(pattern: &str) -> Option<(&str, &str)> {
+    let (label, rest) = pattern.split_once('.')?;
+    (label.contains('*') && label != "*" && !rest.is_empty()).then_some((label, rest))
+}
+
+/// Match one label against a glob where `*` matches any run of characters.
+/// Match a path against a `block_path_glob` glob.
+fn path_glob_matches(path: &str, glob: &str) -> bool {
+    if glob.contains('*') {
+        glob_matches(path, glob)
+    } else {
+        path == glob
+    }
+}
+
+/// Decode percent-escapes, leaving malformed ones as they are.
+fn percent_decode_lossy(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn glob_matches(label: &str, glob: &str) -> bool {
+    let mut parts = glob.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut remaining) = label.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let last = parts.pop().unwrap_or_default();
+    for part in parts {
+        match remaining.find(part) {
+            Some(i) => remaining = &remaining[i + part.len()..],
+            None => return false,
+        }
+    }
+    remaining.len() >= last.len() && remaining.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::Policy;
+
+    // ==================== Note tests ====================
+
+    #[test]
+    fn test_block_notes_surface_in_reasons() {
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr_with_note("10.0.0.0/8", "Use the egress gateway")
+            .block_host_with_note("*.corp.example:5432", "https://wiki.example/db-access")
+            .block_cidr("192.168.0.0/16")
+            .build();
+
+        let reason = policy
+            .is_ip_allowed("10.9.9.9".parse().unwrap())
+            .unwrap_err();
+        assert_eq!(
+            reason,
+            BlockReason::BlockedCidr {
+                rule: "10.0.0.0/8".into(),
+                note: Some("Use the egress gateway".into()),
+            }
+        );
+        assert_eq!(
+            reason.to_string(),
+            "blocked by custom policy CIDR rule: 10.0.0.0/8 (hint: Use the egress gateway)"
+        );
+        let reason = policy.is_host_allowed("pg.corp.example", 5432).unwrap_err();
+        assert_eq!(reason.note(), Some("https://wiki.example/db-access"));
+        assert!(reason
+            .to_string()
+            .ends_with("(hint: https://wiki.example/db-access)"));
+
+        // Rules without notes and allowed results are unchanged
+        let rule = policy.check_ip("192.168.1.1".parse().unwrap());
+        assert_eq!(policy.note_for(&rule), None);
+        assert!(!policy
+            .is_ip_allowed("192.168.1.1".parse().unwrap())
+            .unwrap_err()
+            .to_string()
+            .contains("hint"));
+        assert!(policy.is_host_allowed("pg.corp.example", 443).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_block_note_in_error() {
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr_with_note("10.0.0.0/8", "Use the egress gateway")
+            .build();
+        let err = crate::validate_custom("http://10.0.0.1/", &policy)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("(hint: Use the egress gateway)"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_notes_do_not_change_fingerprint() {
+        let plain = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("10.0.0.0/8")
+            .build();
+        let noted = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr_with_note("10.0.0.0/8", "note")
+            .build();
+        assert_eq!(plain.fingerprint(), noted.fingerprint());
+    }
+
+    // ==================== Lint tests ====================
+
+    #[test]
+    fn test_lint_clean_policy() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.internal.example.com")
+            .allow_host("api.internal.example.com")
+            .allow_cidr("10.1.0.0/16")
+            .build();
+        assert_eq!(policy.lint(), Vec::new());
+    }
+
+    #[test]
+    fn test_lint_allow_all_and_metadata() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_cidr("0.0.0.0/0")
+            .allow_cidr("169.254.0.0/16")
+            .build();
+        let warnings = policy.lint();
+
+        assert!(warnings.contains(&LintWarning::AllowAll {
+            rule: "0.0.0.0/0".into()
+        }));
+        assert!(warnings.contains(&LintWarning::AllowsMetadata {
+            rule: "0.0.0.0/0".into()
+        }));
+        assert!(warnings.contains(&LintWarning::AllowsMetadata {
+            rule: "169.254.0.0/16".into()
+        }));
+    }
+
+    #[test]
+    fn test_lint_redundant_allow() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_cidr("8.8.8.0/24")
+            .allow_host("api.example.com")
+            .build();
+        let rules: Vec<String> = policy
+            .lint()
+            .iter()
+            .filter(|w| matches!(w, LintWarning::RedundantAllow { .. }))
+            .map(|w| w.rule().to_string())
+            .collect();
+        assert_eq!(rules, ["8.8.8.0/24", "api.example.com"]);
+    }
+
+    #[test]
+    fn test_lint_public_suffix_wildcard() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.com")
+            .block_host("*.co.uk")
+            .block_host("*.example.co.uk")
+            .build();
+        let flagged: Vec<String> = policy
+            .lint()
+            .iter()
+            .filter(|w| matches!(w, LintWarning::PublicSuffixWildcard { .. }))
+            .map(|w| w.rule().to_string())
+            .collect();
+        assert_eq!(flagged, ["*.com", "*.co.uk"]);
+    }
+
+    #[test]
+    fn test_lint_shadowed_block() {
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .allow_cidr("10.0.0.0/8")
+            .block_cidr("10.1.0.0/16")
+            .allow_host("*.example.com")
+            .block_host("db.example.com:5432")
+            .build();
+        let warnings = policy.lint();
+
+        assert!(warnings.contains(&LintWarning::ShadowedBlock {
+            rule: "10.1.0.0/16".into(),
+            by: "10.0.0.0/8".into(),
+        }));
+        assert!(warnings.contains(&LintWarning::ShadowedBlock {
+            rule: "db.example.com:5432".into(),
+            by: "*.example.com".into(),
+        }));
+    }
+
+    #[test]
+    fn test_lint_expired_allow() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.corp.example")
+            .allow_host_until("jira.corp.example", SystemTime::UNIX_EPOCH)
+            .build();
+        assert_eq!(
+            policy.lint(),
+            [LintWarning::ExpiredAllow {
+                rule: "jira.corp.example".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_host_pattern_covers() {
+        let p = HostPattern::parse;
+        assert!(p("*.example.com").covers(&p("a.example.com")));
+        assert!(p("*.example.com").covers(&p("*.a.example.com")));
+        assert!(p("*.example.com").covers(&p("example.com:443")));
+        assert!(!p("a.example.com").covers(&p("*.example.com")));
+        assert!(!p("*.example.com:443").covers(&p("a.example.com")));
+        assert!(p("a.example.com:443").overlaps(&p("*.example.com")));
+        assert!(!p("a.example.com:443").overlaps(&p("a.example.com:80")));
+    }
+
+    // ==================== Rule match tests ====================
+
+    #[test]
+    fn test_check_ip_reports_rule() {
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("10.0.0.0/8")
+            .allow_cidr("10.1.0.0/16")
+            .build();
+
+        assert_eq!(
+            policy.check_ip("10.1.2.3".parse().unwrap()),
+            RuleMatch::AllowCidr("10.1.0.0/16".to_string())
+        );
+        assert_eq!(
+            policy.check_ip("10.2.0.1".parse().unwrap()),
+            RuleMatch::BlockCidr("10.0.0.0/8".to_string())
+        );
+        assert!(matches!(
+            policy.check_ip("127.0.0.1".parse().unwrap()),
+            RuleMatch::BasePolicy(_)
+        ));
+        assert_eq!(
+            policy.check_ip("8.8.8.8".parse().unwrap()),
+            RuleMatch::NoMatch
+        );
+    }
+
+    #[test]
+    fn test_check_host_reports_rule() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.example.com")
+            .allow_host("api.example.com:8443")
+            .build();
+
+        assert_eq!(
+            policy.check_host("API.example.com", Some(8443)),
+            RuleMatch::AllowHost("api.example.com:8443".to_string())
+        );
+        assert_eq!(
+            policy.check_host("api.example.com", None),
+            RuleMatch::BlockHost("*.example.com".to_string())
+        );
+        assert_eq!(
+            policy.check_host("other.org", Some(443)),
+            RuleMatch::NoMatch
+        );
+    }
+
+    // ==================== Expiring allow tests ====================
+
+    #[test]
+    fn test_allow_host_until_expires() {
+        let deadline = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000);
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.corp.example")
+            .allow_host_until("jira.corp.example:443", deadline)
+            .build();
+
+        let before = deadline - std::time::Duration::from_secs(1);
+        assert_eq!(
+            policy.check_host_at("jira.corp.example", Some(443), before),
+            RuleMatch::AllowHost("jira.corp.example:443".to_string())
+        );
+        // From the deadline on, the block rule applies again
+        assert_eq!(
+            policy.check_host_at("jira.corp.example", Some(443), deadline),
+            RuleMatch::BlockHost("*.corp.example".to_string())
+        );
+    }
+
+    #[test]
+    fn test_expired_allow_uses_current_time() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.corp.example")
+            .allow_host_until("jira.corp.example", SystemTime::UNIX_EPOCH)
+            .build();
+        assert!(policy.is_hostname_allowed("jira.corp.example").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_allow_host_until_expires_by_policy_clock() {
+        use crate::validate::{validate_custom_with_options, ValidateOptions};
+        use crate::{ManualClock, ValidationCache};
+        use std::time::Duration;
+
+        let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+        let deadline = SystemTime::UNIX_EPOCH + Duration::from_secs(60);
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.corp.example")
+            .allow_host_until("jira.corp.example", deadline)
+            .with_clock(clock.clone())
+            .build();
+        // Pin the hostname so validation needs no DNS
+        let cache = Arc::new(ValidationCache::new(Duration::from_secs(3600)));
+        cache
+            .insert(
+                policy.fingerprint(),
+                "jira.corp.example",
+                "93.184.216.34".parse().unwrap(),
+            )
+            .await;
+        let options = ValidateOptions {
+            cache: Some(cache),
+            ..Default::default()
+        };
+
+        let url = "https://jira.corp.example/";
+        assert!(validate_custom_with_options(url, &policy, options.clone())
+            .await
+            .is_ok());
+        assert_eq!(policy.exact_allowed_hosts().len(), 1);
+
+        clock.advance(Duration::from_secs(60));
+        let err = validate_custom_with_options(url, &policy, options)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, crate::Error::HostnameBlocked { .. }),
+            "{:?}",
+            err
+        );
+        assert!(policy.exact_allowed_hosts().is_empty());
+        assert!(policy
+            .lint()
+            .iter()
+            .any(|w| matches!(w, LintWarning::ExpiredAllow { .. })));
+    }
+
+    #[test]
+    fn test_fingerprint_includes_deadline() {
+        let at = |secs| SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+        let policy = |until| {
+            PolicyBuilder::new(Policy::PublicOnly)
+                .allow_host_until("a.example", until)
+                .build()
+                .fingerprint()
+        };
+        assert_eq!(policy(at(10)), policy(at(10)));
+        assert_ne!(policy(at(10)), policy(at(20)));
+        assert_ne!(
+            policy(at(10)),
+            PolicyBuilder::new(Policy::PublicOnly)
+                .allow_host("a.example")
+                .build()
+                .fingerprint()
+        );
+    }
+
+    #[test]
+    fn test_v4_cidr_matches_mapped_v6() {
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("10.0.0.0/8")
+            .build();
+
+        assert!(policy
+            .is_ip_allowed("::ffff:10.1.2.3".parse().unwrap())
+            .is_err());
+        assert!(policy
+            .is_ip_allowed("::ffff:192.168.1.1".parse().unwrap())
+            .is_ok());
+        // The IPv4-compatible form embeds the same address
+        assert!(policy.is_ip_allowed("::a01:203".parse().unwrap()).is_err());
+        // Plain IPv6 is not confused with embedded addresses
+        assert!(policy
+            .is_ip_allowed("2001:db8::a01:203".parse().unwrap())
+            .is_ok());
+        assert!(policy.is_ip_allowed("::1".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_mapped_v6_cidr_matches_v4() {
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("::ffff:10.0.0.0/104")
+            .build();
+
+        assert_eq!(
+            policy.check_ip("10.1.2.3".parse().unwrap()),
+            RuleMatch::BlockCidr("::ffff:10.0.0.0/104".to_string())
+        );
+        assert!(policy.is_ip_allowed("11.0.0.1".parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_allow_cidr_matches_mapped_v6() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_cidr("10.1.0.0/16")
+            .build();
+
+        assert!(policy
+            .is_ip_allowed("::ffff:10.1.0.5".parse().unwrap())
+            .is_ok());
+        assert!(policy
+            .is_ip_allowed("::ffff:10.2.0.5".parse().unwrap())
+            .is_err());
+        assert!(policy.is_ip_allowed("::10.1.0.5".parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_rule_stats_count_first_match() {
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("10.0.0.0/8")
+            .block_cidr("10.0.0.0/8")
+            .allow_cidr("10.1.0.0/16")
+            .block_host("*.corp.example")
+            .allow_host("wiki.corp.example")
+            .build();
+        let shared = policy.clone();
+        for ip in ["10.1.0.1", "10.2.0.1", "10.3.0.1", "192.168.0.1"] {
+            let _ = shared.check_ip(ip.parse().unwrap());
+        }
+        for host in ["wiki.corp.example", "db.corp.example", "example.com"] {
+            let _ = policy.is_host_allowed(host, 443);
+        }
+
+        let stats: Vec<(String, u64)> = policy
+            .rule_stats()
+            .into_iter()
+            .map(|stat| (stat.rule.to_string(), stat.hits))
+            .collect();
+        assert_eq!(
+            stats,
+            [
+                (
+                    "blocked by custom policy CIDR rule: 10.0.0.0/8".to_string(),
+                    2
+                ),
+                (
+                    "allowed by custom policy CIDR rule: 10.1.0.0/16".to_string(),
+                    1
+                ),
+                (
+                    "blocked by custom policy hostname rule: *.corp.example".to_string(),
+                    1
+                ),
+                (
+                    "allowed by custom policy hostname rule: wiki.corp.example".to_string(),
+                    1
+                ),
+            ]
+        );
+        assert!(PolicyBuilder::new(Policy::PublicOnly)
+            .build()
+            .rule_stats()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_rule_match_allowed_and_display() {
+        assert!(RuleMatch::NoMatch.is_allowed());
+        assert!(RuleMatch::AllowCidr("10.0.0.0/8".into()).is_allowed());
+        assert!(!RuleMatch::BlockHost("a.com".into()).is_allowed());
+        assert!(!RuleMatch::BasePolicy("loopback").is_allowed());
+        assert_eq!(
+            RuleMatch::BlockCidr("10.0.0.0/8".into()).to_string(),
+            "blocked by custom policy CIDR rule: 10.0.0.0/8"
+        );
+        assert_eq!(RuleMatch::BasePolicy("loopback").to_string(), "loopback");
+    }
+
+    // ==================== Fingerprint tests ====================
+
+    #[test]
+    fn test_fingerprint_ignores_rule_order() {
+        let a = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_cidr("10.1.0.0/16")
+            .block_host("a.com")
+            .block_host("b.com")
+            .build();
+        let b = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("b.com")
+            .block_host("a.com")
+            .allow_cidr("10.1.0.0/16")
+            .build();
+        assert_eq!(a.fingerprint(), b.fingerprint());
+    }
+
+    #[test]
+    fn test_fingerprint_distinguishes_rules() {
+        let base = PolicyBuilder::new(Policy::AllowPrivate).build();
+        let stricter = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("10.0.0.0/8")
+            .build();
+        let other_base = PolicyBuilder::new(Policy::PublicOnly).build();
+        let allow_instead = PolicyBuilder::new(Policy::AllowPrivate)
+            .allow_cidr("10.0.0.0/8")
+            .build();
+        let port_scoped = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_host("a.com:8443")
+            .build();
+        let unscoped = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_host("a.com")
+            .build();
+
+        assert_ne!(base.fingerprint(), stricter.fingerprint());
+        assert_ne!(base.fingerprint(), other_base.fingerprint());
+        assert_ne!(stricter.fingerprint(), allow_instead.fingerprint());
+        assert_ne!(port_scoped.fingerprint(), unscoped.fingerprint());
+        assert_ne!(base.fingerprint(), Policy::AllowPrivate.fingerprint());
+    }
+
+    #[test]
+    fn test_builtin_fingerprints_differ() {
+        assert_ne!(
+            Policy::PublicOnly.fingerprint(),
+            Policy::AllowPrivate.fingerprint()
+        );
+    }
+
+    // ==================== CIDR blocking tests ====================
+
+    #[test]
+    fn test_block_cidr() {
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("10.0.0.0/8")
+            .build();
+
+        assert!(policy.is_ip_allowed("10.1.2.3".parse().unwrap()).is_err());
+        assert!(policy.is_ip_allowed("192.168.1.1".parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_block_multiple_cidrs() {
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("10.0.0.0/8")
+            .block_cidr("172.16.0.0/12")
+            .build();
+
+        assert!(policy.is_ip_allowed("10.1.2.3".parse().unwrap()).is_err());
+        assert!(policy.is_ip_allowed("172.20.1.1".parse().unwrap()).is_err());
+        assert!(policy.is_ip_allowed("192.168.1.1".parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_block_single_ip_cidr() {
         let policy = PolicyBuilder::new(Policy::AllowPrivate)
             .block_cidr("192.168.1.100/32")
             .build();
 
-        assert!(policy
-            .is_ip_allowed("192.168.1.100".parse().unwrap())
+        assert!(policy
+            .is_ip_allowed("192.168.1.100".parse().unwrap())
+            .is_err());
+        assert!(policy
+            .is_ip_allowed("192.168.1.101".parse().unwrap())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_block_ipv6_cidr() {
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("2001:db8::/32")
+            .build();
+
+        assert!(policy
+            .is_ip_allowed("2001:db8::1".parse().unwrap())
+            .is_err());
+        assert!(policy.is_ip_allowed("2001:db9::1".parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_block_invalid_cidr_ignored() {
+        // Invalid CIDR should be silently ignored (builder pattern)
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("not-a-cidr")
+            .block_cidr("10.0.0.0/8")
+            .build();
+
+        // 10.x should still be blocked
+        assert!(policy.is_ip_allowed("10.1.2.3".parse().unwrap()).is_err());
+    }
+
+    // ==================== CIDR allowing tests ====================
+
+    #[test]
+    fn test_allow_cidr_overrides() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_cidr("192.168.1.0/24")
+            .build();
+
+        // This private IP is explicitly allowed
+        assert!(policy
+            .is_ip_allowed("192.168.1.50".parse().unwrap())
+            .is_ok());
+        // Other private IPs still blocked by base policy
+        assert!(policy
+            .is_ip_allowed("192.168.2.1".parse().unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_allow_loopback_override() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_cidr("127.0.0.0/8")
+            .build();
+
+        // Loopback is normally always blocked, but we explicitly allowed it
+        assert!(policy.is_ip_allowed("127.0.0.1".parse().unwrap()).is_ok());
+        assert!(policy.is_ip_allowed("127.1.2.3".parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_allow_takes_precedence_over_block() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_cidr("10.0.0.0/8")
+            .allow_cidr("10.1.0.0/16")
+            .build();
+
+        // 10.1.x.x is allowed despite 10.0.0.0/8 being blocked
+        assert!(policy.is_ip_allowed("10.1.2.3".parse().unwrap()).is_ok());
+        // Other 10.x.x.x still blocked
+        assert!(policy.is_ip_allowed("10.2.0.1".parse().unwrap()).is_err());
+    }
+
+    // ==================== Hostname blocking tests ====================
+
+    #[test]
+    fn test_block_host_pattern() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.internal.example.com")
+            .build();
+
+        assert!(policy
+            .is_hostname_allowed("api.internal.example.com")
+            .is_err());
+        assert!(policy.is_hostname_allowed("api.example.com").is_ok());
+    }
+
+    #[test]
+    fn test_block_host_exact() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("blocked.example.com")
+            .build();
+
+        assert!(policy.is_hostname_allowed("blocked.example.com").is_err());
+        assert!(policy.is_hostname_allowed("other.example.com").is_ok());
+        // Subdomain should NOT match exact hostname block
+        assert!(policy
+            .is_hostname_allowed("sub.blocked.example.com")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_block_host_wildcard_matches_exact() {
+        // *.example.com should also match example.com itself
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.example.com")
+            .build();
+
+        assert!(policy.is_hostname_allowed("sub.example.com").is_err());
+        assert!(policy.is_hostname_allowed("example.com").is_err());
+    }
+
+    #[test]
+    fn test_block_host_case_insensitive() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("blocked.example.com")
+            .build();
+
+        assert!(policy.is_hostname_allowed("BLOCKED.EXAMPLE.COM").is_err());
+        assert!(policy.is_hostname_allowed("Blocked.Example.Com").is_err());
+    }
+
+    #[test]
+    fn test_block_multiple_hosts() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.internal.com")
+            .block_host("*.private.org")
+            .build();
+
+        assert!(policy.is_hostname_allowed("api.internal.com").is_err());
+        assert!(policy.is_hostname_allowed("db.private.org").is_err());
+        assert!(policy.is_hostname_allowed("public.example.com").is_ok());
+    }
+
+    // ==================== Hostname allowing tests ====================
+
+    #[test]
+    fn test_allow_host_pattern() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_host("trusted.internal")
+            .build();
+
+        assert!(policy.is_hostname_allowed("trusted.internal").is_ok());
+    }
+
+    #[test]
+    fn test_allow_host_takes_precedence_over_block() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.example.com")
+            .allow_host("trusted.example.com")
+            .build();
+
+        // trusted.example.com is allowed despite *.example.com being blocked
+        assert!(policy.is_hostname_allowed("trusted.example.com").is_ok());
+        // Other subdomains still blocked
+        assert!(policy.is_hostname_allowed("blocked.example.com").is_err());
+    }
+
+    #[test]
+    fn test_allow_host_wildcard() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.example.com")
+            .allow_host("*.trusted.example.com")
+            .build();
+
+        assert!(policy
+            .is_hostname_allowed("api.trusted.example.com")
+            .is_ok());
+        assert!(policy.is_hostname_allowed("other.example.com").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_block_all_allows_only_listed_targets() {
+        let policy = PolicyBuilder::new(Policy::BlockAll)
+            .allow_host("api.stripe.com")
+            .allow_host("*.githubusercontent.com")
+            .allow_cidr("93.184.216.0/24")
+            .build();
+
+        assert!(policy.is_hostname_allowed("API.Stripe.com").is_ok());
+        assert!(policy
+            .is_hostname_allowed("raw.githubusercontent.com")
+            .is_ok());
+        assert_eq!(
+            policy.is_hostname_allowed("example.com"),
+            Err(BlockReason::HostNotAllowed("example.com".to_string()))
+        );
+        // Resolved addresses keep the PublicOnly checks
+        assert!(policy.is_ip_allowed("10.0.0.1".parse().unwrap()).is_err());
+        assert!(policy.is_ip_allowed("1.1.1.1".parse().unwrap()).is_ok());
+
+        // IP literals must be inside an allowed CIDR
+        assert!(crate::validate_custom("https://93.184.216.34/", &policy)
+            .await
+            .is_ok());
+        let err = crate::validate_custom("https://1.1.1.1/", &policy)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("host 1.1.1.1 not in custom policy allowlist"),
+            "{}",
+            err
+        );
+        assert!(crate::validate_custom("http://[2606:4700::1111]/", &policy)
+            .await
+            .is_err());
+        // Metadata hostnames stay blocked even when allowed
+        let metadata = PolicyBuilder::new(Policy::BlockAll)
+            .allow_host("metadata.google.internal")
+            .build();
+        assert!(
+            crate::validate_custom("http://metadata.google.internal/", &metadata)
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_block_all_fingerprint() {
+        let block_all = PolicyBuilder::new(Policy::BlockAll)
+            .allow_host("api.example.com")
+            .build();
+        let public = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_host("api.example.com")
+            .build();
+        assert_ne!(block_all.fingerprint(), public.fingerprint());
+        let flags = BasePolicy::builder().allowlist_only(true).build();
+        assert_eq!(flags.as_policy(), Some(Policy::BlockAll));
+        assert_eq!(
+            PolicyBuilder::new(flags)
+                .allow_host("api.example.com")
+                .build()
+                .fingerprint(),
+            block_all.fingerprint()
+        );
+    }
+
+    // ==================== Composition tests ====================
+
+    #[test]
+    fn test_merge_combines_rules() {
+        let baseline = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("10.0.0.0/8")
+            .block_host("*.corp.example")
+            .allow_schemes(["https"])
+            .block_path_glob("*/admin/*")
+            .build();
+        let service = PolicyBuilder::new(Policy::AllowPrivate)
+            .allow_host("wiki.corp.example")
+            .block_cidr("10.0.0.0/8")
+            .block_ports([25])
+            .build();
+
+        let merged = baseline.merge(&service);
+        // An allow rule from either side wins over a block from the other
+        assert!(merged.is_hostname_allowed("wiki.corp.example").is_ok());
+        assert!(merged.is_hostname_allowed("jira.corp.example").is_err());
+        assert!(merged.is_host_allowed("mail.example", 25).is_err());
+        assert!(merged.is_scheme_allowed("http").is_err());
+        assert!(merged.is_path_allowed("/v1/admin/users").is_err());
+        assert!(merged.is_ip_allowed("10.1.1.1".parse().unwrap()).is_err());
+        // Duplicate rules are kept once
+        assert_eq!(merged.to_config().unwrap().block_cidrs.len(), 1);
+        let same = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("10.0.0.0/8")
+            .block_host("*.corp.example")
+            .allow_schemes(["https"])
+            .block_path_glob("*/admin/*")
+            .allow_host("wiki.corp.example")
+            .block_ports([25])
+            .build();
+        assert_eq!(merged.fingerprint(), same.fingerprint());
+    }
+
+    #[test]
+    fn test_merge_takes_notes_from_other() {
+        let baseline = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr_with_note("10.0.0.0/8", "baseline")
+            .build();
+        let service = PolicyBuilder::new(Policy::PublicOnly)
+            .block_cidr_with_note("10.0.0.0/8", "service")
+            .build();
+
+        let merged = baseline.merge(&service);
+        assert!(merged
+            .is_ip_allowed("192.168.1.1".parse().unwrap())
             .is_err());
-        assert!(policy
-            .is_ip_allowed("192.168.1.101".parse().unwrap())
+        let err = merged
+            .is_ip_allowed("10.1.1.1".parse().unwrap())
+            .unwrap_err();
+        assert_eq!(err.note(), Some("service"));
+        assert!(baseline
+            .merge(&baseline)
+            .is_ip_allowed("192.168.1.1".parse().unwrap())
             .is_ok());
     }
 
     #[test]
-    fn test_block_ipv6_cidr() {
-        let policy = PolicyBuilder::new(Policy::AllowPrivate)
-            .block_cidr("2001:db8::/32")
+    fn test_merge_cannot_loosen_restrictions() {
+        let baseline = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_schemes(["https"])
+            .allow_ports([443, 8443])
+            .allow_url_prefix("https://api.partner.example/")
+            .build();
+        let service = PolicyBuilder::new(Policy::AllowPrivate)
+            .allow_schemes(["http", "https"])
+            .allow_ports([80, 443])
+            .allow_url_prefix("https://api.partner.example/v1/")
+            .allow_url_prefix("https://other.example/")
             .build();
 
-        assert!(policy
-            .is_ip_allowed("2001:db8::1".parse().unwrap())
+        let merged = baseline.merge(&service);
+        assert!(merged.is_scheme_allowed("https").is_ok());
+        assert!(merged.is_scheme_allowed("http").is_err());
+        assert!(merged.is_host_allowed("api.partner.example", 443).is_ok());
+        assert!(merged.is_host_allowed("api.partner.example", 80).is_err());
+        assert!(merged.is_host_allowed("api.partner.example", 8443).is_err());
+        let url = |s| SafeUrl::parse(s).unwrap();
+        assert!(merged
+            .is_url_allowed(&url("https://api.partner.example/v1/orders"))
+            .is_ok());
+        assert!(merged
+            .is_url_allowed(&url("https://api.partner.example/admin"))
             .is_err());
-        assert!(policy.is_ip_allowed("2001:db9::1".parse().unwrap()).is_ok());
+        assert!(merged
+            .is_url_allowed(&url("https://other.example/"))
+            .is_err());
+        // The PublicOnly baseline is not replaced by the service's base
+        assert!(merged.is_ip_allowed("10.0.0.1".parse().unwrap()).is_err());
+        assert!(service
+            .merge(&baseline)
+            .is_ip_allowed("10.0.0.1".parse().unwrap())
+            .is_err());
+
+        // Disjoint allowlists leave nothing allowed
+        let http_only = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_schemes(["http"])
+            .allow_ports([80])
+            .build();
+        let none = baseline.merge(&http_only);
+        assert!(none.is_scheme_allowed("http").is_err());
+        assert!(none.is_scheme_allowed("https").is_err());
+        assert!(none.is_host_allowed("example.com", 80).is_err());
+        assert!(none.to_config().is_err());
     }
 
     #[test]
-    fn test_block_invalid_cidr_ignored() {
-        // Invalid CIDR should be silently ignored (builder pattern)
-        let policy = PolicyBuilder::new(Policy::AllowPrivate)
-            .block_cidr("not-a-cidr")
+    fn test_merge_starts_fresh_counters() {
+        let baseline = PolicyBuilder::new(Policy::AllowPrivate)
             .block_cidr("10.0.0.0/8")
             .build();
-
-        // 10.x should still be blocked
-        assert!(policy.is_ip_allowed("10.1.2.3".parse().unwrap()).is_err());
+        let _ = baseline.is_ip_allowed("10.1.1.1".parse().unwrap());
+        let merged = baseline.merge(&PolicyBuilder::new(Policy::AllowPrivate).build());
+        let _ = merged.is_ip_allowed("10.1.1.1".parse().unwrap());
+        assert_eq!(baseline.rule_stats()[0].hits, 1);
+        assert_eq!(merged.rule_stats()[0].hits, 1);
     }
 
-    // ==================== CIDR allowing tests ====================
-
     #[test]
-    fn test_allow_cidr_overrides() {
-        let policy = PolicyBuilder::new(Policy::PublicOnly)
-            .allow_cidr("192.168.1.0/24")
+    fn test_intersect_requires_both() {
+        let baseline = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_host_with_note("*.corp.example", "use the proxy")
+            .allow_schemes(["https"])
+            .build();
+        let service = PolicyBuilder::new(Policy::AllowPrivate)
+            .allow_host("wiki.corp.example")
+            .block_cidr("192.168.0.0/16")
+            .allow_url_prefix("https://api.example.com/v1/")
+            .block_path_glob("*/internal/*")
             .build();
 
-        // This private IP is explicitly allowed
+        let policy = baseline.intersect(&service);
+        let err = policy.is_hostname_allowed("wiki.corp.example").unwrap_err();
+        assert_eq!(err.note(), Some("use the proxy"));
+        assert_eq!(
+            policy.check_ip("192.168.1.1".parse().unwrap()),
+            RuleMatch::BlockCidr("192.168.0.0/16".to_string())
+        );
+        assert!(policy.is_ip_allowed("10.1.1.1".parse().unwrap()).is_ok());
+        assert!(policy.is_scheme_allowed("http").is_err());
+        assert!(policy.is_scheme_allowed("https").is_ok());
+        let url = |s| SafeUrl::parse(s).unwrap();
         assert!(policy
-            .is_ip_allowed("192.168.1.50".parse().unwrap())
+            .is_url_allowed(&url("https://api.example.com/v1/orders"))
             .is_ok());
-        // Other private IPs still blocked by base policy
         assert!(policy
-            .is_ip_allowed("192.168.2.1".parse().unwrap())
+            .is_url_allowed(&url("https://other.example.com/"))
             .is_err());
+        assert!(policy.is_path_allowed("/v1/internal/x").is_err());
+        assert!(policy.is_path_allowed("/v1/orders").is_ok());
+
+        // Hits are counted in the policy whose rule decided
+        let stats = policy.rule_stats();
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats[0].hits, 1);
+        assert_eq!(stats[1].rule, RuleMatch::BlockCidr("192.168.0.0/16".into()));
+        assert_eq!(stats[1].hits, 1);
+        assert_eq!(service.rule_stats()[0].hits, 0);
     }
 
     #[test]
-    fn test_allow_loopback_override() {
-        let policy = PolicyBuilder::new(Policy::PublicOnly)
-            .allow_cidr("127.0.0.0/8")
+    fn test_intersect_fingerprint_and_config() {
+        let a = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("a.example")
             .build();
-
-        // Loopback is normally always blocked, but we explicitly allowed it
-        assert!(policy.is_ip_allowed("127.0.0.1".parse().unwrap()).is_ok());
-        assert!(policy.is_ip_allowed("127.1.2.3".parse().unwrap()).is_ok());
+        let b = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("b.example")
+            .build();
+        let both = a.intersect(&b);
+        assert_ne!(both.fingerprint(), a.fingerprint());
+        assert_ne!(both.fingerprint(), a.merge(&b).fingerprint());
+        assert_eq!(both.fingerprint(), a.intersect(&b).fingerprint());
+        assert!(both.to_config().is_err());
+        // Merging keeps the intersection
+        let merged = PolicyBuilder::new(Policy::PublicOnly).build().merge(&both);
+        assert!(merged.is_hostname_allowed("a.example").is_err());
+        assert!(merged.is_hostname_allowed("b.example").is_err());
     }
 
+    // ==================== Port-scoped hostname tests ====================
+
     #[test]
-    fn test_allow_takes_precedence_over_block() {
+    fn test_block_host_with_port() {
         let policy = PolicyBuilder::new(Policy::PublicOnly)
-            .block_cidr("10.0.0.0/8")
-            .allow_cidr("10.1.0.0/16")
+            .block_host("*.example.com:6379")
             .build();
 
-        // 10.1.x.x is allowed despite 10.0.0.0/8 being blocked
-        assert!(policy.is_ip_allowed("10.1.2.3".parse().unwrap()).is_ok());
-        // Other 10.x.x.x still blocked
-        assert!(policy.is_ip_allowed("10.2.0.1".parse().unwrap()).is_err());
+        assert!(policy.is_host_allowed("cache.example.com", 6379).is_err());
+        assert!(policy.is_host_allowed("cache.example.com", 443).is_ok());
+        // Without a port, port-scoped rules do not apply
+        assert!(policy.is_hostname_allowed("cache.example.com").is_ok());
     }
 
-    // ==================== Hostname blocking tests ====================
-
     #[test]
-    fn test_block_host_pattern() {
+    fn test_allow_host_with_port() {
         let policy = PolicyBuilder::new(Policy::PublicOnly)
-            .block_host("*.internal.example.com")
+            .block_host("*.example.com")
+            .allow_host("api.example.com:8443")
             .build();
 
-        assert!(policy
-            .is_hostname_allowed("api.internal.example.com")
-            .is_err());
-        assert!(policy.is_hostname_allowed("api.example.com").is_ok());
+        assert!(policy.is_host_allowed("api.example.com", 8443).is_ok());
+        assert!(policy.is_host_allowed("api.example.com", 443).is_err());
+        assert!(policy.is_hostname_allowed("api.example.com").is_err());
     }
 
     #[test]
-    fn test_block_host_exact() {
+    fn test_host_without_port_matches_any_port() {
         let policy = PolicyBuilder::new(Policy::PublicOnly)
             .block_host("blocked.example.com")
             .build();
 
-        assert!(policy.is_hostname_allowed("blocked.example.com").is_err());
-        assert!(policy.is_hostname_allowed("other.example.com").is_ok());
-        // Subdomain should NOT match exact hostname block
-        assert!(policy
-            .is_hostname_allowed("sub.blocked.example.com")
-            .is_ok());
+        assert!(policy.is_host_allowed("blocked.example.com", 80).is_err());
+        assert!(policy.is_host_allowed("blocked.example.com", 8443).is_err());
     }
 
     #[test]
-    fn test_block_host_wildcard_matches_exact() {
-        // *.example.com should also match example.com itself
+    fn test_host_pattern_parse() {
+        assert_eq!(
+            HostPattern::parse("*.Example.com:8443"),
+            HostPattern {
+                host: "*.example.com".into(),
+                port: Some(8443),
+                until: None,
+            }
+        );
+        assert_eq!(HostPattern::parse("example.com").port, None);
+        // Invalid port stays part of the host
+        assert_eq!(HostPattern::parse("example.com:99999").port, None);
+        // Bracketed IPv6 with port, bare IPv6 without
+        assert_eq!(HostPattern::parse("[::1]:8080").port, Some(8080));
+        assert_eq!(HostPattern::parse("fe80::1").port, None);
+    }
+
+    #[test]
+    fn test_error_message_contains_port_rule() {
         let policy = PolicyBuilder::new(Policy::PublicOnly)
-            .block_host("*.example.com")
+            .block_host("*.internal.com:6379")
             .build();
 
-        assert!(policy.is_hostname_allowed("sub.example.com").is_err());
-        assert!(policy.is_hostname_allowed("example.com").is_err());
+        let err = policy
+            .is_host_allowed("cache.internal.com", 6379)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("*.internal.com:6379"), "{}", err);
     }
 
     #[test]
-    fn test_block_host_case_insensitive() {
+    fn test_port_rules_precede_host_rules() {
         let policy = PolicyBuilder::new(Policy::PublicOnly)
-            .block_host("blocked.example.com")
+            .allow_ports([443, 8443])
+            .block_ports([8443])
+            .allow_host("api.example.com")
             .build();
 
-        assert!(policy.is_hostname_allowed("BLOCKED.EXAMPLE.COM").is_err());
-        assert!(policy.is_hostname_allowed("Blocked.Example.Com").is_err());
+        assert_eq!(
+            policy.check_host("api.example.com", Some(443)),
+            RuleMatch::AllowHost("api.example.com".into())
+        );
+        assert_eq!(
+            policy.check_host("api.example.com", Some(8443)),
+            RuleMatch::BlockPort(8443)
+        );
+        assert_eq!(
+            policy.check_host("api.example.com", Some(25)),
+            RuleMatch::PortNotAllowed(25)
+        );
+        // Unknown port: only host rules apply
+        assert!(policy.is_hostname_allowed("api.example.com").is_ok());
+        let reason = policy.is_host_allowed("x.example.com", 25).unwrap_err();
+        assert_eq!(reason, BlockReason::PortNotAllowed(25));
+        assert_eq!(
+            reason.to_string(),
+            "port 25 not in custom policy port allowlist"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_port_rules_enforced_by_validate() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_ports([6379])
+            .build();
+        assert!(crate::validate("http://93.184.216.34:6380/", &policy)
+            .await
+            .is_ok());
+        let err = crate::validate("http://93.184.216.34:6379/", &policy)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, crate::Error::HostnameBlocked { .. }),
+            "{:?}",
+            err
+        );
+
+        let https_only = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_ports([443])
+            .build();
+        assert!(crate::validate("https://93.184.216.34/", &https_only)
+            .await
+            .is_ok());
+        assert!(crate::validate("http://93.184.216.34/", &https_only)
+            .await
+            .is_err());
     }
 
     #[test]
-    fn test_block_multiple_hosts() {
+    fn test_port_rules_change_fingerprint() {
+        let plain = PolicyBuilder::new(Policy::PublicOnly).build();
+        let allow = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_ports([443])
+            .build();
+        let block = PolicyBuilder::new(Policy::PublicOnly)
+            .block_ports([443])
+            .build();
+        assert_ne!(plain.fingerprint(), allow.fingerprint());
+        assert_ne!(allow.fingerprint(), block.fingerprint());
+    }
+
+    // ==================== Scheme allowlist tests ====================
+
+    #[tokio::test]
+    async fn test_scheme_allowlist_enforced_by_validate() {
         let policy = PolicyBuilder::new(Policy::PublicOnly)
-            .block_host("*.internal.com")
-            .block_host("*.private.org")
+            .allow_schemes(["HTTPS"])
             .build();
+        assert!(crate::validate("https://93.184.216.34/", &policy)
+            .await
+            .is_ok());
+        let err = crate::validate("http://93.184.216.34/", &policy)
+            .await
+            .unwrap_err();
+        match err {
+            crate::Error::SchemeBlocked { ref scheme, .. } => assert_eq!(scheme, "http"),
+            ref other => panic!("expected SchemeBlocked, got {:?}", other),
+        }
+        assert!(err.is_blocked());
+        assert_eq!(err.kind(), crate::ErrorKind::SchemeBlocked);
+    }
 
-        assert!(policy.is_hostname_allowed("api.internal.com").is_err());
-        assert!(policy.is_hostname_allowed("db.private.org").is_err());
-        assert!(policy.is_hostname_allowed("public.example.com").is_ok());
+    #[test]
+    fn test_scheme_allowlist_defaults_and_fingerprint() {
+        let plain = PolicyBuilder::new(Policy::PublicOnly).build();
+        assert!(plain.is_scheme_allowed("http").is_ok());
+        assert!(plain.is_scheme_allowed("https").is_ok());
+
+        let https_only = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_schemes(["https"])
+            .build();
+        let reason = https_only.is_scheme_allowed("HTTP").unwrap_err();
+        assert_eq!(
+            reason,
+            BlockReason::SchemeNotAllowed {
+                scheme: "http".into(),
+                allowed: vec!["https".into()],
+            }
+        );
+        assert!(
+            reason.to_string().contains("scheme allowlist"),
+            "{}",
+            reason
+        );
+        assert_ne!(plain.fingerprint(), https_only.fingerprint());
     }
 
-    // ==================== Hostname allowing tests ====================
+    // ==================== URL prefix allowlist tests ====================
 
     #[test]
-    fn test_allow_host_pattern() {
+    fn test_url_prefix_matches_at_path_boundary() {
         let policy = PolicyBuilder::new(Policy::PublicOnly)
-            .allow_host("trusted.internal")
+            .allow_url_prefix("https://API.partner.example:443/v1")
+            .allow_url_prefix("not a url")
             .build();
-
-        assert!(policy.is_hostname_allowed("trusted.internal").is_ok());
+        for (url, allowed) in [
+            ("https://api.partner.example/v1", true),
+            ("https://api.partner.example/v1/orders?id=1", true),
+            ("https://api.partner.example/v1?x", true),
+            ("https://api.partner.example./v1/", true),
+            ("https://api.partner.example/v10", false),
+            ("https://api.partner.example/v1/../admin", false),
+            ("https://api.partner.example/v1/%2e%2e/admin", false),
+            ("http://api.partner.example/v1", false),
+            ("https://api.partner.example.evil.example/v1", false),
+        ] {
+            let result = policy.is_url_allowed(&SafeUrl::parse(url).unwrap());
+            assert_eq!(result.is_ok(), allowed, "{}: {:?}", url, result);
+        }
     }
 
     #[test]
-    fn test_allow_host_takes_precedence_over_block() {
+    fn test_invalid_url_prefix_fails_closed() {
+        let builder =
+            PolicyBuilder::new(Policy::PublicOnly).allow_url_prefix("htps://api.partner.example/");
+        assert!(matches!(
+            builder.clone().try_build().unwrap_err()[..],
+            [PolicyError::InvalidUrlPrefix { ref prefix, .. }] if prefix == "htps://api.partner.example/"
+        ));
+        let policy = builder.build();
+        for url in ["https://api.partner.example/", "https://example.com/"] {
+            assert!(policy
+                .is_url_allowed(&SafeUrl::parse(url).unwrap())
+                .is_err());
+        }
+        assert_ne!(
+            policy.fingerprint(),
+            PolicyBuilder::new(Policy::PublicOnly).build().fingerprint()
+        );
+        assert!(policy.to_config().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_url_prefix_enforced_by_validate() {
         let policy = PolicyBuilder::new(Policy::PublicOnly)
-            .block_host("*.example.com")
-            .allow_host("trusted.example.com")
+            .allow_url_prefix("http://93.184.216.34/v1/")
             .build();
+        assert!(crate::validate("http://93.184.216.34/v1/items", &policy)
+            .await
+            .is_ok());
+        let err = crate::validate("http://93.184.216.34/admin", &policy)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, crate::Error::HostnameBlocked { ref reason, .. } if reason.contains("URL prefix")),
+            "{:?}",
+            err
+        );
+        assert_ne!(
+            policy.fingerprint(),
+            PolicyBuilder::new(Policy::PublicOnly).build().fingerprint()
+        );
+    }
 
-        // trusted.example.com is allowed despite *.example.com being blocked
-        assert!(policy.is_hostname_allowed("trusted.example.com").is_ok());
-        // Other subdomains still blocked
-        assert!(policy.is_hostname_allowed("blocked.example.com").is_err());
+    // ==================== Invalid rule tests ====================
+
+    #[test]
+    fn test_try_methods_reject_invalid_rules() {
+        let builder = PolicyBuilder::new(Policy::AllowPrivate);
+        for cidr in ["10.0.0/8", "10.0.0.0/33", "", "example.com"] {
+            assert!(
+                matches!(
+                    builder.clone().try_block_cidr(cidr),
+                    Err(PolicyError::InvalidCidr { .. })
+                ),
+                "{}",
+                cidr
+            );
+            assert!(builder.clone().try_allow_cidr(cidr).is_err(), "{}", cidr);
+        }
+        for pattern in ["*", "a..example", "*.*.example", "a.example:99999"] {
+            assert!(
+                matches!(
+                    builder.clone().try_block_host(pattern),
+                    Err(PolicyError::InvalidHostPattern { .. })
+                ),
+                "{}",
+                pattern
+            );
+            assert!(
+                builder.clone().try_allow_host(pattern).is_err(),
+                "{}",
+                pattern
+            );
+        }
+
+        let policy = builder
+            .try_block_cidr("10.0.0.0/8")
+            .and_then(|b| b.try_allow_cidr("10.1.0.0/16"))
+            .and_then(|b| b.try_block_host("*.corp.example:6379"))
+            .and_then(|b| b.try_allow_host("[::1]:8080"))
+            .unwrap()
+            .try_build()
+            .unwrap();
+        assert!(policy.is_ip_allowed("10.2.0.1".parse().unwrap()).is_err());
     }
 
     #[test]
-    fn test_allow_host_wildcard() {
+    fn test_try_build_reports_all_invalid_rules() {
+        let builder = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("10.0.0/8")
+            .block_cidr_with_note("172.16.0.0/12", "note")
+            .allow_cidr("fe80::/129")
+            .block_host_with_note("", "note")
+            .allow_host_until("a.*.example", SystemTime::now());
+        assert_eq!(
+            builder.clone().try_build().unwrap_err(),
+            vec![
+                PolicyError::InvalidCidr {
+                    cidr: "10.0.0/8".into(),
+                    reason: "invalid IP address syntax".into(),
+                },
+                PolicyError::InvalidCidr {
+                    cidr: "fe80::/129".into(),
+                    reason: "invalid IP address syntax".into(),
+                },
+                PolicyError::InvalidHostPattern {
+                    pattern: "".into(),
+                    reason: "empty hostname".into(),
+                },
+                PolicyError::InvalidHostPattern {
+                    pattern: "a.*.example".into(),
+                    reason: "'*' is only allowed in the left-most label".into(),
+                },
+            ]
+        );
+        // build() keeps its lenient behavior
+        let policy = builder.build();
+        assert!(policy.is_ip_allowed("10.0.0.1".parse().unwrap()).is_ok());
+        assert!(policy.is_ip_allowed("172.16.0.1".parse().unwrap()).is_err());
+    }
+
+    // ==================== Path rule tests ====================
+
+    #[test]
+    fn test_path_globs() {
         let policy = PolicyBuilder::new(Policy::PublicOnly)
-            .block_host("*.example.com")
-            .allow_host("*.trusted.example.com")
+            .block_path_glob("*/admin/*")
+            .block_path_glob("/Internal")
             .build();
+        for (path, allowed) in [
+            ("/v1/admin/users", false),
+            ("/admin/", false),
+            ("/v1/ADMIN/x", false),
+            ("/v1/%61dmin/x", false),
+            ("/v1/admin%2Fx", false),
+            ("/v1/administrators", true),
+            ("/internal", false),
+            ("/internal/x", true),
+            ("/", true),
+        ] {
+            let result = policy.is_path_allowed(path);
+            assert_eq!(result.is_ok(), allowed, "{}: {:?}", path, result);
+        }
+        assert_eq!(
+            policy.is_path_allowed("/a/admin/b").unwrap_err(),
+            BlockReason::BlockedPath {
+                rule: "*/admin/*".into()
+            }
+        );
+    }
 
-        assert!(policy
-            .is_hostname_allowed("api.trusted.example.com")
-            .is_ok());
-        assert!(policy.is_hostname_allowed("other.example.com").is_err());
+    #[tokio::test]
+    async fn test_path_rules_enforced_by_validate() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_host("93.184.216.34")
+            .block_path_glob("*/admin/*")
+            .build();
+        assert!(
+            crate::validate("http://93.184.216.34/v1/orders?admin/=1", &policy)
+                .await
+                .is_ok()
+        );
+        // Normalization runs first: `..` cannot hide the blocked segment
+        let err = crate::validate("http://93.184.216.34/v1/x/../admin/users", &policy)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("path rule"), "{}", err);
     }
 
     // ==================== Base policy interaction tests ====================
@@ -489,6 +3811,220 @@ mod tests {
         assert!(matches_hostname_pattern("example.com", "*.example.com"));
     }
 
+    #[test]
+    fn test_matches_hostname_pattern_label_glob() {
+        assert!(matches_hostname_pattern(
+            "api-1.example.com",
+            "api-*.example.com"
+        ));
+        assert!(matches_hostname_pattern(
+            "api-eu-2.example.com",
+            "api-*.example.com"
+        ));
+        assert!(matches_hostname_pattern(
+            "api-.example.com",
+            "api-*.example.com"
+        ));
+        assert!(!matches_hostname_pattern(
+            "api.example.com",
+            "api-*.example.com"
+        ));
+        assert!(!matches_hostname_pattern(
+            "api-1.eu.example.com",
+            "api-*.example.com"
+        ));
+        assert!(!matches_hostname_pattern(
+            "api-1.example.com.evil",
+            "api-*.example.com"
+        ));
+        assert!(!matches_hostname_pattern(
+            "web-1.example.com",
+            "api-*.example.com"
+        ));
+
+        assert!(matches_hostname_pattern(
+            "db-7-replica.example.com",
+            "db-*-replica.example.com"
+        ));
+        assert!(!matches_hostname_pattern(
+            "db-7-primary.example.com",
+            "db-*-replica.example.com"
+        ));
+        assert!(matches_hostname_pattern(
+            "a-x-b-y-c.example.com",
+            "a-*-b-*-c.example.com"
+        ));
+        // '*' in other labels stays literal
+        assert!(!matches_hostname_pattern(
+            "api.a.example.com",
+            "api.*a.example.com"
+        ));
+    }
+
+    #[test]
+    fn test_label_glob_rules() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.svc.example.com")
+            .allow_host("api-*.svc.example.com:443")
+            .build();
+        assert!(policy.is_host_allowed("api-3.svc.example.com", 443).is_ok());
+        assert!(policy.is_host_allowed("api-3.svc.example.com", 80).is_err());
+        assert!(policy.is_host_allowed("db-1.svc.example.com", 443).is_err());
+    }
+
+    #[test]
+    fn test_lint_label_glob() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_host("api-*.com")
+            .allow_host("*.example.com")
+            .allow_host("api-*.example.com")
+            .block_host("db-*.example.com")
+            .build();
+        let warnings = policy.lint();
+        assert!(warnings.contains(&LintWarning::PublicSuffixWildcard {
+            rule: "api-*.com".into()
+        }));
+        assert!(warnings.contains(&LintWarning::RedundantAllow {
+            rule: "api-*.example.com".into()
+        }));
+        assert!(warnings.contains(&LintWarning::ShadowedBlock {
+            rule: "db-*.example.com".into(),
+            by: "*.example.com".into()
+        }));
+    }
+
+    #[cfg(feature = "psl")]
+    #[test]
+    fn test_site_patterns() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("site:Example.co.uk")
+            .allow_host("site:example.com:8443")
+            .block_host("*.api.example.com")
+            .block_host("*.blogspot.com")
+            .try_build()
+            .unwrap();
+        assert_eq!(HostPattern::parse("site:example.com:8443").port, Some(8443));
+        assert_eq!(
+            policy.check_host("a.b.example.co.uk", None),
+            RuleMatch::BlockHost("site:example.co.uk".into())
+        );
+        assert!(policy.is_hostname_allowed("example.co.uk").is_err());
+        assert!(policy.is_hostname_allowed("evilexample.co.uk").is_ok());
+        assert!(policy.is_hostname_allowed("co.uk").is_ok());
+        assert!(policy.is_host_allowed("v1.api.example.com", 8443).is_ok());
+        assert!(policy.is_host_allowed("v1.api.example.com", 443).is_err());
+
+        let warnings = policy.lint();
+        assert!(warnings.contains(&LintWarning::PublicSuffixWildcard {
+            rule: "*.blogspot.com".into()
+        }));
+        // A site rule covers wildcards under it; it is not exact for
+        // allowlist verification
+        assert!(
+            HostPattern::parse("site:example.com").covers(&HostPattern::parse("*.api.example.com"))
+        );
+        assert!(HostPattern::parse("*.co.uk").covers(&HostPattern::parse("site:example.co.uk")));
+        assert!(policy.exact_allowed_hosts().is_empty());
+
+        for bad in [
+            "site:co.uk",
+            "site:www.example.com",
+            "site:10.0.0.1",
+            "site:",
+        ] {
+            assert!(
+                PolicyBuilder::new(Policy::PublicOnly)
+                    .try_block_host(bad)
+                    .is_err(),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[cfg(not(feature = "psl"))]
+    #[test]
+    fn test_site_patterns_require_psl() {
+        let err = check_host_pattern("site:example.com").unwrap_err();
+        assert!(
+            err.to_string().contains("require the psl feature"),
+            "{}",
+            err
+        );
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("site:example.com")
+            .build();
+        assert!(policy.is_hostname_allowed("example.com").is_ok());
+    }
+
+    #[cfg(feature = "psl")]
+    #[test]
+    fn test_allow_sites() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_sites(["example.com", "alice.github.io"])
+            .allow_host("partner.example.net")
+            .block_host("*.internal.example.com")
+            .build();
+        for host in ["example.com", "cdn.eu.example.com", "alice.github.io"] {
+            assert!(policy.is_hostname_allowed(host).is_ok(), "{}", host);
+        }
+        assert!(policy
+            .is_hostname_allowed("db.internal.example.com")
+            .is_err());
+        for host in [
+            "evilexample.com",
+            "example.com.evil.net",
+            "bob.github.io",
+            "partner.example.net",
+            "93.184.216.34",
+            "localhost",
+        ] {
+            assert_eq!(
+                policy.check_host(host, None),
+                RuleMatch::SiteNotAllowed(host.into()),
+                "{}",
+                host
+            );
+        }
+        assert_eq!(
+            policy
+                .is_hostname_allowed("Evil.NET")
+                .unwrap_err()
+                .to_string(),
+            "host evil.net not under any custom policy site"
+        );
+
+        let plain = PolicyBuilder::new(Policy::PublicOnly).build();
+        assert_ne!(policy.fingerprint(), plain.fingerprint());
+        let errors = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_sites(["co.uk", "example.com", "www.example.org"])
+            .try_build()
+            .unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[cfg(feature = "psl")]
+    #[test]
+    fn test_rejected_sites_fail_closed() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_sites(["co.uk", "www.example.com"])
+            .build();
+        for host in ["example.co.uk", "www.example.com", "example.org"] {
+            assert_eq!(
+                policy.check_host(host, None),
+                RuleMatch::SiteNotAllowed(host.into()),
+                "{}",
+                host
+            );
+        }
+        assert!(policy.to_config().is_err());
+        // No sites at all leaves hosts unrestricted
+        let none = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_sites(Vec::<&str>::new())
+            .build();
+        assert!(none.is_hostname_allowed("example.org").is_ok());
+    }
+
     #[test]
     fn test_matches_hostname_pattern_case() {
         // Function expects lowercase input
@@ -538,7 +4074,8 @@ mod tests {
 
         let err = policy
             .is_ip_allowed("10.1.2.3".parse().unwrap())
-            .unwrap_err();
+            .unwrap_err()
+            .to_string();
         // Error should mention the CIDR rule that blocked it
         assert!(
             err.contains("10.0.0.0/8"),
@@ -558,7 +4095,10 @@ mod tests {
             .block_host("*.internal.com")
             .build();
 
-        let err = policy.is_hostname_allowed("api.internal.com").unwrap_err();
+        let err = policy
+            .is_hostname_allowed("api.internal.com")
+            .unwrap_err()
+            .to_string();
         // Error should mention the hostname pattern that blocked it
         assert!(
             err.contains("*.internal.com"),
@@ -571,4 +4111,35 @@ mod tests {
             err
         );
     }
+
+    #[test]
+    fn test_block_local_addresses() {
+        let local = LocalAddresses::from_addrs(["10.0.0.5".parse().unwrap()]);
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .allow_cidr("10.0.0.0/8")
+            .block_local_addresses(local.clone())
+            .build();
+        let ip: IpAddr = "10.0.0.5".parse().unwrap();
+        assert_eq!(policy.check_ip(ip), RuleMatch::LocalAddress(ip));
+        assert_eq!(
+            policy.is_ip_allowed(ip).unwrap_err().to_string(),
+            "address of a local interface: 10.0.0.5"
+        );
+        assert!(policy.is_ip_allowed("10.0.0.6".parse().unwrap()).is_ok());
+        assert!(policy.to_config().is_err());
+        assert_ne!(
+            policy.fingerprint(),
+            PolicyBuilder::new(Policy::AllowPrivate)
+                .allow_cidr("10.0.0.0/8")
+                .build()
+                .fingerprint()
+        );
+
+        // Refreshes apply to policies already built
+        let fingerprint = policy.fingerprint();
+        local.replace(vec!["10.0.0.6".parse().unwrap()]);
+        assert!(policy.is_ip_allowed(ip).is_ok());
+        assert!(policy.is_ip_allowed("10.0.0.6".parse().unwrap()).is_err());
+        assert_eq!(policy.fingerprint(), fingerprint);
+    }
 }
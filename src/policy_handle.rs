@@ -0,0 +1,277 @@
+//! Shared policy handle with scoped relaxation.
+//!
+//! A [`PolicyHandle`] is a cheaply cloneable reference to a [`CustomPolicy`]
+//! that services pass to their request paths. For tightly scoped operations
+//! that need more access than the service's policy grants, such as
+//! health-checking an internal endpoint from an otherwise `PublicOnly`
+//! service, [`PolicyHandle::relax_scope`] returns a [`RelaxGuard`] that adds
+//! allow rules only while it is held, and only for the task that created it.
+//!
+//! Relaxations are keyed by Tokio task (or by thread, outside a task), so a
+//! relaxation never leaks into concurrent requests served by other tasks.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::ThreadId;
+
+use ipnet::IpNet;
+
+use crate::error::Error;
+use crate::policy_builder::{CustomPolicy, HostPattern};
+use crate::validate::{validate_custom_with_options, ValidateOptions, Validated};
+
+/// Extra allow rules for [`PolicyHandle::relax_scope`].
+///
+/// Invalid CIDRs are ignored, as in [`PolicyBuilder`](crate::PolicyBuilder).
+#[derive(Debug, Clone, Default)]
+pub struct Relaxation {
+    allowed_cidrs: Vec<IpNet>,
+    allowed_hosts: Vec<HostPattern>,
+}
+
+impl Relaxation {
+    /// Create an empty relaxation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow an IP range (CIDR notation) while the guard is held.
+    pub fn allow_cidr(mut self, cidr: &str) -> Self {
+        if let Ok(net) = cidr.parse() {
+            self.allowed_cidrs.push(net);
+        }
+        self
+    }
+
+    /// Allow a hostname or pattern (optionally `:port`-scoped) while the
+    /// guard is held.
+    pub fn allow_host(mut self, pattern: &str) -> Self {
+        self.allowed_hosts.push(HostPattern::parse(pattern));
+        self
+    }
+}
+
+/// Who a relaxation applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Scope {
+    Task(tokio::task::Id),
+    Thread(ThreadId),
+}
+
+impl Scope {
+    fn current() -> Self {
+        match tokio::task::try_id() {
+            Some(id) => Scope::Task(id),
+            None => Scope::Thread(std::thread::current().id()),
+        }
+    }
+}
+
+struct Inner {
+    policy: CustomPolicy,
+    next_guard: AtomicU64,
+    relaxations: Mutex<HashMap<Scope, Vec<(u64, Relaxation)>>>,
+}
+
+/// A shared handle to a custom policy.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use url_jail::{Policy, PolicyBuilder, PolicyHandle, Relaxation};
+///
+/// # async fn example() -> Result<(), url_jail::Error> {
+/// let handle = PolicyHandle::new(PolicyBuilder::new(Policy::PublicOnly).build());
+///
+/// // Only this task, and only until `guard` is dropped
+/// let guard = handle.relax_scope(Relaxation::new().allow_cidr("10.0.5.20/32"));
+/// handle.validate("http://10.0.5.20:8080/healthz").await?;
+/// drop(guard);
+///
+/// assert!(handle.validate("http://10.0.5.20:8080/healthz").await.is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PolicyHandle {
+    inner: Arc<Inner>,
+}
+
+impl PolicyHandle {
+    /// Create a handle to `policy`.
+    pub fn new(policy: CustomPolicy) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                policy,
+                next_guard: AtomicU64::new(0),
+                relaxations: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// The policy in effect for the current task, including any relaxations
+    /// it holds.
+    pub fn policy(&self) -> CustomPolicy {
+        let relaxations = self.lock();
+        let Some(active) = relaxations.get(&Scope::current()) else {
+            return self.inner.policy.clone();
+        };
+        let cidrs: Vec<IpNet> = active
+            .iter()
+            .flat_map(|(_, r)| r.allowed_cidrs.iter().copied())
+            .collect();
+        let hosts: Vec<HostPattern> = active
+            .iter()
+            .flat_map(|(_, r)| r.allowed_hosts.iter().cloned())
+            .collect();
+        self.inner.policy.relaxed(&cidrs, &hosts)
+    }
+
+    /// Apply `rules` for the current task until the returned guard is dropped.
+    ///
+    /// Guards may nest and be dropped in any order. A relaxed policy has its
+    /// own [`fingerprint`](CustomPolicy::fingerprint), so results validated
+    /// under it are never served from a cache to unrelaxed callers.
+    ///
+    /// The scope is the Tokio task calling this (or the thread, outside a
+    /// task). Work spawned onto other tasks does not inherit it.
+    pub fn relax_scope(&self, rules: Relaxation) -> RelaxGuard {
+        let id = self.inner.next_guard.fetch_add(1, Ordering::Relaxed);
+        let scope = Scope::current();
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(?scope, "policy relaxation entered");
+
+        self.lock().entry(scope).or_default().push((id, rules));
+        RelaxGuard {
+            handle: self.clone(),
+            scope,
+            id,
+        }
+    }
+
+    /// Validate a URL against the current task's effective policy.
+    pub async fn validate(&self, url: &str) -> Result<Validated, Error> {
+        self.validate_with_options(url, ValidateOptions::default())
+            .await
+    }
+
+    /// Validate a URL against the current task's effective policy with options.
+    pub async fn validate_with_options(
+        &self,
+        url: &str,
+        options: ValidateOptions,
+    ) -> Result<Validated, Error> {
+        let policy = self.policy();
+        validate_custom_with_options(url, &policy, options).await
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Scope, Vec<(u64, Relaxation)>>> {
+        self.inner
+            .relaxations
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl fmt::Debug for PolicyHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PolicyHandle")
+            .field("policy", &self.inner.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Keeps a [`PolicyHandle::relax_scope`] relaxation active until dropped.
+#[must_use = "the relaxation ends as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct RelaxGuard {
+    handle: PolicyHandle,
+    scope: Scope,
+    id: u64,
+}
+
+impl Drop for RelaxGuard {
+    fn drop(&mut self) {
+        let mut relaxations = self.handle.lock();
+        if let Some(active) = relaxations.get_mut(&self.scope) {
+            active.retain(|(id, _)| *id != self.id);
+            if active.is_empty() {
+                relaxations.remove(&self.scope);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Policy, PolicyBuilder};
+
+    fn base() -> CustomPolicy {
+        PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.internal")
+            .build()
+    }
+
+    fn handle() -> PolicyHandle {
+        PolicyHandle::new(base())
+    }
+
+    #[tokio::test]
+    async fn test_relaxation_only_while_held() {
+        let handle = handle();
+        assert!(handle.validate("http://10.0.5.20/").await.is_err());
+
+        let guard = handle.relax_scope(Relaxation::new().allow_cidr("10.0.5.20/32"));
+        assert!(handle.validate("http://10.0.5.20/").await.is_ok());
+        assert!(handle.validate("http://10.0.5.21/").await.is_err());
+        drop(guard);
+
+        assert!(handle.validate("http://10.0.5.20/").await.is_err());
+        assert!(handle.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_relaxation_not_visible_to_other_tasks() {
+        let handle = handle();
+        let _guard = handle.relax_scope(Relaxation::new().allow_cidr("10.0.0.0/8"));
+
+        let other = handle.clone();
+        let result = tokio::spawn(async move { other.validate("http://10.0.0.1/").await })
+            .await
+            .unwrap();
+        assert!(result.is_err());
+        assert!(handle.validate("http://10.0.0.1/").await.is_ok());
+    }
+
+    #[test]
+    fn test_nested_guards_drop_out_of_order() {
+        let handle = handle();
+        let outer = handle.relax_scope(Relaxation::new().allow_cidr("10.0.0.1/32"));
+        let inner = handle.relax_scope(Relaxation::new().allow_host("health.internal"));
+        assert!(handle
+            .policy()
+            .is_ip_allowed("10.0.0.1".parse().unwrap())
+            .is_ok());
+
+        assert!(handle
+            .policy()
+            .is_hostname_allowed("health.internal")
+            .is_ok());
+
+        drop(outer);
+        let policy = handle.policy();
+        assert!(policy.is_ip_allowed("10.0.0.1".parse().unwrap()).is_err());
+        assert!(policy.is_hostname_allowed("health.internal").is_ok());
+
+        drop(inner);
+        assert!(handle
+            .policy()
+            .is_hostname_allowed("health.internal")
+            .is_err());
+        assert_eq!(handle.policy().fingerprint(), base().fingerprint());
+    }
+}
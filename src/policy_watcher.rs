@@ -0,0 +1,414 @@
+//! Hot-reloadable policy files.
+//!
+//! A [`PolicyWatcher`] loads a custom policy from a file and reloads it when
+//! the file changes, so rotating a blocklist does not need a restart. Callers
+//! take a snapshot with [`PolicyWatcher::current`] per request; a reload
+//! swaps the snapshot atomically, and requests already holding the old one
+//! finish under it.
+//!
+//! The file format is up to the caller: the watcher takes a loader from file
+//! contents to a [`CustomPolicy`], such as `toml::from_str` with the `serde`
+//! feature. A file that fails to load never replaces a working policy; the
+//! watcher keeps the last good one and reports the failure through
+//! [`PolicyWatcher::last_error`].
+//!
+//! The watcher polls: every interval it reads the file and reloads it if the
+//! contents changed. A change takes effect within one interval, and editors
+//! that replace the file, network filesystems, and Kubernetes ConfigMap
+//! symlink swaps work the same way. With the `notify` feature it also
+//! subscribes to filesystem notifications for the file's directory and
+//! reloads as soon as something there changes; polling stays on as the
+//! fallback for missed events and filesystems without notifications.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, Weak};
+use std::time::Duration;
+
+use crate::cache::fnv1a;
+use crate::policy_builder::CustomPolicy;
+
+type Loader = dyn Fn(&str) -> Result<CustomPolicy, String> + Send + Sync;
+
+struct State {
+    policy: Arc<CustomPolicy>,
+    /// Hash of the file contents `policy` was loaded from.
+    contents: u64,
+    last_error: Option<String>,
+}
+
+struct Inner {
+    path: PathBuf,
+    loader: Box<Loader>,
+    state: RwLock<State>,
+}
+
+/// A custom policy loaded from a file and swapped when the file changes.
+///
+/// Cloning is cheap; clones share the policy and the background watch.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use std::time::Duration;
+/// use url_jail::{validate, CustomPolicy, PolicyWatcher};
+///
+/// let watcher = PolicyWatcher::watch(
+///     "/etc/egress/policy.toml",
+///     |contents| toml::from_str::<CustomPolicy>(contents).map_err(|e| e.to_string()),
+///     Duration::from_secs(10),
+/// )?;
+///
+/// // Per request
+/// let policy = watcher.current();
+/// let validated = validate(url, &*policy).await?;
+/// ```
+#[derive(Clone)]
+pub struct PolicyWatcher {
+    inner: Arc<Inner>,
+}
+
+impl PolicyWatcher {
+    /// Load the policy at `path` with `loader`.
+    ///
+    /// The file is only reloaded by [`reload`](Self::reload); use
+    /// [`watch`](Self::watch) to reload in the background.
+    ///
+    /// # Errors
+    ///
+    /// Returns the read or load error if the initial policy cannot be loaded.
+    pub fn open<F>(path: impl Into<PathBuf>, loader: F) -> Result<Self, String>
+    where
+        F: Fn(&str) -> Result<CustomPolicy, String> + Send + Sync + 'static,
+    {
+        let path = path.into();
+        let contents = read(&path)?;
+        let policy = loader(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                path,
+                loader: Box::new(loader),
+                state: RwLock::new(State {
+                    policy: Arc::new(policy),
+                    contents: fnv1a(contents.as_bytes()),
+                    last_error: None,
+                }),
+            }),
+        })
+    }
+
+    /// Load the policy at `path` and check it for changes every `interval`.
+    ///
+    /// Each check reads the whole file and compares a hash of its contents
+    /// with the loaded one, so it costs one read per interval; the first
+    /// check is one interval after the initial load. Checks run on a
+    /// background Tokio task, which stops once every clone of the watcher
+    /// is dropped.
+    ///
+    /// With the `notify` feature, a filesystem notification in the file's
+    /// directory also triggers a check, and the interval becomes the
+    /// longest gap between checks. If notifications cannot be set up (for
+    /// example, the inotify watch limit is reached), the watcher only polls.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `interval` is zero, and otherwise as
+    /// [`open`](Self::open).
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a Tokio runtime.
+    pub fn watch<F>(path: impl Into<PathBuf>, loader: F, interval: Duration) -> Result<Self, String>
+    where
+        F: Fn(&str) -> Result<CustomPolicy, String> + Send + Sync + 'static,
+    {
+        if interval.is_zero() {
+            return Err("the policy watch interval must be greater than zero".to_string());
+        }
+        let watcher = Self::open(path, loader)?;
+        let inner = Arc::downgrade(&watcher.inner);
+        #[cfg(feature = "notify")]
+        let events = Events::subscribe(&watcher.inner.path)
+            .map_err(|_e| {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    path = %watcher.inner.path.display(),
+                    error = %_e,
+                    "policy file notifications unavailable, polling only"
+                );
+            })
+            .ok();
+        tokio::spawn(poll(
+            inner,
+            interval,
+            #[cfg(feature = "notify")]
+            events,
+        ));
+        Ok(watcher)
+    }
+
+    /// The current policy.
+    ///
+    /// Take one snapshot per request, so a reload cannot change the rules
+    /// partway through it.
+    pub fn current(&self) -> Arc<CustomPolicy> {
+        self.state().policy.clone()
+    }
+
+    /// Reload the file now if its contents changed.
+    ///
+    /// Returns `true` if a new policy was swapped in. On failure the current
+    /// policy stays in effect and the error is also kept for
+    /// [`last_error`](Self::last_error).
+    ///
+    /// # Errors
+    ///
+    /// Returns the read or load error.
+    pub fn reload(&self) -> Result<bool, String> {
+        let result = self.try_reload();
+        let mut state = self.inner.state.write().unwrap_or_else(|e| e.into_inner());
+        state.last_error = result.as_ref().err().cloned();
+
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(true) => tracing::info!(path = %self.inner.path.display(), "policy reloaded"),
+            Err(e) => {
+                tracing::warn!(path = %self.inner.path.display(), error = %e, "policy reload failed")
+            }
+            Ok(false) => {}
+        }
+
+        result
+    }
+
+    /// The error from the most recent reload, if it failed.
+    pub fn last_error(&self) -> Option<String> {
+        self.state().last_error.clone()
+    }
+
+    /// The watched file.
+    pub fn path(&self) -> &Path {
+        &self.inner.path
+    }
+
+    fn try_reload(&self) -> Result<bool, String> {
+        let contents = read(&self.inner.path)?;
+        let hash = fnv1a(contents.as_bytes());
+        if hash == self.state().contents {
+            return Ok(false);
+        }
+        let policy = (self.inner.loader)(&contents)
+            .map_err(|e| format!("{}: {}", self.inner.path.display(), e))?;
+
+        let mut state = self.inner.state.write().unwrap_or_else(|e| e.into_inner());
+        state.policy = Arc::new(policy);
+        state.contents = hash;
+        Ok(true)
+    }
+
+    fn state(&self) -> std::sync::RwLockReadGuard<'_, State> {
+        self.inner.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl fmt::Debug for PolicyWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PolicyWatcher")
+            .field("path", &self.inner.path)
+            .field("policy", &self.current())
+            .field("last_error", &self.last_error())
+            .finish()
+    }
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+async fn poll(
+    inner: Weak<Inner>,
+    interval: Duration,
+    #[cfg(feature = "notify")] events: Option<Events>,
+) {
+    let mut ticks = tokio::time::interval(interval);
+    // The first tick completes immediately; the file was just loaded
+    ticks.tick().await;
+    loop {
+        #[cfg(feature = "notify")]
+        match &events {
+            Some(events) => {
+                let _ = tokio::time::timeout(interval, events.changed.notified()).await;
+            }
+            None => {
+                ticks.tick().await;
+            }
+        }
+        #[cfg(not(feature = "notify"))]
+        ticks.tick().await;
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        let watcher = PolicyWatcher { inner };
+        // Reading the file blocks; keep it off the runtime's worker threads
+        let _ = tokio::task::spawn_blocking(move || watcher.reload()).await;
+    }
+}
+
+/// Filesystem notifications for the directory holding a policy file.
+#[cfg(feature = "notify")]
+struct Events {
+    /// Notifications stop when the watcher is dropped.
+    _watcher: notify::RecommendedWatcher,
+    /// Signalled on each change; signals before a wait coalesce into one.
+    changed: Arc<tokio::sync::Notify>,
+}
+
+#[cfg(feature = "notify")]
+impl Events {
+    fn subscribe(path: &Path) -> Result<Self, String> {
+        use notify::event::{AccessKind, AccessMode};
+        use notify::{EventKind, Watcher};
+
+        let changed = Arc::new(tokio::sync::Notify::new());
+        let signal = changed.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                // Reads, including the watcher's own, are not changes
+                let read = matches!(
+                    &event,
+                    Ok(notify::Event { kind: EventKind::Access(access), .. })
+                        if *access != AccessKind::Close(AccessMode::Write)
+                );
+                if !read {
+                    signal.notify_one();
+                }
+            })
+            .map_err(|e| e.to_string())?;
+        // Editors and ConfigMap updates replace the file rather than write to
+        // it, so watch its directory
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        watcher
+            .watch(dir, notify::RecursiveMode::NonRecursive)
+            .map_err(|e| format!("{}: {}", dir.display(), e))?;
+        Ok(Self {
+            _watcher: watcher,
+            changed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Policy, PolicyBuilder};
+
+    /// One `block_host` pattern per line.
+    fn load(contents: &str) -> Result<CustomPolicy, String> {
+        contents
+            .lines()
+            .try_fold(
+                PolicyBuilder::new(Policy::PublicOnly),
+                |builder, line| match line.strip_prefix("block ") {
+                    Some(pattern) => Ok(builder.block_host(pattern)),
+                    None => Err(format!("bad line {:?}", line)),
+                },
+            )
+            .map(PolicyBuilder::build)
+    }
+
+    fn policy_file(name: &str, contents: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("url_jail-{}-{}.policy", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_reload_swaps_policy_and_keeps_last_good() {
+        let path = policy_file("reload", "block a.example");
+        let watcher = PolicyWatcher::open(&path, load).unwrap();
+        let before = watcher.current();
+        assert!(before.is_hostname_allowed("a.example").is_err());
+        assert!(!watcher.reload().unwrap());
+
+        std::fs::write(&path, "block b.example").unwrap();
+        assert!(watcher.reload().unwrap());
+        let after = watcher.current();
+        assert!(after.is_hostname_allowed("a.example").is_ok());
+        assert!(after.is_hostname_allowed("b.example").is_err());
+        // Snapshots taken before the reload are unaffected
+        assert!(before.is_hostname_allowed("a.example").is_err());
+
+        std::fs::write(&path, "allow everything").unwrap();
+        let err = watcher.reload().unwrap_err();
+        assert!(err.contains("bad line"), "{}", err);
+        assert_eq!(watcher.last_error(), Some(err));
+        assert_eq!(watcher.current().fingerprint(), after.fingerprint());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(watcher.reload().is_err());
+        assert_eq!(watcher.current().fingerprint(), after.fingerprint());
+    }
+
+    #[test]
+    fn test_open_fails_without_valid_policy() {
+        let missing = std::env::temp_dir().join("url_jail-missing.policy");
+        assert!(PolicyWatcher::open(&missing, load).is_err());
+
+        let path = policy_file("invalid", "nonsense");
+        assert!(PolicyWatcher::open(&path, load).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_watch_reloads_in_background() {
+        let path = policy_file("watch", "block a.example");
+        let watcher = PolicyWatcher::watch(&path, load, Duration::from_millis(10)).unwrap();
+        std::fs::write(&path, "block b.example").unwrap();
+
+        for _ in 0..100 {
+            if watcher.current().is_hostname_allowed("b.example").is_err() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(watcher.current().is_hostname_allowed("b.example").is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "notify")]
+    #[tokio::test]
+    async fn test_watch_reloads_on_notification() {
+        let dir = std::env::temp_dir().join(format!("url_jail-notify-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("policy");
+        std::fs::write(&path, "block a.example").unwrap();
+        // Far longer than the test: only a notification can trigger a reload
+        let watcher = PolicyWatcher::watch(&path, load, Duration::from_secs(3600)).unwrap();
+
+        // Replace the file the way editors and ConfigMap updates do
+        let staged = dir.join("policy.tmp");
+        std::fs::write(&staged, "block b.example").unwrap();
+        std::fs::rename(&staged, &path).unwrap();
+
+        for _ in 0..500 {
+            if watcher.current().is_hostname_allowed("b.example").is_err() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(watcher.current().is_hostname_allowed("b.example").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_watch_rejects_zero_interval() {
+        let path = policy_file("zero", "block a.example");
+        let err = PolicyWatcher::watch(&path, load, Duration::ZERO).unwrap_err();
+        assert!(err.contains("greater than zero"), "{}", err);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,364 @@
+//! Pre-flight checks for hostname allowlists.
+//!
+//! Admin UIs that let customers configure webhook endpoints want to tell them
+//! immediately which hosts will be rejected, before the configuration is
+//! saved. [`preflight`] runs every check that needs no DNS, synchronously,
+//! so it can run on each keystroke. [`validate_many_hosts`] resolves a set of
+//! bare hostnames concurrently and reports, per host, whether it resolves and
+//! is allowed.
+//!
+//! A passing pre-flight check does not replace validation at request time:
+//! DNS can change between saving the configuration and using it.
+//!
+//! Schedulers that know the next wave of targets ahead of time use
+//! [`prefetch`] to run the same checks in the background and warm a
+//! [`ValidationCache`], so the wave itself validates without waiting on DNS.
+
+use std::sync::Arc;
+
+use tokio::task::{JoinHandle, JoinSet};
+
+use crate::cache::ValidationCache;
+use crate::error::Error;
+use crate::machine::Step;
+use crate::policy::{Policy, ValidationPolicy};
+use crate::policy_builder::CustomPolicy;
+use crate::validate::{validate_custom, validate_with_options, ValidateOptions, Validated};
+
+/// Check a URL against `policy` without DNS.
+///
+/// Runs the lexical part of validation: URL parsing, scheme, the custom
+/// policy's URL, path, host and port rules, the cloud metadata hostname
+/// blocklist, and, for IP literals, the address rules. Hostnames are not
+/// resolved, so `Ok` means only that nothing rejects the URL before DNS;
+/// [`validate`](crate::validate) remains the authoritative check.
+///
+/// # Example
+///
+/// ```rust
+/// use url_jail::{preflight, Policy, PolicyBuilder};
+///
+/// let policy = PolicyBuilder::new(Policy::PublicOnly)
+///     .allow_schemes(["https"])
+///     .block_ports([8080])
+///     .build();
+///
+/// assert!(preflight("https://hooks.example.com/deliver", &policy).is_ok());
+/// assert!(preflight("http://hooks.example.com/deliver", &policy).is_err());
+/// assert!(preflight("https://hooks.example.com:8080/", &policy).is_err());
+/// assert!(preflight("https://169.254.169.254/", &policy).is_err());
+/// ```
+///
+/// # Errors
+///
+/// Returns the error `validate` would return for the first rule the URL
+/// violates before DNS.
+pub fn preflight<P: ValidationPolicy>(url: &str, policy: P) -> Result<(), Error> {
+    match policy.machine(url).start() {
+        Step::Done(result) => result.map(drop),
+        Step::NeedDns(_) => Ok(()),
+    }
+}
+
+/// The pre-flight result for one hostname.
+#[derive(Debug)]
+pub struct HostCheck {
+    /// The hostname as given.
+    pub host: String,
+
+    /// The validation result for the host on the default HTTPS port.
+    pub result: Result<Validated, Error>,
+}
+
+impl HostCheck {
+    /// Returns `true` if the host resolved and every address is allowed.
+    pub fn is_allowed(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    /// Returns `true` if the host was rejected by policy, as opposed to
+    /// failing to parse or resolve.
+    pub fn is_blocked(&self) -> bool {
+        self.result.as_ref().is_err_and(Error::is_blocked)
+    }
+}
+
+/// Check a set of hostnames against a built-in policy.
+///
+/// Hosts are resolved concurrently as Tokio tasks, so this must run inside a
+/// Tokio runtime; results are returned in input order.
+/// Each entry must be a bare hostname or IP address (`example.com`,
+/// `203.0.113.7`, `2001:db8::1`); URLs and `host:port` are reported as
+/// [`Error::InvalidUrl`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use url_jail::{validate_many_hosts, Policy};
+///
+/// # async fn example() {
+/// let checks = validate_many_hosts(
+///     ["hooks.example.com", "metadata.google.internal", "10.0.0.1"],
+///     Policy::PublicOnly,
+/// )
+/// .await;
+/// for check in &checks {
+///     match &check.result {
+///         Ok(v) => println!("{}: ok ({})", check.host, v.ip),
+///         Err(e) => println!("{}: {}", check.host, e),
+///     }
+/// }
+/// # }
+/// ```
+pub async fn validate_many_hosts<'a, I>(hosts: I, policy: Policy) -> Vec<HostCheck>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    check_all(hosts, move |url| async move {
+        validate_with_options(&url, policy, ValidateOptions::default()).await
+    })
+    .await
+}
+
+/// Check a set of hostnames against a custom policy.
+///
+/// See [`validate_many_hosts`].
+pub async fn validate_many_hosts_custom<'a, I>(hosts: I, policy: &CustomPolicy) -> Vec<HostCheck>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let policy = Arc::new(policy.clone());
+    check_all(hosts, move |url| {
+        let policy = policy.clone();
+        async move { validate_custom(&url, &policy).await }
+    })
+    .await
+}
+
+/// Resolve and validate a set of hostnames in the background, warming `cache`.
+///
+/// Returns at once; the checks run as Tokio tasks, so this must be called
+/// inside a Tokio runtime. Await the handle for the per-host results, or drop
+/// it to let the prefetch finish unobserved. Hosts are given as for
+/// [`validate_many_hosts`].
+///
+/// Entries are cached under `policy`'s fingerprint, so later validations
+/// must use the same policy (and cache) to hit them.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use url_jail::{prefetch, validate_with_options, Policy, ValidateOptions, ValidationCache};
+///
+/// # async fn example() -> Result<(), url_jail::Error> {
+/// let cache = Arc::new(ValidationCache::new(Duration::from_secs(60)));
+/// let next_wave = vec!["hooks.example.com".to_string(), "api.example.org".to_string()];
+/// prefetch(next_wave, Policy::PublicOnly, cache.clone());
+///
+/// // Later, when the wave is due
+/// let opts = ValidateOptions { cache: Some(cache), ..Default::default() };
+/// let v = validate_with_options("https://hooks.example.com/deliver", Policy::PublicOnly, opts).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn prefetch<I, P>(
+    hosts: I,
+    policy: P,
+    cache: Arc<ValidationCache>,
+) -> JoinHandle<Vec<HostCheck>>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+    P: ValidationPolicy + 'static,
+{
+    let options = ValidateOptions {
+        cache: Some(cache),
+        ..Default::default()
+    };
+    prefetch_with_options(hosts, policy, options)
+}
+
+/// Prefetch hostnames in the background with `options`.
+///
+/// Only [`ValidateOptions::cache`] is warmed; without a cache this is a
+/// background [`validate_many_hosts`]. See [`prefetch`].
+pub fn prefetch_with_options<I, P>(
+    hosts: I,
+    policy: P,
+    options: ValidateOptions,
+) -> JoinHandle<Vec<HostCheck>>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+    P: ValidationPolicy + 'static,
+{
+    let hosts: Vec<String> = hosts
+        .into_iter()
+        .map(|host| host.as_ref().to_string())
+        .collect();
+    let policy = Arc::new(policy);
+    tokio::spawn(async move {
+        check_all(hosts.iter().map(String::as_str), move |url| {
+            let policy = policy.clone();
+            let options = options.clone();
+            async move { validate_with_options(&url, &*policy, options).await }
+        })
+        .await
+    })
+}
+
+async fn check_all<'a, I, F, Fut>(hosts: I, validate: F) -> Vec<HostCheck>
+where
+    I: IntoIterator<Item = &'a str>,
+    F: Fn(String) -> Fut,
+    Fut: std::future::Future<Output = Result<Validated, Error>> + Send + 'static,
+{
+    let mut hosts_in_order = Vec::new();
+    let mut results = Vec::new();
+    let mut pending = JoinSet::new();
+
+    for (i, host) in hosts.into_iter().enumerate() {
+        hosts_in_order.push(host.to_string());
+        match host_url(host) {
+            Ok(url) => {
+                let lookup = validate(url);
+                pending.spawn(async move { (i, lookup.await) });
+                results.push(None);
+            }
+            Err(e) => results.push(Some(Err(e))),
+        }
+    }
+
+    while let Some(joined) = pending.join_next().await {
+        // Tasks are never aborted, so a join error is a panic in validation
+        let (i, result) = joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        results[i] = Some(result);
+    }
+
+    hosts_in_order
+        .into_iter()
+        .zip(results)
+        .map(|(host, result)| HostCheck {
+            host,
+            result: result.expect("every spawned check is joined"),
+        })
+        .collect()
+}
+
+/// Build the URL to validate for a bare hostname or IP address.
+fn host_url(host: &str) -> Result<String, Error> {
+    let trimmed = host.trim();
+    if let Ok(std::net::IpAddr::V6(v6)) = trimmed.parse() {
+        return Ok(format!("https://[{}]/", v6));
+    }
+    let forbidden = |c: char| c.is_whitespace() || "/?#\\:@[]%".contains(c);
+    if trimmed.is_empty() || trimmed.contains(forbidden) {
+        return Err(Error::invalid_url(
+            host,
+            "expected a bare hostname, not a URL or host:port",
+        ));
+    }
+    Ok(format!("https://{}/", trimmed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PolicyBuilder;
+
+    #[test]
+    fn test_preflight_needs_no_dns() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.corp.example")
+            .allow_cidr("10.20.0.0/16")
+            .build();
+        for (url, allowed) in [
+            // Would fail to resolve, but preflight never looks it up
+            ("https://does-not-exist.invalid/", true),
+            ("https://wiki.corp.example/", false),
+            ("http://metadata.google.internal/", false),
+            ("http://10.20.0.1/", true),
+            ("http://10.30.0.1/", false),
+            ("http://[::1]/", false),
+            ("ftp://example.com/", false),
+        ] {
+            let result = preflight(url, &policy);
+            assert_eq!(result.is_ok(), allowed, "{}: {:?}", url, result);
+        }
+        let err = preflight("http://127.0.0.1/", Policy::PublicOnly).unwrap_err();
+        assert!(matches!(err, Error::SsrfBlocked { .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn test_host_url() {
+        assert_eq!(host_url("example.com").unwrap(), "https://example.com/");
+        assert_eq!(host_url(" 203.0.113.7 ").unwrap(), "https://203.0.113.7/");
+        assert_eq!(host_url("2001:db8::1").unwrap(), "https://[2001:db8::1]/");
+        for input in ["", "https://example.com/", "example.com:443", "a/b", "a b"] {
+            assert!(
+                matches!(host_url(input), Err(Error::InvalidUrl { .. })),
+                "{:?} accepted",
+                input
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_results_in_input_order() {
+        let checks = validate_many_hosts(
+            [
+                "8.8.8.8",
+                "metadata.google.internal",
+                "127.0.0.1",
+                "https://x/",
+                "1.1.1.1",
+            ],
+            Policy::PublicOnly,
+        )
+        .await;
+
+        let hosts: Vec<&str> = checks.iter().map(|c| c.host.as_str()).collect();
+        assert_eq!(
+            hosts,
+            [
+                "8.8.8.8",
+                "metadata.google.internal",
+                "127.0.0.1",
+                "https://x/",
+                "1.1.1.1"
+            ]
+        );
+        assert!(checks[0].is_allowed());
+        assert!(checks[1].is_blocked());
+        assert!(checks[2].is_blocked());
+        assert!(!checks[3].is_allowed() && !checks[3].is_blocked());
+        assert_eq!(checks[4].result.as_ref().unwrap().ip.to_string(), "1.1.1.1");
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_runs_in_background() {
+        let cache = Arc::new(ValidationCache::new(std::time::Duration::from_secs(60)));
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_cidr("10.20.0.0/16")
+            .build();
+        let hosts = vec!["10.20.0.1".to_string(), "10.30.0.1".to_string()];
+        let handle = prefetch(hosts, policy, cache);
+        // IP literals need no DNS, so nothing is cached; only the checks run
+        let checks = handle.await.unwrap();
+        assert_eq!(checks.len(), 2);
+        assert!(checks[0].is_allowed());
+        assert!(checks[1].is_blocked());
+    }
+
+    #[tokio::test]
+    async fn test_custom_policy() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_cidr("10.20.0.0/16")
+            .build();
+        let checks = validate_many_hosts_custom(["10.20.0.1", "10.30.0.1"], &policy).await;
+        assert!(checks[0].is_allowed());
+        assert!(checks[1].is_blocked());
+    }
+}
@@ -0,0 +1,341 @@
+//! Link previews (URL unfurling) built on validated fetch.
+//!
+//! [`preview`] fetches a page with [`fetch_with_options`](crate::fetch_with_options),
+//! so every redirect hop is validated, then extracts the title, description,
+//! and OpenGraph image. The image URL is validated too before it is returned,
+//! so callers can fetch it without another check-then-use gap.
+//!
+//! ## Limits
+//!
+//! - Only `text/html` and `application/xhtml+xml` responses are accepted
+//! - At most [`PreviewOptions::max_bytes`] of the body are read; metadata
+//!   after that point is ignored
+//! - The whole operation (all hops plus body) is bounded by
+//!   [`PreviewOptions::timeout`]
+//!
+//! The HTML scanner is intentionally small: it reads `<title>` and `<meta>`
+//! tags only and never executes or follows anything else in the document.
+
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::fetch::{fetch_with_options, FetchOptions};
+use crate::policy::Policy;
+use crate::redirect::resolve_redirect_url;
+use crate::validate::{validate, Validated};
+
+/// Options for [`preview_with_options`].
+#[derive(Debug, Clone)]
+pub struct PreviewOptions {
+    /// Maximum number of body bytes to scan for metadata.
+    ///
+    /// Default: 512 KiB.
+    pub max_bytes: usize,
+
+    /// Time limit for the whole preview, including redirects.
+    ///
+    /// Default: 10 seconds.
+    pub timeout: Duration,
+
+    /// See [`FetchOptions::max_redirects`].
+    ///
+    /// Default: 10.
+    pub max_redirects: Option<u8>,
+}
+
+impl Default for PreviewOptions {
+    fn default() -> Self {
+        Self {
+            max_bytes: 512 * 1024,
+            timeout: Duration::from_secs(10),
+            max_redirects: None,
+        }
+    }
+}
+
+/// Metadata extracted from a page.
+#[derive(Debug, Clone)]
+pub struct Preview {
+    /// Final URL after redirects.
+    pub url: String,
+
+    /// `og:title`, falling back to `<title>`.
+    pub title: Option<String>,
+
+    /// `og:description`, falling back to `<meta name="description">`.
+    pub description: Option<String>,
+
+    /// The validated `og:image` (or `twitter:image`) target.
+    ///
+    /// `None` if the page has no image or the image URL is blocked by the policy.
+    pub image: Option<Validated>,
+}
+
+/// Fetch a page and extract preview metadata.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use url_jail::{preview, Policy};
+///
+/// let p = preview("https://example.com/article", Policy::PublicOnly).await?;
+/// println!("{:?} - {:?}", p.title, p.description);
+/// if let Some(image) = p.image {
+///     println!("image at {} ({})", image.url, image.ip);
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns the errors of [`fetch_with_options`](crate::fetch_with_options),
+/// [`Error::ResponseBlocked`] for non-HTML responses, and [`Error::Timeout`]
+/// if the time limit is exceeded.
+pub async fn preview(url: &str, policy: Policy) -> Result<Preview, Error> {
+    preview_with_options(url, policy, PreviewOptions::default()).await
+}
+
+/// Fetch a page and extract preview metadata with custom limits.
+///
+/// See [`preview`] for details.
+pub async fn preview_with_options(
+    url: &str,
+    policy: Policy,
+    options: PreviewOptions,
+) -> Result<Preview, Error> {
+    tokio::time::timeout(options.timeout, preview_inner(url, policy, &options))
+        .await
+        .map_err(|_| Error::Timeout {
+            message: format!("preview of {} timed out after {:?}", url, options.timeout),
+        })?
+}
+
+async fn preview_inner(
+    url: &str,
+    policy: Policy,
+    options: &PreviewOptions,
+) -> Result<Preview, Error> {
+    let max_bytes = options.max_bytes;
+    let fetch_options = FetchOptions {
+        allowed_content_types: vec!["text/html".into(), "application/xhtml+xml".into()],
+        max_redirects: options.max_redirects,
+        ..Default::default()
+    };
+    let mut result = fetch_with_options(url, policy, fetch_options).await?;
+    let final_url = result
+        .chain
+        .last()
+        .map(|v| v.url.clone())
+        .unwrap_or_else(|| url.to_string());
+
+    // Read a bounded prefix; metadata lives in <head>, so truncation is fine
+    let mut body = Vec::new();
+    while body.len() < max_bytes {
+        let chunk = result
+            .response
+            .chunk()
+            .await
+            .map_err(|e| Error::HttpError {
+                url: final_url.clone(),
+                message: e.to_string(),
+            })?;
+        let Some(chunk) = chunk else { break };
+        let take = chunk.len().min(max_bytes - body.len());
+        body.extend_from_slice(&chunk[..take]);
+    }
+    let html = String::from_utf8_lossy(&body);
+    let meta = extract_metadata(&html);
+
+    let image = match meta.image {
+        Some(src) => match resolve_redirect_url(&final_url, &src) {
+            Ok(image_url) => validate(&image_url, policy).await.ok(),
+            Err(_) => None,
+        },
+        None => None,
+    };
+
+    Ok(Preview {
+        url: final_url,
+        title: meta.title,
+        description: meta.description,
+        image,
+    })
+}
+
+/// Raw metadata found in a document, before URL resolution.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Metadata {
+    title: Option<String>,
+    description: Option<String>,
+    image: Option<String>,
+}
+
+/// Extract title, description, and image from HTML.
+fn extract_metadata(html: &str) -> Metadata {
+    let lower = html.to_ascii_lowercase();
+    let mut og = Metadata::default();
+    let mut fallback = Metadata::default();
+
+    let mut pos = 0;
+    while let Some(start) = lower[pos..].find('<').map(|i| pos + i) {
+        let Some(end) = lower[start..].find('>').map(|i| start + i) else {
+            break;
+        };
+        let tag = &html[start + 1..end];
+        let tag_lower = &lower[start + 1..end];
+
+        if tag_lower.starts_with("title") && fallback.title.is_none() {
+            if let Some(close) = lower[end..].find("</title").map(|i| end + i) {
+                fallback.title = clean(&html[end + 1..close]);
+            }
+        } else if tag_lower.starts_with("meta")
+            && tag_lower[4..].starts_with(|c: char| c.is_ascii_whitespace())
+        {
+            let key = attribute(tag, "property")
+                .or_else(|| attribute(tag, "name"))
+                .map(|k| k.to_ascii_lowercase());
+            let content = attribute(tag, "content").and_then(|c| clean(&c));
+            if let (Some(key), Some(content)) = (key, content) {
+                let slot = match key.as_str() {
+                    "og:title" => &mut og.title,
+                    "og:description" => &mut og.description,
+                    "og:image" | "og:image:url" => &mut og.image,
+                    "description" => &mut fallback.description,
+                    "twitter:image" => &mut fallback.image,
+                    _ => {
+                        pos = end + 1;
+                        continue;
+                    }
+                };
+                slot.get_or_insert(content);
+            }
+        }
+        pos = end + 1;
+    }
+
+    Metadata {
+        title: og.title.or(fallback.title),
+        description: og.description.or(fallback.description),
+        image: og.image.or(fallback.image),
+    }
+}
+
+/// Read a quoted or bare attribute value from the inside of a tag.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let bytes = lower.as_bytes();
+    let mut search = 0;
+    while let Some(i) = lower[search..].find(name).map(|i| search + i) {
+        search = i + name.len();
+        // Must be a whole attribute name followed by '='
+        if i > 0 && !bytes[i - 1].is_ascii_whitespace() {
+            continue;
+        }
+        let rest = lower[search..].trim_start();
+        let Some(value_lower) = rest.strip_prefix('=') else {
+            continue;
+        };
+        let value_start = tag.len() - value_lower.trim_start().len();
+        let value = &tag[value_start..];
+        return Some(match value.chars().next() {
+            Some(q @ ('"' | '\'')) => value[1..].split(q).next().unwrap_or("").to_string(),
+            _ => value
+                .split(|c: char| c.is_ascii_whitespace() || c == '/')
+                .next()
+                .unwrap_or("")
+                .to_string(),
+        });
+    }
+    None
+}
+
+/// Decode common entities, collapse whitespace, and drop empty values.
+fn clean(text: &str) -> Option<String> {
+    let decoded = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&");
+    let collapsed = decoded.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!collapsed.is_empty()).then_some(collapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_title_and_description() {
+        let html = r#"<html><head>
+            <TITLE> Example   Page </TITLE>
+            <meta name="description" content="A page &amp; more">
+        </head></html>"#;
+        let meta = extract_metadata(html);
+        assert_eq!(meta.title.as_deref(), Some("Example Page"));
+        assert_eq!(meta.description.as_deref(), Some("A page & more"));
+        assert_eq!(meta.image, None);
+    }
+
+    #[test]
+    fn test_opengraph_takes_precedence() {
+        let html = r#"<title>Fallback</title>
+            <meta name="description" content="fallback description">
+            <meta property="og:title" content="OG Title" />
+            <meta property='og:description' content='OG description'>
+            <meta content="https://cdn.example.com/a.png" property="og:image">"#;
+        let meta = extract_metadata(html);
+        assert_eq!(meta.title.as_deref(), Some("OG Title"));
+        assert_eq!(meta.description.as_deref(), Some("OG description"));
+        assert_eq!(meta.image.as_deref(), Some("https://cdn.example.com/a.png"));
+    }
+
+    #[test]
+    fn test_twitter_image_fallback() {
+        let html = r#"<meta name="twitter:image" content="/img.png">"#;
+        assert_eq!(extract_metadata(html).image.as_deref(), Some("/img.png"));
+    }
+
+    #[test]
+    fn test_first_value_wins() {
+        let html = r#"<meta property="og:image" content="/first.png">
+            <meta property="og:image" content="/second.png">"#;
+        assert_eq!(extract_metadata(html).image.as_deref(), Some("/first.png"));
+    }
+
+    #[test]
+    fn test_empty_and_unparseable_documents() {
+        assert_eq!(extract_metadata(""), Metadata::default());
+        assert_eq!(extract_metadata("<title>"), Metadata::default());
+        assert_eq!(
+            extract_metadata("<meta property=\"og:title\" content=\"  \">"),
+            Metadata::default()
+        );
+    }
+
+    #[test]
+    fn test_attribute_parsing() {
+        assert_eq!(
+            attribute(r#"meta name="a" content="b c""#, "content").as_deref(),
+            Some("b c")
+        );
+        assert_eq!(
+            attribute("meta name=description content=bare", "content").as_deref(),
+            Some("bare")
+        );
+        // data-content is a different attribute
+        assert_eq!(attribute(r#"meta data-content="x""#, "content"), None);
+    }
+
+    #[test]
+    fn test_metatag_prefix_is_not_meta() {
+        let html = r#"<metadata property="og:title" content="nope">"#;
+        assert_eq!(extract_metadata(html).title, None);
+    }
+
+    #[tokio::test]
+    async fn test_preview_blocked_url() {
+        let result = preview("http://169.254.169.254/", Policy::PublicOnly).await;
+        assert!(result.unwrap_err().is_blocked());
+    }
+}
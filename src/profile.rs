@@ -0,0 +1,259 @@
+//! Per-environment policies from one config file.
+//!
+//! Services usually run slightly different policies in development, staging
+//! and production: loopback allowed on a laptop, a staging-only internal
+//! host, and so on. Written as separate builder chains, the copies drift. A
+//! [`PolicyProfile`] keeps the rules every environment shares in one place
+//! and adds each environment's differences on top, and the service picks
+//! its environment by name at startup:
+//!
+//! ```toml
+//! [shared]
+//! block_hosts = ["*.corp.example"]
+//! block_ports = [25, 6379]
+//!
+//! [environments.dev]
+//! base = { block_loopback = false }
+//!
+//! [environments.staging]
+//! allow_hosts = ["wiki.corp.example"]
+//!
+//! [environments.prod]
+//! allow_schemes = ["https"]
+//! ```
+//!
+//! ```rust,ignore
+//! let profile: PolicyProfile = toml::from_str(&std::fs::read_to_string("egress.toml")?)?;
+//! let policy = profile.build_from_env(PolicyProfile::ENV_VAR)?;
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::config::PolicyConfig;
+use crate::policy_builder::CustomPolicy;
+
+/// Named environments sharing a common set of rules.
+///
+/// The policy for an environment is its [`PolicyConfig`] with every rule
+/// list of [`shared`](Self::shared) prepended. The base policy comes from
+/// the environment alone, so `shared` must leave `base` at its default.
+///
+/// With the `serde` feature, a profile is written as a `shared` table and an
+/// `environments` table of [`PolicyConfig`]s:
+///
+/// ```toml
+/// [shared]
+/// block_hosts = ["*.corp.example"]
+///
+/// [environments.dev]
+/// base = { block_loopback = false }
+///
+/// [environments.prod]
+/// allow_schemes = ["https"]
+/// ```
+///
+/// # Example
+///
+/// ```rust
+/// use url_jail::{BasePolicy, PolicyConfig, PolicyProfile};
+///
+/// let mut profile = PolicyProfile {
+///     shared: PolicyConfig {
+///         block_hosts: vec!["*.corp.example".into()],
+///         ..Default::default()
+///     },
+///     ..Default::default()
+/// };
+/// profile.environments.insert(
+///     "dev".into(),
+///     PolicyConfig {
+///         base: BasePolicy::builder().block_loopback(false).build(),
+///         ..Default::default()
+///     },
+/// );
+/// profile.environments.insert("prod".into(), PolicyConfig::default());
+/// profile.check().unwrap();
+///
+/// let dev = profile.build("dev").unwrap();
+/// assert!(dev.is_ip_allowed("127.0.0.1".parse().unwrap()).is_ok());
+/// assert!(dev.is_hostname_allowed("db.corp.example").is_err());
+///
+/// let prod = profile.build("prod").unwrap();
+/// assert!(prod.is_ip_allowed("127.0.0.1".parse().unwrap()).is_err());
+/// assert!(profile.build("qa").is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct PolicyProfile {
+    /// Rules every environment includes.
+    pub shared: PolicyConfig,
+
+    /// Each environment's base policy and additional rules, by name.
+    pub environments: BTreeMap<String, PolicyConfig>,
+}
+
+impl PolicyProfile {
+    /// The environment variable conventionally naming the environment.
+    pub const ENV_VAR: &'static str = "URL_JAIL_ENV";
+
+    /// The merged config for `environment`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a reason if the environment is unknown or `shared` sets a
+    /// base policy.
+    pub fn config(&self, environment: &str) -> Result<PolicyConfig, String> {
+        if self.shared.base != PolicyConfig::default().base {
+            return Err("shared: base must be set per environment".to_string());
+        }
+        let Some(env) = self.environments.get(environment) else {
+            let known: Vec<&str> = self.environments.keys().map(String::as_str).collect();
+            return Err(format!(
+                "unknown environment {:?} (expected one of: {})",
+                environment,
+                known.join(", ")
+            ));
+        };
+        let shared = &self.shared;
+        Ok(PolicyConfig {
+            base: env.base,
+            block_cidrs: [&shared.block_cidrs[..], &env.block_cidrs].concat(),
+            allow_cidrs: [&shared.allow_cidrs[..], &env.allow_cidrs].concat(),
+            block_hosts: [&shared.block_hosts[..], &env.block_hosts].concat(),
+            allow_hosts: [&shared.allow_hosts[..], &env.allow_hosts].concat(),
+            allow_ports: [&shared.allow_ports[..], &env.allow_ports].concat(),
+            block_ports: [&shared.block_ports[..], &env.block_ports].concat(),
+            allow_schemes: [&shared.allow_schemes[..], &env.allow_schemes].concat(),
+            allow_url_prefixes: [&shared.allow_url_prefixes[..], &env.allow_url_prefixes].concat(),
+            block_paths: [&shared.block_paths[..], &env.block_paths].concat(),
+            allow_sites: [&shared.allow_sites[..], &env.allow_sites].concat(),
+        })
+    }
+
+    /// Build the policy for `environment`.
+    ///
+    /// # Errors
+    ///
+    /// As [`config`](Self::config), or the first invalid rule, prefixed
+    /// with where it is defined (`environments.dev: block_cidrs[0]: ...`).
+    pub fn build(&self, environment: &str) -> Result<CustomPolicy, String> {
+        let config = self.config(environment)?;
+        // Report invalid rules at their own index, not the merged one
+        self.shared.build().map_err(|e| format!("shared: {}", e))?;
+        self.environments[environment]
+            .build()
+            .map_err(|e| format!("environments.{}: {}", environment, e))?;
+        config.build()
+    }
+
+    /// Build the policy for the environment named by the variable `var`.
+    ///
+    /// Usually called once at startup with [`ENV_VAR`](Self::ENV_VAR).
+    ///
+    /// # Errors
+    ///
+    /// Returns a reason if `var` is unset or not valid Unicode, or as
+    /// [`build`](Self::build).
+    pub fn build_from_env(&self, var: &str) -> Result<CustomPolicy, String> {
+        let environment =
+            std::env::var(var).map_err(|e| format!("environment variable {}: {}", var, e))?;
+        self.build(&environment)
+    }
+
+    /// Build every environment, so a mistake in one fails in CI rather than
+    /// when that environment is deployed.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from [`build`](Self::build).
+    pub fn check(&self) -> Result<(), String> {
+        self.environments
+            .keys()
+            .try_for_each(|environment| self.build(environment).map(drop))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Policy, RuleConfig};
+
+    fn profile() -> PolicyProfile {
+        PolicyProfile {
+            shared: PolicyConfig {
+                block_hosts: vec!["*.corp.example".into()],
+                block_ports: vec![6379],
+                ..Default::default()
+            },
+            environments: BTreeMap::from([
+                (
+                    "staging".to_string(),
+                    PolicyConfig {
+                        base: Policy::AllowPrivate.into(),
+                        allow_hosts: vec![RuleConfig::from("wiki.corp.example")],
+                        block_ports: vec![25],
+                        ..Default::default()
+                    },
+                ),
+                ("prod".to_string(), PolicyConfig::default()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_environments_extend_shared_rules() {
+        let profile = profile();
+        profile.check().unwrap();
+
+        let staging = profile.config("staging").unwrap();
+        assert_eq!(staging.block_ports, [6379, 25]);
+        let staging = profile.build("staging").unwrap();
+        assert!(staging.is_hostname_allowed("wiki.corp.example").is_ok());
+        assert!(staging.is_hostname_allowed("db.corp.example").is_err());
+        assert!(staging.is_ip_allowed("10.0.0.1".parse().unwrap()).is_ok());
+
+        let prod = profile.build("prod").unwrap();
+        assert!(prod.is_hostname_allowed("wiki.corp.example").is_err());
+        assert!(prod.is_host_allowed("api.example.com", 6379).is_err());
+        assert!(prod.is_ip_allowed("10.0.0.1".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_errors_name_their_source() {
+        let mut profile = profile();
+        let err = profile.build("qa").unwrap_err();
+        assert_eq!(
+            err,
+            "unknown environment \"qa\" (expected one of: prod, staging)"
+        );
+
+        profile
+            .environments
+            .get_mut("staging")
+            .unwrap()
+            .block_cidrs
+            .push("10.0.0/8".into());
+        let err = profile.check().unwrap_err();
+        assert!(
+            err.starts_with("environments.staging: block_cidrs[0]: invalid CIDR"),
+            "{}",
+            err
+        );
+        assert!(profile.build("prod").is_ok());
+
+        profile.shared.base = Policy::AllowPrivate.into();
+        let err = profile.build("prod").unwrap_err();
+        assert_eq!(err, "shared: base must be set per environment");
+    }
+
+    #[test]
+    fn test_build_from_env() {
+        let var = "URL_JAIL_TEST_PROFILE_ENV";
+        assert!(profile().build_from_env(var).is_err());
+        std::env::set_var(var, "staging");
+        let policy = profile().build_from_env(var).unwrap();
+        assert!(policy.is_hostname_allowed("wiki.corp.example").is_ok());
+        std::env::remove_var(var);
+    }
+}
@@ -12,9 +12,11 @@ pyo3::create_exception!(url_jail, UrlJailError, PyException);
 // SSRF-related exceptions
 pyo3::create_exception!(url_jail, SsrfBlocked, UrlJailError);
 pyo3::create_exception!(url_jail, HostnameBlocked, UrlJailError);
+pyo3::create_exception!(url_jail, SchemeBlocked, UrlJailError);
 
 // URL/input errors
 pyo3::create_exception!(url_jail, InvalidUrl, UrlJailError);
+pyo3::create_exception!(url_jail, AmbiguousUrl, InvalidUrl);
 pyo3::create_exception!(url_jail, DnsError, UrlJailError);
 
 // Timeout error
@@ -24,6 +26,7 @@ pyo3::create_exception!(url_jail, Timeout, UrlJailError);
 pyo3::create_exception!(url_jail, RedirectBlocked, UrlJailError);
 pyo3::create_exception!(url_jail, TooManyRedirects, UrlJailError);
 pyo3::create_exception!(url_jail, HttpError, UrlJailError);
+pyo3::create_exception!(url_jail, ResponseBlocked, UrlJailError);
 
 /// Policy enum for Python.
 #[pyclass(name = "Policy", eq, eq_int)]
@@ -33,6 +36,8 @@ pub enum PyPolicy {
     PublicOnly,
     #[pyo3(name = "ALLOW_PRIVATE")]
     AllowPrivate,
+    #[pyo3(name = "BLOCK_ALL")]
+    BlockAll,
 }
 
 impl From<PyPolicy> for RustPolicy {
@@ -40,6 +45,7 @@ impl From<PyPolicy> for RustPolicy {
         match p {
             PyPolicy::PublicOnly => RustPolicy::PublicOnly,
             PyPolicy::AllowPrivate => RustPolicy::AllowPrivate,
+            PyPolicy::BlockAll => RustPolicy::BlockAll,
         }
     }
 }
@@ -51,6 +57,8 @@ pub struct PyValidated {
     #[pyo3(get)]
     pub ip: String,
     #[pyo3(get)]
+    pub ips: Vec<String>,
+    #[pyo3(get)]
     pub host: String,
     #[pyo3(get)]
     pub port: u16,
@@ -64,6 +72,7 @@ impl From<RustValidated> for PyValidated {
     fn from(v: RustValidated) -> Self {
         Self {
             ip: v.ip.to_string(),
+            ips: v.ips.iter().map(|ip| ip.to_string()).collect(),
             host: v.host,
             port: v.port,
             url: v.url,
@@ -92,7 +101,7 @@ impl PyPolicyBuilder {
     #[new]
     fn new(base: PyPolicy) -> Self {
         Self {
-            inner: crate::PolicyBuilder::new(base.into()),
+            inner: crate::PolicyBuilder::new(RustPolicy::from(base)),
         }
     }
 
@@ -124,6 +133,41 @@ impl PyPolicyBuilder {
         }
     }
 
+    /// Only allow these ports.
+    fn allow_ports(&self, ports: Vec<u16>) -> Self {
+        Self {
+            inner: self.inner.clone().allow_ports(ports),
+        }
+    }
+
+    /// Only allow URLs under this prefix.
+    fn allow_url_prefix(&self, prefix: &str) -> Self {
+        Self {
+            inner: self.inner.clone().allow_url_prefix(prefix),
+        }
+    }
+
+    /// Block URL paths matching a glob.
+    fn block_path_glob(&self, glob: &str) -> Self {
+        Self {
+            inner: self.inner.clone().block_path_glob(glob),
+        }
+    }
+
+    /// Block these ports.
+    fn block_ports(&self, ports: Vec<u16>) -> Self {
+        Self {
+            inner: self.inner.clone().block_ports(ports),
+        }
+    }
+
+    /// Only allow these URL schemes.
+    fn allow_schemes(&self, schemes: Vec<String>) -> Self {
+        Self {
+            inner: self.inner.clone().allow_schemes(schemes),
+        }
+    }
+
     /// Build the custom policy.
     fn build(&self) -> PyCustomPolicy {
         PyCustomPolicy {
@@ -144,9 +188,19 @@ fn to_py_err(e: Error) -> PyErr {
             // Use dedicated HostnameBlocked exception
             HostnameBlocked::new_err(format!("{} ({}) - {}", url, host, reason))
         }
+        Error::SchemeBlocked {
+            url,
+            scheme,
+            reason,
+        } => SchemeBlocked::new_err(format!("{} ({}) - {}", url, scheme, reason)),
         Error::InvalidUrl { url, reason } => InvalidUrl::new_err(format!("{} - {}", url, reason)),
+        Error::AmbiguousUrl { url, reason } => {
+            AmbiguousUrl::new_err(format!("{} - {}", url, reason))
+        }
         Error::DnsError { host, message } => DnsError::new_err(format!("{} - {}", host, message)),
         Error::Timeout { message } => Timeout::new_err(message),
+        // Not produced by the Python API, which never collects violations
+        Error::Violations { mut errors, .. } => to_py_err(errors.remove(0)),
         #[cfg(feature = "fetch")]
         Error::RedirectBlocked {
             original_url,
@@ -161,6 +215,10 @@ fn to_py_err(e: Error) -> PyErr {
             TooManyRedirects::new_err(format!("{} - max {} redirects", url, max))
         }
         #[cfg(feature = "fetch")]
+        Error::ResponseBlocked { url, reason } => {
+            ResponseBlocked::new_err(format!("{} - {}", url, reason))
+        }
+        #[cfg(feature = "fetch")]
         Error::HttpError { url, message } => HttpError::new_err(format!("{} - {}", url, message)),
     }
 }
@@ -169,7 +227,7 @@ fn to_py_err(e: Error) -> PyErr {
 #[pyfunction]
 #[pyo3(name = "validate_sync")]
 fn py_validate_sync(url: &str, policy: PyPolicy) -> PyResult<PyValidated> {
-    let result = crate::validate_sync(url, policy.into()).map_err(to_py_err)?;
+    let result = crate::validate_sync(url, RustPolicy::from(policy)).map_err(to_py_err)?;
     Ok(result.into())
 }
 
@@ -178,7 +236,7 @@ fn py_validate_sync(url: &str, policy: PyPolicy) -> PyResult<PyValidated> {
 #[pyo3(name = "validate")]
 fn py_validate<'py>(py: Python<'py>, url: String, policy: PyPolicy) -> PyResult<Bound<'py, PyAny>> {
     pyo3_async_runtimes::tokio::future_into_py(py, async move {
-        let result = crate::validate(&url, policy.into())
+        let result = crate::validate(&url, RustPolicy::from(policy))
             .await
             .map_err(to_py_err)?;
         Ok(PyValidated::from(result))
@@ -189,31 +247,31 @@ fn py_validate<'py>(py: Python<'py>, url: String, policy: PyPolicy) -> PyResult<
 #[pyfunction]
 #[pyo3(name = "validate_custom_sync")]
 fn py_validate_custom_sync(url: &str, policy: &PyCustomPolicy) -> PyResult<PyValidated> {
-    // Need to run async in sync context
-    let policy_clone = policy.inner.clone();
-    let result = if let Ok(handle) = tokio::runtime::Handle::try_current() {
-        tokio::task::block_in_place(|| handle.block_on(crate::validate_custom(url, &policy_clone)))
-    } else {
-        let rt =
-            tokio::runtime::Runtime::new().map_err(|e| UrlJailError::new_err(e.to_string()))?;
-        rt.block_on(crate::validate_custom(url, &policy_clone))
-    };
-    result.map(PyValidated::from).map_err(to_py_err)
+    crate::validate_custom_sync(url, &policy.inner)
+        .map(PyValidated::from)
+        .map_err(to_py_err)
 }
 
 /// Fetch a URL and return the response body as a string.
 /// This is the recommended way to safely fetch user-provided URLs.
 #[cfg(feature = "fetch")]
 #[pyfunction]
-#[pyo3(name = "get", signature = (url, policy = None))]
+#[pyo3(name = "get", signature = (url, policy = None, max_redirects = None))]
 fn py_get<'py>(
     py: Python<'py>,
     url: String,
     policy: Option<PyPolicy>,
+    max_redirects: Option<u8>,
 ) -> PyResult<Bound<'py, PyAny>> {
     let policy = policy.unwrap_or(PyPolicy::PublicOnly);
+    let options = crate::FetchOptions {
+        max_redirects,
+        ..Default::default()
+    };
     pyo3_async_runtimes::tokio::future_into_py(py, async move {
-        let result = crate::fetch(&url, policy.into()).await.map_err(to_py_err)?;
+        let result = crate::fetch_with_options(&url, policy.into(), options)
+            .await
+            .map_err(to_py_err)?;
         let body = result
             .response
             .text()
@@ -226,21 +284,31 @@ fn py_get<'py>(
 /// Synchronous version of get().
 #[cfg(feature = "fetch")]
 #[pyfunction]
-#[pyo3(name = "get_sync", signature = (url, policy = None))]
-fn py_get_sync(url: &str, policy: Option<PyPolicy>) -> PyResult<String> {
+#[pyo3(name = "get_sync", signature = (url, policy = None, max_redirects = None))]
+fn py_get_sync(url: &str, policy: Option<PyPolicy>, max_redirects: Option<u8>) -> PyResult<String> {
     let policy = policy.unwrap_or(PyPolicy::PublicOnly).into();
-    let result = crate::fetch_sync(url, policy).map_err(to_py_err)?;
+    let options = crate::FetchOptions {
+        max_redirects,
+        ..Default::default()
+    };
+    let fetch = async {
+        let result = crate::fetch_with_options(url, policy, options)
+            .await
+            .map_err(to_py_err)?;
+        result
+            .response
+            .text()
+            .await
+            .map_err(|e| UrlJailError::new_err(e.to_string()))
+    };
 
-    // Use tokio to read the body synchronously
-    let body = if let Ok(handle) = tokio::runtime::Handle::try_current() {
-        tokio::task::block_in_place(|| handle.block_on(async { result.response.text().await }))
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        tokio::task::block_in_place(|| handle.block_on(fetch))
     } else {
         let rt =
             tokio::runtime::Runtime::new().map_err(|e| UrlJailError::new_err(e.to_string()))?;
-        rt.block_on(async { result.response.text().await })
-    };
-
-    body.map_err(|e| UrlJailError::new_err(e.to_string()))
+        rt.block_on(fetch)
+    }
 }
 
 /// Register all Python bindings.
@@ -265,9 +333,11 @@ pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
     // SSRF exceptions
     m.add("SsrfBlocked", m.py().get_type::<SsrfBlocked>())?;
     m.add("HostnameBlocked", m.py().get_type::<HostnameBlocked>())?;
+    m.add("SchemeBlocked", m.py().get_type::<SchemeBlocked>())?;
 
     // Input/validation errors
     m.add("InvalidUrl", m.py().get_type::<InvalidUrl>())?;
+    m.add("AmbiguousUrl", m.py().get_type::<AmbiguousUrl>())?;
     m.add("DnsError", m.py().get_type::<DnsError>())?;
 
     // Timeout
@@ -277,6 +347,7 @@ pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add("RedirectBlocked", m.py().get_type::<RedirectBlocked>())?;
     m.add("TooManyRedirects", m.py().get_type::<TooManyRedirects>())?;
     m.add("HttpError", m.py().get_type::<HttpError>())?;
+    m.add("ResponseBlocked", m.py().get_type::<ResponseBlocked>())?;
 
     Ok(())
 }
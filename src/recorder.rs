@@ -0,0 +1,503 @@
+//! Recording and replay of validation decisions for forensics.
+//!
+//! A [`Recorder`] attached through
+//! [`ValidateOptions::recorder`](crate::ValidateOptions::recorder) captures
+//! every decision that needed a DNS answer: the URL, the addresses the
+//! hostname resolved to, their TTL, when it happened, and whether the URL was
+//! allowed. [`replay`] re-runs those decisions against another policy using
+//! the recorded answers instead of live DNS, so incident responders can ask
+//! "would the new policy have blocked last week's traffic?" and get the
+//! answer the new policy would have given at the time.
+//!
+//! Recordings serialize to a compact line-based log with
+//! [`Recorder::to_log`] and load back with [`parse_log`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::net::IpAddr;
+//! use std::time::SystemTime;
+//! use url_jail::{replay, Decision, Policy, Resolution};
+//!
+//! let last_week = vec![Resolution {
+//!     url: "http://build.corp.example/".into(),
+//!     host: "build.corp.example".into(),
+//!     answers: vec!["10.1.2.3".parse::<IpAddr>().unwrap()],
+//!     ttl: None,
+//!     timestamp: SystemTime::UNIX_EPOCH,
+//!     decision: Decision::Allowed { ip: "10.1.2.3".parse().unwrap() },
+//! }];
+//!
+//! let outcomes = replay(&last_week, Policy::PublicOnly);
+//! assert!(outcomes[0].newly_blocked());
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use crate::clock::{Clock, SystemClock};
+use crate::error::Error;
+use crate::machine::{Step, ValidationMachine};
+use crate::policy::Policy;
+use crate::policy_builder::CustomPolicy;
+use crate::validate::Validated;
+
+const DEFAULT_MAX_RECORDS: usize = 10_000;
+
+/// The outcome of a recorded validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// The URL was allowed; `ip` is the address it was pinned to.
+    Allowed {
+        /// The validated address.
+        ip: IpAddr,
+    },
+
+    /// The URL was rejected.
+    Blocked {
+        /// The error message.
+        reason: String,
+    },
+}
+
+impl Decision {
+    /// Returns `true` for [`Decision::Allowed`].
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Decision::Allowed { .. })
+    }
+
+    pub(crate) fn from_result(result: &Result<Validated, Error>) -> Self {
+        match result {
+            Ok(validated) => Decision::Allowed { ip: validated.ip },
+            Err(e) => Decision::Blocked {
+                reason: e.to_string(),
+            },
+        }
+    }
+}
+
+/// One recorded validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolution {
+    /// The URL as passed to validation.
+    pub url: String,
+
+    /// The normalized hostname that was resolved.
+    pub host: String,
+
+    /// Every address the hostname resolved to.
+    pub answers: Vec<IpAddr>,
+
+    /// TTL of the DNS answer, or `None` if it came from the cache or a
+    /// [`DnsFallback`](crate::DnsFallback).
+    pub ttl: Option<Duration>,
+
+    /// When the decision was made.
+    pub timestamp: SystemTime,
+
+    /// What validation decided.
+    pub decision: Decision,
+}
+
+/// Opt-in recorder of validation decisions.
+///
+/// Keeps the most recent records in memory, up to a fixed capacity; export
+/// them with [`to_log`](Self::to_log) before they are evicted.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::sync::Arc;
+/// use url_jail::{validate_with_options, Policy, Recorder, ValidateOptions};
+///
+/// # async fn example() -> Result<(), url_jail::Error> {
+/// let recorder = Arc::new(Recorder::new());
+/// let opts = ValidateOptions {
+///     recorder: Some(recorder.clone()),
+///     ..Default::default()
+/// };
+/// validate_with_options("https://example.com/", Policy::PublicOnly, opts).await?;
+/// std::fs::write("resolutions.log", recorder.to_log()).unwrap();
+/// # Ok(())
+/// # }
+/// ```
+pub struct Recorder {
+    max_records: usize,
+    records: Mutex<VecDeque<Resolution>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Recorder {
+    /// Create a recorder keeping the latest 10,000 records.
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_MAX_RECORDS)
+    }
+
+    /// Create a recorder keeping the latest `max_records` records.
+    pub fn with_capacity(max_records: usize) -> Self {
+        Self {
+            max_records,
+            records: Mutex::new(VecDeque::new()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Use `clock` for record timestamps instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// A snapshot of the records, oldest first.
+    pub fn records(&self) -> Vec<Resolution> {
+        self.lock().iter().cloned().collect()
+    }
+
+    /// Number of records held.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if nothing has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Remove all records.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Serialize the records as a log, one line per record.
+    ///
+    /// Each line holds tab-separated fields: timestamp (Unix milliseconds),
+    /// TTL in seconds (`-` if unknown), URL, host, comma-separated answers,
+    /// and the decision (`allow <ip>` or `block <reason>`).
+    pub fn to_log(&self) -> String {
+        self.lock()
+            .iter()
+            .map(|record| format!("{}\n", LogLine(record)))
+            .collect()
+    }
+
+    /// Record the decision for a DNS-backed validation.
+    pub(crate) fn record(
+        &self,
+        url: &str,
+        host: &str,
+        answers: Vec<IpAddr>,
+        ttl: Option<Duration>,
+        result: &Result<Validated, Error>,
+    ) {
+        let record = Resolution {
+            url: url.to_string(),
+            host: host.to_string(),
+            answers,
+            ttl,
+            timestamp: self.clock.now(),
+            decision: Decision::from_result(result),
+        };
+
+        let mut records = self.lock();
+        if self.max_records == 0 {
+            return;
+        }
+        if records.len() >= self.max_records {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<Resolution>> {
+        self.records.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("max_records", &self.max_records)
+            .field("records", &self.len())
+            .finish()
+    }
+}
+
+/// One record in log format.
+struct LogLine<'a>(&'a Resolution);
+
+impl fmt::Display for LogLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let record = self.0;
+        let millis = record
+            .timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        write!(f, "{}\t", millis)?;
+        match record.ttl {
+            Some(ttl) => write!(f, "{}\t", ttl.as_secs())?,
+            None => f.write_str("-\t")?,
+        }
+        write!(f, "{}\t{}\t", record.url, record.host)?;
+        for (i, ip) in record.answers.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", ip)?;
+        }
+        match &record.decision {
+            Decision::Allowed { ip } => write!(f, "\tallow {}", ip),
+            // The reason is the last field: only line breaks need escaping
+            Decision::Blocked { reason } => {
+                write!(f, "\tblock {}", reason.replace(['\r', '\n'], " "))
+            }
+        }
+    }
+}
+
+/// Parse a log produced by [`Recorder::to_log`].
+///
+/// Blank lines are skipped.
+///
+/// # Errors
+///
+/// Returns a description of the first malformed line, with its line number.
+pub fn parse_log(log: &str) -> Result<Vec<Resolution>, String> {
+    log.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_line(line).map_err(|e| format!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+fn parse_line(line: &str) -> Result<Resolution, String> {
+    let mut fields = line.splitn(6, '\t');
+    let mut field = |name: &str| fields.next().ok_or_else(|| format!("missing {}", name));
+
+    let millis: u64 = field("timestamp")?
+        .parse()
+        .map_err(|_| "invalid timestamp".to_string())?;
+    let ttl = match field("ttl")? {
+        "-" => None,
+        secs => Some(Duration::from_secs(
+            secs.parse().map_err(|_| "invalid ttl".to_string())?,
+        )),
+    };
+    let url = field("url")?.to_string();
+    let host = field("host")?.to_string();
+    let answers = field("answers")?
+        .split(',')
+        .map(|ip| ip.parse().map_err(|_| format!("invalid address {:?}", ip)))
+        .collect::<Result<Vec<IpAddr>, String>>()?;
+    let decision = match field("decision")?.split_once(' ') {
+        Some(("allow", ip)) => Decision::Allowed {
+            ip: ip
+                .parse()
+                .map_err(|_| format!("invalid address {:?}", ip))?,
+        },
+        Some(("block", reason)) => Decision::Blocked {
+            reason: reason.to_string(),
+        },
+        _ => return Err("invalid decision".to_string()),
+    };
+
+    Ok(Resolution {
+        url,
+        host,
+        answers,
+        ttl,
+        timestamp: SystemTime::UNIX_EPOCH + Duration::from_millis(millis),
+        decision,
+    })
+}
+
+/// A recorded decision re-run against another policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayOutcome {
+    /// The original record.
+    pub resolution: Resolution,
+
+    /// What the replayed policy decides for the same URL and answers.
+    pub decision: Decision,
+}
+
+impl ReplayOutcome {
+    /// Returns `true` if the replayed policy decides differently.
+    pub fn changed(&self) -> bool {
+        self.resolution.decision.is_allowed() != self.decision.is_allowed()
+    }
+
+    /// Returns `true` if traffic that was allowed would now be blocked.
+    pub fn newly_blocked(&self) -> bool {
+        self.resolution.decision.is_allowed() && !self.decision.is_allowed()
+    }
+
+    /// Returns `true` if traffic that was blocked would now be allowed.
+    pub fn newly_allowed(&self) -> bool {
+        !self.resolution.decision.is_allowed() && self.decision.is_allowed()
+    }
+}
+
+/// Re-run recorded decisions against a built-in policy.
+///
+/// Each URL is validated again with its recorded DNS answers; no lookups are
+/// performed.
+pub fn replay(records: &[Resolution], policy: Policy) -> Vec<ReplayOutcome> {
+    replay_with(records, |url| ValidationMachine::new(url, policy))
+}
+
+/// Re-run recorded decisions against a custom policy.
+///
+/// See [`replay`].
+pub fn replay_custom(records: &[Resolution], policy: &CustomPolicy) -> Vec<ReplayOutcome> {
+    replay_with(records, |url| ValidationMachine::custom(url, policy))
+}
+
+fn replay_with<'a>(
+    records: &[Resolution],
+    machine: impl Fn(&str) -> ValidationMachine<'a>,
+) -> Vec<ReplayOutcome> {
+    records
+        .iter()
+        .map(|record| {
+            let result = run_offline(machine(&record.url), &record.answers);
+            ReplayOutcome {
+                resolution: record.clone(),
+                decision: Decision::from_result(&result),
+            }
+        })
+        .collect()
+}
+
+/// Drive `machine` to completion, answering its DNS query with `answers`.
+pub(crate) fn run_offline(
+    machine: ValidationMachine<'_>,
+    answers: &[IpAddr],
+) -> Result<Validated, Error> {
+    match machine.start() {
+        Step::Done(result) => result,
+        Step::NeedDns(query) => match query.respond(Ok(answers.to_vec())) {
+            Step::Done(result) => result,
+            Step::NeedDns(_) => unreachable!("a DNS answer always finishes validation"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::policy_builder::PolicyBuilder;
+
+    fn resolution(url: &str, answers: &[&str], decision: Decision) -> Resolution {
+        Resolution {
+            url: url.to_string(),
+            host: url
+                .trim_start_matches("http://")
+                .trim_end_matches('/')
+                .to_string(),
+            answers: answers.iter().map(|ip| ip.parse().unwrap()).collect(),
+            ttl: Some(Duration::from_secs(300)),
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            decision,
+        }
+    }
+
+    fn allowed(ip: &str) -> Decision {
+        Decision::Allowed {
+            ip: ip.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_log_round_trip() {
+        let records = vec![
+            resolution(
+                "http://api.example.com/",
+                &["93.184.216.34"],
+                allowed("93.184.216.34"),
+            ),
+            Resolution {
+                ttl: None,
+                ..resolution(
+                    "http://rebind.example/",
+                    &["8.8.8.8", "127.0.0.1"],
+                    Decision::Blocked {
+                        reason: "SSRF blocked: rebind.example resolved to 127.0.0.1 - loopback"
+                            .into(),
+                    },
+                )
+            },
+        ];
+        let log: String = records
+            .iter()
+            .map(|r| format!("{}\n", LogLine(r)))
+            .collect();
+
+        assert_eq!(parse_log(&log).unwrap(), records);
+        assert!(log.starts_with("1700000000123\t300\thttp://api.example.com/\t"));
+    }
+
+    #[test]
+    fn test_parse_log_reports_line() {
+        let err = parse_log("\n1\t-\thttp://a/\ta\tnot-an-ip\tallow 8.8.8.8\n").unwrap_err();
+        assert!(err.starts_with("line 2:"), "{}", err);
+        assert!(parse_log("1\t-\thttp://a/\ta\t8.8.8.8\tmaybe\n").is_err());
+    }
+
+    #[test]
+    fn test_replay_against_stricter_policy() {
+        let records = vec![
+            resolution("http://build.corp/", &["10.0.0.5"], allowed("10.0.0.5")),
+            resolution(
+                "http://api.example.com/",
+                &["93.184.216.34"],
+                allowed("93.184.216.34"),
+            ),
+        ];
+
+        let outcomes = replay(&records, Policy::PublicOnly);
+        assert!(outcomes[0].newly_blocked());
+        assert!(!outcomes[1].changed());
+
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.example.com")
+            .build();
+        let outcomes = replay_custom(&records, &policy);
+        assert!(outcomes.iter().all(ReplayOutcome::newly_blocked));
+    }
+
+    #[test]
+    fn test_recorder_capacity_and_clock() {
+        let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+        let recorder = Recorder::with_capacity(2).with_clock(clock.clone());
+        let blocked: Result<Validated, Error> = Err(Error::dns_error("x", "unused"));
+
+        for host in ["a", "b", "c"] {
+            clock.advance(Duration::from_secs(1));
+            recorder.record(
+                &format!("http://{}/", host),
+                host,
+                vec!["8.8.8.8".parse().unwrap()],
+                None,
+                &blocked,
+            );
+        }
+
+        let records = recorder.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].host, "b");
+        assert_eq!(
+            records[1].timestamp,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(3)
+        );
+    }
+}
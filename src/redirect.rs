@@ -0,0 +1,130 @@
+//! Validation of redirect targets for manual redirect handling.
+//!
+//! [`fetch`](crate::fetch) validates every hop of a redirect chain itself.
+//! Callers that follow redirects by hand (a custom HTTP stack, a queue
+//! worker retrying later) use [`validate_redirect`] to resolve each
+//! `Location` header against the previous hop and validate the result.
+
+use crate::error::Error;
+use crate::policy::ValidationPolicy;
+use crate::validate::{validate_with_options, ValidateOptions, Validated};
+
+/// Resolve a `Location` header against `base` and validate the target.
+///
+/// Relative locations (`/next`, `../up`, `//other.example/`) are resolved
+/// against [`base.url`](Validated::url), and the result goes through the full
+/// validation pipeline again, including DNS resolution.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use url_jail::{validate, validate_redirect, Policy};
+///
+/// # async fn example() -> Result<(), url_jail::Error> {
+/// let first = validate("https://example.com/start", Policy::PublicOnly).await?;
+/// // ... send the request; the response is a 302 with `Location: /next` ...
+/// let next = validate_redirect(&first, "/next", Policy::PublicOnly).await?;
+/// assert_eq!(next.url, "https://example.com/next");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidUrl`] if the location cannot be resolved or
+/// contains a backslash, plus any error from [`validate`](crate::validate)
+/// for the resolved URL.
+pub async fn validate_redirect<P: ValidationPolicy>(
+    base: &Validated,
+    location: &str,
+    policy: P,
+) -> Result<Validated, Error> {
+    validate_redirect_with_options(base, location, policy, ValidateOptions::default()).await
+}
+
+/// Resolve a `Location` header against `base` and validate the target with
+/// `options`.
+///
+/// See [`validate_redirect`].
+pub async fn validate_redirect_with_options<P: ValidationPolicy>(
+    base: &Validated,
+    location: &str,
+    policy: P,
+    options: ValidateOptions,
+) -> Result<Validated, Error> {
+    let url = resolve_redirect_url(&base.url, location)?;
+    validate_with_options(&url, policy, options).await
+}
+
+/// Resolve a redirect URL (which may be relative) against the base URL.
+///
+/// # Security
+///
+/// This function rejects redirect locations containing backslashes because
+/// `url::Url::join()` treats `\` like `/`, which can cause `\\evil.com` to
+/// change the host (protocol-relative interpretation). This is a known
+/// attack vector for redirect-based SSRF.
+pub(crate) fn resolve_redirect_url(base: &str, location: &str) -> Result<String, Error> {
+    // SECURITY: Reject backslashes to prevent host override attacks.
+    // The URL crate treats `\` as `/`, so `\\evil.com` becomes `//evil.com`
+    // which is protocol-relative and changes the host.
+    if location.contains('\\') {
+        return Err(Error::InvalidUrl {
+            url: location.to_string(),
+            reason: "redirect location contains backslash (potential host override)".to_string(),
+        });
+    }
+
+    let base_url = url::Url::parse(base).map_err(|e| Error::InvalidUrl {
+        url: base.to_string(),
+        reason: e.to_string(),
+    })?;
+
+    let resolved = base_url.join(location).map_err(|e| Error::InvalidUrl {
+        url: location.to_string(),
+        reason: e.to_string(),
+    })?;
+
+    Ok(resolved.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{validate, Policy};
+
+    #[tokio::test]
+    async fn test_relative_location_resolved_and_validated() {
+        let base = validate("http://93.184.216.34/a/b?x=1", Policy::PublicOnly)
+            .await
+            .unwrap();
+        let next = validate_redirect(&base, "c?y=2", Policy::PublicOnly)
+            .await
+            .unwrap();
+        assert_eq!(next.url, "http://93.184.216.34/a/c?y=2");
+        assert_eq!(next.ip, base.ip);
+
+        let err = validate_redirect(&base, "//127.0.0.1/admin", Policy::PublicOnly)
+            .await
+            .unwrap_err();
+        assert!(err.is_blocked(), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_unsafe_location_rejected() {
+        let base = validate("https://93.184.216.34/", Policy::PublicOnly)
+            .await
+            .unwrap();
+        for location in ["\\\\127.0.0.1\\x", "file:///etc/passwd"] {
+            let err = validate_redirect(&base, location, Policy::PublicOnly)
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(err, Error::InvalidUrl { .. }),
+                "{}: {:?}",
+                location,
+                err
+            );
+        }
+    }
+}
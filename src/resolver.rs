@@ -0,0 +1,792 @@
+//! DNS resolver construction and startup warm-up.
+//!
+//! The system resolver configuration (`/etc/resolv.conf` on Unix, the registry
+//! on Windows) is read once and shared by every validation. Resolver instances
+//! themselves are cheap and are still created per lookup, so they are always
+//! bound to the runtime that drives them (important for [`validate_sync`](crate::validate_sync),
+//! which may run on a temporary runtime).
+//!
+//! Call [`init`] at service startup to load the configuration eagerly and
+//! verify that DNS actually works, instead of discovering a broken resolver on
+//! the first user request.
+//!
+//! Every resolver query issued by the crate first takes a permit from a
+//! process-wide limit (see [`set_max_concurrent_lookups`]), so a burst of
+//! validations cannot exhaust file descriptors or flood the local resolver.
+//!
+//! To query specific nameservers instead of the system ones, such as a
+//! CoreDNS sidecar on a non-standard port, call [`set_nameservers`]. Search
+//! domains and resolver options still come from the system configuration.
+//! With the `dns-over-tls` feature, `set_tls_nameservers` queries them over
+//! TLS instead, and `set_https_nameservers` over HTTPS, for networks that
+//! block plaintext DNS.
+//!
+//! [`set_nameservers`] applies to the whole process. To give one validation
+//! its own nameservers, query timeout or attempts, such as one tenant's DNS
+//! in a multi-tenant service, pass a [`NameserverGroup`](crate::NameserverGroup)
+//! as [`ValidateOptions::resolver`](crate::ValidateOptions::resolver) instead.
+//!
+//! To resolve through something other than the system resolver, such as a
+//! split-horizon DNS client shared with production traffic, implement
+//! [`Resolve`] and pass it to [`validate_with_resolver`](crate::validate_with_resolver)
+//! or [`ValidateOptions::resolver`](crate::ValidateOptions::resolver).
+
+use std::fmt;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use hickory_resolver::config::{NameServerConfig, ResolverConfig, ResolverOpts};
+use hickory_resolver::name_server::TokioConnectionProvider;
+use hickory_resolver::proto::xfer::Protocol;
+use hickory_resolver::TokioResolver;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::error::Error;
+
+/// Hostname resolved by [`init`] to prove the resolver is reachable.
+const DEFAULT_CANARY: &str = "example.com";
+
+/// Default bound on concurrent resolver queries.
+const DEFAULT_MAX_CONCURRENT_LOOKUPS: usize = 256;
+
+/// System resolver configuration, loaded on first use.
+static SYSTEM_CONFIG: OnceLock<(ResolverConfig, ResolverOpts)> = OnceLock::new();
+
+/// Process-wide limit on concurrent resolver queries.
+static LOOKUP_LIMIT: LookupLimit = LookupLimit::new();
+
+/// Nameservers set by [`set_nameservers`], replacing the system ones.
+static NAMESERVERS: RwLock<Option<Upstream>> = RwLock::new(None);
+
+/// Port used for nameservers given without one.
+pub(crate) const DNS_PORT: u16 = 53;
+
+/// Port used for DNS-over-TLS nameservers given without one.
+#[cfg(feature = "dns-over-tls")]
+pub(crate) const DOT_PORT: u16 = 853;
+
+/// Port used for DNS-over-HTTPS nameservers given without one.
+#[cfg(feature = "dns-over-tls")]
+pub(crate) const DOH_PORT: u16 = 443;
+
+/// Nameservers replacing the system ones, and how to reach them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Upstream {
+    pub(crate) addrs: Vec<SocketAddr>,
+    pub(crate) transport: Transport,
+    /// Per-query timeout, replacing the system `options timeout:`.
+    pub(crate) timeout: Option<Duration>,
+    /// Queries per nameserver, replacing the system `options attempts:`.
+    pub(crate) attempts: Option<usize>,
+}
+
+/// How queries reach the [`Upstream`] nameservers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) enum Transport {
+    /// Plain DNS over UDP and TCP.
+    #[default]
+    Plain,
+    /// DNS-over-TLS, verifying certificates against the server name.
+    #[cfg(feature = "dns-over-tls")]
+    Tls(String),
+    /// DNS-over-HTTPS at `/dns-query`, verifying certificates against the
+    /// server name.
+    #[cfg(feature = "dns-over-tls")]
+    Https(String),
+}
+
+/// Options for [`init_with_options`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use url_jail::{init_with_options, InitOptions};
+/// use std::time::Duration;
+///
+/// # async fn example() -> Result<(), url_jail::Error> {
+/// let opts = InitOptions {
+///     canary: "dns-canary.example.net".to_string(),
+///     timeout: Duration::from_secs(2),
+/// };
+/// let readiness = init_with_options(opts).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct InitOptions {
+    /// Hostname to resolve as a health check.
+    ///
+    /// Default: `example.com`.
+    pub canary: String,
+
+    /// Maximum time to wait for the canary lookup.
+    ///
+    /// Default: 10 seconds.
+    pub timeout: Duration,
+}
+
+impl Default for InitOptions {
+    fn default() -> Self {
+        Self {
+            canary: DEFAULT_CANARY.to_string(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Resolver readiness information returned by [`init`].
+#[derive(Debug, Clone)]
+pub struct Readiness {
+    /// Nameservers the resolver will query.
+    pub nameservers: Vec<SocketAddr>,
+
+    /// The canary hostname that was resolved.
+    pub canary: String,
+
+    /// Addresses the canary resolved to.
+    pub canary_ips: Vec<IpAddr>,
+
+    /// Time taken by the canary lookup.
+    pub elapsed: Duration,
+}
+
+/// Load the shared resolver configuration and verify DNS is working.
+///
+/// Resolves a canary hostname (`example.com`) and reports the result. The
+/// canary is only resolved, never checked against a policy.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # async fn example() -> Result<(), url_jail::Error> {
+/// let readiness = url_jail::init().await?;
+/// println!("DNS ready via {:?} in {:?}", readiness.nameservers, readiness.elapsed);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::DnsError`] if the system configuration cannot be read or
+/// the canary does not resolve, and [`Error::Timeout`] if the lookup exceeds
+/// the timeout.
+pub async fn init() -> Result<Readiness, Error> {
+    init_with_options(InitOptions::default()).await
+}
+
+/// Load the shared resolver configuration and verify DNS with custom options.
+///
+/// See [`init`] for details.
+pub async fn init_with_options(options: InitOptions) -> Result<Readiness, Error> {
+    let resolver = build_resolver(&options.canary)?;
+    let nameservers = resolver
+        .config()
+        .name_servers()
+        .iter()
+        .map(|ns| ns.socket_addr)
+        .collect();
+
+    let start = Instant::now();
+    let lookup = async {
+        let _permit = lookup_permit().await;
+        resolver.lookup_ip(&options.canary).await
+    };
+    let response = tokio::time::timeout(options.timeout, lookup)
+        .await
+        .map_err(|_| Error::Timeout {
+            message: format!(
+                "DNS canary lookup for {} timed out after {:?}",
+                options.canary, options.timeout
+            ),
+        })?
+        .map_err(|e| Error::dns_error(&options.canary, e.to_string()))?;
+
+    let canary_ips: Vec<IpAddr> = response.iter().collect();
+    if canary_ips.is_empty() {
+        return Err(Error::dns_error(&options.canary, "no IP addresses found"));
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!(canary = %options.canary, ips = ?canary_ips, "resolver ready");
+
+    Ok(Readiness {
+        nameservers,
+        canary: options.canary,
+        canary_ips,
+        elapsed: start.elapsed(),
+    })
+}
+
+/// Set the maximum number of resolver queries the crate runs at once.
+///
+/// Queries beyond the limit wait for a slot; the wait counts against the
+/// lookup's own timeout (such as
+/// [`ValidateOptions::dns_timeout`](crate::ValidateOptions::dns_timeout)).
+/// The limit applies to the whole process. Lookups already in flight finish
+/// under the previous limit. Default: 256; values are clamped to at least 1.
+///
+/// # Example
+///
+/// ```rust
+/// url_jail::set_max_concurrent_lookups(64);
+/// assert_eq!(url_jail::max_concurrent_lookups(), 64);
+/// ```
+pub fn set_max_concurrent_lookups(max: usize) {
+    LOOKUP_LIMIT.set(max);
+}
+
+/// The current limit on concurrent resolver queries.
+///
+/// See [`set_max_concurrent_lookups`].
+pub fn max_concurrent_lookups() -> usize {
+    LOOKUP_LIMIT.max()
+}
+
+/// Query `nameservers` instead of the ones in the system configuration.
+///
+/// Each nameserver is an IP address with an optional port: `10.0.0.53`,
+/// `10.0.0.53:5353`, `fd00::53` or `[fd00::53]:5353`. The port defaults to
+/// 53. Hostnames are rejected, since resolving the resolver would need DNS.
+/// Each one is queried over UDP and TCP, like system nameservers.
+///
+/// The setting applies to the whole process and to lookups started after the
+/// call. An empty list restores the system nameservers. Call [`init`]
+/// afterwards to check the new nameservers answer. Query timeout and attempts
+/// stay those of the system configuration; for per-validation nameservers,
+/// timeout or attempts, pass a [`NameserverGroup`](crate::NameserverGroup) as
+/// [`ValidateOptions::resolver`](crate::ValidateOptions::resolver).
+///
+/// # Example
+///
+/// ```rust
+/// // A CoreDNS sidecar listening on a non-standard port
+/// url_jail::set_nameservers(["[fd00::53]:5353", "10.0.0.53"]).unwrap();
+/// assert_eq!(
+///     url_jail::nameservers().unwrap(),
+///     ["[fd00::53]:5353".parse().unwrap(), "10.0.0.53:53".parse().unwrap()]
+/// );
+///
+/// assert!(url_jail::set_nameservers(["dns.internal:53"]).is_err());
+///
+/// url_jail::set_nameservers(Vec::<String>::new()).unwrap();
+/// assert_eq!(url_jail::nameservers(), None);
+/// ```
+///
+/// # Errors
+///
+/// Returns a reason naming the first invalid nameserver. The setting is
+/// unchanged on error.
+pub fn set_nameservers<I>(nameservers: I) -> Result<(), String>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let addrs = parse_nameservers(nameservers, DNS_PORT)?;
+    set_upstream(addrs, Transport::Plain);
+    Ok(())
+}
+
+/// Query `nameservers` over DNS-over-TLS instead of the system ones.
+///
+/// Like [`set_nameservers`], but the port defaults to 853 and each server's
+/// certificate must be valid for `server_name` under the Mozilla root store
+/// (`webpki-roots`). Nothing falls back to plaintext DNS.
+///
+/// # Example
+///
+/// ```rust
+/// url_jail::set_tls_nameservers(["1.1.1.1", "[2606:4700:4700::1111]"], "cloudflare-dns.com")
+///     .unwrap();
+/// assert_eq!(url_jail::nameservers().unwrap()[0], "1.1.1.1:853".parse().unwrap());
+/// ```
+///
+/// # Errors
+///
+/// As [`set_nameservers`], or if `server_name` is empty.
+#[cfg(feature = "dns-over-tls")]
+pub fn set_tls_nameservers<I>(nameservers: I, server_name: &str) -> Result<(), String>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let server_name = tls_server_name(server_name)?;
+    let addrs = parse_nameservers(nameservers, DOT_PORT)?;
+    set_upstream(addrs, Transport::Tls(server_name));
+    Ok(())
+}
+
+/// Query `nameservers` over DNS-over-HTTPS instead of the system ones.
+///
+/// Like [`set_tls_nameservers`], but the port defaults to 443 and queries are
+/// HTTP/2 requests to `https://<server_name>/dns-query` sent to each address,
+/// so they pass networks that only allow HTTPS egress. Nothing falls back to
+/// plaintext DNS.
+///
+/// # Example
+///
+/// ```rust
+/// // Queries https://1.1.1.1/dns-query; Cloudflare's certificate covers the IP
+/// url_jail::set_https_nameservers(["1.1.1.1"], "1.1.1.1").unwrap();
+/// assert_eq!(url_jail::nameservers().unwrap()[0], "1.1.1.1:443".parse().unwrap());
+/// ```
+///
+/// # Errors
+///
+/// As [`set_tls_nameservers`].
+#[cfg(feature = "dns-over-tls")]
+pub fn set_https_nameservers<I>(nameservers: I, server_name: &str) -> Result<(), String>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let server_name = tls_server_name(server_name)?;
+    let addrs = parse_nameservers(nameservers, DOH_PORT)?;
+    set_upstream(addrs, Transport::Https(server_name));
+    Ok(())
+}
+
+/// The nameservers set by [`set_nameservers`], or `None` if the system
+/// nameservers are in use.
+pub fn nameservers() -> Option<Vec<SocketAddr>> {
+    upstream().map(|upstream| upstream.addrs)
+}
+
+fn upstream() -> Option<Upstream> {
+    NAMESERVERS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Replace the nameservers, or restore the system ones if `addrs` is empty.
+fn set_upstream(addrs: Vec<SocketAddr>, transport: Transport) {
+    let mut state = NAMESERVERS.write().unwrap_or_else(|e| e.into_inner());
+    *state = (!addrs.is_empty()).then_some(Upstream {
+        addrs,
+        transport,
+        ..Default::default()
+    });
+}
+
+/// Parse every nameserver, defaulting ports to `default_port`.
+pub(crate) fn parse_nameservers<I>(
+    nameservers: I,
+    default_port: u16,
+) -> Result<Vec<SocketAddr>, String>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    nameservers
+        .into_iter()
+        .map(|ns| parse_nameserver(ns.as_ref(), default_port))
+        .collect()
+}
+
+/// Check a DNS-over-TLS or DNS-over-HTTPS server name.
+#[cfg(feature = "dns-over-tls")]
+pub(crate) fn tls_server_name(server_name: &str) -> Result<String, String> {
+    let server_name = server_name.trim().trim_end_matches('.');
+    if server_name.is_empty() {
+        return Err("encrypted DNS needs a server name to verify".to_string());
+    }
+    Ok(server_name.to_ascii_lowercase())
+}
+
+/// Parse a nameserver address, defaulting the port to `default_port`.
+fn parse_nameserver(nameserver: &str, default_port: u16) -> Result<SocketAddr, String> {
+    let trimmed = nameserver.trim();
+    if let Ok(addr) = trimmed.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    let ip = trimmed
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .unwrap_or(trimmed);
+    ip.parse::<IpAddr>()
+        .map(|ip| SocketAddr::new(ip, default_port))
+        .map_err(|_| {
+            format!(
+                "invalid nameserver {:?}: expected an IP address with an optional port, \
+                 such as 10.0.0.53 or [fd00::53]:5353",
+                nameserver
+            )
+        })
+}
+
+/// Boxed future returned by [`Resolve::lookup`].
+pub type ResolveFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<IpAddr>, Error>> + Send + 'a>>;
+
+/// A DNS resolver used for validation instead of the system resolver.
+///
+/// `lookup` returns every address for `host`, which is normalized
+/// (lowercase, punycode, no brackets or trailing dot). All of them are
+/// checked against the policy. Validation still applies
+/// [`ValidateOptions::dns_timeout`](crate::ValidateOptions::dns_timeout) and
+/// the concurrent lookup limit around each call.
+///
+/// Return [`Error::DnsError`] when the name has no addresses, and
+/// [`Error::Timeout`] when the resolver cannot be reached; only the latter
+/// triggers [`DnsFallback`](crate::DnsFallback). An empty list counts as no
+/// addresses.
+///
+/// # Example
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use std::net::IpAddr;
+/// use url_jail::{Error, Resolve, ResolveFuture};
+///
+/// /// Answers from a fixed table, like an internal view of split-horizon DNS.
+/// struct StaticResolver(HashMap<String, Vec<IpAddr>>);
+///
+/// impl Resolve for StaticResolver {
+///     fn lookup<'a>(&'a self, host: &'a str) -> ResolveFuture<'a> {
+///         Box::pin(async move {
+///             self.0
+///                 .get(host)
+///                 .cloned()
+///                 .ok_or_else(|| Error::DnsError {
+///                     host: host.to_string(),
+///                     message: "unknown host".to_string(),
+///                 })
+///         })
+///     }
+/// }
+/// ```
+pub trait Resolve: Send + Sync {
+    /// Resolve `host` to its addresses.
+    fn lookup<'a>(&'a self, host: &'a str) -> ResolveFuture<'a>;
+}
+
+impl<R: Resolve + ?Sized> Resolve for Arc<R> {
+    fn lookup<'a>(&'a self, host: &'a str) -> ResolveFuture<'a> {
+        (**self).lookup(host)
+    }
+}
+
+impl fmt::Debug for dyn Resolve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Resolve(..)")
+    }
+}
+
+/// Wait for a slot under the concurrent lookup limit.
+///
+/// Hold the permit for the duration of the query.
+pub(crate) async fn lookup_permit() -> OwnedSemaphorePermit {
+    LOOKUP_LIMIT.acquire().await
+}
+
+/// A resizable bound on concurrent work.
+///
+/// Resizing swaps in a fresh semaphore rather than adjusting permits, so
+/// permits held under the old limit are simply released to the old one.
+struct LookupLimit {
+    state: RwLock<Option<(usize, Arc<Semaphore>)>>,
+}
+
+impl LookupLimit {
+    const fn new() -> Self {
+        Self {
+            state: RwLock::new(None),
+        }
+    }
+
+    fn set(&self, max: usize) {
+        let max = max.clamp(1, Semaphore::MAX_PERMITS);
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        *state = Some((max, Arc::new(Semaphore::new(max))));
+    }
+
+    fn max(&self) -> usize {
+        self.semaphore().0
+    }
+
+    async fn acquire(&self) -> OwnedSemaphorePermit {
+        self.semaphore()
+            .1
+            .acquire_owned()
+            .await
+            .expect("lookup semaphore is never closed")
+    }
+
+    fn semaphore(&self) -> (usize, Arc<Semaphore>) {
+        if let Some(state) = &*self.state.read().unwrap_or_else(|e| e.into_inner()) {
+            return state.clone();
+        }
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        state
+            .get_or_insert_with(|| {
+                (
+                    DEFAULT_MAX_CONCURRENT_LOOKUPS,
+                    Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_LOOKUPS)),
+                )
+            })
+            .clone()
+    }
+}
+
+/// Build a resolver from the shared system configuration and any
+/// nameservers set by [`set_nameservers`].
+///
+/// `host` is only used for error reporting.
+pub(crate) fn build_resolver(host: &str) -> Result<TokioResolver, Error> {
+    let (config, opts) = match upstream() {
+        Some(upstream) => override_config(&upstream),
+        None => system_config()
+            .cloned()
+            .map_err(|e| Error::dns_error(host, e))?,
+    };
+    Ok(
+        TokioResolver::builder_with_config(config, TokioConnectionProvider::default())
+            .with_options(opts)
+            .build(),
+    )
+}
+
+/// A configuration querying `upstream`, keeping the system search domains
+/// and options if the system configuration is readable.
+pub(crate) fn override_config(upstream: &Upstream) -> (ResolverConfig, ResolverOpts) {
+    let (domain, search, mut opts) = match system_config() {
+        Ok((config, opts)) => (
+            config.domain().cloned(),
+            config.search().to_vec(),
+            opts.clone(),
+        ),
+        Err(_) => (None, Vec::new(), ResolverOpts::default()),
+    };
+    if let Some(timeout) = upstream.timeout {
+        opts.timeout = timeout;
+    }
+    if let Some(attempts) = upstream.attempts {
+        opts.attempts = attempts;
+    }
+    let mut config = ResolverConfig::from_parts(domain, search, Vec::new());
+    for &addr in &upstream.addrs {
+        match &upstream.transport {
+            Transport::Plain => {
+                for protocol in [Protocol::Udp, Protocol::Tcp] {
+                    config.add_name_server(NameServerConfig::new(addr, protocol));
+                }
+            }
+            #[cfg(feature = "dns-over-tls")]
+            Transport::Tls(tls_name) => {
+                config.add_name_server(encrypted_nameserver(addr, Protocol::Tls, tls_name));
+            }
+            #[cfg(feature = "dns-over-tls")]
+            Transport::Https(tls_name) => {
+                config.add_name_server(encrypted_nameserver(addr, Protocol::Https, tls_name));
+            }
+        }
+    }
+    #[cfg(feature = "dns-over-tls")]
+    if upstream.transport != Transport::Plain {
+        opts.tls_config = tls_config();
+    }
+    (config, opts)
+}
+
+/// A nameserver reached over `protocol`, verifying its certificate against
+/// `tls_name`.
+#[cfg(feature = "dns-over-tls")]
+fn encrypted_nameserver(addr: SocketAddr, protocol: Protocol, tls_name: &str) -> NameServerConfig {
+    let mut nameserver = NameServerConfig::new(addr, protocol);
+    nameserver.tls_dns_name = Some(tls_name.to_string());
+    nameserver
+}
+
+/// TLS settings for DNS-over-TLS and DNS-over-HTTPS, trusting the Mozilla root store.
+///
+/// Built once; clones share the certificate verifier and its roots.
+#[cfg(feature = "dns-over-tls")]
+fn tls_config() -> rustls::ClientConfig {
+    static TLS_CONFIG: OnceLock<rustls::ClientConfig> = OnceLock::new();
+    TLS_CONFIG
+        .get_or_init(|| {
+            let roots = rustls::RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            };
+            rustls::ClientConfig::builder_with_provider(Arc::new(
+                rustls::crypto::ring::default_provider(),
+            ))
+            .with_safe_default_protocol_versions()
+            .expect("ring supports the default protocol versions")
+            .with_root_certificates(roots)
+            .with_no_client_auth()
+        })
+        .clone()
+}
+
+/// Return the cached system configuration, reading it on first use.
+///
+/// Read failures are not cached, so a later call can succeed once the
+/// configuration becomes available.
+fn system_config() -> Result<&'static (ResolverConfig, ResolverOpts), String> {
+    if let Some(config) = SYSTEM_CONFIG.get() {
+        return Ok(config);
+    }
+    let config = hickory_resolver::system_conf::read_system_conf().map_err(|e| e.to_string())?;
+    Ok(SYSTEM_CONFIG.get_or_init(|| config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_config_is_cached() {
+        let first = system_config().unwrap() as *const _;
+        let second = system_config().unwrap() as *const _;
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_parse_nameserver() {
+        for (input, expected) in [
+            ("10.0.0.53", "10.0.0.53:53"),
+            ("10.0.0.53:5353", "10.0.0.53:5353"),
+            ("fd00::53", "[fd00::53]:53"),
+            ("[fd00::53]", "[fd00::53]:53"),
+            (" [fd00::53]:5353 ", "[fd00::53]:5353"),
+        ] {
+            assert_eq!(
+                parse_nameserver(input, DNS_PORT).unwrap(),
+                expected.parse::<SocketAddr>().unwrap(),
+                "{}",
+                input
+            );
+        }
+        for input in [
+            "",
+            "dns.internal",
+            "10.0.0.53:dns",
+            "fd00::53:5353:x",
+            "[fd00::53",
+        ] {
+            assert!(parse_nameserver(input, DNS_PORT).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_override_config_queries_each_nameserver() {
+        let addr: SocketAddr = "[fd00::53]:5353".parse().unwrap();
+        let (config, opts) = override_config(&Upstream {
+            addrs: vec![addr],
+            timeout: Some(Duration::from_millis(500)),
+            attempts: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(
+            (opts.timeout, opts.attempts),
+            (Duration::from_millis(500), 1)
+        );
+        let servers: Vec<_> = config
+            .name_servers()
+            .iter()
+            .map(|ns| (ns.socket_addr, ns.protocol))
+            .collect();
+        assert_eq!(servers, [(addr, Protocol::Udp), (addr, Protocol::Tcp)]);
+    }
+
+    #[cfg(feature = "dns-over-tls")]
+    #[test]
+    fn test_tls_upstream_config() {
+        let addrs =
+            parse_nameservers(["1.1.1.1", "[2606:4700:4700::1111]:8853"], DOT_PORT).unwrap();
+        assert_eq!(addrs[0], "1.1.1.1:853".parse().unwrap());
+        let (config, _) = override_config(&Upstream {
+            addrs: addrs.clone(),
+            transport: Transport::Tls(tls_server_name("Cloudflare-DNS.com.").unwrap()),
+            ..Default::default()
+        });
+        let servers: Vec<_> = config
+            .name_servers()
+            .iter()
+            .map(|ns| (ns.socket_addr, ns.protocol, ns.tls_dns_name.as_deref()))
+            .collect();
+        assert_eq!(
+            servers,
+            [
+                (addrs[0], Protocol::Tls, Some("cloudflare-dns.com")),
+                (addrs[1], Protocol::Tls, Some("cloudflare-dns.com")),
+            ]
+        );
+        assert!(tls_server_name(" ").is_err());
+    }
+
+    #[cfg(feature = "dns-over-tls")]
+    #[test]
+    fn test_https_upstream_config() {
+        let addrs =
+            parse_nameservers(["1.1.1.1", "[2606:4700:4700::1111]:8443"], DOH_PORT).unwrap();
+        assert_eq!(addrs[0], "1.1.1.1:443".parse().unwrap());
+        let (config, _) = override_config(&Upstream {
+            addrs: addrs.clone(),
+            transport: Transport::Https(tls_server_name("1.1.1.1").unwrap()),
+            ..Default::default()
+        });
+        let servers: Vec<_> = config
+            .name_servers()
+            .iter()
+            .map(|ns| (ns.socket_addr, ns.protocol, ns.tls_dns_name.as_deref()))
+            .collect();
+        assert_eq!(
+            servers,
+            [
+                (addrs[0], Protocol::Https, Some("1.1.1.1")),
+                (addrs[1], Protocol::Https, Some("1.1.1.1")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lookup_limit_bounds_and_resizes() {
+        let limit = LookupLimit::new();
+        assert_eq!(limit.max(), DEFAULT_MAX_CONCURRENT_LOOKUPS);
+
+        limit.set(0);
+        assert_eq!(limit.max(), 1);
+        let held = limit.acquire().await;
+        let blocked = tokio::time::timeout(Duration::from_millis(20), limit.acquire()).await;
+        assert!(blocked.is_err(), "second permit granted over the limit");
+
+        // A resize applies to new lookups while the old permit is still held
+        limit.set(2);
+        let _a = limit.acquire().await;
+        let _b = limit.acquire().await;
+        drop(held);
+        assert_eq!(limit.max(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_init_with_literal_canary() {
+        // IP literals resolve without touching the network
+        let opts = InitOptions {
+            canary: "127.0.0.1".to_string(),
+            ..Default::default()
+        };
+        let readiness = init_with_options(opts).await.unwrap();
+        assert_eq!(readiness.canary, "127.0.0.1");
+        assert_eq!(
+            readiness.canary_ips,
+            vec!["127.0.0.1".parse::<IpAddr>().unwrap()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_init_unresolvable_canary_fails() {
+        let opts = InitOptions {
+            canary: "this-domain-does-not-exist-12345.invalid".to_string(),
+            timeout: Duration::from_secs(5),
+        };
+        let result = init_with_options(opts).await;
+        assert!(matches!(
+            result,
+            Err(Error::DnsError { .. }) | Err(Error::Timeout { .. })
+        ));
+    }
+}
@@ -8,18 +8,34 @@
 //! | Component | Normalization | Notes |
 //! |-----------|---------------|-------|
 //! | Scheme | Validated | Only `http` and `https` allowed |
-//! | Hostname | Lowercased, trailing dot removed | Case-insensitive matching |
-//! | Port | Defaults applied | 80 for http, 443 for https |
+//! | Hostname | Lowercased, trailing dot removed | Case-insensitive matching; repeated trailing dots rejected |
+//! | Port | Defaults applied, default port stripped | 80 for http, 443 for https; port 0, an empty port and leading zeros rejected |
 //! | Userinfo | Rejected | `user:pass@` not allowed |
 //! | Path | **Not normalized** | Preserved exactly as provided |
 //! | Query | **Not normalized** | Preserved exactly as provided |
 //! | Fragment | **Not normalized** | Preserved exactly as provided |
 //!
+//! ## IPv6 Zone Identifiers
+//!
+//! A scoped literal such as `http://[fe80::1%25eth0]/` names an interface
+//! on the machine that makes the request, and parsers disagree on it: the
+//! WHATWG standard rejects it, RFC 6874 allows it, and some clients drop the
+//! zone. [`SafeUrl::parse`] rejects zone identifiers as ambiguous.
+//! [`SafeUrl::parse_with_zone_ids`] with [`ZoneIds::Strip`] removes the zone
+//! instead, so the policy sees the bare address (`[fe80::1]`).
+//!
 //! ## Internationalized Domain Names (IDN)
 //!
 //! Punycode/IDNA handling is delegated to the [`url`](https://crates.io/crates/url)
 //! crate, which implements IDNA 2008 via the [`idna`](https://crates.io/crates/idna)
-//! crate. Unicode hostnames are converted to ASCII-compatible encoding.
+//! crate. Unicode hostnames are converted to ASCII-compatible encoding;
+//! [`SafeUrl::host`] is the punycode form and [`SafeUrl::host_unicode`] the
+//! form to show a person.
+//!
+//! A valid IDN can still imitate another hostname (`аpple.com` with a
+//! Cyrillic `а`). [`SafeUrl::confusable`] flags such hostnames, and
+//! [`Confusables::Reject`](crate::Confusables::Reject) rejects them during
+//! validation.
 //!
 //! ## What This Module Does NOT Do
 //!
@@ -27,10 +43,28 @@
 //! - Query parameter validation
 //! - Content-type validation
 
+use std::ops::Range;
+
 use url::Url;
 
+use crate::confusable;
 use crate::Error;
 
+/// How [`SafeUrl::parse_with_zone_ids`] treats an IPv6 zone identifier, as
+/// in `http://[fe80::1%25eth0]/`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ZoneIds {
+    /// Fail with [`Error::AmbiguousUrl`].
+    #[default]
+    Reject,
+
+    /// Remove the zone and validate the bare address.
+    ///
+    /// Only the RFC 6874 form (`%25` followed by unreserved characters) is
+    /// stripped; anything else after `%` is still rejected.
+    Strip,
+}
+
 /// A parsed and normalized URL that is safe for further processing.
 ///
 /// This struct represents a URL after parsing and normalization, but before
@@ -60,15 +94,59 @@ impl SafeUrl {
     /// - Scheme validation (only http/https allowed)
     /// - Hostname normalization (lowercase, no trailing dot)
     /// - Rejection of userinfo (user:pass@)
+    /// - Rejection of port 0, empty ports (`host:`) and ports with leading
+    ///   zeros (`host:080`)
     /// - Rejection of non-standard IP formats (octal, decimal, hexadecimal, short-form)
+    /// - Rejection of parser-differential syntax (encoded delimiters, nested schemes)
     ///
     /// # Errors
     ///
     /// Returns [`Error::InvalidUrl`] if the URL is malformed or uses a
-    /// forbidden scheme.
+    /// forbidden scheme, and [`Error::AmbiguousUrl`] if it uses syntax that
+    /// other URL parsers interpret differently.
     pub fn parse(input: &str) -> Result<Self, Error> {
-        // First, check the raw input for non-standard IP formats
-        // We must do this BEFORE url::Url::parse because it normalizes them
+        Self::parse_with_zone_ids(input, ZoneIds::Reject)
+    }
+
+    /// Parse and normalize a URL string, handling IPv6 zone identifiers as
+    /// `zone_ids` says.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{SafeUrl, ZoneIds};
+    ///
+    /// let url = SafeUrl::parse_with_zone_ids("http://[fe80::1%25eth0]/", ZoneIds::Strip).unwrap();
+    /// assert_eq!(url.host(), "[fe80::1]");
+    /// assert!(SafeUrl::parse("http://[fe80::1%25eth0]/").is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// As [`parse`](Self::parse), and [`Error::AmbiguousUrl`] for a
+    /// rejected or malformed zone identifier.
+    pub fn parse_with_zone_ids(input: &str, zone_ids: ZoneIds) -> Result<Self, Error> {
+        let stripped;
+        let input = match zone_id_range(input) {
+            None => input,
+            Some(_) if zone_ids == ZoneIds::Reject => {
+                return Err(Error::ambiguous_url(input, "IPv6 zone identifier in host"));
+            }
+            Some(range) => {
+                if !is_valid_zone_id(&input[range.clone()]) {
+                    return Err(Error::ambiguous_url(
+                        input,
+                        "malformed IPv6 zone identifier",
+                    ));
+                }
+                stripped = format!("{}{}", &input[..range.start], &input[range.end..]);
+                &stripped
+            }
+        };
+
+        // First, check the raw input for syntax and IP formats that
+        // url::Url::parse would silently normalize
+        reject_ambiguous_syntax(input)?;
         reject_non_standard_ip_in_raw_url(input)?;
 
         let url = Url::parse(input).map_err(|e| Error::invalid_url(input, e.to_string()))?;
@@ -94,6 +172,11 @@ impl SafeUrl {
             ));
         }
 
+        // Clients either fail on port 0 or let the OS pick a port
+        if url.port() == Some(0) {
+            return Err(Error::invalid_url(input, "port 0 not allowed"));
+        }
+
         let normalized_host = normalize_host(host, input)?;
 
         Ok(Self {
@@ -110,6 +193,47 @@ impl SafeUrl {
         &self.host
     }
 
+    /// The hostname with punycode labels decoded, for display.
+    ///
+    /// Connect and match rules with [`host`](Self::host); this form is for
+    /// showing a person where a link goes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::SafeUrl;
+    ///
+    /// let url = SafeUrl::parse("https://München.de/").unwrap();
+    /// assert_eq!(url.host(), "xn--mnchen-3ya.de");
+    /// assert_eq!(url.host_unicode(), "münchen.de");
+    /// ```
+    pub fn host_unicode(&self) -> String {
+        confusable::to_unicode(&self.host)
+    }
+
+    /// Why the hostname could imitate another one, if it could: a label
+    /// mixing scripts, or written only with Latin lookalikes.
+    ///
+    /// See [`Confusables`](crate::Confusables) to reject such hostnames
+    /// during validation.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::SafeUrl;
+    ///
+    /// let url = SafeUrl::parse("https://\u{0430}pple.com/").unwrap();
+    /// assert_eq!(url.host(), "xn--pple-43d.com");
+    /// assert_eq!(
+    ///     url.confusable().as_deref(),
+    ///     Some("label 'аpple' mixes Latin and Cyrillic scripts")
+    /// );
+    /// assert_eq!(SafeUrl::parse("https://münchen.de/").unwrap().confusable(), None);
+    /// ```
+    pub fn confusable(&self) -> Option<String> {
+        confusable::check_host(&self.host)
+    }
+
     /// Get the port number.
     ///
     /// Returns the explicit port if specified, otherwise defaults to:
@@ -133,6 +257,37 @@ impl SafeUrl {
         self.inner.as_str()
     }
 
+    /// Get the canonical form of the URL.
+    ///
+    /// Like [`as_str`](Self::as_str), but with the normalized hostname (no
+    /// trailing dot). The canonical form is idempotent: parsing it yields a
+    /// `SafeUrl` with the same canonical form, host, and port. Store this
+    /// form if URLs are re-validated later, so re-validation sees exactly
+    /// the URL that was approved.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::SafeUrl;
+    ///
+    /// let url = SafeUrl::parse("HTTPS://Example.COM.:443/a b?q#f").unwrap();
+    /// assert_eq!(url.canonical(), "https://example.com/a%20b?q#f");
+    ///
+    /// let again = SafeUrl::parse(&url.canonical()).unwrap();
+    /// assert_eq!(again.canonical(), url.canonical());
+    /// ```
+    pub fn canonical(&self) -> String {
+        if self.inner.host_str() == Some(self.host.as_str()) {
+            return self.inner.as_str().to_string();
+        }
+        let mut url = self.inner.clone();
+        // The normalized host was derived from a host this URL already
+        // accepted, so it is always settable
+        url.set_host(Some(&self.host))
+            .expect("normalized host is valid");
+        url.into()
+    }
+
     /// Check if the URL uses HTTPS.
     ///
     /// Returns `true` for `https://` URLs, `false` for `http://`.
@@ -140,6 +295,93 @@ impl SafeUrl {
         self.inner.scheme() == "https"
     }
 
+    /// Re-parse the normalized URL with `url::Url` and `http::Uri` and check
+    /// that both agree with this `SafeUrl` on scheme, host, and port.
+    ///
+    /// HTTP clients built on either parser then connect to the authority
+    /// that was validated. Enable it for every validation with
+    /// [`ValidateOptions::cross_check_parsers`](crate::ValidateOptions::cross_check_parsers).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AmbiguousUrl`] naming the parser that disagrees.
+    pub fn cross_check(&self) -> Result<(), Error> {
+        let url = self.as_str();
+        let reparsed = Url::parse(url)
+            .map_err(|e| Error::ambiguous_url(url, format!("url::Url cannot re-parse: {}", e)))?;
+        let authority = reparsed.host_str().map(|host| {
+            (
+                reparsed.scheme().to_string(),
+                host.to_string(),
+                reparsed.port_or_known_default(),
+            )
+        });
+        self.agrees(url, "url::Url", authority)?;
+        self.cross_check_uri(url)
+    }
+
+    /// Check that `http::Uri` reads `input` (the URL as originally given)
+    /// with the same authority, if it accepts it at all.
+    pub(crate) fn cross_check_input(&self, input: &str) -> Result<(), Error> {
+        if input.parse::<http::Uri>().is_err() {
+            // A client built on http::Uri cannot send it
+            return Ok(());
+        }
+        self.cross_check_uri(input)
+    }
+
+    fn cross_check_uri(&self, url: &str) -> Result<(), Error> {
+        let uri: http::Uri = url
+            .parse()
+            .map_err(|e| Error::ambiguous_url(url, format!("http::Uri cannot parse: {}", e)))?;
+        let authority = match (uri.scheme_str(), uri.host()) {
+            (Some(scheme), Some(host)) => {
+                let scheme = scheme.to_ascii_lowercase();
+                let port = uri.port_u16().or(match scheme.as_str() {
+                    "http" => Some(80),
+                    "https" => Some(443),
+                    _ => None,
+                });
+                Some((scheme, host.to_string(), port))
+            }
+            _ => None,
+        };
+        self.agrees(url, "http::Uri", authority)
+    }
+
+    fn agrees(
+        &self,
+        url: &str,
+        parser: &str,
+        authority: Option<(String, String, Option<u16>)>,
+    ) -> Result<(), Error> {
+        let Some((scheme, host, port)) = authority else {
+            return Err(Error::ambiguous_url(
+                url,
+                format!("{} sees no host", parser),
+            ));
+        };
+        let host = host.to_ascii_lowercase();
+        let host = host.strip_suffix('.').unwrap_or(&host);
+        let expected_scheme = if self.is_https() { "https" } else { "http" };
+        if scheme != expected_scheme || !same_host(host, &self.host) || port != Some(self.port()) {
+            return Err(Error::ambiguous_url(
+                url,
+                format!(
+                    "{} sees {}://{}:{}, validated {}://{}:{}",
+                    parser,
+                    scheme,
+                    host,
+                    port.map_or("?".to_string(), |p| p.to_string()),
+                    expected_scheme,
+                    self.host,
+                    self.port()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
     /// Consume self and return the underlying [`url::Url`].
     ///
     /// Use this if you need access to the full URL parsing capabilities.
@@ -148,6 +390,18 @@ impl SafeUrl {
     }
 }
 
+/// Hostnames are equal, or are the same IP address written differently
+/// (`[::ffff:1.2.3.4]` and `[::ffff:102:304]`).
+fn same_host(a: &str, b: &str) -> bool {
+    let ip = |host: &str| {
+        host.trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<std::net::IpAddr>()
+            .ok()
+    };
+    a == b || ip(a).is_some_and(|ip_a| ip(b) == Some(ip_a))
+}
+
 /// Normalize a hostname: lowercase, remove trailing dot.
 fn normalize_host(host: &str, original_url: &str) -> Result<String, Error> {
     let mut normalized = host.to_lowercase();
@@ -161,6 +415,13 @@ fn normalize_host(host: &str, original_url: &str) -> Result<String, Error> {
         return Err(Error::invalid_url(original_url, "empty hostname"));
     }
 
+    // A second trailing dot means an empty label; stripping only one would
+    // leave "example.com." which matches neither "example.com" rules nor the
+    // canonical form
+    if normalized.ends_with('.') {
+        return Err(Error::invalid_url(original_url, "empty label in hostname"));
+    }
+
     if normalized.starts_with('[') {
         if !normalized.ends_with(']') {
             return Err(Error::invalid_url(
@@ -180,6 +441,173 @@ fn normalize_host(host: &str, original_url: &str) -> Result<String, Error> {
     Ok(normalized)
 }
 
+/// Schemes that must not appear nested inside another URL's authority.
+const NESTED_SCHEMES: &[&str] = &[
+    "http:",
+    "https:",
+    "ftp:",
+    "file:",
+    "gopher:",
+    "ws:",
+    "wss:",
+    "data:",
+    "javascript:",
+];
+
+/// Reject raw URL syntax that parsers disagree on.
+///
+/// `url::Url::parse` follows the WHATWG URL standard, which repairs many
+/// malformed inputs (stripping tabs and newlines, treating `\` as `/`,
+/// decoding the host). Other parsers (the HTTP client, a proxy, another
+/// service that re-validates the URL) may read the same input differently,
+/// so anything the standard would repair is rejected instead.
+fn reject_ambiguous_syntax(input: &str) -> Result<(), Error> {
+    let ambiguous = |reason: &str| Err(Error::ambiguous_url(input, reason));
+
+    if input.chars().any(|c| c.is_ascii_control()) {
+        return ambiguous("control character in URL");
+    }
+
+    let Some((scheme, rest)) = input.split_once(':') else {
+        if starts_with_scheme(&percent_decode_fully(input)) {
+            return ambiguous("percent-encoded scheme");
+        }
+        return Ok(());
+    };
+    if scheme.contains('%') {
+        return ambiguous("percent-encoded scheme");
+    }
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        // Leave other schemes to the scheme check
+        return Ok(());
+    }
+
+    let after_slashes = rest.trim_start_matches(['/', '\\']);
+    let after_slashes_lower = after_slashes.to_ascii_lowercase();
+    if NESTED_SCHEMES
+        .iter()
+        .any(|nested| after_slashes_lower.starts_with(nested))
+    {
+        return ambiguous("nested scheme");
+    }
+    if rest.len() - after_slashes.len() != 2 || !rest.starts_with("//") {
+        return ambiguous("scheme must be followed by exactly two slashes");
+    }
+
+    let authority_end = after_slashes
+        .find(['/', '?', '#'])
+        .unwrap_or(after_slashes.len());
+    let authority = &after_slashes[..authority_end];
+    if authority.contains('\\') {
+        return ambiguous("backslash in authority");
+    }
+    if authority.contains('%') {
+        if percent_decode_fully(authority).contains(['/', '?', '#', '@', '\\', ':']) {
+            return ambiguous("percent-encoded delimiter in authority");
+        }
+        return ambiguous("percent-encoding in hostname");
+    }
+
+    check_port(input, authority)
+}
+
+/// Reject port numbers clients read differently: `url::Url` drops an empty
+/// port and leading zeros, while other parsers fail or read `010` as octal.
+///
+/// Ports that are not numbers at all are left to `url::Url`, which rejects
+/// them, and port 0 to [`SafeUrl::parse`], which sees it once parsed.
+fn check_port(input: &str, authority: &str) -> Result<(), Error> {
+    let host_port = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, rest)| rest);
+    let port = match host_port.strip_prefix('[') {
+        Some(rest) => rest
+            .split_once(']')
+            .and_then(|(_, rest)| rest.strip_prefix(':')),
+        None => host_port.rsplit_once(':').map(|(_, port)| port),
+    };
+    let Some(port) = port else {
+        return Ok(());
+    };
+    // url::Url trims trailing spaces from the input
+    let port = port.trim_end_matches(' ');
+    if port.is_empty() {
+        return Err(Error::ambiguous_url(input, "empty port"));
+    }
+    if !port.bytes().all(|b| b.is_ascii_digit()) || port.bytes().all(|b| b == b'0') {
+        return Ok(());
+    }
+    if port.starts_with('0') {
+        return Err(Error::ambiguous_url(input, "leading zero in port"));
+    }
+    Ok(())
+}
+
+/// The byte range of the zone identifier (`%25eth0`) in the bracketed IPv6
+/// host of `input`, if it has one.
+fn zone_id_range(input: &str) -> Option<Range<usize>> {
+    let start = input.find("//")? + 2;
+    let rest = &input[start..];
+    let authority = &rest[..rest.find(['/', '?', '#']).unwrap_or(rest.len())];
+    let open = authority.find('[')?;
+    let close = open + authority[open..].find(']')?;
+    let percent = open + authority[open..close].find('%')?;
+    Some(start + percent..start + close)
+}
+
+/// Whether `zone` is an RFC 6874 zone identifier: `%25` and one or more
+/// unreserved characters.
+fn is_valid_zone_id(zone: &str) -> bool {
+    zone.strip_prefix("%25").is_some_and(|id| {
+        !id.is_empty()
+            && id
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-._~".contains(&b))
+    })
+}
+
+/// Whether `s` starts with a URL scheme followed by `:`.
+fn starts_with_scheme(s: &str) -> bool {
+    let s = s.trim_start().to_ascii_lowercase();
+    NESTED_SCHEMES.iter().any(|scheme| s.starts_with(scheme))
+}
+
+/// Percent-decode `s` repeatedly until it stops changing, to see through
+/// double (or deeper) encoding.
+fn percent_decode_fully(s: &str) -> String {
+    let mut current = s.to_string();
+    // Each round shortens the string, so this terminates
+    loop {
+        let decoded = percent_decode_once(&current);
+        if decoded == current {
+            return current;
+        }
+        current = decoded;
+    }
+}
+
+fn percent_decode_once(s: &str) -> String {
+    fn hex(byte: u8) -> Option<u8> {
+        (byte as char).to_digit(16).map(|d| d as u8)
+    }
+
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                out.push(hi << 4 | lo);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
 /// Reject non-standard IP address formats (octal, decimal, hex) in the raw URL.
 /// This must be done BEFORE url::Url::parse because it normalizes these formats.
 fn reject_non_standard_ip_in_raw_url(url: &str) -> Result<(), Error> {
@@ -306,9 +734,237 @@ fn check_non_standard_ip(host: &str, original_url: &str) -> Result<(), Error> {
 }
 
 #[cfg(test)]
+// Red-team tests match on both outcomes to document each one
+#[allow(clippy::single_match)]
 mod tests {
     use super::*;
 
+    // ==================== Parser Differential Tests ====================
+
+    /// Known parser-differential payloads and the reason each is rejected.
+    const DIFFERENTIAL_CORPUS: &[(&str, &str)] = &[
+        (
+            "https://example.com%2F@evil.com",
+            "percent-encoded delimiter",
+        ),
+        (
+            "https://example.com%252F@evil.com",
+            "percent-encoded delimiter",
+        ),
+        (
+            "https://example.com%3F@evil.com",
+            "percent-encoded delimiter",
+        ),
+        (
+            "https://example.com%23@evil.com",
+            "percent-encoded delimiter",
+        ),
+        (
+            "https://evil.com%40example.com",
+            "percent-encoded delimiter",
+        ),
+        ("https://evil.com%3A443", "percent-encoded delimiter"),
+        (
+            "https://evil.com%5C@example.com",
+            "percent-encoded delimiter",
+        ),
+        ("http://ex%61mple.com/", "percent-encoding in hostname"),
+        ("http://127.0.0.%31/", "percent-encoding in hostname"),
+        ("http:http://evil.com", "nested scheme"),
+        ("http://http://evil.com", "nested scheme"),
+        ("https://https:evil.com", "nested scheme"),
+        ("http:/\\javascript:alert(1)", "nested scheme"),
+        ("http:evil.com", "two slashes"),
+        ("http:/evil.com", "two slashes"),
+        ("http:///evil.com", "two slashes"),
+        ("http:\\\\evil.com", "two slashes"),
+        ("http:/\\evil.com", "two slashes"),
+        ("https://evil.com\\@example.com", "backslash in authority"),
+        ("https://example.com\\.evil.com/", "backslash in authority"),
+        ("ht\ttp://evil.com", "control character"),
+        ("http://exa\nmple.com", "control character"),
+        ("http://example.com/\r\nHost: evil.com", "control character"),
+        ("\u{0}http://evil.com", "control character"),
+        ("http%3A%2F%2Fevil.com", "percent-encoded scheme"),
+        ("http%253A%252F%252Fevil.com", "percent-encoded scheme"),
+        ("%68ttp://evil.com", "percent-encoded scheme"),
+    ];
+
+    #[test]
+    fn test_parser_differential_corpus() {
+        for (payload, expected) in DIFFERENTIAL_CORPUS {
+            match SafeUrl::parse(payload) {
+                Err(Error::AmbiguousUrl { reason, .. }) => assert!(
+                    reason.contains(expected),
+                    "{:?}: expected {:?}, got {:?}",
+                    payload,
+                    expected,
+                    reason
+                ),
+                other => panic!("{:?} not rejected as ambiguous: {:?}", payload, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_ambiguous_url_is_blocked() {
+        let err = SafeUrl::parse("http:http://evil.com").unwrap_err();
+        assert!(err.is_blocked());
+        assert_eq!(err.url(), Some("http:http://evil.com"));
+    }
+
+    #[test]
+    fn test_unambiguous_urls_still_accepted() {
+        for url in [
+            "https://example.com/a%2Fb?next=http://other.example/%2F#x",
+            "https://example.com:8443/path\\with\\backslash",
+            "http://example.com/?q=a@b",
+            "https://[2001:db8::1]:443/",
+            "HTTPS://EXAMPLE.COM/",
+            "https://example.com/redirect?u=http%253A%252F%252Fevil",
+        ] {
+            assert!(SafeUrl::parse(url).is_ok(), "{} rejected", url);
+        }
+    }
+
+    #[test]
+    fn test_cross_check_agrees_on_normalized_urls() {
+        for input in [
+            "HTTPS://Example.COM.:443/a b?q#f",
+            "http://example.com:8080",
+            "http://[::ffff:1.2.3.4]/",
+            "https://bücher.example/",
+        ] {
+            let url = SafeUrl::parse(input).unwrap();
+            assert!(url.cross_check().is_ok(), "{}", input);
+            assert!(url.cross_check_input(input).is_ok(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_cross_check_reports_divergence() {
+        let url = SafeUrl::parse("http://example.com/").unwrap();
+        let seen = Some(("http".to_string(), "evil.example".to_string(), Some(80)));
+        match url.agrees(url.as_str(), "http::Uri", seen) {
+            Err(Error::AmbiguousUrl { reason, .. }) => assert_eq!(
+                reason,
+                "http::Uri sees http://evil.example:80, validated http://example.com:80"
+            ),
+            other => panic!("{:?}", other),
+        }
+        let port = Some(("http".to_string(), "example.com".to_string(), Some(8080)));
+        assert!(url.agrees(url.as_str(), "url::Url", port).is_err());
+        assert!(url.agrees(url.as_str(), "url::Url", None).is_err());
+    }
+
+    #[test]
+    fn test_other_schemes_keep_scheme_error() {
+        assert!(matches!(
+            SafeUrl::parse("file:///etc/passwd"),
+            Err(Error::InvalidUrl { .. })
+        ));
+        assert!(matches!(
+            SafeUrl::parse("javascript:alert(1)"),
+            Err(Error::InvalidUrl { .. })
+        ));
+    }
+
+    #[test]
+    fn test_percent_decode_fully() {
+        assert_eq!(percent_decode_fully("a%252Fb"), "a/b");
+        assert_eq!(percent_decode_fully("100%"), "100%");
+        assert_eq!(percent_decode_fully("%+f%zz"), "%+f%zz");
+    }
+
+    // ==================== Canonical Form Tests ====================
+
+    fn assert_round_trip(input: &str) {
+        let Ok(first) = SafeUrl::parse(input) else {
+            return;
+        };
+        let canonical = first.canonical();
+        let second = SafeUrl::parse(&canonical)
+            .unwrap_or_else(|e| panic!("canonical form of {:?} rejected: {}", input, e));
+        assert_eq!(second.canonical(), canonical, "input: {:?}", input);
+        assert_eq!(second.as_str(), canonical, "input: {:?}", input);
+        assert_eq!(second.host(), first.host(), "input: {:?}", input);
+        assert_eq!(second.port(), first.port(), "input: {:?}", input);
+        assert_eq!(second.is_https(), first.is_https(), "input: {:?}", input);
+    }
+
+    #[test]
+    fn test_canonical_strips_trailing_dot() {
+        let url = SafeUrl::parse("http://Example.com./path").unwrap();
+        assert_eq!(url.canonical(), "http://example.com/path");
+        assert_round_trip("http://Example.com./path");
+    }
+
+    #[test]
+    fn test_double_trailing_dot_rejected() {
+        assert!(SafeUrl::parse("http://example.com../").is_err());
+    }
+
+    #[test]
+    fn test_canonical_known_cases() {
+        for input in [
+            "https://example.com",
+            "HTTP://EXAMPLE.COM:80/",
+            "https://example.com:8443/a/../b/./c?x=1&y=%zz#frag",
+            "http://[::FFFF:127.0.0.1]:8080/",
+            "https://münchen.de/straße",
+            "http://example.com/%2e%2e/%2F?q=a b",
+            "http://example.com./?#",
+            "http://1.2.3.4/\\path",
+        ] {
+            assert_round_trip(input);
+        }
+    }
+
+    /// Round-trip URLs assembled from random pieces (seeded, so failures
+    /// are reproducible).
+    #[test]
+    fn test_canonical_round_trip_property() {
+        const SCHEMES: &[&str] = &["http", "https", "HTTP", "hTtPs"];
+        const HOSTS: &[&str] = &[
+            "example.com",
+            "EXAMPLE.com.",
+            "a.b.c.example",
+            "xn--mnchen-3ya.de",
+            "münchen.de",
+            "8.8.8.8",
+            "[::1]",
+            "[2001:DB8::1]",
+            "sub-domain.example",
+        ];
+        const PORTS: &[&str] = &["", ":80", ":443", ":8080", ":0", ":65535"];
+        const PIECES: &[&str] = &[
+            "/", "a", "..", ".", "%2e", "%2F", "%41", " ", "é", "\\", "//", "?", "&", "=", "#",
+            "%", "%zz", "~", "+", ";", "@", ":", "[", "]", "|", "^", "`", "{", "}", "\"",
+        ];
+
+        // xorshift64*
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut next = |n: usize| {
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+            (state.wrapping_mul(0x2545_F491_4F6C_DD1D) % n as u64) as usize
+        };
+
+        for _ in 0..2_000 {
+            let mut url = format!(
+                "{}://{}{}",
+                SCHEMES[next(SCHEMES.len())],
+                HOSTS[next(HOSTS.len())],
+                PORTS[next(PORTS.len())]
+            );
+            for _ in 0..next(8) {
+                url.push_str(PIECES[next(PIECES.len())]);
+            }
+            assert_round_trip(&url);
+        }
+    }
+
     // ==================== Valid URL Tests ====================
 
     #[test]
@@ -705,9 +1361,55 @@ mod tests {
 
     #[test]
     fn test_url_with_port_zero() {
-        // Port 0 is technically valid
-        let url = SafeUrl::parse("http://example.com:0/").unwrap();
-        assert_eq!(url.port(), 0);
+        for url in [
+            "http://example.com:0/",
+            "http://example.com:0000/",
+            "http://1.2.3.4:0/",
+            "http://[::1]:0/",
+            "https://example.com:0 ",
+        ] {
+            let err = SafeUrl::parse(url).unwrap_err();
+            assert!(
+                matches!(err, Error::InvalidUrl { .. }),
+                "{}: {:?}",
+                url,
+                err
+            );
+            assert!(err.to_string().contains("port 0 not allowed"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_url_with_empty_port() {
+        for url in [
+            "http://example.com:/",
+            "http://[::1]:/",
+            "http://example.com:?q",
+            "http://example.com: ",
+            "http://example.com:080 ",
+        ] {
+            let err = SafeUrl::parse(url).unwrap_err();
+            assert!(
+                matches!(err, Error::AmbiguousUrl { .. }),
+                "{}: {:?}",
+                url,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_default_port_stripped() {
+        for (input, expected, port) in [
+            ("http://example.com:80/a", "http://example.com/a", 80),
+            ("https://example.com:443/a", "https://example.com/a", 443),
+            ("http://example.com:443/a", "http://example.com:443/a", 443),
+            ("https://[::1]:443/", "https://[::1]/", 443),
+        ] {
+            let url = SafeUrl::parse(input).unwrap();
+            assert_eq!(url.as_str(), expected);
+            assert_eq!(url.port(), port);
+        }
     }
 
     #[test]
@@ -751,10 +1453,44 @@ mod tests {
 
     #[test]
     fn test_ipv6_with_zone_id() {
-        // Zone IDs (fe80::1%eth0) - may or may not be supported
-        let result = SafeUrl::parse("http://[fe80::1%25eth0]/");
-        // Just ensure it doesn't panic
-        let _ = result;
+        let err = SafeUrl::parse("http://[fe80::1%25eth0]/").unwrap_err();
+        assert!(matches!(err, Error::AmbiguousUrl { .. }));
+        assert!(err.to_string().contains("IPv6 zone identifier in host"));
+
+        for url in [
+            "http://[fe80::1%25eth0]/",
+            "http://[fe80::1%25eth0]:8080/a?b",
+            "http://[fe80::1%251]/",
+        ] {
+            let url = SafeUrl::parse_with_zone_ids(url, ZoneIds::Strip).unwrap();
+            assert_eq!(url.host(), "[fe80::1]");
+            assert!(!url.as_str().contains('%'), "{}", url.as_str());
+        }
+        let url = SafeUrl::parse_with_zone_ids("http://[fe80::1%25eth0]:8080/a?b", ZoneIds::Strip)
+            .unwrap();
+        assert_eq!(url.as_str(), "http://[fe80::1]:8080/a?b");
+    }
+
+    #[test]
+    fn test_ipv6_malformed_zone_id_rejected_when_stripping() {
+        for url in [
+            "http://[fe80::1%eth0]/",
+            "http://[fe80::1%25]/",
+            "http://[fe80::1%25eth0%2f]/",
+            "http://[fe80::1%25eth%40x]/",
+        ] {
+            let err = SafeUrl::parse_with_zone_ids(url, ZoneIds::Strip).unwrap_err();
+            assert!(
+                err.to_string().contains("malformed IPv6 zone identifier"),
+                "{}: {}",
+                url,
+                err
+            );
+        }
+        // Percent-encoding outside the brackets is still caught later
+        assert!(
+            SafeUrl::parse_with_zone_ids("http://[fe80::1%25eth0]%2f/", ZoneIds::Strip).is_err()
+        );
     }
 
     #[test]
@@ -1059,13 +1795,11 @@ mod tests {
         // Cyrillic 'а' (U+0430) looks like ASCII 'a' (U+0061)
         // "locаlhost" with Cyrillic а
         let result = SafeUrl::parse("http://loc\u{0430}lhost/");
-        match result {
-            Ok(url) => {
-                // Should NOT be treated as "localhost"
-                // Either punycode-encoded or kept as-is
-                assert_ne!(url.host(), "localhost");
-            }
-            Err(_) => {}
+        if let Ok(url) = result {
+            // Should NOT be treated as "localhost"
+            // Either punycode-encoded or kept as-is
+            assert_ne!(url.host(), "localhost");
+            assert!(url.confusable().is_some());
         }
     }
 
@@ -1074,12 +1808,11 @@ mod tests {
         // Cyrillic characters that look like "metadata"
         // Using Cyrillic 'е' (U+0435) instead of ASCII 'e'
         let result = SafeUrl::parse("http://m\u{0435}tadata.google.internal/");
-        match result {
-            Ok(url) => {
-                // Should NOT match the blocked hostname
-                assert!(!url.host().eq_ignore_ascii_case("metadata.google.internal"));
-            }
-            Err(_) => {}
+        if let Ok(url) = result {
+            // Should NOT match the blocked hostname
+            assert!(!url.host().eq_ignore_ascii_case("metadata.google.internal"));
+            assert_eq!(url.host_unicode(), "m\u{0435}tadata.google.internal");
+            assert!(url.confusable().is_some());
         }
     }
 
@@ -1163,21 +1896,13 @@ mod tests {
         // Zone ID: [fe80::1%eth0] - some systems support this
         // %25 = % in URL encoding
         let result = SafeUrl::parse("http://[fe80::1%25eth0]/");
-        // Should either reject or handle safely
-        match result {
-            Ok(url) => {
-                // Zone ID in host is unusual
-                assert!(url.host().contains("fe80"));
-            }
-            Err(_) => {} // Rejection is fine
-        }
+        assert!(matches!(result, Err(Error::AmbiguousUrl { .. })));
     }
 
     #[test]
     fn test_redteam_ipv6_zone_id_unencoded() {
         let result = SafeUrl::parse("http://[fe80::1%eth0]/");
-        // Likely rejected due to invalid URL
-        let _ = result; // Just ensure no panic
+        assert!(matches!(result, Err(Error::AmbiguousUrl { .. })));
     }
 
     #[test]
@@ -1301,12 +2026,10 @@ mod tests {
 
     #[test]
     fn test_redteam_port_with_leading_zeros() {
-        // 0080 = 80, but might confuse some parsers
-        let result = SafeUrl::parse("http://example.com:0080/");
-        match result {
-            Ok(url) => assert_eq!(url.port(), 80),
-            Err(_) => {} // Rejection is also acceptable
-        }
+        // 0080 = 80 to url::Url, but might be octal to other parsers
+        let err = SafeUrl::parse("http://example.com:0080/").unwrap_err();
+        assert!(matches!(err, Error::AmbiguousUrl { .. }), "{:?}", err);
+        assert!(err.to_string().contains("leading zero in port"), "{}", err);
     }
 
     #[test]
@@ -0,0 +1,192 @@
+//! Startup self-test for readiness probes.
+//!
+//! [`self_test`] runs a battery of canary checks through the same code paths
+//! as real validations: loopback and metadata addresses must be blocked, a
+//! known-good public address must be allowed, and the resolver must answer.
+//! A failing check means the service should not take traffic, whether the
+//! cause is a broken resolver or a build whose blocklist has regressed.
+
+use std::fmt;
+
+use crate::error::Error;
+use crate::policy::Policy;
+use crate::resolver::{init_with_options, InitOptions};
+use crate::validate::{validate_with_options, ValidateOptions};
+
+/// URLs that every policy must block, by check name.
+const MUST_BLOCK: &[(&str, &str)] = &[
+    ("loopback_v4_blocked", "http://127.0.0.1/"),
+    ("loopback_v6_blocked", "http://[::1]/"),
+    (
+        "metadata_ip_blocked",
+        "http://169.254.169.254/latest/meta-data/",
+    ),
+    (
+        "metadata_hostname_blocked",
+        "http://metadata.google.internal/",
+    ),
+];
+
+/// A public address literal every policy must allow (no DNS needed).
+const MUST_ALLOW: (&str, &str) = ("public_literal_allowed", "https://93.184.216.34/");
+
+/// One check run by [`self_test`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestCheck {
+    /// Stable check name, e.g. `metadata_ip_blocked`.
+    pub name: &'static str,
+
+    /// Whether the check passed.
+    pub passed: bool,
+
+    /// What happened, for logs.
+    pub detail: String,
+}
+
+/// The result of [`self_test`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestReport {
+    /// The policy that was tested.
+    pub policy: Policy,
+
+    /// Every check, in the order run.
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    /// Returns `true` if every check passed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    /// The checks that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &SelfTestCheck> {
+        self.checks.iter().filter(|check| !check.passed)
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    /// One line per check: `ok name: detail` or `FAIL name: detail`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let status = if check.passed { "ok" } else { "FAIL" };
+            writeln!(f, "{} {}: {}", status, check.name, check.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run the startup self-test for `policy`.
+///
+/// The resolver check resolves `example.com`, as [`init`](crate::init) does.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use url_jail::{self_test, Policy};
+///
+/// # async fn readiness_probe() -> bool {
+/// let report = self_test(Policy::PublicOnly).await;
+/// if !report.passed() {
+///     eprintln!("url_jail self-test failed:\n{}", report);
+/// }
+/// report.passed()
+/// # }
+/// ```
+pub async fn self_test(policy: Policy) -> SelfTestReport {
+    self_test_with_options(policy, InitOptions::default()).await
+}
+
+/// Run the startup self-test with a custom resolver canary and timeout.
+///
+/// See [`self_test`].
+pub async fn self_test_with_options(policy: Policy, options: InitOptions) -> SelfTestReport {
+    let mut checks = Vec::new();
+
+    for (name, url) in MUST_BLOCK {
+        checks.push(match validate(url, policy).await {
+            Err(e) if e.is_blocked() => pass(name, format!("{} blocked", url)),
+            Err(e) => fail(name, format!("{} failed without a block: {}", url, e)),
+            Ok(v) => fail(name, format!("{} was allowed (pinned to {})", url, v.ip)),
+        });
+    }
+
+    let (name, url) = MUST_ALLOW;
+    checks.push(match validate(url, policy).await {
+        Ok(_) => pass(name, format!("{} allowed", url)),
+        Err(e) => fail(name, format!("{} rejected: {}", url, e)),
+    });
+
+    checks.push(match init_with_options(options).await {
+        Ok(readiness) => pass(
+            "resolver_reachable",
+            format!(
+                "{} resolved in {:?} via {:?}",
+                readiness.canary, readiness.elapsed, readiness.nameservers
+            ),
+        ),
+        Err(e) => fail("resolver_reachable", e.to_string()),
+    });
+
+    #[cfg(feature = "tracing")]
+    for check in checks.iter().filter(|check| !check.passed) {
+        tracing::error!(check = check.name, detail = %check.detail, "self-test check failed");
+    }
+
+    SelfTestReport { policy, checks }
+}
+
+async fn validate(url: &str, policy: Policy) -> Result<crate::Validated, Error> {
+    validate_with_options(url, policy, ValidateOptions::default()).await
+}
+
+fn pass(name: &'static str, detail: String) -> SelfTestCheck {
+    SelfTestCheck {
+        name,
+        passed: true,
+        detail,
+    }
+}
+
+fn fail(name: &'static str, detail: String) -> SelfTestCheck {
+    SelfTestCheck {
+        name,
+        passed: false,
+        detail,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literal_canary() -> InitOptions {
+        // IP literals resolve without touching the network
+        InitOptions {
+            canary: "127.0.0.1".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builtin_policies_pass() {
+        for policy in [Policy::PublicOnly, Policy::AllowPrivate] {
+            let report = self_test_with_options(policy, literal_canary()).await;
+            assert!(report.passed(), "{:?}:\n{}", policy, report);
+            assert_eq!(report.checks.len(), MUST_BLOCK.len() + 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolver_failure_reported() {
+        let options = InitOptions {
+            canary: "this-domain-does-not-exist-12345.invalid".to_string(),
+            timeout: std::time::Duration::from_secs(2),
+        };
+        let report = self_test_with_options(Policy::PublicOnly, options).await;
+        assert!(!report.passed());
+        let failures: Vec<_> = report.failures().map(|c| c.name).collect();
+        assert_eq!(failures, ["resolver_reachable"]);
+        assert!(report.to_string().contains("FAIL resolver_reachable"));
+    }
+}
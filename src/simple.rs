@@ -0,0 +1,159 @@
+//! One-line helpers with safe defaults.
+//!
+//! For projects that only need "may I fetch this URL?", these functions fix
+//! every setting to a safe value, so there is nothing to get wrong:
+//!
+//! ```rust,no_run
+//! # async fn example() -> Result<(), url_jail::Error> {
+//! if url_jail::simple::is_safe("https://example.com/feed").await {
+//!     // ...
+//! }
+//! # #[cfg(feature = "fetch")]
+//! let body = url_jail::simple::fetch_text("https://example.com/feed").await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! | Setting | Value |
+//! |---------|-------|
+//! | Policy | [`Policy::PublicOnly`] |
+//! | Schemes | `http` and `https`; `fetch_text` refuses redirects from `https` to `http` |
+//! | DNS timeout | 10 seconds |
+//! | Parser cross-check | On ([`ValidateOptions::cross_check_parsers`]) |
+//! | Lookalike hostnames | Rejected ([`Confusables::Reject`]) |
+//! | `fetch_text` limits | 5 redirects, 10 MiB body, 30 seconds in total |
+//!
+//! Use [`validate_with_options`] and, with the `fetch` feature,
+//! `fetch_with_options` to choose for yourself.
+
+#[cfg(feature = "fetch")]
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "fetch")]
+use crate::budget::{BudgetLimits, FetchBudget};
+use crate::confusable::Confusables;
+#[cfg(feature = "fetch")]
+use crate::error::Error;
+#[cfg(feature = "fetch")]
+use crate::fetch::{fetch_with_options, FetchOptions};
+use crate::policy::Policy;
+use crate::validate::{validate_on_worker, validate_with_options, ValidateOptions};
+
+const POLICY: Policy = Policy::PublicOnly;
+const DNS_TIMEOUT: Duration = Duration::from_secs(10);
+#[cfg(feature = "fetch")]
+const MAX_REDIRECTS: u8 = 5;
+#[cfg(feature = "fetch")]
+const MAX_BODY_SIZE: u64 = 10 * 1024 * 1024;
+#[cfg(feature = "fetch")]
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn validate_options() -> ValidateOptions {
+    ValidateOptions {
+        dns_timeout: DNS_TIMEOUT,
+        cross_check_parsers: true,
+        confusables: Confusables::Reject,
+        ..Default::default()
+    }
+}
+
+/// Returns `true` if `url` may be fetched: it parses, and its hostname
+/// resolves only to public addresses.
+///
+/// A DNS failure counts as unsafe.
+pub async fn is_safe(url: &str) -> bool {
+    validate_with_options(url, POLICY, validate_options())
+        .await
+        .is_ok()
+}
+
+/// Blocking version of [`is_safe`].
+///
+/// Validation runs on url_jail's worker thread, so this may be called from
+/// any thread, including inside a single-threaded Tokio runtime.
+///
+/// # Example
+///
+/// ```rust
+/// use url_jail::simple::is_safe_sync;
+///
+/// assert!(is_safe_sync("https://93.184.216.34/"));
+/// assert!(!is_safe_sync("http://169.254.169.254/latest/meta-data/"));
+/// assert!(!is_safe_sync("file:///etc/passwd"));
+/// ```
+pub fn is_safe_sync(url: &str) -> bool {
+    validate_on_worker(url, POLICY, validate_options(), DNS_TIMEOUT).is_ok()
+}
+
+/// Fetch `url` and return its body as text, following redirects safely.
+///
+/// `url` and every redirect are checked as by [`is_safe`] before they are
+/// requested. Invalid UTF-8 is replaced with U+FFFD.
+///
+/// # Errors
+///
+/// Returns the validation error if `url` or a redirect is blocked,
+/// [`Error::TooManyRedirects`] past 5 redirects,
+/// [`Error::ResponseBlocked`] if the body exceeds 10 MiB or the fetch takes
+/// longer than 30 seconds, or [`Error::HttpError`] if the request fails.
+#[cfg(feature = "fetch")]
+pub async fn fetch_text(url: &str) -> Result<String, Error> {
+    let budget = FetchBudget::new(BudgetLimits {
+        max_duration: Some(FETCH_TIMEOUT),
+        ..Default::default()
+    });
+    let options = FetchOptions {
+        max_body_size: Some(MAX_BODY_SIZE),
+        max_redirects: Some(MAX_REDIRECTS),
+        block_downgrade: true,
+        budget: Some(Arc::new(budget)),
+        validate_options: validate_options(),
+        ..Default::default()
+    };
+    fetch_with_options(url, POLICY, options).await?.text().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_is_safe() {
+        assert!(is_safe("https://93.184.216.34/").await);
+        for url in [
+            "http://127.0.0.1/",
+            "http://[::ffff:10.0.0.1]/",
+            "http://100.100.100.200/",
+            "ftp://93.184.216.34/",
+            "not a url",
+            // Lookalike hostnames are rejected before DNS
+            "https://\u{0430}pple.com/",
+        ] {
+            assert!(!is_safe(url).await, "{}", url);
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_is_safe_sync_in_current_thread_runtime() {
+        assert!(is_safe_sync("https://93.184.216.34/"));
+        assert!(!is_safe_sync("http://169.254.169.254/latest/meta-data/"));
+        assert!(!is_safe_sync("https://\u{0430}pple.com/"));
+    }
+
+    #[cfg(feature = "fetch")]
+    #[tokio::test]
+    async fn test_fetch_text_blocks_before_connecting() {
+        let err = fetch_text("http://169.254.169.254/latest/meta-data/")
+            .await
+            .unwrap_err();
+        assert!(err.is_blocked(), "{:?}", err);
+    }
+
+    #[cfg(feature = "fetch")]
+    #[tokio::test]
+    async fn test_fetch_text_rejects_lookalike_host() {
+        let err = fetch_text("https://\u{0430}pple.com/").await.unwrap_err();
+        assert!(matches!(err, Error::HostnameBlocked { .. }), "{:?}", err);
+    }
+}
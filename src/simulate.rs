@@ -0,0 +1,223 @@
+//! Policy simulation against recorded traffic.
+//!
+//! Before tightening a policy, teams want to know what it would break.
+//! [`simulate`] runs recorded `(url, resolved IPs)` pairs through the current
+//! and a candidate policy, using the recorded answers instead of live DNS,
+//! and reports how many requests each policy allows and which destinations
+//! the candidate would newly block.
+//!
+//! Pairs can come from any log source; a [`Recorder`](crate::Recorder)
+//! provides them directly:
+//!
+//! ```rust,ignore
+//! let traffic = parse_log(&std::fs::read_to_string("resolutions.log")?)?
+//!     .into_iter()
+//!     .map(|r| (r.url, r.answers));
+//! let report = simulate(traffic, Policy::AllowPrivate, &candidate);
+//! ```
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use crate::policy::ValidationPolicy;
+use crate::recorder::run_offline;
+use crate::safe_url::SafeUrl;
+
+/// Allow/deny counts for one policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tally {
+    /// Requests the policy allows.
+    pub allowed: usize,
+
+    /// Requests the policy blocks.
+    pub blocked: usize,
+}
+
+impl Tally {
+    fn count(&mut self, allowed: bool) {
+        if allowed {
+            self.allowed += 1;
+        } else {
+            self.blocked += 1;
+        }
+    }
+}
+
+/// A destination whose decision changes under the candidate policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Destination {
+    /// The normalized hostname (or the URL, if it does not parse).
+    pub host: String,
+
+    /// Number of recorded requests to this host whose decision changes.
+    pub requests: usize,
+
+    /// An affected URL.
+    pub example_url: String,
+
+    /// Why the candidate blocks it, for newly blocked destinations; empty
+    /// otherwise.
+    pub reason: String,
+}
+
+/// The result of [`simulate`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Simulation {
+    /// Number of requests simulated.
+    pub total: usize,
+
+    /// Decisions under the current policy.
+    pub current: Tally,
+
+    /// Decisions under the candidate policy.
+    pub candidate: Tally,
+
+    /// Destinations the current policy allows and the candidate blocks,
+    /// most requests first.
+    pub newly_blocked: Vec<Destination>,
+
+    /// Destinations the current policy blocks and the candidate allows,
+    /// most requests first.
+    pub newly_allowed: Vec<Destination>,
+}
+
+impl Simulation {
+    /// Number of requests that would newly be blocked.
+    pub fn newly_blocked_requests(&self) -> usize {
+        self.newly_blocked.iter().map(|d| d.requests).sum()
+    }
+
+    /// Returns `true` if no decision changes.
+    pub fn is_unchanged(&self) -> bool {
+        self.newly_blocked.is_empty() && self.newly_allowed.is_empty()
+    }
+}
+
+/// Simulate replacing `current` with `candidate` on recorded traffic.
+///
+/// Each URL is validated under both policies with its recorded DNS answers;
+/// no lookups are performed. IP-literal URLs ignore the answers.
+///
+/// # Example
+///
+/// ```rust
+/// use url_jail::{simulate, Policy, PolicyBuilder};
+///
+/// let traffic = vec![
+///     ("http://build.corp.example/", vec!["10.1.2.3".parse().unwrap()]),
+///     ("http://build.corp.example/log", vec!["10.1.2.3".parse().unwrap()]),
+///     ("https://api.example.com/", vec!["93.184.216.34".parse().unwrap()]),
+/// ];
+/// let candidate = PolicyBuilder::new(Policy::PublicOnly).build();
+///
+/// let report = simulate(traffic, Policy::AllowPrivate, &candidate);
+/// assert_eq!(report.current.allowed, 3);
+/// assert_eq!(report.candidate.blocked, 2);
+/// assert_eq!(report.newly_blocked[0].host, "build.corp.example");
+/// assert_eq!(report.newly_blocked[0].requests, 2);
+/// ```
+pub fn simulate<I, U, C, P>(traffic: I, current: C, candidate: P) -> Simulation
+where
+    I: IntoIterator<Item = (U, Vec<IpAddr>)>,
+    U: AsRef<str>,
+    C: ValidationPolicy,
+    P: ValidationPolicy,
+{
+    let mut report = Simulation::default();
+    let mut blocked: HashMap<String, Destination> = HashMap::new();
+    let mut allowed: HashMap<String, Destination> = HashMap::new();
+
+    for (url, answers) in traffic {
+        let url = url.as_ref();
+        let before = run_offline(current.machine(url), &answers);
+        let after = run_offline(candidate.machine(url), &answers);
+
+        report.total += 1;
+        report.current.count(before.is_ok());
+        report.candidate.count(after.is_ok());
+
+        let (changes, reason) = match (&before, &after) {
+            (Ok(_), Err(e)) => (&mut blocked, e.to_string()),
+            (Err(_), Ok(_)) => (&mut allowed, String::new()),
+            _ => continue,
+        };
+        let host = SafeUrl::parse(url).map_or_else(|_| url.to_string(), |u| u.host().to_string());
+        changes
+            .entry(host.clone())
+            .or_insert_with(|| Destination {
+                host,
+                requests: 0,
+                example_url: url.to_string(),
+                reason,
+            })
+            .requests += 1;
+    }
+
+    report.newly_blocked = ranked(blocked);
+    report.newly_allowed = ranked(allowed);
+    report
+}
+
+fn ranked(destinations: HashMap<String, Destination>) -> Vec<Destination> {
+    let mut destinations: Vec<Destination> = destinations.into_values().collect();
+    destinations.sort_by(|a, b| b.requests.cmp(&a.requests).then(a.host.cmp(&b.host)));
+    destinations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Policy, PolicyBuilder};
+
+    fn ips(ips: &[&str]) -> Vec<IpAddr> {
+        ips.iter().map(|ip| ip.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_newly_blocked_ranked_by_requests() {
+        let traffic = vec![
+            ("http://a.corp.example/", ips(&["10.0.0.1"])),
+            ("http://b.corp.example/", ips(&["10.0.0.2"])),
+            ("http://b.corp.example/x", ips(&["10.0.0.2"])),
+            ("http://api.example.com/", ips(&["93.184.216.34"])),
+            ("http://127.0.0.1/", vec![]),
+        ];
+        let candidate = PolicyBuilder::new(Policy::PublicOnly)
+            .allow_host("a.corp.example")
+            .allow_cidr("10.0.0.1/32")
+            .build();
+        let report = simulate(traffic, Policy::AllowPrivate, &candidate);
+
+        assert_eq!(report.total, 5);
+        assert_eq!(
+            report.current,
+            Tally {
+                allowed: 4,
+                blocked: 1
+            }
+        );
+        assert_eq!(
+            report.candidate,
+            Tally {
+                allowed: 2,
+                blocked: 3
+            }
+        );
+        assert_eq!(report.newly_blocked.len(), 1);
+        let b = &report.newly_blocked[0];
+        assert_eq!((b.host.as_str(), b.requests), ("b.corp.example", 2));
+        assert!(b.reason.contains("10.0.0.2"), "{}", b.reason);
+        assert_eq!(report.newly_blocked_requests(), 2);
+        assert!(report.newly_allowed.is_empty());
+    }
+
+    #[test]
+    fn test_relaxing_reports_newly_allowed() {
+        let traffic = vec![("http://build.internal/", ips(&["10.0.0.9"]))];
+        let report = simulate(traffic.clone(), Policy::PublicOnly, Policy::AllowPrivate);
+        assert_eq!(report.newly_allowed[0].host, "build.internal");
+        assert_eq!(report.newly_allowed[0].reason, "");
+
+        assert!(simulate(traffic, Policy::PublicOnly, Policy::PublicOnly).is_unchanged());
+    }
+}
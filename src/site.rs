@@ -0,0 +1,119 @@
+//! Host rules by registrable domain, using the Public Suffix List.
+//!
+//! A `*.example.com` rule matches by suffix, which says nothing about who
+//! runs the hosts it covers: `*.github.io` matches every user's site, and a
+//! typo such as `*.co.uk` matches every company in the country. The
+//! *registrable domain* (eTLD+1) of a host is its public suffix plus one
+//! label, as defined by the [Public Suffix List](https://publicsuffix.org/):
+//! `api.example.co.uk` belongs to `example.co.uk`, and `alice.github.io` to
+//! `alice.github.io`, not `github.io`.
+//!
+//! With the `psl` feature, host rules can match by registrable domain:
+//!
+//! - A `site:example.com` pattern, accepted wherever a host pattern is,
+//!   matches `example.com` and every host whose registrable domain is
+//!   `example.com`. The rule must name a registrable domain itself, so
+//!   `site:github.io` and `site:www.example.com` are rejected.
+//! - [`PolicyBuilder::allow_sites`](crate::PolicyBuilder::allow_sites)
+//!   blocks every host whose registrable domain is not listed.
+//!
+//! ```rust
+//! use url_jail::{Policy, PolicyBuilder};
+//!
+//! let policy = PolicyBuilder::new(Policy::PublicOnly)
+//!     .allow_sites(["example.com", "example.co.uk"])
+//!     .block_host("site:example.co.uk")
+//!     .allow_host("api.example.co.uk")
+//!     .build();
+//! assert!(policy.is_hostname_allowed("cdn.example.com").is_ok());
+//! assert!(policy.is_hostname_allowed("evilexample.com").is_err());
+//! assert!(policy.is_hostname_allowed("www.example.co.uk").is_err());
+//! assert!(policy.is_hostname_allowed("api.example.co.uk").is_ok());
+//! ```
+//!
+//! The list is compiled into the `psl` crate; update that dependency to pick
+//! up new suffixes.
+
+use std::net::IpAddr;
+
+/// The registrable domain (eTLD+1) of `host`, lowercased.
+///
+/// Returns `None` for IP literals and for hosts that are themselves a public
+/// suffix, such as `com`, `co.uk` or `github.io`. Suffixes missing from the
+/// list count as single-label public suffixes, so `db.corp.internal`
+/// belongs to `corp.internal`.
+///
+/// # Example
+///
+/// ```rust
+/// use url_jail::registrable_domain;
+///
+/// assert_eq!(registrable_domain("api.example.co.uk").as_deref(), Some("example.co.uk"));
+/// assert_eq!(registrable_domain("alice.github.io").as_deref(), Some("alice.github.io"));
+/// assert_eq!(registrable_domain("co.uk"), None);
+/// assert_eq!(registrable_domain("127.0.0.1"), None);
+/// ```
+pub fn registrable_domain(host: &str) -> Option<String> {
+    let host = host.strip_suffix('.').unwrap_or(host).to_lowercase();
+    if host.starts_with('[') || host.parse::<IpAddr>().is_ok() {
+        return None;
+    }
+    psl::domain_str(&host).map(str::to_string)
+}
+
+/// Whether `domain` is listed as a public suffix.
+pub(crate) fn is_public_suffix(domain: &str) -> bool {
+    psl::suffix(domain.as_bytes())
+        .is_some_and(|suffix| suffix.is_known() && suffix.as_bytes() == domain.as_bytes())
+}
+
+/// Check that `site` can be used as a `site:` rule or in
+/// [`allow_sites`](crate::PolicyBuilder::allow_sites).
+pub(crate) fn check_site(site: &str) -> Result<(), String> {
+    match registrable_domain(site) {
+        Some(domain) if domain == site => Ok(()),
+        Some(domain) => Err(format!(
+            "not a registrable domain; did you mean {:?}?",
+            domain
+        )),
+        None => Err("not a registrable domain (a public suffix or IP address)".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registrable_domain() {
+        for (host, domain) in [
+            ("example.com", Some("example.com")),
+            ("WWW.Example.COM.", Some("example.com")),
+            ("a.b.example.co.uk", Some("example.co.uk")),
+            ("bucket.s3.amazonaws.com", Some("bucket.s3.amazonaws.com")),
+            ("db.corp.internal", Some("corp.internal")),
+            ("com", None),
+            ("github.io", None),
+            ("localhost", None),
+            ("10.0.0.1", None),
+            ("[::1]", None),
+        ] {
+            assert_eq!(registrable_domain(host).as_deref(), domain, "{}", host);
+        }
+        assert!(is_public_suffix("co.uk"));
+        assert!(is_public_suffix("github.io"));
+        assert!(!is_public_suffix("example.com"));
+        assert!(!is_public_suffix("internal"));
+    }
+
+    #[test]
+    fn test_check_site() {
+        assert!(check_site("example.co.uk").is_ok());
+        assert_eq!(
+            check_site("www.example.com").unwrap_err(),
+            "not a registrable domain; did you mean \"example.com\"?"
+        );
+        assert!(check_site("github.io").is_err());
+        assert!(check_site("10.0.0.1").is_err());
+    }
+}
@@ -0,0 +1,274 @@
+//! Streaming fetch for proxying user-supplied URLs.
+//!
+//! [`fetch_stream`] is intended for camo-style image and file proxies: the
+//! target (and every redirect) is validated, the response is checked against
+//! [`FetchOptions`] before any body bytes are read, and the body is yielded as
+//! an async stream so it can be forwarded without buffering.
+//!
+//! The body size limit is enforced twice: up front via `Content-Length`, and
+//! while streaming, in case the header is missing or wrong. Once the limit is
+//! exceeded the stream yields [`Error::ResponseBlocked`] and then ends. The
+//! same applies when the [`FetchOptions::budget`] runs out.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_core::Stream;
+use reqwest::header::HeaderMap;
+use reqwest::{Response, StatusCode};
+
+use crate::error::Error;
+use crate::fetch::{fetch_with_options, BudgetScope, FetchOptions};
+use crate::policy::Policy;
+use crate::validate::Validated;
+
+type ChunkFuture = Pin<Box<dyn Future<Output = (Response, Result<Option<Bytes>, Error>)> + Send>>;
+
+/// A validated response whose body is read as a stream of chunks.
+///
+/// Implements [`Stream`] with items of `Result<Bytes, Error>`.
+pub struct FetchStream {
+    /// Response status.
+    pub status: StatusCode,
+
+    /// Response headers.
+    pub headers: HeaderMap,
+
+    /// Chain of validated URLs that were followed (including the original).
+    pub chain: Vec<Validated>,
+
+    url: String,
+    max_body_size: Option<u64>,
+    budget: Option<BudgetScope>,
+    received: u64,
+    state: State,
+}
+
+enum State {
+    Idle(Response),
+    Reading(ChunkFuture),
+    Done,
+}
+
+impl std::fmt::Debug for FetchStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FetchStream")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .field("chain", &self.chain)
+            .field("received", &self.received)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FetchStream {
+    fn new(
+        response: Response,
+        chain: Vec<Validated>,
+        max_body_size: Option<u64>,
+        budget: Option<BudgetScope>,
+    ) -> Self {
+        Self {
+            status: response.status(),
+            headers: response.headers().clone(),
+            chain,
+            url: response.url().to_string(),
+            max_body_size,
+            budget,
+            received: 0,
+            state: State::Idle(response),
+        }
+    }
+
+    /// Value of the `Content-Type` header, if present and valid UTF-8.
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers
+            .get("content-type")
+            .and_then(|h| h.to_str().ok())
+    }
+
+    /// Value of the `Content-Length` header, if present.
+    pub fn content_length(&self) -> Option<u64> {
+        self.headers
+            .get("content-length")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|s| s.parse().ok())
+    }
+
+    /// Read the next body chunk.
+    ///
+    /// Returns `None` once the body is complete or after an error.
+    pub async fn next_chunk(&mut self) -> Option<Result<Bytes, Error>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+}
+
+impl Stream for FetchStream {
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match std::mem::replace(&mut this.state, State::Done) {
+                State::Done => return Poll::Ready(None),
+                State::Idle(mut response) => {
+                    let budget = this.budget.clone();
+                    let url = this.url.clone();
+                    this.state = State::Reading(Box::pin(async move {
+                        let chunk = BudgetScope::chunk(budget.as_ref(), &mut response, &url).await;
+                        (response, chunk)
+                    }));
+                }
+                State::Reading(mut future) => {
+                    let (response, chunk) = match future.as_mut().poll(cx) {
+                        Poll::Pending => {
+                            this.state = State::Reading(future);
+                            return Poll::Pending;
+                        }
+                        Poll::Ready(output) => output,
+                    };
+                    return Poll::Ready(match chunk {
+                        Ok(Some(chunk)) => {
+                            this.received += chunk.len() as u64;
+                            match this.max_body_size {
+                                Some(max) if this.received > max => {
+                                    Some(Err(Error::ResponseBlocked {
+                                        url: this.url.clone(),
+                                        reason: format!("body exceeds {} bytes", max),
+                                    }))
+                                }
+                                _ => {
+                                    this.state = State::Idle(response);
+                                    Some(Ok(chunk))
+                                }
+                            }
+                        }
+                        Ok(None) => None,
+                        Err(e) => Some(Err(e)),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Fetch a URL and stream its body, for proxying user-supplied URLs.
+///
+/// Redirects are validated as in [`fetch`](crate::fetch) and the final
+/// response is checked against `options` before it is returned. Set
+/// [`FetchOptions::max_body_size`] and [`FetchOptions::allowed_content_types`]
+/// when proxying untrusted URLs.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use url_jail::{fetch_stream, FetchOptions, Policy};
+///
+/// let opts = FetchOptions {
+///     max_body_size: Some(5 * 1024 * 1024),
+///     allowed_content_types: vec!["image/*".into()],
+///     ..Default::default()
+/// };
+/// let mut stream = fetch_stream(&user_url, Policy::PublicOnly, opts).await?;
+/// println!("{} {:?}", stream.status, stream.content_type());
+/// while let Some(chunk) = stream.next_chunk().await {
+///     sink.write_all(&chunk?).await?;
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns the errors of [`fetch_with_options`](crate::fetch_with_options).
+/// Body errors, including [`Error::ResponseBlocked`] when the size limit is
+/// exceeded mid-stream, are yielded by the stream.
+pub async fn fetch_stream(
+    url: &str,
+    policy: Policy,
+    options: FetchOptions,
+) -> Result<FetchStream, Error> {
+    let result = fetch_with_options(url, policy, options).await?;
+    Ok(FetchStream::new(
+        result.response,
+        result.chain,
+        result.max_body_size,
+        result.budget,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream_of(body: &'static str, max_body_size: Option<u64>) -> FetchStream {
+        let response = http::Response::builder()
+            .header("content-type", "image/png")
+            .header("content-length", body.len().to_string())
+            .body(body)
+            .unwrap();
+        FetchStream::new(Response::from(response), Vec::new(), max_body_size, None)
+    }
+
+    async fn collect(stream: &mut FetchStream) -> (Vec<u8>, Option<Error>) {
+        let mut body = Vec::new();
+        while let Some(chunk) = stream.next_chunk().await {
+            match chunk {
+                Ok(chunk) => body.extend_from_slice(&chunk),
+                Err(e) => return (body, Some(e)),
+            }
+        }
+        (body, None)
+    }
+
+    #[tokio::test]
+    async fn test_stream_reads_body() {
+        let mut stream = stream_of("hello world", None);
+        assert_eq!(stream.status, StatusCode::OK);
+        assert_eq!(stream.content_type(), Some("image/png"));
+        assert_eq!(stream.content_length(), Some(11));
+
+        let (body, err) = collect(&mut stream).await;
+        assert_eq!(body, b"hello world");
+        assert!(err.is_none());
+        assert!(stream.next_chunk().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_enforces_size_limit() {
+        let mut stream = stream_of("hello world", Some(5));
+        let (_, err) = collect(&mut stream).await;
+        assert!(matches!(err, Some(Error::ResponseBlocked { .. })));
+        // The stream ends after the limit error
+        assert!(stream.next_chunk().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_limit_is_inclusive() {
+        let mut stream = stream_of("hello", Some(5));
+        let (body, err) = collect(&mut stream).await;
+        assert_eq!(body, b"hello");
+        assert!(err.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_stream_blocked_url() {
+        let result = fetch_stream(
+            "http://169.254.169.254/latest/meta-data/",
+            Policy::PublicOnly,
+            FetchOptions::default(),
+        )
+        .await;
+        assert!(result.unwrap_err().is_blocked());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_stream_content_type_blocked() {
+        let opts = FetchOptions {
+            allowed_content_types: vec!["image/*".into()],
+            ..Default::default()
+        };
+        let result = fetch_stream("https://httpbin.org/html", Policy::PublicOnly, opts).await;
+        assert!(matches!(result, Err(Error::ResponseBlocked { .. })));
+    }
+}
@@ -0,0 +1,240 @@
+//! Signed validation tokens for cross-service trust.
+//!
+//! A front-end service that validates a URL can hand the result to a worker
+//! that performs the fetch. [`Validated::to_token`] encodes the result as a
+//! compact token, signed with HMAC-SHA256 under a key both services share
+//! and carrying an expiry; the worker calls [`Validated::from_token`] to
+//! check the signature and expiry instead of resolving the host again, and
+//! connects to the pinned address.
+//!
+//! Tokens are signed, not encrypted: the URL and addresses are readable by
+//! anyone who sees the token.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use url_jail::{validate, Policy, TokenKey, Validated};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let key = TokenKey::new(b"shared secret from the key store");
+//!
+//! // Front end
+//! let validated = validate("https://example.com/hook", Policy::PublicOnly).await?;
+//! let token = validated.to_token(&key, Duration::from_secs(30));
+//!
+//! // Worker
+//! let validated = Validated::from_token(&token, &key)?;
+//! println!("fetch {} via {}", validated.url, validated.to_socket_addr());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::hmac;
+
+use crate::validate::Validated;
+
+/// Version prefix of the token format.
+const VERSION: &str = "v1";
+
+/// Key for signing and verifying validation tokens.
+///
+/// Use at least 32 random bytes, shared only between the services that
+/// exchange tokens.
+#[derive(Clone)]
+pub struct TokenKey(hmac::Key);
+
+impl TokenKey {
+    /// Create a key from secret bytes.
+    pub fn new(secret: &[u8]) -> Self {
+        Self(hmac::Key::new(hmac::HMAC_SHA256, secret))
+    }
+}
+
+impl fmt::Debug for TokenKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TokenKey(..)")
+    }
+}
+
+impl Validated {
+    /// Encode this result as a token signed with `key`, valid for `ttl`.
+    ///
+    /// Keep `ttl` short: the addresses are only as trustworthy as the DNS
+    /// answer they came from.
+    pub fn to_token(&self, key: &TokenKey, ttl: Duration) -> String {
+        self.to_token_at(key, SystemTime::now() + ttl)
+    }
+
+    /// Encode this result as a token signed with `key`, valid until
+    /// `expires`.
+    pub fn to_token_at(&self, key: &TokenKey, expires: SystemTime) -> String {
+        let expires = expires
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let ips: Vec<String> = self.ips.iter().map(IpAddr::to_string).collect();
+        let payload = format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            expires,
+            self.ip,
+            ips.join(","),
+            self.host,
+            self.port,
+            u8::from(self.https),
+            self.url
+        );
+        let payload = URL_SAFE_NO_PAD.encode(payload);
+        let tag = hmac::sign(&key.0, signed_part(&payload).as_bytes());
+        format!(
+            "{}.{}",
+            signed_part(&payload),
+            URL_SAFE_NO_PAD.encode(tag.as_ref())
+        )
+    }
+
+    /// Verify a token from [`to_token`](Self::to_token) and decode it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a reason if the token is malformed, was not signed with
+    /// `key`, or has expired.
+    pub fn from_token(token: &str, key: &TokenKey) -> Result<Validated, String> {
+        Self::from_token_at(token, key, SystemTime::now())
+    }
+
+    /// Verify a token as of `now` and decode it.
+    ///
+    /// See [`from_token`](Self::from_token).
+    pub fn from_token_at(
+        token: &str,
+        key: &TokenKey,
+        now: SystemTime,
+    ) -> Result<Validated, String> {
+        let malformed = || "malformed token".to_string();
+        let (signed, tag) = token.trim().rsplit_once('.').ok_or_else(malformed)?;
+        let payload = signed
+            .strip_prefix(VERSION)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .ok_or_else(|| "unsupported token version".to_string())?;
+        let tag = URL_SAFE_NO_PAD.decode(tag).map_err(|_| malformed())?;
+        hmac::verify(&key.0, signed.as_bytes(), &tag)
+            .map_err(|_| "token signature is invalid".to_string())?;
+
+        // Signed by a holder of the key: the payload is well-formed unless
+        // the key is shared with an incompatible encoder
+        let payload = URL_SAFE_NO_PAD
+            .decode(payload)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(malformed)?;
+        let fields: Vec<&str> = payload.splitn(7, '\t').collect();
+        let [expires, ip, ips, host, port, https, url] = fields[..] else {
+            return Err(malformed());
+        };
+
+        let expires: u64 = expires.parse().map_err(|_| malformed())?;
+        let remaining = (SystemTime::UNIX_EPOCH + Duration::from_secs(expires))
+            .duration_since(now)
+            .ok()
+            .filter(|remaining| !remaining.is_zero())
+            .ok_or_else(|| "token has expired".to_string())?;
+        Ok(Validated {
+            ip: ip.parse().map_err(|_| malformed())?,
+            ips: ips
+                .split(',')
+                .map(|ip| ip.parse().map_err(|_| malformed()))
+                .collect::<Result<_, _>>()?,
+            host: host.to_string(),
+            port: port.parse().map_err(|_| malformed())?,
+            https: https == "1",
+            url: url.to_string(),
+            // The worker holds the addresses for as long as the token allows
+            valid_until: Some(now + remaining),
+            clock: Default::default(),
+        })
+    }
+}
+
+fn signed_part(payload: &str) -> String {
+    format!("{}.{}", VERSION, payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validated() -> Validated {
+        Validated {
+            ip: "93.184.216.34".parse().unwrap(),
+            ips: vec![
+                "93.184.216.34".parse().unwrap(),
+                "2606:2800:220:1::248".parse().unwrap(),
+            ],
+            host: "example.com".to_string(),
+            port: 443,
+            url: "https://example.com/hook?id=1".to_string(),
+            https: true,
+            valid_until: None,
+            clock: Default::default(),
+        }
+    }
+
+    fn key() -> TokenKey {
+        TokenKey::new(b"0123456789abcdef0123456789abcdef")
+    }
+
+    #[test]
+    fn test_token_round_trip() {
+        let original = validated();
+        let token = original.to_token(&key(), Duration::from_secs(30));
+        assert!(token.starts_with("v1."));
+        assert!(!token.contains(['\t', '/', '+', '=']), "{}", token);
+
+        let decoded = Validated::from_token(&token, &key()).unwrap();
+        assert_eq!(decoded.ip, original.ip);
+        assert_eq!(decoded.ips, original.ips);
+        assert_eq!(decoded.host, original.host);
+        assert_eq!(decoded.port, original.port);
+        assert_eq!(decoded.url, original.url);
+        assert!(decoded.https);
+        assert!(!decoded.is_expired());
+    }
+
+    #[test]
+    fn test_token_rejected_when_tampered_or_expired() {
+        let expires = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let token = validated().to_token_at(&key(), expires);
+        let before = expires - Duration::from_secs(1);
+        assert!(Validated::from_token_at(&token, &key(), before).is_ok());
+
+        let err = Validated::from_token_at(&token, &key(), expires).unwrap_err();
+        assert_eq!(err, "token has expired");
+
+        let other = TokenKey::new(b"another key");
+        let err = Validated::from_token_at(&token, &other, before).unwrap_err();
+        assert_eq!(err, "token signature is invalid");
+
+        // Swap in the payload of a token for another address
+        let mut evil = validated();
+        evil.ip = "169.254.169.254".parse().unwrap();
+        let evil_token = evil.to_token_at(&other, expires);
+        let (_, tag) = token.rsplit_once('.').unwrap();
+        let (evil_signed, _) = evil_token.rsplit_once('.').unwrap();
+        let forged = format!("{}.{}", evil_signed, tag);
+        assert!(Validated::from_token_at(&forged, &key(), before).is_err());
+
+        for bad in ["", "v1", "v2.abc.def", "v1.abc.%%%"] {
+            assert!(
+                Validated::from_token_at(bad, &key(), before).is_err(),
+                "{}",
+                bad
+            );
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Tower middleware for outbound request validation.
+//!
+//! [`ValidateLayer`] wraps any `Service<http::Request<B>>`, such as an HTTP
+//! client in an outbound tower stack. Every request URI is validated against
+//! the policy before the request reaches the inner service; blocked
+//! destinations fail with [`ValidateError::Rejected`] and are never sent.
+//!
+//! The [`Validated`] result is inserted into the request's extensions, so
+//! inner layers can log or pin the validated IP. The layer does not control
+//! how the inner service resolves the host: for DNS rebinding protection,
+//! build the client on [`PinnedConnector`](crate::PinnedConnector) (feature
+//! `hyper`) or [`pinned_client`](crate::pinned_client) (feature `fetch`).
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use tower::ServiceBuilder;
+//! use url_jail::tower::ValidateLayer;
+//! use url_jail::Policy;
+//!
+//! let client = ServiceBuilder::new()
+//!     .layer(ValidateLayer::new(Policy::PublicOnly))
+//!     .service(http_client);
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::error::Error;
+use crate::policy::{Policy, ValidationPolicy};
+use crate::validate::{validate_with_options, ValidateOptions, Validated};
+
+/// Error returned by [`ValidateService`].
+#[derive(Debug)]
+pub enum ValidateError<E> {
+    /// The request URI failed validation and was not sent.
+    Rejected(Error),
+
+    /// The inner service failed.
+    Service(E),
+}
+
+impl<E: fmt::Display> fmt::Display for ValidateError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidateError::Rejected(e) => write!(f, "request rejected: {}", e),
+            ValidateError::Service(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: StdError + 'static> StdError for ValidateError<E> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            ValidateError::Rejected(e) => Some(e),
+            ValidateError::Service(e) => Some(e),
+        }
+    }
+}
+
+/// A [`Layer`] that validates request URIs with [`ValidateService`].
+pub struct ValidateLayer<P: ValidationPolicy = Policy> {
+    policy: Arc<P>,
+    options: ValidateOptions,
+}
+
+impl<P: ValidationPolicy> ValidateLayer<P> {
+    /// Create a layer validating against `policy`.
+    pub fn new(policy: P) -> Self {
+        Self {
+            policy: Arc::new(policy),
+            options: ValidateOptions::default(),
+        }
+    }
+
+    /// Use `options` (DNS timeout, cache, ...) for validation.
+    pub fn with_options(mut self, options: ValidateOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl<P: ValidationPolicy> Clone for ValidateLayer<P> {
+    fn clone(&self) -> Self {
+        Self {
+            policy: self.policy.clone(),
+            options: self.options.clone(),
+        }
+    }
+}
+
+impl<P: ValidationPolicy> fmt::Debug for ValidateLayer<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValidateLayer")
+            .field("policy", &self.policy)
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl<S, P: ValidationPolicy> Layer<S> for ValidateLayer<P> {
+    type Service = ValidateService<S, P>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ValidateService {
+            inner,
+            policy: self.policy.clone(),
+            options: self.options.clone(),
+        }
+    }
+}
+
+/// A service that validates each request URI before calling `S`.
+///
+/// Created by [`ValidateLayer`].
+pub struct ValidateService<S, P: ValidationPolicy = Policy> {
+    inner: S,
+    policy: Arc<P>,
+    options: ValidateOptions,
+}
+
+impl<S: Clone, P: ValidationPolicy> Clone for ValidateService<S, P> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            policy: self.policy.clone(),
+            options: self.options.clone(),
+        }
+    }
+}
+
+impl<S: fmt::Debug, P: ValidationPolicy> fmt::Debug for ValidateService<S, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValidateService")
+            .field("inner", &self.inner)
+            .field("policy", &self.policy)
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl<S, P, B> Service<http::Request<B>> for ValidateService<S, P>
+where
+    S: Service<http::Request<B>> + Clone + Send + 'static,
+    S::Future: Send,
+    P: ValidationPolicy + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = ValidateError<S::Error>;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(ValidateError::Service)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        // Keep the instance that was polled ready; the clone serves the next call
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let policy = self.policy.clone();
+        let options = self.options.clone();
+        Box::pin(async move {
+            let url = request.uri().to_string();
+            let validated: Validated = validate_with_options(&url, &*policy, options)
+                .await
+                .map_err(ValidateError::Rejected)?;
+            request.extensions_mut().insert(validated);
+            inner.call(request).await.map_err(ValidateError::Service)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use std::future::Ready;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Echoes the validated IP from the request extensions.
+    #[derive(Clone, Default)]
+    struct Echo(Arc<AtomicUsize>);
+
+    impl Service<http::Request<()>> for Echo {
+        type Response = String;
+        type Error = Infallible;
+        type Future = Ready<Result<String, Infallible>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<()>) -> Self::Future {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            let validated = request.extensions().get::<Validated>().unwrap();
+            std::future::ready(Ok(validated.ip.to_string()))
+        }
+    }
+
+    fn request(uri: &str) -> http::Request<()> {
+        http::Request::get(uri).body(()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_allowed_request_reaches_inner() {
+        let echo = Echo::default();
+        let mut service = ValidateLayer::new(Policy::PublicOnly).layer(echo.clone());
+        let ip = service
+            .call(request("https://93.184.216.34/path"))
+            .await
+            .unwrap();
+        assert_eq!(ip, "93.184.216.34");
+        assert_eq!(echo.0.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_blocked_request_never_sent() {
+        let echo = Echo::default();
+        let mut service = ValidateLayer::new(Policy::AllowPrivate).layer(echo.clone());
+        for uri in ["http://169.254.169.254/latest/meta-data/", "/relative"] {
+            let err = service.call(request(uri)).await.unwrap_err();
+            assert!(matches!(err, ValidateError::Rejected(_)), "{}", err);
+        }
+        assert_eq!(echo.0.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,334 @@
+//! Opt-in Unix domain socket targets.
+//!
+//! Some platforms intentionally proxy to local sockets (a Docker API, a
+//! sidecar). [`validate_target`] accepts `http+unix://` URLs, with the
+//! percent-encoded socket path as the host:
+//!
+//! ```text
+//! http+unix://%2Fvar%2Frun%2Fdocker.sock/v1.41/containers/json
+//! ```
+//!
+//! Socket paths must lie inside a directory allowed by a
+//! [`UnixSocketPolicy`]; the default policy allows none. Every other URL is
+//! validated as usual. [`validate`](crate::validate) itself never accepts
+//! `http+unix://` URLs.
+
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use url::Url;
+
+use crate::error::Error;
+use crate::policy::ValidationPolicy;
+use crate::validate::{validate_with_options, ValidateOptions, Validated};
+
+/// The URL prefix for Unix domain socket targets.
+const UNIX_PREFIX: &str = "http+unix://";
+
+/// Directories that Unix domain socket targets may live in.
+///
+/// # Example
+///
+/// ```rust
+/// use url_jail::UnixSocketPolicy;
+///
+/// let sockets = UnixSocketPolicy::new().allow_dir("/run/sidecar");
+/// assert!(sockets.check("/run/sidecar/api.sock").is_ok());
+/// assert!(sockets.check("/run/sidecar/../docker.sock").is_err());
+/// assert!(sockets.check("/var/run/docker.sock").is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnixSocketPolicy {
+    dirs: Vec<PathBuf>,
+}
+
+impl UnixSocketPolicy {
+    /// A policy allowing no sockets.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow sockets anywhere under `dir`, which must be absolute.
+    pub fn allow_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dirs.push(dir.into());
+        self
+    }
+
+    /// The allowed directories.
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    /// Check a socket path against the allowed directories.
+    ///
+    /// The path must be absolute and free of `.` and `..` components. If the
+    /// path exists, symlinks are resolved and the real path must be allowed
+    /// too.
+    ///
+    /// Returns a reason on rejection.
+    pub fn check(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        if !path.is_absolute() {
+            return Err("socket path must be absolute".to_string());
+        }
+        if path
+            .components()
+            .any(|c| matches!(c, Component::CurDir | Component::ParentDir))
+        {
+            return Err("socket path must not contain '.' or '..'".to_string());
+        }
+        if !self.contains(path, false) {
+            return Err("socket path is outside the allowed directories".to_string());
+        }
+        if let Ok(real) = path.canonicalize() {
+            if !self.contains(&real, true) {
+                return Err(format!(
+                    "socket path resolves to {} outside the allowed directories",
+                    real.display()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn contains(&self, path: &Path, canonical: bool) -> bool {
+        self.dirs.iter().filter(|dir| dir.is_absolute()).any(|dir| {
+            let dir = if canonical {
+                dir.canonicalize().unwrap_or_else(|_| dir.clone())
+            } else {
+                dir.clone()
+            };
+            path.starts_with(&dir) && path != dir
+        })
+    }
+}
+
+/// A Unix domain socket target that passed validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatedSocket {
+    /// The socket to connect to.
+    pub socket_path: PathBuf,
+
+    /// The request path and query to send, e.g. `/v1.41/containers/json`.
+    pub request_path: String,
+
+    /// Full URL (normalized).
+    pub url: String,
+}
+
+/// A validated target: a TCP address or an allowed Unix domain socket.
+#[derive(Debug, Clone)]
+pub enum Target {
+    /// An `http://` or `https://` URL, pinned to a validated IP.
+    Tcp(Validated),
+
+    /// An `http+unix://` URL for an allowed socket.
+    Unix(ValidatedSocket),
+}
+
+impl Target {
+    /// The normalized URL.
+    pub fn url(&self) -> &str {
+        match self {
+            Target::Tcp(v) => &v.url,
+            Target::Unix(s) => &s.url,
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Tcp(v) => write!(f, "{} via {}", v.url, v.to_socket_addr()),
+            Target::Unix(s) => write!(f, "{} via {}", s.url, s.socket_path.display()),
+        }
+    }
+}
+
+/// Validate a URL that may target a Unix domain socket.
+///
+/// `http+unix://` URLs are checked against `sockets`; every other URL is
+/// validated against `policy` as by [`validate`](crate::validate).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use url_jail::{validate_target, Policy, Target, UnixSocketPolicy};
+///
+/// # async fn example() -> Result<(), url_jail::Error> {
+/// let sockets = UnixSocketPolicy::new().allow_dir("/var/run");
+/// let url = "http+unix://%2Fvar%2Frun%2Fdocker.sock/v1.41/containers/json";
+/// match validate_target(url, Policy::PublicOnly, &sockets).await? {
+///     Target::Unix(s) => println!("{} on {}", s.request_path, s.socket_path.display()),
+///     Target::Tcp(v) => println!("{} at {}", v.url, v.ip),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidUrl`] for a malformed `http+unix://` URL and
+/// [`Error::HostnameBlocked`] (with the socket path as host) for a socket
+/// outside the allowed directories, plus any error from
+/// [`validate`](crate::validate).
+pub async fn validate_target<P: ValidationPolicy>(
+    url: &str,
+    policy: P,
+    sockets: &UnixSocketPolicy,
+) -> Result<Target, Error> {
+    validate_target_with_options(url, policy, sockets, ValidateOptions::default()).await
+}
+
+/// Validate a URL that may target a Unix domain socket, with `options` for
+/// TCP targets.
+///
+/// See [`validate_target`].
+pub async fn validate_target_with_options<P: ValidationPolicy>(
+    url: &str,
+    policy: P,
+    sockets: &UnixSocketPolicy,
+    options: ValidateOptions,
+) -> Result<Target, Error> {
+    if is_unix_url(url) {
+        return validate_socket(url, sockets).map(Target::Unix);
+    }
+    validate_with_options(url, policy, options)
+        .await
+        .map(Target::Tcp)
+}
+
+fn is_unix_url(url: &str) -> bool {
+    url.trim_start()
+        .get(..UNIX_PREFIX.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(UNIX_PREFIX))
+}
+
+fn validate_socket(url: &str, sockets: &UnixSocketPolicy) -> Result<ValidatedSocket, Error> {
+    let invalid = |reason: &str| Error::InvalidUrl {
+        url: url.to_string(),
+        reason: reason.to_string(),
+    };
+    let parsed = Url::parse(url.trim()).map_err(|e| invalid(&e.to_string()))?;
+    if parsed.port().is_some() || !parsed.username().is_empty() || parsed.password().is_some() {
+        return Err(invalid("http+unix URLs cannot carry a port or credentials"));
+    }
+    let encoded = parsed
+        .host_str()
+        .filter(|host| !host.is_empty())
+        .ok_or_else(|| invalid("missing socket path"))?;
+    let socket_path = percent_decode(encoded)
+        .filter(|path| !path.contains('\0'))
+        .ok_or_else(|| invalid("socket path is not valid percent-encoded UTF-8"))?;
+
+    sockets
+        .check(&socket_path)
+        .map_err(|reason| Error::HostnameBlocked {
+            url: url.to_string(),
+            host: socket_path.clone(),
+            reason,
+        })?;
+
+    let request_path = match parsed.query() {
+        Some(query) => format!("{}?{}", parsed.path(), query),
+        None => parsed.path().to_string(),
+    };
+    Ok(ValidatedSocket {
+        socket_path: PathBuf::from(socket_path),
+        request_path,
+        url: parsed.to_string(),
+    })
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Policy;
+
+    fn sockets() -> UnixSocketPolicy {
+        UnixSocketPolicy::new().allow_dir("/run/sidecar")
+    }
+
+    #[test]
+    fn test_socket_url_parsed() {
+        let url = "http+unix://%2Frun%2Fsidecar%2Fapi.sock/v1/items?limit=5";
+        let socket = validate_socket(url, &sockets()).unwrap();
+        assert_eq!(socket.socket_path, Path::new("/run/sidecar/api.sock"));
+        assert_eq!(socket.request_path, "/v1/items?limit=5");
+        assert_eq!(socket.url, url);
+    }
+
+    #[test]
+    fn test_socket_outside_allowlist_blocked() {
+        for path in [
+            "%2Frun%2Fdocker.sock",
+            "%2Frun%2Fsidecar%2F..%2Fdocker.sock",
+            "%2Frun%2Fsidecar",
+            "sidecar.sock",
+        ] {
+            let url = format!("http+unix://{}/", path);
+            let err = validate_socket(&url, &sockets()).unwrap_err();
+            assert!(err.is_blocked(), "{}: {:?}", path, err);
+        }
+
+        let err = validate_socket("http+unix://%2Frun%2Fx.sock/", &UnixSocketPolicy::new());
+        assert!(err.unwrap_err().is_blocked());
+    }
+
+    #[test]
+    fn test_malformed_socket_url_rejected() {
+        for url in [
+            "http+unix:///path",
+            "http+unix://%2Frun%2Fsidecar%2Fa.sock:80/",
+            "http+unix://%2Frun%2Fsidecar%2F%00.sock/",
+            "http+unix://%2Frun%2Fsidecar%2F%zz/",
+        ] {
+            let err = validate_socket(url, &sockets()).unwrap_err();
+            assert!(
+                matches!(err, Error::InvalidUrl { .. }),
+                "{}: {:?}",
+                url,
+                err
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_target_dispatch() {
+        let unix = validate_target(
+            "HTTP+UNIX://%2Frun%2Fsidecar%2Fa.sock/",
+            Policy::PublicOnly,
+            &sockets(),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(unix, Target::Unix(_)));
+
+        let tcp = validate_target("http://93.184.216.34/", Policy::PublicOnly, &sockets())
+            .await
+            .unwrap();
+        assert!(matches!(tcp, Target::Tcp(ref v) if v.ip.to_string() == "93.184.216.34"));
+
+        // Plain validation never accepts socket URLs
+        let err =
+            crate::validate("http+unix://%2Frun%2Fsidecar%2Fa.sock/", Policy::PublicOnly).await;
+        assert!(err.is_err());
+    }
+}
@@ -1,14 +1,25 @@
 //! URL validation with DNS resolution.
 
+use std::collections::HashMap;
+use std::fmt;
 use std::net::{IpAddr, SocketAddr};
-use std::time::Duration;
-
-use hickory_resolver::TokioResolver;
-
-use crate::blocklist::{is_hostname_blocked, is_ip_blocked};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::cache::ValidationCache;
+use crate::clock::SharedClock;
+use crate::confusable::{self, Confusables};
+use crate::context::Context;
+use crate::encoding::PercentEncoding;
 use crate::error::Error;
-use crate::policy::Policy;
-use crate::safe_url::SafeUrl;
+#[cfg(feature = "fetch")]
+use crate::export::DecisionExporter;
+use crate::latency::LatencyBudget;
+use crate::machine::Step;
+use crate::policy::ValidationPolicy;
+use crate::recorder::Recorder;
+use crate::resolver::{build_resolver, lookup_permit, Resolve};
+use crate::safe_url::{SafeUrl, ZoneIds};
 
 /// Options for URL validation.
 ///
@@ -24,6 +35,7 @@ use crate::safe_url::SafeUrl;
 /// # async fn example() -> Result<(), url_jail::Error> {
 /// let opts = ValidateOptions {
 ///     dns_timeout: Duration::from_secs(5),
+///     ..Default::default()
 /// };
 /// let result = validate_with_options("https://example.com/", Policy::PublicOnly, opts).await?;
 /// # Ok(())
@@ -36,22 +48,172 @@ pub struct ValidateOptions {
     /// If DNS resolution takes longer than this, a [`Error::Timeout`] is returned.
     /// Default: 30 seconds.
     pub dns_timeout: Duration,
+
+    /// Cache of successful validations, shared between calls.
+    ///
+    /// Entries are partitioned by policy fingerprint, so a result cached under
+    /// one policy is never reused for another. Default: `None` (no caching).
+    pub cache: Option<Arc<ValidationCache>>,
+
+    /// Behavior when the DNS resolver is unreachable or times out.
+    ///
+    /// Default: [`DnsFallback::FailClosed`].
+    pub dns_fallback: DnsFallback,
+
+    /// Caller-supplied tags (tenant, feature, request id) for audit logs.
+    ///
+    /// Metadata only: tags are attached to `tracing` events and appended to
+    /// error messages, but never affect the decision. Default: empty.
+    pub context: Context,
+
+    /// Recorder capturing each DNS-backed decision for later replay.
+    ///
+    /// Default: `None` (nothing is recorded).
+    pub recorder: Option<Arc<Recorder>>,
+
+    /// Exporter sending every decision to a telemetry endpoint in batches.
+    ///
+    /// Default: `None` (nothing is exported).
+    #[cfg(feature = "fetch")]
+    pub exporter: Option<DecisionExporter>,
+
+    /// Latency budget per validation, flagging slow validations.
+    ///
+    /// Default: `None`.
+    pub latency_budget: Option<LatencyBudget>,
+
+    /// Strict percent-encoding rules for the path and query.
+    ///
+    /// Default: `None` (escapes in the path and query are passed through).
+    pub percent_encoding: Option<PercentEncoding>,
+
+    /// Re-parse each URL with `url::Url` and `http::Uri` and fail validation
+    /// with [`Error::AmbiguousUrl`] if either sees a different scheme, host,
+    /// or port. See [`SafeUrl::cross_check`](crate::SafeUrl::cross_check).
+    ///
+    /// Default: `false`.
+    pub cross_check_parsers: bool,
+
+    /// Run every check and report all violations, instead of failing at
+    /// the first.
+    ///
+    /// Useful when a user configures a URL in a form: one pass shows that a
+    /// webhook is both plain `http` and on a blocked port. If several
+    /// checks fail the error is an [`Error::Violations`]; see
+    /// [`ValidationMachine::collect_violations`](crate::ValidationMachine::collect_violations).
+    /// The hostname is resolved even if a hostname rule rejects it.
+    ///
+    /// Default: `false`.
+    pub collect_violations: bool,
+
+    /// How to treat an IPv6 zone identifier (`http://[fe80::1%25eth0]/`).
+    /// See [`ValidationMachine::zone_ids`](crate::ValidationMachine::zone_ids).
+    ///
+    /// Default: [`ZoneIds::Reject`].
+    pub zone_ids: ZoneIds,
+
+    /// How to treat a hostname that could imitate another one.
+    /// See [`ValidationMachine::confusables`](crate::ValidationMachine::confusables).
+    ///
+    /// Default: [`Confusables::Allow`].
+    pub confusables: Confusables,
+
+    /// Resolver to use instead of the system resolver.
+    ///
+    /// Answers are checked, cached and recorded like system resolver
+    /// answers, except that no TTL is recorded. See [`Resolve`].
+    ///
+    /// Default: `None` (the system resolver).
+    pub resolver: Option<Arc<dyn Resolve>>,
 }
 
 impl Default for ValidateOptions {
     fn default() -> Self {
         Self {
             dns_timeout: Duration::from_secs(30),
+            cache: None,
+            dns_fallback: DnsFallback::FailClosed,
+            context: Context::new(),
+            recorder: None,
+            #[cfg(feature = "fetch")]
+            exporter: None,
+            latency_budget: None,
+            percent_encoding: None,
+            cross_check_parsers: false,
+            collect_violations: false,
+            zone_ids: ZoneIds::Reject,
+            confusables: Confusables::Allow,
+            resolver: None,
         }
     }
 }
 
+/// What to do when the DNS resolver is unavailable.
+///
+/// A fallback applies only when the resolver cannot be reached or times out.
+/// A definitive answer that a name does not exist (NXDOMAIN, no records)
+/// always fails. Addresses produced by a fallback are still checked against
+/// the policy, and are never written to the cache.
+///
+/// # Example
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use std::time::Duration;
+/// use url_jail::{DnsFallback, ValidateOptions};
+///
+/// // Keep talking to the payments API during a resolver outage
+/// let opts = ValidateOptions {
+///     dns_fallback: DnsFallback::FailOpen {
+///         hosts: HashMap::from([("api.payments.example".to_string(), "203.0.113.10".parse().unwrap())]),
+///     },
+///     ..Default::default()
+/// };
+///
+/// // Or reuse cached results up to 5 minutes past their TTL
+/// let opts = ValidateOptions {
+///     dns_fallback: DnsFallback::ServeStale { max_staleness: Duration::from_secs(300) },
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DnsFallback {
+    /// Return the DNS error. This is the default.
+    #[default]
+    FailClosed,
+
+    /// Use operator-supplied addresses for explicitly allowlisted hostnames.
+    ///
+    /// Hostnames are matched case-insensitively and exactly (no wildcards).
+    /// Every other hostname fails closed.
+    FailOpen {
+        /// Hostname to the address to use while DNS is down.
+        hosts: HashMap<String, IpAddr>,
+    },
+
+    /// Serve expired entries from [`ValidateOptions::cache`].
+    ///
+    /// Entries are used at most `max_staleness` past their expiry. Fails
+    /// closed if no cache is configured or no entry is fresh enough.
+    ServeStale {
+        /// How long past expiry a cached entry may still be used.
+        max_staleness: Duration,
+    },
+}
+
 /// Result of successful URL validation.
 #[derive(Debug, Clone)]
 pub struct Validated {
     /// The verified IP address to connect to.
     pub ip: IpAddr,
 
+    /// Every address the host resolved to, all of them checked against the
+    /// policy, in resolver order. [`ip`](Self::ip) is the first.
+    ///
+    /// A result served from a [`ValidationCache`] holds only the cached
+    /// address.
+    pub ips: Vec<IpAddr>,
+
     /// Original hostname (use for Host header / SNI).
     pub host: String,
 
@@ -63,6 +225,21 @@ pub struct Validated {
 
     /// Whether HTTPS.
     pub https: bool,
+
+    /// When the DNS answer behind [`ips`](Self::ips) expires, or `None` if
+    /// the host is an IP literal, which cannot go stale.
+    ///
+    /// Answers with no known TTL, such as those from a custom
+    /// [`Resolve`](crate::Resolve), a [`ValidationCache`] hit or a
+    /// [`DnsFallback`], are expired from the start, so
+    /// [`revalidate`](Self::revalidate) always resolves them again.
+    ///
+    /// The time is read from the [`ValidationCache`]'s clock, if the
+    /// validation used one, or the system clock.
+    pub valid_until: Option<SystemTime>,
+
+    /// The clock [`valid_until`](Self::valid_until) is measured on.
+    pub(crate) clock: SharedClock,
 }
 
 impl Validated {
@@ -70,6 +247,227 @@ impl Validated {
     pub fn to_socket_addr(&self) -> SocketAddr {
         SocketAddr::new(self.ip, self.port)
     }
+
+    /// The scheme, host and port this result is for, as a key for
+    /// connection pools, rate limiters and caches.
+    ///
+    /// Results for the same target compare equal whatever their path,
+    /// query, resolved addresses or expiry.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{validate_sync, Policy};
+    ///
+    /// let a = validate_sync("https://93.184.216.34/a", Policy::PublicOnly).unwrap();
+    /// let b = validate_sync("https://93.184.216.34:443/b?q", Policy::PublicOnly).unwrap();
+    /// assert_eq!(a.target_key(), b.target_key());
+    /// assert_eq!(a.target_key().to_string(), "https://93.184.216.34:443");
+    /// ```
+    pub fn target_key(&self) -> TargetKey {
+        let host = self.host.to_ascii_lowercase();
+        TargetKey {
+            https: self.https,
+            host: match host.strip_suffix('.') {
+                Some(host) => host.to_string(),
+                None => host,
+            },
+            port: self.port,
+        }
+    }
+
+    /// The hostname with punycode labels decoded, for display.
+    /// [`host`](Self::host) holds the ASCII form that was resolved.
+    pub fn host_unicode(&self) -> String {
+        confusable::to_unicode(&self.host)
+    }
+
+    /// Why the hostname could imitate another one, if it could.
+    /// See [`SafeUrl::confusable`].
+    pub fn confusable(&self) -> Option<String> {
+        confusable::check_host(&self.host)
+    }
+
+    /// Get the `(domain, addresses)` pair for a resolver override.
+    ///
+    /// The result matches the arguments of
+    /// `reqwest::ClientBuilder::resolve_to_addrs`, so the HTTP client connects
+    /// to a validated address instead of performing its own DNS lookup. Every
+    /// address in [`ips`](Self::ips) is included, so the client can fall back
+    /// to the next one if a connection fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let v = url_jail::validate("https://example.com/", url_jail::Policy::PublicOnly).await?;
+    /// let (domain, addrs) = v.resolve_to_addrs();
+    /// let client = reqwest::Client::builder()
+    ///     .resolve_to_addrs(domain, &addrs)
+    ///     .build()?;
+    /// ```
+    pub fn resolve_to_addrs(&self) -> (&str, Vec<SocketAddr>) {
+        if self.ips.is_empty() {
+            return (&self.host, vec![self.to_socket_addr()]);
+        }
+        let addrs = self
+            .ips
+            .iter()
+            .map(|&ip| SocketAddr::new(ip, self.port))
+            .collect();
+        (&self.host, addrs)
+    }
+
+    /// The curl `--resolve` value pinning the host to the validated address,
+    /// e.g. `example.com:443:93.184.216.34`.
+    pub fn curl_resolve(&self) -> String {
+        format!("{}:{}:{}", self.host, self.port, self.bracketed_ip())
+    }
+
+    /// The curl `--connect-to` value routing connections for the host to the
+    /// validated address, e.g. `example.com:443:93.184.216.34:443`.
+    pub fn curl_connect_to(&self) -> String {
+        format!(
+            "{}:{}:{}:{}",
+            self.host,
+            self.port,
+            self.bracketed_ip(),
+            self.port
+        )
+    }
+
+    /// A complete curl argument vector for fetching the validated URL.
+    ///
+    /// Pins the host with `--resolve` and disables what would let curl reach
+    /// a different address: proxies (`--noproxy '*'`), redirects
+    /// (`--max-redirs 0`), and protocols other than HTTP(S). Pass the
+    /// elements as separate arguments (no shell quoting needed); add further
+    /// options before the URL, which comes last.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail::{validate_sync, Policy};
+    ///
+    /// let v = validate_sync("https://93.184.216.34/data", Policy::PublicOnly)?;
+    /// assert_eq!(
+    ///     v.curl_args(),
+    ///     [
+    ///         "--resolve", "93.184.216.34:443:93.184.216.34",
+    ///         "--noproxy", "*",
+    ///         "--max-redirs", "0",
+    ///         "--proto", "=http,https",
+    ///         "https://93.184.216.34/data",
+    ///     ]
+    /// );
+    /// // std::process::Command::new("curl").args(v.curl_args()).status()?;
+    /// # Ok::<(), url_jail::Error>(())
+    /// ```
+    pub fn curl_args(&self) -> Vec<String> {
+        vec![
+            "--resolve".to_string(),
+            self.curl_resolve(),
+            "--noproxy".to_string(),
+            "*".to_string(),
+            "--max-redirs".to_string(),
+            "0".to_string(),
+            "--proto".to_string(),
+            "=http,https".to_string(),
+            self.url.clone(),
+        ]
+    }
+
+    /// Returns `true` once [`valid_until`](Self::valid_until) has passed.
+    pub fn is_expired(&self) -> bool {
+        self.valid_until
+            .is_some_and(|until| until <= self.clock.now())
+    }
+
+    /// Validate [`url`](Self::url) again if this result has expired,
+    /// otherwise return it unchanged.
+    ///
+    /// For long-lived workers that keep results around: call this before
+    /// each use instead of trusting an address past its DNS TTL. A result
+    /// that has not expired is returned without checking `policy`, so a
+    /// policy change takes effect once it expires.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use url_jail::{validate, Policy};
+    ///
+    /// # async fn example() -> Result<(), url_jail::Error> {
+    /// let mut validated = validate("https://example.com/hook", Policy::PublicOnly).await?;
+    /// // Later, per delivery
+    /// validated = validated.revalidate(Policy::PublicOnly).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// As [`validate`].
+    pub async fn revalidate<P: ValidationPolicy>(&self, policy: P) -> Result<Validated, Error> {
+        self.revalidate_with_options(policy, ValidateOptions::default())
+            .await
+    }
+
+    /// [`revalidate`](Self::revalidate) with custom options.
+    pub async fn revalidate_with_options<P: ValidationPolicy>(
+        &self,
+        policy: P,
+        options: ValidateOptions,
+    ) -> Result<Validated, Error> {
+        if !self.is_expired() {
+            return Ok(self.clone());
+        }
+        validate_with_options(&self.url, policy, options).await
+    }
+
+    /// The IP in URL/curl form: IPv6 addresses are bracketed.
+    fn bracketed_ip(&self) -> String {
+        match self.ip {
+            IpAddr::V4(v4) => v4.to_string(),
+            IpAddr::V6(v6) => format!("[{}]", v6),
+        }
+    }
+}
+
+/// Identity of a validated target: scheme, normalized host and port.
+///
+/// Returned by [`Validated::target_key`]. Displays as
+/// `<scheme>://<host>:<port>`, with IPv6 hosts in brackets, suitable as a
+/// key in external stores.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TargetKey {
+    /// Whether HTTPS.
+    pub https: bool,
+
+    /// Lowercase hostname without a trailing dot, or IP address.
+    pub host: String,
+
+    /// Port number, with the scheme's default filled in.
+    pub port: u16,
+}
+
+impl TargetKey {
+    /// `"https"` or `"http"`.
+    pub fn scheme(&self) -> &'static str {
+        if self.https {
+            "https"
+        } else {
+            "http"
+        }
+    }
+}
+
+impl fmt::Display for TargetKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') && !self.host.starts_with('[') {
+            write!(f, "{}://[{}]:{}", self.scheme(), self.host, self.port)
+        } else {
+            write!(f, "{}://{}:{}", self.scheme(), self.host, self.port)
+        }
+    }
 }
 
 /// Validate a URL, resolve DNS, and check the IP against the policy.
@@ -80,14 +478,20 @@ impl Validated {
 /// 3. Resolves DNS to get the IP address
 /// 4. Checks the IP against the policy
 ///
+/// `policy` is any [`ValidationPolicy`]: a [`Policy`](crate::Policy) or a
+/// [`&CustomPolicy`](crate::CustomPolicy).
+///
 /// # Example
 ///
 /// ```rust,no_run
-/// use url_jail::{validate, Policy};
+/// use url_jail::{validate, Policy, PolicyBuilder};
 ///
 /// # async fn example() -> Result<(), url_jail::Error> {
 /// let result = validate("https://example.com/api", Policy::PublicOnly).await?;
 /// println!("Safe to connect to {} ({})", result.host, result.ip);
+///
+/// let custom = PolicyBuilder::new(Policy::PublicOnly).block_host("*.corp.example").build();
+/// let result = validate("https://example.com/api", &custom).await?;
 /// # Ok(())
 /// # }
 /// ```
@@ -99,56 +503,103 @@ impl Validated {
 /// - The hostname is in the blocklist
 /// - DNS resolution fails or times out
 /// - The resolved IP is blocked by the policy
-pub async fn validate(url: &str, policy: Policy) -> Result<Validated, Error> {
+pub async fn validate<P: ValidationPolicy>(url: &str, policy: P) -> Result<Validated, Error> {
     validate_with_options(url, policy, ValidateOptions::default()).await
 }
 
 /// Validate a URL with custom options.
 ///
 /// See [`validate`] for details.
-#[cfg_attr(feature = "tracing", tracing::instrument(skip(options), fields(host)))]
-pub async fn validate_with_options(
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(options), fields(host, context))
+)]
+pub async fn validate_with_options<P: ValidationPolicy>(
     url: &str,
-    policy: Policy,
+    policy: P,
     options: ValidateOptions,
 ) -> Result<Validated, Error> {
-    let safe_url = SafeUrl::parse(url)?;
-
-    #[cfg(feature = "tracing")]
-    tracing::Span::current().record("host", safe_url.host());
-
-    if let Some(blocked_host) = is_hostname_blocked(safe_url.host()) {
-        #[cfg(feature = "tracing")]
-        tracing::warn!(host = safe_url.host(), "hostname blocked");
-        return Err(Error::hostname_blocked(
-            url,
-            safe_url.host(),
-            format!(
-                "matches blocked pattern '{}' (cloud metadata)",
-                blocked_host
-            ),
-        ));
+    let started = Instant::now();
+    let mut checked = match &options.percent_encoding {
+        Some(rules) => rules.check(url),
+        None => Ok(()),
+    };
+    if checked.is_ok() && options.cross_check_parsers {
+        checked = cross_check(url);
+    }
+    let result = match checked {
+        Ok(()) => {
+            let mut machine = policy
+                .machine(url)
+                .zone_ids(options.zone_ids)
+                .confusables(options.confusables);
+            if let Some(cache) = &options.cache {
+                machine = machine.clock(cache.clock());
+            }
+            if options.collect_violations {
+                machine = machine.collect_violations();
+            }
+            drive(machine.start(), &options, policy.fingerprint()).await
+        }
+        Err(e) => Err(e),
+    };
+    if let Some(budget) = &options.latency_budget {
+        budget.observe(url, started.elapsed(), result.is_ok());
     }
+    #[cfg(feature = "fetch")]
+    if let Some(exporter) = &options.exporter {
+        exporter.observe(url, &options.context, &result);
+    }
+    result
+}
 
-    // Resolve DNS and check ALL returned IPs against policy
-    let ip = resolve_and_verify_dns(safe_url.host(), options.dns_timeout, policy).await?;
+/// Validate a URL, resolving its hostname with `resolver`.
+///
+/// Use this when the system resolver gives different answers from the one
+/// production traffic uses, as with split-horizon DNS. Shorthand for
+/// [`validate_with_options`] with [`ValidateOptions::resolver`] set.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::sync::Arc;
+/// use url_jail::{validate_with_resolver, Policy, Resolve};
+///
+/// # async fn example(internal_dns: Arc<dyn Resolve>) -> Result<(), url_jail::Error> {
+/// let validated =
+///     validate_with_resolver("https://api.example.com/", Policy::PublicOnly, internal_dns).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn validate_with_resolver<P: ValidationPolicy>(
+    url: &str,
+    policy: P,
+    resolver: impl Resolve + 'static,
+) -> Result<Validated, Error> {
+    let options = ValidateOptions {
+        resolver: Some(Arc::new(resolver)),
+        ..Default::default()
+    };
+    validate_with_options(url, policy, options).await
+}
 
-    #[cfg(feature = "tracing")]
-    tracing::debug!(%ip, host = safe_url.host(), "URL validated successfully");
-
-    Ok(Validated {
-        ip,
-        host: safe_url.host().to_string(),
-        port: safe_url.port(),
-        url: safe_url.as_str().to_string(),
-        https: safe_url.is_https(),
-    })
+/// Cross-check the parsers on a URL that `SafeUrl` accepts.
+///
+/// URLs `SafeUrl` rejects are left to the machine, which reports why.
+fn cross_check(url: &str) -> Result<(), Error> {
+    match SafeUrl::parse(url) {
+        Ok(safe_url) => safe_url
+            .cross_check()
+            .and_then(|()| safe_url.cross_check_input(url)),
+        Err(_) => Ok(()),
+    }
 }
 
 /// Validate a URL with a custom policy.
 ///
 /// This allows using `CustomPolicy` created via `PolicyBuilder` for
 /// fine-grained control over what IPs and hostnames are allowed.
+/// Equivalent to `validate(url, policy)`.
 ///
 /// # Example
 ///
@@ -177,39 +628,7 @@ pub async fn validate_custom_with_options(
     policy: &crate::policy_builder::CustomPolicy,
     options: ValidateOptions,
 ) -> Result<Validated, Error> {
-    let safe_url = SafeUrl::parse(url)?;
-
-    // Check hostname against custom policy
-    if let Err(reason) = policy.is_hostname_allowed(safe_url.host()) {
-        return Err(Error::hostname_blocked(url, safe_url.host(), reason));
-    }
-
-    // Check built-in hostname blocklist
-    if let Some(blocked_host) = is_hostname_blocked(safe_url.host()) {
-        return Err(Error::hostname_blocked(
-            url,
-            safe_url.host(),
-            format!(
-                "matches blocked pattern '{}' (cloud metadata)",
-                blocked_host
-            ),
-        ));
-    }
-
-    let ip = resolve_dns_with_timeout(safe_url.host(), options.dns_timeout).await?;
-
-    // Check IP against custom policy
-    if let Err(reason) = policy.is_ip_allowed(ip) {
-        return Err(Error::ssrf_blocked(url, ip, reason));
-    }
-
-    Ok(Validated {
-        ip,
-        host: safe_url.host().to_string(),
-        port: safe_url.port(),
-        url: safe_url.as_str().to_string(),
-        https: safe_url.is_https(),
-    })
+    validate_with_options(url, policy, options).await
 }
 
 /// Synchronous version of [`validate`].
@@ -219,6 +638,8 @@ pub async fn validate_custom_with_options(
 ///
 /// This function works both inside and outside of a Tokio runtime.
 /// When called from outside a runtime, it creates a temporary one.
+/// Inside a runtime it uses `block_in_place`, which panics on a
+/// single-threaded runtime; use [`validate_sync_with_timeout`] there.
 ///
 /// # Example
 ///
@@ -229,7 +650,7 @@ pub async fn validate_custom_with_options(
 /// println!("Safe to connect to {} ({})", result.host, result.ip);
 /// # Ok::<(), url_jail::Error>(())
 /// ```
-pub fn validate_sync(url: &str, policy: Policy) -> Result<Validated, Error> {
+pub fn validate_sync<P: ValidationPolicy>(url: &str, policy: P) -> Result<Validated, Error> {
     if let Ok(handle) = tokio::runtime::Handle::try_current() {
         tokio::task::block_in_place(|| handle.block_on(validate(url, policy)))
     } else {
@@ -239,89 +660,379 @@ pub fn validate_sync(url: &str, policy: Policy) -> Result<Validated, Error> {
     }
 }
 
-/// Resolve a hostname to IP addresses with timeout, checking ALL against policy.
-/// Returns the first allowed IP, or an error if any IP is blocked.
-async fn resolve_and_verify_dns(
-    host: &str,
+/// Synchronous version of [`validate_custom`].
+///
+/// Hostname and IP checks run through the custom policy's allow and block
+/// rules, then its base policy, exactly as in the async version. Runtime
+/// handling is the same as [`validate_sync`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use url_jail::{validate_custom_sync, PolicyBuilder, Policy};
+///
+/// let policy = PolicyBuilder::new(Policy::PublicOnly)
+///     .block_host("*.internal.example.com")
+///     .build();
+///
+/// let result = validate_custom_sync("https://api.example.com/", &policy)?;
+/// # Ok::<(), url_jail::Error>(())
+/// ```
+pub fn validate_custom_sync(
+    url: &str,
+    policy: &crate::policy_builder::CustomPolicy,
+) -> Result<Validated, Error> {
+    validate_sync(url, policy)
+}
+
+/// Synchronous version of [`validate`] with an overall deadline.
+///
+/// Validation runs on a dedicated background worker thread (started on
+/// first use and shared by all callers), so this never uses
+/// `block_in_place` and is safe to call from any thread, including a
+/// single-threaded runtime. The calling thread blocks for at most `timeout`.
+///
+/// The policy moves to the worker thread, so pass a
+/// [`CustomPolicy`](crate::CustomPolicy) by value; clones share its rule
+/// counters.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use url_jail::{validate_sync_with_timeout, Policy};
+///
+/// let result = validate_sync_with_timeout(
+///     "https://example.com/api",
+///     Policy::PublicOnly,
+///     Duration::from_secs(2),
+/// )?;
+/// # Ok::<(), url_jail::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::Timeout`] if validation does not finish within
+/// `timeout`, plus the errors of [`validate`].
+pub fn validate_sync_with_timeout<P: ValidationPolicy + 'static>(
+    url: &str,
+    policy: P,
     timeout: Duration,
-    policy: Policy,
-) -> Result<IpAddr, Error> {
-    let host_str = host.trim_start_matches('[').trim_end_matches(']');
-    if let Ok(ip) = host_str.parse::<IpAddr>() {
-        // Literal IP - check directly
-        if let Some(reason) = is_ip_blocked(ip, policy) {
-            return Err(Error::ssrf_blocked(host, ip, reason));
-        }
-        return Ok(ip);
+) -> Result<Validated, Error> {
+    let options = ValidateOptions {
+        dns_timeout: timeout,
+        ..Default::default()
+    };
+    validate_on_worker(url, policy, options, timeout)
+}
+
+/// Validate on the shared worker thread, blocking for at most `timeout`.
+pub(crate) fn validate_on_worker<P: ValidationPolicy + 'static>(
+    url: &str,
+    policy: P,
+    options: ValidateOptions,
+    timeout: Duration,
+) -> Result<Validated, Error> {
+    let handle = worker_handle()?;
+    let (tx, rx) = std::sync::mpsc::sync_channel(1);
+    let url = url.to_string();
+    handle.spawn(async move {
+        let result = tokio::time::timeout(timeout, validate_with_options(&url, policy, options))
+            .await
+            .unwrap_or_else(|_| Err(sync_timeout(timeout)));
+        // The caller may have given up already
+        let _ = tx.send(result);
+    });
+
+    rx.recv_timeout(timeout)
+        .unwrap_or_else(|_| Err(sync_timeout(timeout)))
+}
+
+fn sync_timeout(timeout: Duration) -> Error {
+    Error::Timeout {
+        message: format!("validation timed out after {:?}", timeout),
     }
+}
 
-    let resolve_future = async {
-        let resolver = TokioResolver::builder_tokio()
-            .map_err(|e| Error::dns_error(host, e.to_string()))?
-            .build();
+/// Handle to the runtime on the shared validation worker thread.
+///
+/// The worker is a single-threaded runtime parked on a never-ending future
+/// so that spawned validations (which are I/O bound) run concurrently on it.
+fn worker_handle() -> Result<&'static tokio::runtime::Handle, Error> {
+    static WORKER: OnceLock<Result<tokio::runtime::Handle, String>> = OnceLock::new();
+
+    WORKER
+        .get_or_init(|| {
+            let (tx, rx) = std::sync::mpsc::channel();
+            std::thread::Builder::new()
+                .name("url-jail-worker".into())
+                .spawn(move || {
+                    match tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                    {
+                        Ok(rt) => {
+                            let _ = tx.send(Ok(rt.handle().clone()));
+                            rt.block_on(std::future::pending::<()>());
+                        }
+                        Err(e) => {
+                            let _ = tx.send(Err(e.to_string()));
+                        }
+                    }
+                })
+                .map_err(|e| e.to_string())?;
+            rx.recv().map_err(|e| e.to_string())?
+        })
+        .as_ref()
+        .map_err(|e| Error::dns_error("runtime", e.clone()))
+}
 
-        let response = resolver
-            .lookup_ip(host)
-            .await
-            .map_err(|e| Error::dns_error(host, e.to_string()))?;
+/// Look up a previously validated IP for `host` under the policy `fingerprint`.
+async fn cached_ip(options: &ValidateOptions, fingerprint: u64, host: &str) -> Option<IpAddr> {
+    let ip = options.cache.as_ref()?.get(fingerprint, host).await?;
 
-        let ips: Vec<IpAddr> = response.iter().collect();
-        if ips.is_empty() {
-            return Err(Error::dns_error(host, "no IP addresses found"));
+    #[cfg(feature = "tracing")]
+    tracing::debug!(%ip, host, "validation cache hit");
+
+    Some(ip)
+}
+
+/// Record a successful validation in the cache, if one is configured.
+///
+/// `ttl` is the DNS answer's TTL, if known.
+async fn cache_ip(
+    options: &ValidateOptions,
+    fingerprint: u64,
+    host: &str,
+    ip: IpAddr,
+    ttl: Option<Duration>,
+) {
+    if let Some(cache) = &options.cache {
+        cache.insert_answer(fingerprint, host, ip, ttl).await;
+    }
+}
+
+/// Drive a [`ValidationMachine`] to completion, answering its DNS queries
+/// from the cache, the resolver, or the configured [`DnsFallback`].
+pub(crate) async fn drive(
+    mut step: Step<'_>,
+    options: &ValidateOptions,
+    fingerprint: u64,
+) -> Result<Validated, Error> {
+    #[cfg(feature = "tracing")]
+    if !options.context.is_empty() {
+        tracing::Span::current().record("context", tracing::field::display(&options.context));
+    }
+
+    let result = loop {
+        let query = match step {
+            Step::Done(result) => break result,
+            Step::NeedDns(query) => query,
+        };
+        let host = query.host().to_string();
+
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("host", host.as_str());
+
+        // Cached addresses go back through the machine, so they are re-checked
+        // against the policy: a shared store cannot smuggle in a blocked IP
+        let (answer, ttl, fresh) = match cached_ip(options, fingerprint, &host).await {
+            Some(ip) => (Ok(vec![ip]), None, false),
+            None => match resolve_host(&host, options, fingerprint).await {
+                Ok((ips, ttl, fresh)) => (Ok(ips), ttl, fresh),
+                Err(e) => (Err(e), None, false),
+            },
+        };
+
+        let url = query.url().to_string();
+        let recorded = match (&options.recorder, &answer) {
+            (Some(recorder), Ok(ips)) => Some((recorder, ips.clone())),
+            _ => None,
+        };
+
+        step = match ttl {
+            Some(ttl) => query.respond_with_ttl(answer, ttl),
+            None => query.respond(answer),
+        };
+        let Step::Done(result) = &step else {
+            continue;
+        };
+        if let Some((recorder, ips)) = recorded {
+            recorder.record(&url, &host, ips, ttl, result);
+        }
+        // Only fresh DNS answers are cached
+        if let (true, Ok(validated)) = (fresh, result) {
+            cache_ip(options, fingerprint, &host, validated.ip, ttl).await;
         }
+    };
 
-        // Check ALL resolved IPs - if ANY is blocked, fail
-        // This prevents attackers from hiding a blocked IP among allowed ones
-        for ip in &ips {
-            if let Some(reason) = is_ip_blocked(*ip, policy) {
-                return Err(Error::ssrf_blocked(host, *ip, reason));
-            }
+    result.map_err(|e| {
+        #[cfg(feature = "tracing")]
+        if e.is_blocked() {
+            tracing::warn!(context = %options.context, error = %e, "URL blocked");
         }
+        e.with_context(&options.context)
+    })
+}
 
-        // All IPs are safe, return the first one
-        Ok(ips[0])
+/// Resolve `host`, applying the configured [`DnsFallback`] if the resolver is
+/// unreachable.
+///
+/// Returns a non-empty address list, the DNS answer's TTL if known, and
+/// whether the addresses are a fresh answer rather than from the fallback.
+async fn resolve_host(
+    host: &str,
+    options: &ValidateOptions,
+    fingerprint: u64,
+) -> Result<(Vec<IpAddr>, Option<Duration>, bool), Error> {
+    let lookup = match &options.resolver {
+        Some(resolver) => lookup_custom(&**resolver, host, options.dns_timeout)
+            .await
+            .map(|ips| (ips, None)),
+        None => lookup_host(host, options.dns_timeout)
+            .await
+            .map(|(ips, ttl)| (ips, Some(ttl))),
     };
+    match lookup {
+        Ok((ips, ttl)) => Ok((ips, ttl, true)),
+        Err(failure) => fallback(host, failure, options, fingerprint)
+            .await
+            .map(|ips| (ips, None, false)),
+    }
+}
 
-    tokio::time::timeout(timeout, resolve_future)
-        .await
-        .map_err(|_| Error::Timeout {
-            message: format!("DNS resolution for {} timed out after {:?}", host, timeout),
-        })?
+/// Resolve `host` with the resolver and timeout of `options`, bypassing the
+/// cache and any [`DnsFallback`].
+pub(crate) async fn lookup(host: &str, options: &ValidateOptions) -> Result<Vec<IpAddr>, Error> {
+    let lookup = match &options.resolver {
+        Some(resolver) => lookup_custom(&**resolver, host, options.dns_timeout).await,
+        None => lookup_host(host, options.dns_timeout)
+            .await
+            .map(|(ips, _)| ips),
+    };
+    lookup.map_err(|failure| failure.error)
+}
+
+/// Apply [`ValidateOptions::dns_fallback`] to a failed lookup.
+async fn fallback(
+    host: &str,
+    failure: LookupError,
+    options: &ValidateOptions,
+    fingerprint: u64,
+) -> Result<Vec<IpAddr>, Error> {
+    if !failure.resolver_down {
+        return Err(failure.error);
+    }
+
+    let fallback_ip = match &options.dns_fallback {
+        DnsFallback::FailClosed => None,
+        DnsFallback::FailOpen { hosts } => hosts
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(host))
+            .map(|(_, ip)| *ip),
+        DnsFallback::ServeStale { max_staleness } => match &options.cache {
+            Some(cache) => cache.get_stale(fingerprint, host, *max_staleness).await,
+            None => None,
+        },
+    };
+
+    match fallback_ip {
+        Some(ip) => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(%ip, host, error = %failure.error, "resolver unavailable, using DNS fallback");
+
+            Ok(vec![ip])
+        }
+        None => Err(failure.error),
+    }
+}
+
+/// A failed lookup, classified for [`DnsFallback`].
+struct LookupError {
+    error: Error,
+    /// `true` if the resolver could not be reached or timed out, as opposed
+    /// to answering that the name has no addresses.
+    resolver_down: bool,
 }
 
-/// Resolve hostname for custom policies (doesn't do policy check internally).
-async fn resolve_dns_with_timeout(host: &str, timeout: Duration) -> Result<IpAddr, Error> {
-    let host_str = host.trim_start_matches('[').trim_end_matches(']');
-    if let Ok(ip) = host_str.parse::<IpAddr>() {
-        return Ok(ip);
+/// Resolve `host` with a [`Resolve`] implementation, with a timeout.
+async fn lookup_custom(
+    resolver: &dyn Resolve,
+    host: &str,
+    timeout: Duration,
+) -> Result<Vec<IpAddr>, LookupError> {
+    let resolve_future = async {
+        let _permit = lookup_permit().await;
+        resolver.lookup(host).await
+    };
+    let answer = tokio::time::timeout(timeout, resolve_future)
+        .await
+        .unwrap_or_else(|_| {
+            Err(Error::Timeout {
+                message: format!("DNS resolution for {} timed out after {:?}", host, timeout),
+            })
+        });
+    match answer {
+        Ok(ips) if ips.is_empty() => Err(LookupError {
+            error: Error::dns_error(host, "no IP addresses found"),
+            resolver_down: false,
+        }),
+        Ok(ips) => Ok(ips),
+        Err(error) => Err(LookupError {
+            resolver_down: matches!(error, Error::Timeout { .. }),
+            error,
+        }),
     }
+}
 
+/// Resolve `host` to a non-empty list of addresses and their TTL, with a
+/// timeout.
+async fn lookup_host(
+    host: &str,
+    timeout: Duration,
+) -> Result<(Vec<IpAddr>, Duration), LookupError> {
     let resolve_future = async {
-        let resolver = TokioResolver::builder_tokio()
-            .map_err(|e| Error::dns_error(host, e.to_string()))?
-            .build();
+        let _permit = lookup_permit().await;
+        let resolver = build_resolver(host).map_err(|error| LookupError {
+            error,
+            resolver_down: true,
+        })?;
 
-        let response = resolver
-            .lookup_ip(host)
-            .await
-            .map_err(|e| Error::dns_error(host, e.to_string()))?;
+        let response = resolver.lookup_ip(host).await.map_err(|e| LookupError {
+            resolver_down: !e.is_no_records_found(),
+            error: Error::dns_error(host, e.to_string()),
+        })?;
 
-        response
-            .iter()
-            .next()
-            .ok_or_else(|| Error::dns_error(host, "no IP addresses found"))
+        let ips: Vec<IpAddr> = response.iter().collect();
+        if ips.is_empty() {
+            return Err(LookupError {
+                error: Error::dns_error(host, "no IP addresses found"),
+                resolver_down: false,
+            });
+        }
+        let ttl = response
+            .valid_until()
+            .saturating_duration_since(std::time::Instant::now());
+        Ok((ips, ttl))
     };
 
     tokio::time::timeout(timeout, resolve_future)
         .await
-        .map_err(|_| Error::Timeout {
-            message: format!("DNS resolution for {} timed out after {:?}", host, timeout),
+        .map_err(|_| LookupError {
+            error: Error::Timeout {
+                message: format!("DNS resolution for {} timed out after {:?}", host, timeout),
+            },
+            resolver_down: true,
         })?
 }
 
 #[cfg(test)]
+// Red-team tests match on both outcomes to document each one
+#[allow(clippy::single_match)]
 mod tests {
     use super::*;
+    use crate::cache::{CacheEntry, CacheKey, CacheStore, MemoryStore};
+    use crate::policy::Policy;
     use crate::policy_builder::PolicyBuilder;
 
     #[tokio::test]
@@ -330,6 +1041,95 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_custom_sync() {
+        let policy = PolicyBuilder::new(Policy::PublicOnly)
+            .block_host("*.internal.example")
+            .allow_cidr("10.20.0.0/16")
+            .block_cidr("8.8.0.0/16")
+            .build();
+
+        assert!(validate_custom_sync("http://10.20.0.1/", &policy).is_ok());
+        for url in [
+            "http://db.internal.example/",
+            "http://8.8.8.8/",
+            "http://10.30.0.1/",
+            "http://169.254.169.254/",
+        ] {
+            let err = validate_custom_sync(url, &policy).unwrap_err();
+            assert!(err.is_blocked(), "{}: {:?}", url, err);
+        }
+    }
+
+    #[test]
+    fn test_sync_with_timeout_outside_runtime() {
+        let v = validate_sync_with_timeout(
+            "http://8.8.8.8/",
+            Policy::PublicOnly,
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        assert_eq!(v.ip.to_string(), "8.8.8.8");
+
+        let err = validate_sync_with_timeout(
+            "http://127.0.0.1/",
+            Policy::PublicOnly,
+            Duration::from_secs(5),
+        )
+        .unwrap_err();
+        assert!(err.is_blocked());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_sync_with_timeout_inside_current_thread_runtime() {
+        // validate_sync would panic here: block_in_place needs a multi-threaded runtime
+        let v = validate_sync_with_timeout(
+            "http://8.8.8.8/",
+            Policy::PublicOnly,
+            Duration::from_secs(5),
+        );
+        assert!(v.is_ok());
+
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("10.30.0.0/16")
+            .build();
+        let err =
+            validate_sync_with_timeout("http://10.30.0.1/", policy.clone(), Duration::from_secs(5))
+                .unwrap_err();
+        assert!(err.is_blocked(), "{:?}", err);
+        assert!(
+            validate_sync_with_timeout("http://10.1.0.1/", policy, Duration::from_secs(5)).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_sync_with_timeout_expires() {
+        let err = validate_sync_with_timeout(
+            "https://slow.url-jail-test/",
+            Policy::PublicOnly,
+            Duration::ZERO,
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::Timeout { .. }), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_context_in_error_not_decision() {
+        let opts = || ValidateOptions {
+            context: Context::new().tag("tenant", "acme").tag("request_id", "r1"),
+            ..Default::default()
+        };
+
+        let err = validate_with_options("http://127.0.0.1/", Policy::PublicOnly, opts())
+            .await
+            .unwrap_err();
+        assert!(err.is_blocked());
+        assert!(err.to_string().ends_with("[request_id=r1 tenant=acme]"));
+
+        let ok = validate_with_options("http://8.8.8.8/", Policy::PublicOnly, opts()).await;
+        assert!(ok.is_ok());
+    }
+
     #[tokio::test]
     async fn test_block_loopback() {
         let result = validate("http://127.0.0.1/", Policy::PublicOnly).await;
@@ -403,6 +1203,7 @@ mod tests {
     async fn test_validate_with_custom_timeout() {
         let opts = ValidateOptions {
             dns_timeout: Duration::from_secs(5),
+            ..Default::default()
         };
         let result = validate_with_options("https://example.com/", Policy::PublicOnly, opts).await;
         assert!(result.is_ok());
@@ -413,6 +1214,7 @@ mod tests {
         // This may or may not timeout depending on DNS cache, but should not panic
         let opts = ValidateOptions {
             dns_timeout: Duration::from_millis(1),
+            ..Default::default()
         };
         let result = validate_with_options(
             "https://very-slow-dns-example.invalid/",
@@ -426,6 +1228,261 @@ mod tests {
 
     // ==================== validate_custom tests ====================
 
+    #[tokio::test]
+    async fn test_cache_partitioned_by_builtin_policy() {
+        let cache = Arc::new(ValidationCache::new(Duration::from_secs(60)));
+        let opts = ValidateOptions {
+            cache: Some(cache.clone()),
+            ..Default::default()
+        };
+        cache
+            .insert(
+                Policy::AllowPrivate.fingerprint(),
+                "internal.url-jail-test",
+                "10.0.0.1".parse().unwrap(),
+            )
+            .await;
+
+        // Served from the cache without DNS
+        let v = validate_with_options(
+            "http://internal.url-jail-test/",
+            Policy::AllowPrivate,
+            opts.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(v.ip.to_string(), "10.0.0.1");
+
+        // A stricter policy must not see the AllowPrivate entry (it falls
+        // through to DNS, which cannot resolve this name)
+        let result =
+            validate_with_options("http://internal.url-jail-test/", Policy::PublicOnly, opts).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_recorder_captures_dns_backed_decisions() {
+        let cache = Arc::new(ValidationCache::new(Duration::from_secs(60)));
+        let recorder = Arc::new(Recorder::new());
+        let opts = ValidateOptions {
+            cache: Some(cache.clone()),
+            recorder: Some(recorder.clone()),
+            ..Default::default()
+        };
+        cache
+            .insert(
+                Policy::AllowPrivate.fingerprint(),
+                "internal.url-jail-test",
+                "10.0.0.1".parse().unwrap(),
+            )
+            .await;
+
+        validate_with_options(
+            "http://internal.url-jail-test/",
+            Policy::AllowPrivate,
+            opts.clone(),
+        )
+        .await
+        .unwrap();
+        // Literals need no DNS answer and are not recorded
+        validate_with_options("http://8.8.8.8/", Policy::AllowPrivate, opts)
+            .await
+            .unwrap();
+
+        let records = recorder.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].host, "internal.url-jail-test");
+        assert_eq!(records[0].ttl, None);
+        assert!(records[0].decision.is_allowed());
+
+        let outcomes = crate::replay(&records, Policy::PublicOnly);
+        assert!(outcomes[0].newly_blocked());
+    }
+
+    #[tokio::test]
+    async fn test_cache_partitioned_by_custom_policy() {
+        let cache = Arc::new(ValidationCache::new(Duration::from_secs(60)));
+        let opts = ValidateOptions {
+            cache: Some(cache.clone()),
+            ..Default::default()
+        };
+        let lenient = PolicyBuilder::new(Policy::AllowPrivate).build();
+        let strict = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_cidr("10.0.0.0/8")
+            .build();
+        cache
+            .insert(
+                lenient.fingerprint(),
+                "internal.url-jail-test",
+                "10.1.2.3".parse().unwrap(),
+            )
+            .await;
+
+        let result =
+            validate_custom_with_options("http://internal.url-jail-test/", &lenient, opts.clone())
+                .await;
+        assert_eq!(result.unwrap().ip.to_string(), "10.1.2.3");
+
+        let result =
+            validate_custom_with_options("http://internal.url-jail-test/", &strict, opts).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cache_does_not_bypass_hostname_rules() {
+        let store = Arc::new(MemoryStore::new());
+        let cache = Arc::new(ValidationCache::with_store(
+            Duration::from_secs(60),
+            store.clone(),
+        ));
+        let opts = ValidateOptions {
+            cache: Some(cache.clone()),
+            ..Default::default()
+        };
+        cache
+            .insert(
+                Policy::PublicOnly.fingerprint(),
+                "metadata.google.internal",
+                "8.8.8.8".parse().unwrap(),
+            )
+            .await;
+
+        let result =
+            validate_with_options("http://metadata.google.internal/", Policy::PublicOnly, opts)
+                .await;
+        assert!(matches!(result, Err(Error::HostnameBlocked { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_cache_poisoned_entry_is_rechecked() {
+        // A shared store returning a blocked address must not bypass policy
+        let store = Arc::new(MemoryStore::new());
+        let entry = CacheEntry {
+            ip: "169.254.169.254".parse().unwrap(),
+            expires_at: std::time::SystemTime::now() + Duration::from_secs(60),
+        };
+        let custom = PolicyBuilder::new(Policy::PublicOnly).build();
+        for fingerprint in [Policy::PublicOnly.fingerprint(), custom.fingerprint()] {
+            let key = CacheKey {
+                fingerprint,
+                host: "poisoned.example".to_string(),
+            };
+            store.set(key, entry, Duration::from_secs(60)).await;
+        }
+        let opts = ValidateOptions {
+            cache: Some(Arc::new(ValidationCache::with_store(
+                Duration::from_secs(60),
+                store,
+            ))),
+            ..Default::default()
+        };
+
+        let result =
+            validate_with_options("http://poisoned.example/", Policy::PublicOnly, opts.clone())
+                .await;
+        assert!(matches!(result, Err(Error::SsrfBlocked { .. })));
+        let result = validate_custom_with_options("http://poisoned.example/", &custom, opts).await;
+        assert!(matches!(result, Err(Error::SsrfBlocked { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_cache_does_not_store_failures() {
+        let store = Arc::new(MemoryStore::new());
+        let cache = Arc::new(ValidationCache::with_store(
+            Duration::from_secs(60),
+            store.clone(),
+        ));
+        let opts = ValidateOptions {
+            cache: Some(cache.clone()),
+            ..Default::default()
+        };
+        let result = validate_with_options("http://127.0.0.1/", Policy::PublicOnly, opts).await;
+        assert!(result.is_err());
+        assert!(store.is_empty());
+    }
+
+    fn lookup_failure(resolver_down: bool) -> LookupError {
+        LookupError {
+            error: Error::dns_error("fallback.example", "resolver unreachable"),
+            resolver_down,
+        }
+    }
+
+    fn fallback_options(dns_fallback: DnsFallback) -> ValidateOptions {
+        ValidateOptions {
+            dns_fallback,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dns_fallback_fail_closed_by_default() {
+        let opts = ValidateOptions::default();
+        let result = fallback("fallback.example", lookup_failure(true), &opts, 1).await;
+        assert!(matches!(result, Err(Error::DnsError { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_dns_fallback_fail_open_allowlisted_host() {
+        let hosts = HashMap::from([(
+            "Fallback.Example".to_string(),
+            "203.0.113.10".parse().unwrap(),
+        )]);
+        let opts = fallback_options(DnsFallback::FailOpen { hosts });
+
+        let ips = fallback("fallback.example", lookup_failure(true), &opts, 1)
+            .await
+            .unwrap();
+        assert_eq!(ips, vec!["203.0.113.10".parse::<IpAddr>().unwrap()]);
+
+        // Hosts that are not listed still fail closed
+        let result = fallback("other.example", lookup_failure(true), &opts, 1).await;
+        assert!(matches!(result, Err(Error::DnsError { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_dns_fallback_not_used_for_nxdomain() {
+        let hosts = HashMap::from([(
+            "fallback.example".to_string(),
+            "203.0.113.10".parse().unwrap(),
+        )]);
+        let opts = fallback_options(DnsFallback::FailOpen { hosts });
+        let result = fallback("fallback.example", lookup_failure(false), &opts, 1).await;
+        assert!(matches!(result, Err(Error::DnsError { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_dns_fallback_serve_stale() {
+        let cache = Arc::new(ValidationCache::new(Duration::ZERO));
+        cache
+            .insert(1, "fallback.example", "203.0.113.10".parse().unwrap())
+            .await;
+        let opts = ValidateOptions {
+            cache: Some(cache),
+            ..fallback_options(DnsFallback::ServeStale {
+                max_staleness: Duration::from_secs(60),
+            })
+        };
+
+        let ips = fallback("fallback.example", lookup_failure(true), &opts, 1)
+            .await
+            .unwrap();
+        assert_eq!(ips, vec!["203.0.113.10".parse::<IpAddr>().unwrap()]);
+
+        // Stale entries stay partitioned by policy
+        let result = fallback("fallback.example", lookup_failure(true), &opts, 2).await;
+        assert!(matches!(result, Err(Error::DnsError { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_dns_fallback_serve_stale_without_cache_fails_closed() {
+        let opts = fallback_options(DnsFallback::ServeStale {
+            max_staleness: Duration::from_secs(60),
+        });
+        let result = fallback("fallback.example", lookup_failure(true), &opts, 1).await;
+        assert!(matches!(result, Err(Error::DnsError { .. })));
+    }
+
     #[tokio::test]
     async fn test_validate_custom_block_cidr() {
         let policy = PolicyBuilder::new(Policy::AllowPrivate)
@@ -458,11 +1515,25 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_validate_custom_block_hostname_port() {
+        let policy = PolicyBuilder::new(Policy::AllowPrivate)
+            .block_host("10.0.0.1:6379")
+            .build();
+
+        let result = validate_custom("http://10.0.0.1:6379/", &policy).await;
+        assert!(matches!(result, Err(Error::HostnameBlocked { .. })));
+
+        let result = validate_custom("http://10.0.0.1:8080/", &policy).await;
+        assert!(result.is_ok());
+    }
+
     #[tokio::test]
     async fn test_validate_custom_with_options() {
         let policy = PolicyBuilder::new(Policy::PublicOnly).build();
         let opts = ValidateOptions {
             dns_timeout: Duration::from_secs(10),
+            ..Default::default()
         };
 
         let result = validate_custom_with_options("https://example.com/", &policy, opts).await;
@@ -495,6 +1566,134 @@ mod tests {
         assert_eq!(socket_addr.ip(), result.ip);
     }
 
+    #[test]
+    fn test_validated_resolve_to_addrs() {
+        let result = validate_sync("http://10.0.0.1:8080/", Policy::AllowPrivate).unwrap();
+
+        let (domain, addrs) = result.resolve_to_addrs();
+        assert_eq!(domain, "10.0.0.1");
+        assert_eq!(addrs, vec!["10.0.0.1:8080".parse::<SocketAddr>().unwrap()]);
+
+        let mut multi = result.clone();
+        multi.ips = vec!["10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()];
+        let (_, addrs) = multi.resolve_to_addrs();
+        assert_eq!(
+            addrs,
+            vec![
+                "10.0.0.1:8080".parse::<SocketAddr>().unwrap(),
+                "10.0.0.2:8080".parse::<SocketAddr>().unwrap(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revalidate_only_when_expired() {
+        let literal = validate("https://93.184.216.34/", Policy::PublicOnly)
+            .await
+            .unwrap();
+        assert_eq!(literal.valid_until, None);
+        assert!(!literal.is_expired());
+
+        // A fresh result is returned as it is, even if it no longer resolves
+        let mut cached = literal.clone();
+        cached.ip = "203.0.113.7".parse().unwrap();
+        cached.valid_until = Some(SystemTime::now() + Duration::from_secs(60));
+        let kept = cached.revalidate(Policy::PublicOnly).await.unwrap();
+        assert_eq!(kept.ip.to_string(), "203.0.113.7");
+
+        cached.valid_until = Some(SystemTime::now() - Duration::from_secs(1));
+        assert!(cached.is_expired());
+        let renewed = cached.revalidate(Policy::PublicOnly).await.unwrap();
+        assert_eq!(renewed.ip.to_string(), "93.184.216.34");
+        assert_eq!(renewed.valid_until, None);
+
+        let mut private = validate("http://10.0.0.1/", Policy::AllowPrivate)
+            .await
+            .unwrap();
+        private.valid_until = Some(SystemTime::now());
+        assert!(private.revalidate(Policy::PublicOnly).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_expiry_follows_cache_clock() {
+        use crate::machine::ValidationMachine;
+        use crate::{Clock, ManualClock};
+
+        let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+        let Step::NeedDns(query) =
+            ValidationMachine::new("https://example.com/", Policy::PublicOnly)
+                .clock(clock.clone())
+                .start()
+        else {
+            panic!("expected a DNS query");
+        };
+        let validated = match query.respond_with_ttl(
+            Ok(vec!["93.184.216.34".parse().unwrap()]),
+            Duration::from_secs(60),
+        ) {
+            Step::Done(result) => result.unwrap(),
+            Step::NeedDns(_) => panic!("expected a result"),
+        };
+        assert_eq!(
+            validated.valid_until,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(60))
+        );
+        assert!(!validated.is_expired());
+        // Not expired by the clock, so kept without resolving again
+        let kept = validated.revalidate(Policy::BlockAll).await.unwrap();
+        assert_eq!(kept.ip, validated.ip);
+
+        clock.advance(Duration::from_secs(60));
+        assert!(validated.is_expired());
+        assert!(validated.revalidate(Policy::BlockAll).await.is_err());
+
+        // Validation through a cache reads the cache's clock
+        let cache =
+            Arc::new(ValidationCache::new(Duration::from_secs(60)).with_clock(clock.clone()));
+        cache
+            .insert(
+                Policy::PublicOnly.fingerprint(),
+                "example.com",
+                "93.184.216.34".parse().unwrap(),
+            )
+            .await;
+        let options = ValidateOptions {
+            cache: Some(cache),
+            ..Default::default()
+        };
+        let hit = validate_with_options("https://example.com/", Policy::PublicOnly, options)
+            .await
+            .unwrap();
+        assert_eq!(hit.valid_until, Some(clock.now()));
+    }
+
+    #[test]
+    fn test_validated_curl_args() {
+        let v = Validated {
+            ip: "2001:db8::1".parse().unwrap(),
+            ips: vec!["2001:db8::1".parse().unwrap()],
+            host: "api.example.com".to_string(),
+            port: 8443,
+            url: "https://api.example.com:8443/v1".to_string(),
+            https: true,
+            valid_until: None,
+            clock: Default::default(),
+        };
+        assert_eq!(v.curl_resolve(), "api.example.com:8443:[2001:db8::1]");
+        assert_eq!(
+            v.curl_connect_to(),
+            "api.example.com:8443:[2001:db8::1]:8443"
+        );
+
+        let args = v.curl_args();
+        assert_eq!(
+            args[..2],
+            ["--resolve", "api.example.com:8443:[2001:db8::1]"]
+        );
+        assert_eq!(args.last().unwrap(), "https://api.example.com:8443/v1");
+        assert!(args.windows(2).any(|w| w == ["--noproxy", "*"]));
+    }
+
     #[tokio::test]
     async fn test_validated_http_port() {
         let result = validate("http://example.com/", Policy::PublicOnly)
@@ -603,6 +1802,7 @@ mod tests {
         // Very short timeout should produce Timeout error for slow/invalid domain
         let opts = ValidateOptions {
             dns_timeout: Duration::from_nanos(1), // Impossibly short
+            ..Default::default()
         };
         let result = validate_with_options(
             "https://this-domain-will-definitely-timeout-due-to-short-timeout.invalid/",
@@ -867,4 +2067,274 @@ mod tests {
         let result = validate("gopher://127.0.0.1/", Policy::PublicOnly).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_cross_check_parsers_option() {
+        let opts = ValidateOptions {
+            cross_check_parsers: true,
+            ..Default::default()
+        };
+        let v = validate_with_options("HTTP://93.184.216.34./x", Policy::PublicOnly, opts.clone())
+            .await
+            .unwrap();
+        assert_eq!(v.ip.to_string(), "93.184.216.34");
+        // Rejections still come from the validation pipeline itself
+        let err = validate_with_options("http://127.0.0.1/", Policy::PublicOnly, opts)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::SsrfBlocked { .. }), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_zone_ids_option() {
+        let url = "http://[fd00::1%25eth0]:8080/";
+        let err = validate_with_options(url, Policy::AllowPrivate, ValidateOptions::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::AmbiguousUrl { .. }), "{:?}", err);
+
+        let opts = ValidateOptions {
+            zone_ids: ZoneIds::Strip,
+            ..Default::default()
+        };
+        let v = validate_with_options(url, Policy::AllowPrivate, opts.clone())
+            .await
+            .unwrap();
+        assert_eq!(v.ip.to_string(), "fd00::1");
+        assert_eq!(v.url, "http://[fd00::1]:8080/");
+        let err = validate_with_options(url, Policy::PublicOnly, opts)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::SsrfBlocked { .. }), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_confusables_option() {
+        let url = "https://\u{0430}pple.com/";
+        let opts = ValidateOptions {
+            confusables: Confusables::Reject,
+            ..Default::default()
+        };
+        let err = validate_with_options(url, Policy::PublicOnly, opts)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::HostnameBlocked { .. }), "{:?}", err);
+        assert!(
+            err.to_string()
+                .contains("confusable hostname: label 'аpple' mixes Latin and Cyrillic scripts"),
+            "{}",
+            err
+        );
+
+        let v = Validated {
+            ip: "93.184.216.34".parse().unwrap(),
+            ips: vec!["93.184.216.34".parse().unwrap()],
+            host: "xn--mnchen-3ya.de".to_string(),
+            port: 443,
+            url: "https://xn--mnchen-3ya.de/".to_string(),
+            https: true,
+            valid_until: None,
+            clock: Default::default(),
+        };
+        assert_eq!(v.host_unicode(), "münchen.de");
+        assert_eq!(v.confusable(), None);
+    }
+
+    #[test]
+    fn test_target_key() {
+        let validated = |url: &str, host: &str, ip: &str, port: u16| Validated {
+            ip: ip.parse().unwrap(),
+            ips: vec![ip.parse().unwrap()],
+            host: host.to_string(),
+            port,
+            url: url.to_string(),
+            https: url.starts_with("https:"),
+            valid_until: Some(SystemTime::now()),
+            clock: Default::default(),
+        };
+        let a = validated(
+            "https://api.example.com/a",
+            "api.example.com",
+            "93.184.216.34",
+            443,
+        );
+        let mut b = validated(
+            "https://api.example.com/b",
+            "API.Example.com.",
+            "93.184.216.35",
+            443,
+        );
+        b.valid_until = None;
+        assert_eq!(a.target_key(), b.target_key());
+
+        let mut pool = HashMap::new();
+        pool.insert(a.target_key(), "conn");
+        assert_eq!(pool.get(&b.target_key()), Some(&"conn"));
+        for other in [
+            validated(
+                "http://api.example.com/a",
+                "api.example.com",
+                "93.184.216.34",
+                443,
+            ),
+            validated(
+                "https://api.example.com:8443/",
+                "api.example.com",
+                "93.184.216.34",
+                8443,
+            ),
+            validated("https://example.com/", "example.com", "93.184.216.34", 443),
+        ] {
+            assert!(!pool.contains_key(&other.target_key()), "{}", other.url);
+        }
+
+        assert_eq!(a.target_key().to_string(), "https://api.example.com:443");
+        let v6 = validated(
+            "http://[2606:4700::1111]:8080/",
+            "2606:4700::1111",
+            "2606:4700::1111",
+            8080,
+        );
+        assert_eq!(v6.target_key().scheme(), "http");
+        assert_eq!(v6.target_key().to_string(), "http://[2606:4700::1111]:8080");
+    }
+
+    #[tokio::test]
+    async fn test_collect_violations_option() {
+        let policy = crate::PolicyBuilder::new(Policy::PublicOnly)
+            .allow_schemes(["https"])
+            .block_ports([8080])
+            .build();
+        let opts = ValidateOptions {
+            collect_violations: true,
+            context: Context::new().tag("tenant", "acme"),
+            ..Default::default()
+        };
+        let err = validate_with_options("http://10.0.0.1:8080/hook", &policy, opts)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Violations { .. }), "{:?}", err);
+        assert_eq!(err.violations().len(), 3);
+        assert!(err
+            .violations()
+            .iter()
+            .all(|e| e.to_string().ends_with("[tenant=acme]")));
+
+        let err = validate_with_options("http://10.0.0.1:8080/hook", &policy, Default::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::SchemeBlocked { .. }), "{:?}", err);
+    }
+
+    /// Answers from a fixed table, counting lookups.
+    #[derive(Default)]
+    struct TableResolver {
+        hosts: HashMap<&'static str, Vec<IpAddr>>,
+        lookups: std::sync::atomic::AtomicUsize,
+    }
+
+    impl Resolve for TableResolver {
+        fn lookup<'a>(&'a self, host: &'a str) -> crate::ResolveFuture<'a> {
+            self.lookups
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Box::pin(async move {
+                match host {
+                    "down.example" => Err(Error::Timeout {
+                        message: "unreachable".into(),
+                    }),
+                    _ => Ok(self.hosts.get(host).cloned().unwrap_or_default()),
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_with_resolver() {
+        let resolver = Arc::new(TableResolver {
+            hosts: HashMap::from([
+                ("api.split.example", vec!["93.184.216.34".parse().unwrap()]),
+                (
+                    "mixed.split.example",
+                    vec![
+                        "93.184.216.34".parse().unwrap(),
+                        "10.0.0.5".parse().unwrap(),
+                    ],
+                ),
+            ]),
+            ..Default::default()
+        });
+
+        let v = validate_with_resolver(
+            "https://API.split.example/",
+            Policy::PublicOnly,
+            resolver.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(v.ip.to_string(), "93.184.216.34");
+
+        let err = validate_with_resolver(
+            "https://mixed.split.example/",
+            Policy::PublicOnly,
+            resolver.clone(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, Error::SsrfBlocked { .. }), "{:?}", err);
+
+        let err = validate_with_resolver(
+            "https://missing.split.example/",
+            Policy::PublicOnly,
+            resolver.clone(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, Error::DnsError { .. }), "{:?}", err);
+        // Literal addresses never reach the resolver
+        validate_with_resolver(
+            "http://93.184.216.34/",
+            Policy::PublicOnly,
+            resolver.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            resolver.lookups.load(std::sync::atomic::Ordering::Relaxed),
+            3
+        );
+        // Custom resolvers report no TTL
+        assert!(v.is_expired());
+    }
+
+    #[tokio::test]
+    async fn test_resolver_with_cache_and_fallback() {
+        let resolver: Arc<dyn Resolve> = Arc::new(TableResolver {
+            hosts: HashMap::from([("api.split.example", vec!["93.184.216.34".parse().unwrap()])]),
+            ..Default::default()
+        });
+        let opts = ValidateOptions {
+            resolver: Some(resolver),
+            cache: Some(Arc::new(ValidationCache::new(Duration::from_secs(60)))),
+            dns_fallback: DnsFallback::FailOpen {
+                hosts: HashMap::from([(
+                    "down.example".to_string(),
+                    "93.184.216.35".parse().unwrap(),
+                )]),
+            },
+            ..Default::default()
+        };
+        let url = "https://api.split.example/";
+        validate_with_options(url, Policy::PublicOnly, opts.clone())
+            .await
+            .unwrap();
+        let cache = opts.cache.as_ref().unwrap();
+        let fingerprint = Policy::PublicOnly.fingerprint();
+        assert!(cache.get(fingerprint, "api.split.example").await.is_some());
+
+        // A timeout from the resolver counts as the resolver being down
+        let v = validate_with_options("https://down.example/", Policy::PublicOnly, opts)
+            .await
+            .unwrap();
+        assert_eq!(v.ip.to_string(), "93.184.216.35");
+    }
 }
@@ -25,6 +25,8 @@ class Policy:
             This is the default and recommended for most use cases.
         ALLOW_PRIVATE: Allow private IPs (10.x, 172.16.x, 192.168.x), but still
             block loopback and metadata endpoints. Use for internal services.
+        BLOCK_ALL: Block everything. As the base of a PolicyBuilder, only hosts
+            and CIDRs named by its allow rules are reachable.
     
     Example:
         >>> from url_jail import validate_sync, Policy
@@ -32,6 +34,7 @@ class Policy:
     """
     PUBLIC_ONLY: Policy
     ALLOW_PRIVATE: Policy
+    BLOCK_ALL: Policy
 
 class Validated:
     """Result of successful URL validation.
@@ -41,6 +44,7 @@ class Validated:
     
     Attributes:
         ip: The verified IP address to connect to (as string).
+        ips: Every resolved address (as strings), all checked against the policy.
         host: Original hostname (use for Host header / SNI).
         port: Port number (80 for http, 443 for https, or custom).
         url: Full URL (normalized).
@@ -52,6 +56,7 @@ class Validated:
         >>> print(f"Host header: {result.host}")
     """
     ip: str
+    ips: list[str]
     host: str
     port: int
     url: str
@@ -157,6 +162,71 @@ class PolicyBuilder:
         """
         ...
     
+    def allow_ports(self, ports: list[int]) -> "PolicyBuilder":
+        """Only allow these ports; every other port is blocked.
+        
+        Port rules are checked before host rules.
+        
+        Args:
+            ports: Allowed port numbers, e.g. [443, 8443].
+        
+        Returns:
+            Self for method chaining.
+        """
+        ...
+    
+    def allow_url_prefix(self, prefix: str) -> "PolicyBuilder":
+        """Only allow URLs under this prefix, e.g. "https://api.partner.com/v1/".
+        
+        URLs are normalized before matching, and a prefix only matches at a path
+        boundary. Other URLs raise HostnameBlocked. Calls accumulate.
+        
+        Args:
+            prefix: An http or https URL prefix.
+        
+        Returns:
+            Self for method chaining.
+        """
+        ...
+    
+    def block_path_glob(self, glob: str) -> "PolicyBuilder":
+        """Block URL paths matching a glob, e.g. "*/admin/*".
+        
+        * matches any run of characters, including /. Matching is case-insensitive
+        and runs on the normalized path. Blocked URLs raise HostnameBlocked.
+        
+        Args:
+            glob: Path glob.
+        
+        Returns:
+            Self for method chaining.
+        """
+        ...
+    
+    def block_ports(self, ports: list[int]) -> "PolicyBuilder":
+        """Block these ports, even for allowed hosts.
+        
+        Args:
+            ports: Blocked port numbers, e.g. [25, 6379].
+        
+        Returns:
+            Self for method chaining.
+        """
+        ...
+    
+    def allow_schemes(self, schemes: list[str]) -> "PolicyBuilder":
+        """Only allow these URL schemes, e.g. ["https"].
+        
+        Other schemes raise SchemeBlocked. Only http and https are ever valid.
+        
+        Args:
+            schemes: Allowed scheme names.
+        
+        Returns:
+            Self for method chaining.
+        """
+        ...
+    
     def build(self) -> CustomPolicy:
         """Build the custom policy.
         
@@ -190,6 +260,15 @@ class HostnameBlocked(UrlJailError):
     """
     ...
 
+class SchemeBlocked(UrlJailError):
+    """URL scheme is not in a custom policy's scheme allowlist.
+    
+    Raised when a PolicyBuilder.allow_schemes() allowlist excludes the URL's scheme.
+    
+    Message format: "url (scheme) - reason"
+    """
+    ...
+
 class InvalidUrl(UrlJailError):
     """Invalid URL syntax or forbidden scheme.
     
@@ -203,6 +282,19 @@ class InvalidUrl(UrlJailError):
     """
     ...
 
+class AmbiguousUrl(InvalidUrl):
+    """URL syntax that different parsers interpret differently.
+    
+    Raised for parser-differential tricks such as:
+    - Percent-encoded delimiters in the authority (`https://a.com%2F@evil.com`)
+    - Nested schemes (`http:http://evil.com`)
+    - Missing or extra slashes after the scheme, or backslashes in the authority
+    - Control characters, or a percent-encoded scheme
+    
+    Message format: "url - reason"
+    """
+    ...
+
 class DnsError(UrlJailError):
     """DNS resolution failed.
     
@@ -246,6 +338,16 @@ class HttpError(UrlJailError):
     """
     ...
 
+class ResponseBlocked(UrlJailError):
+    """The final response violated the fetch response policy.
+    
+    Raised when a response exceeds the body size limit, has a disallowed
+    content type, or carries a forbidden header.
+    
+    Message format: "url - reason"
+    """
+    ...
+
 def validate_sync(url: str, policy: Policy) -> Validated:
     """Validate a URL synchronously.
     
@@ -325,7 +427,9 @@ def validate_custom_sync(url: str, policy: CustomPolicy) -> Validated:
     """
     ...
 
-def get_sync(url: str, policy: Optional[Policy] = None) -> str:
+def get_sync(
+    url: str, policy: Optional[Policy] = None, max_redirects: Optional[int] = None
+) -> str:
     """Fetch a URL synchronously with full SSRF protection.
     
     This is the recommended way to safely fetch user-provided URLs.
@@ -334,6 +438,8 @@ def get_sync(url: str, policy: Optional[Policy] = None) -> str:
     Args:
         url: The URL to fetch.
         policy: Validation policy (defaults to PUBLIC_ONLY).
+        max_redirects: Redirects to follow before raising TooManyRedirects
+            (defaults to 10; 0 raises on the first redirect).
     
     Returns:
         Response body as string.
@@ -355,7 +461,9 @@ def get_sync(url: str, policy: Optional[Policy] = None) -> str:
     """
     ...
 
-async def get(url: str, policy: Optional[Policy] = None) -> str:
+async def get(
+    url: str, policy: Optional[Policy] = None, max_redirects: Optional[int] = None
+) -> str:
     """Fetch a URL asynchronously with full SSRF protection.
     
     Async version of get_sync(). Validates the initial URL and all
@@ -364,6 +472,8 @@ async def get(url: str, policy: Optional[Policy] = None) -> str:
     Args:
         url: The URL to fetch.
         policy: Validation policy (defaults to PUBLIC_ONLY).
+        max_redirects: Redirects to follow before raising TooManyRedirects
+            (defaults to 10; 0 raises on the first redirect).
     
     Returns:
         Response body as string.
@@ -0,0 +1,72 @@
+//! Address classes.
+
+use core::fmt;
+
+use crate::policy::Policy;
+
+/// What kind of address an IP is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum IpClass {
+    /// The unspecified address (`0.0.0.0`, `::`).
+    Unspecified,
+    /// Loopback (`127.0.0.0/8`, `::1`).
+    Loopback,
+    /// Link-local (`169.254.0.0/16`, `fe80::/10`).
+    LinkLocal,
+    /// A cloud metadata endpoint.
+    Metadata,
+    /// Private network (RFC 1918, `fc00::/7`).
+    Private,
+    /// Shared address space (`100.64.0.0/10`), used by carrier-grade NAT
+    /// and overlay networks such as Tailscale. Neither built-in policy
+    /// blocks it; url_jail's `BasePolicyBuilder::block_shared` can.
+    Shared,
+    /// Anything else.
+    Public,
+}
+
+impl IpClass {
+    /// Stable snake_case name, e.g. `"link_local"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IpClass::Unspecified => "unspecified",
+            IpClass::Loopback => "loopback",
+            IpClass::LinkLocal => "link_local",
+            IpClass::Metadata => "metadata",
+            IpClass::Private => "private",
+            IpClass::Shared => "shared",
+            IpClass::Public => "public",
+        }
+    }
+
+    /// Returns `true` if `policy` blocks addresses of this class.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use url_jail_core::{IpClass, Policy};
+    ///
+    /// assert!(IpClass::Private.is_blocked_by(Policy::PublicOnly));
+    /// assert!(!IpClass::Private.is_blocked_by(Policy::AllowPrivate));
+    /// assert!(IpClass::Metadata.is_blocked_by(Policy::AllowPrivate));
+    /// assert!(!IpClass::Shared.is_blocked_by(Policy::PublicOnly));
+    /// assert!(IpClass::Public.is_blocked_by(Policy::BlockAll));
+    /// ```
+    pub fn is_blocked_by(&self, policy: Policy) -> bool {
+        if policy == Policy::BlockAll {
+            return true;
+        }
+        match self {
+            IpClass::Public | IpClass::Shared => false,
+            IpClass::Private => policy == Policy::PublicOnly,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for IpClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
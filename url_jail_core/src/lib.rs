@@ -0,0 +1,44 @@
+//! # url_jail_core
+//!
+//! The address and hostname classification rules of
+//! [url_jail](https://crates.io/crates/url_jail), without `std`, `alloc` or
+//! any dependency, for embedded gateways and agents that filter traffic
+//! themselves and cannot link an async runtime, a resolver or a URL parser.
+//!
+//! url_jail re-exports everything here, and its [`Policy`] and [`IpClass`]
+//! are these types, so both sides of a deployment agree on every address.
+//!
+//! ```rust
+//! use core::net::IpAddr;
+//!
+//! use url_jail_core::{classify_ip, is_hostname_blocked, is_ip_blocked, IpClass, Policy};
+//!
+//! let ip: IpAddr = "::ffff:169.254.169.254".parse().unwrap();
+//! assert_eq!(classify_ip(ip).map(|(class, _)| class), Some(IpClass::Metadata));
+//! assert!(is_ip_blocked(ip, Policy::AllowPrivate).is_some());
+//! assert!(is_ip_blocked("10.0.0.1".parse().unwrap(), Policy::AllowPrivate).is_none());
+//! assert_eq!(is_hostname_blocked("metadata.google.internal"), Some("metadata.google.internal"));
+//! ```
+//!
+//! Addresses are `core::net` types, which smoltcp 0.12 and later also use,
+//! so packets can be checked without conversion.
+//!
+//! Only the fixed rules live here. Custom policies, DNS resolution and URL
+//! parsing stay in url_jail: a hostname that is not blocked by name must
+//! still be resolved and each of its addresses checked.
+//!
+//! ## Feature Flags
+//!
+//! - `serde`: `Serialize` and `Deserialize` for [`Policy`]
+
+#![no_std]
+
+mod blocklist;
+mod class;
+mod policy;
+
+pub use blocklist::{
+    classify_ip, embedded_ipv4, is_hostname_blocked, is_ip_blocked, unwrap_ipv4, BLOCK_ALL,
+};
+pub use class::IpClass;
+pub use policy::Policy;
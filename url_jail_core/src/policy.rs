@@ -0,0 +1,63 @@
+//! Built-in policies.
+
+/// Validation policy that controls which IP ranges are allowed.
+///
+/// Policies are pure validation constraints based solely on IP addresses.
+/// They do not consider user identity, request context, or time.
+///
+/// This enum is `Copy` and immutable. It is `#[non_exhaustive]`, so code
+/// matching on it needs a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum Policy {
+    /// Block private IPs, loopback, link-local, and metadata endpoints.
+    ///
+    /// This is the default and recommended for most use cases where you're
+    /// fetching URLs from untrusted sources (user input, webhooks, etc.).
+    #[default]
+    PublicOnly,
+
+    /// Allow private IPs, but still block loopback and metadata endpoints.
+    ///
+    /// Use for internal service-to-service calls within a trusted network
+    /// where you need to access private IP ranges but still want protection
+    /// against localhost and cloud metadata attacks.
+    AllowPrivate,
+
+    /// Block every address and hostname.
+    ///
+    /// As the base of a url_jail `CustomPolicy`, only targets its allow
+    /// rules name are reachable: with `allow_host("api.example.com")` and
+    /// no other rules, every other hostname is rejected before DNS.
+    /// Addresses an allowed hostname resolves to are still checked as under
+    /// [`PublicOnly`](Policy::PublicOnly), and an IP literal must match an
+    /// `allow_cidr` rule.
+    ///
+    /// On its own, blocks every URL.
+    BlockAll,
+}
+
+impl Policy {
+    /// Stable identifier for this policy, used to partition caches.
+    ///
+    /// Stable across processes and releases, so it can key shared caches.
+    pub fn fingerprint(self) -> u64 {
+        fnv1a(match self {
+            Policy::PublicOnly => b"builtin:public-only",
+            Policy::AllowPrivate => b"builtin:allow-private",
+            Policy::BlockAll => b"builtin:block-all",
+        })
+    }
+}
+
+/// 64-bit FNV-1a hash.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}